@@ -1,5 +1,45 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
 fn main() {
     // Build script simplificado - ya no necesitamos wasm3
     // El runtime de WASM se manejará directamente desde Rust usando wasmtime o similar
     println!("cargo:rerun-if-changed=build.rs");
+
+    // Tabla de assets del paquete .wpk (wpk/assets) embebidos en el kernel
+    let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../wpk/assets");
+    println!("cargo:rerun-if-changed={}", assets_dir.display());
+
+    let mut files = Vec::new();
+    collect_assets(&assets_dir, &mut files);
+    files.sort();
+
+    let mut table = String::from("pub static WPK_ASSETS: &[(&str, &[u8])] = &[\n");
+    for path in files {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let name = path.strip_prefix(&assets_dir).unwrap().to_string_lossy().replace('\\', "/");
+        let absolute = fs::canonicalize(&path).unwrap();
+        table.push_str(&format!("    ({:?}, include_bytes!({:?})),\n", name, absolute));
+    }
+    table.push_str("];\n");
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("wpk_assets.rs");
+    fs::write(out, table).unwrap();
+}
+
+/// Recorrer el directorio de assets; si no existe el paquete, la tabla queda vacía
+fn collect_assets(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            println!("cargo:rerun-if-changed={}", path.display());
+            collect_assets(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
 }
@@ -0,0 +1,249 @@
+//! FerroOS Mobile - Reloj de frames y animaciones
+//!
+//! El reloj de frames marca, con el System Timer, cuándo toca el siguiente
+//! frame (60 por segundo) y lleva las estadísticas de tiempo de cada uno. Las
+//! interpolaciones (`Tween`) van de un valor a otro (número, posición o color)
+//! siguiendo una curva de aceleración; la opacidad es un número de 0 a 1.
+
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+/// Duración de un frame a 60 Hz, en microsegundos
+pub const FRAME_INTERVAL_US: u64 = 16_667;
+
+/// Curva de aceleración de una animación
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Cuadráticas
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Cúbicas
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    /// Rebote al llegar
+    Bounce,
+}
+
+/// Nombres de las curvas, en el orden en que se listan
+pub const EASINGS: &[(&str, Easing)] = &[
+    ("linear", Easing::Linear),
+    ("ease_in", Easing::EaseIn),
+    ("ease_out", Easing::EaseOut),
+    ("ease_in_out", Easing::EaseInOut),
+    ("cubic_in", Easing::CubicIn),
+    ("cubic_out", Easing::CubicOut),
+    ("cubic_in_out", Easing::CubicInOut),
+    ("bounce", Easing::Bounce),
+];
+
+impl Easing {
+    pub fn parse(name: &str) -> Option<Self> {
+        EASINGS.iter().find(|(easing, _)| *easing == name).map(|(_, easing)| *easing)
+    }
+
+    /// Progreso de la animación para un tiempo `t` de 0 a 1 (se limita a ese rango)
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Easing::EaseInOut => -1.0 + (4.0 - 2.0 * t) * t,
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => (t - 1.0) * (t - 1.0) * (t - 1.0) + 1.0,
+            Easing::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::CubicInOut => (t - 1.0) * (2.0 * t - 2.0) * (2.0 * t - 2.0) + 1.0,
+            Easing::Bounce => bounce(t),
+        }
+    }
+}
+
+fn bounce(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Valor que se puede interpolar
+#[derive(Clone, Copy)]
+pub enum TweenValue {
+    /// Cualquier número: tamaños, ángulos, opacidad (0 a 1)
+    Number(f32),
+    /// Posición (x, y)
+    Point(f32, f32),
+    /// Color con su alfa
+    Color(Rgb888, u8),
+}
+
+impl TweenValue {
+    /// Valor a una fracción `t` del camino hacia `to`; `None` si son de tipos distintos
+    ///
+    /// `t` puede salir de 0 a 1 (curvas con rebote); los colores se limitan a su rango.
+    pub fn lerp(self, to: TweenValue, t: f32) -> Option<TweenValue> {
+        let value = match (self, to) {
+            (TweenValue::Number(a), TweenValue::Number(b)) => TweenValue::Number(lerp(a, b, t)),
+            (TweenValue::Point(ax, ay), TweenValue::Point(bx, by)) => TweenValue::Point(lerp(ax, bx, t), lerp(ay, by, t)),
+            (TweenValue::Color(a, a_alpha), TweenValue::Color(b, b_alpha)) => TweenValue::Color(
+                Rgb888::new(lerp_channel(a.r(), b.r(), t), lerp_channel(a.g(), b.g(), t), lerp_channel(a.b(), b.b(), t)),
+                lerp_channel(a_alpha, b_alpha, t),
+            ),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+pub fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+fn lerp_channel(from: u8, to: u8, t: f32) -> u8 {
+    libm::roundf(lerp(from as f32, to as f32, t)).clamp(0.0, 255.0) as u8
+}
+
+/// Animación de un valor a otro durante un tiempo
+#[derive(Clone, Copy)]
+pub struct Tween {
+    pub from: TweenValue,
+    pub to: TweenValue,
+    pub easing: Easing,
+    start_us: u64,
+    duration_us: u64,
+}
+
+impl Tween {
+    /// Empieza en `start_us` (tiempo del System Timer); `None` si los valores son de tipos distintos
+    pub fn new(from: TweenValue, to: TweenValue, duration_us: u64, easing: Easing, start_us: u64) -> Option<Self> {
+        from.lerp(to, 0.0)?;
+        Some(Self { from, to, easing, start_us, duration_us })
+    }
+
+    /// Fracción del tiempo transcurrida, de 0 a 1
+    pub fn progress(&self, now_us: u64) -> f32 {
+        if self.duration_us == 0 {
+            return 1.0;
+        }
+        (now_us.saturating_sub(self.start_us) as f32 / self.duration_us as f32).min(1.0)
+    }
+
+    /// Valor en el instante `now_us`
+    pub fn value(&self, now_us: u64) -> TweenValue {
+        let t = self.easing.apply(self.progress(now_us));
+        self.from.lerp(self.to, t).unwrap_or(self.to)
+    }
+
+    pub fn finished(&self, now_us: u64) -> bool {
+        self.progress(now_us) >= 1.0
+    }
+}
+
+/// Frame que toca dibujar
+#[derive(Clone, Copy)]
+pub struct FrameTick {
+    /// Tiempo del System Timer al empezar el frame
+    pub time_us: u64,
+    /// Tiempo desde el frame anterior (0 en el primero)
+    pub delta_us: u64,
+}
+
+/// Estadísticas de los frames dibujados
+#[derive(Clone, Copy, Default)]
+pub struct FrameStats {
+    pub frames: u64,
+    /// Frames que no se llegaron a dibujar a tiempo
+    pub dropped: u64,
+    /// Tiempo de trabajo del último frame y mínimo y máximo
+    pub last_us: u64,
+    pub min_us: u64,
+    pub max_us: u64,
+    total_us: u64,
+    /// Suma de los tiempos entre frames, para la tasa media
+    total_delta_us: u64,
+    deltas: u64,
+}
+
+impl FrameStats {
+    /// Tiempo medio de trabajo por frame
+    pub fn average_us(&self) -> u64 {
+        self.total_us.checked_div(self.frames).unwrap_or(0)
+    }
+
+    /// Frames por segundo medios mientras hubo animación
+    pub fn fps(&self) -> f32 {
+        if self.total_delta_us == 0 {
+            return 0.0;
+        }
+        self.deltas as f32 * 1_000_000.0 / self.total_delta_us as f32
+    }
+}
+
+/// Reloj de frames a intervalo fijo
+pub struct FrameClock {
+    interval_us: u64,
+    /// Cuándo toca el siguiente frame; `None` en reposo (el próximo sale en cuanto se pida)
+    next_us: Option<u64>,
+    last_us: Option<u64>,
+    stats: FrameStats,
+}
+
+impl FrameClock {
+    pub fn new(interval_us: u64) -> Self {
+        Self { interval_us, next_us: None, last_us: None, stats: FrameStats::default() }
+    }
+
+    /// Frame que toca en `now_us`, si ya llegó su hora
+    ///
+    /// Si se pasó más de un intervalo, los frames saltados cuentan como perdidos
+    /// y el siguiente se cita a partir de ahora.
+    pub fn poll(&mut self, now_us: u64) -> Option<FrameTick> {
+        let due = self.next_us.unwrap_or(now_us);
+        if now_us < due {
+            return None;
+        }
+        let skipped = (now_us - due) / self.interval_us;
+        self.stats.dropped += skipped;
+        self.next_us = Some(due + (skipped + 1) * self.interval_us);
+
+        let delta_us = self.last_us.map_or(0, |last| now_us - last);
+        if self.last_us.is_some() {
+            self.stats.total_delta_us += delta_us;
+            self.stats.deltas += 1;
+        }
+        self.last_us = Some(now_us);
+        Some(FrameTick { time_us: now_us, delta_us })
+    }
+
+    /// Anotar lo que tardó en dibujarse el último frame
+    pub fn record(&mut self, work_us: u64) {
+        let stats = &mut self.stats;
+        stats.min_us = if stats.frames == 0 { work_us } else { stats.min_us.min(work_us) };
+        stats.max_us = stats.max_us.max(work_us);
+        stats.last_us = work_us;
+        stats.total_us += work_us;
+        stats.frames += 1;
+    }
+
+    /// Pasar a reposo: sin frames pendientes no hay nada que medir entre uno y otro
+    pub fn pause(&mut self) {
+        self.next_us = None;
+        self.last_us = None;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+}
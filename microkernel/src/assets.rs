@@ -0,0 +1,24 @@
+//! FerroOS Mobile - Recursos embebidos en el kernel
+//!
+//! Los assets del paquete `.wpk` (`wpk/assets`) los incluye `build.rs` en una
+//! tabla generada; los recursos propios del kernel (fuentes) viven en
+//! `microkernel/resources`.
+
+include!(concat!(env!("OUT_DIR"), "/wpk_assets.rs"));
+
+/// Buscar un asset del paquete por su ruta relativa a `assets/` (p. ej. `"logo.bmp"`)
+pub fn wpk_asset(name: &str) -> Option<&'static [u8]> {
+    let name = name.trim_start_matches("./");
+    WPK_ASSETS.iter().find(|(path, _)| *path == name).map(|(_, data)| *data)
+}
+
+/// Fuentes bitmap del kernel: (nombre, datos BDF)
+///
+/// Subconjuntos de las fuentes misc-fixed de X11 (dominio público) con los
+/// rangos que dibuja el sistema: latín, puntuación, flechas, cajas y símbolos.
+pub static KERNEL_FONTS: &[(&str, &[u8])] = &[
+    ("fixed", include_bytes!("../resources/fonts/fixed-6x13.bdf")),
+    ("fixed", include_bytes!("../resources/fonts/fixed-9x18.bdf")),
+    ("fixed-bold", include_bytes!("../resources/fonts/fixed-bold-9x18.bdf")),
+];
+
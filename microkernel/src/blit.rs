@@ -0,0 +1,260 @@
+//! FerroOS Mobile - Relleno y copia rápidos de pixels
+//!
+//! Rutinas de bajo nivel de `FrameBuffer` para rellenar tramos de pixels con un
+//! valor ya codificado y copiar filas. Una vez alineado el destino a 16 bytes
+//! escriben 64 bytes por iteración: con registros NEON de 128 bits en AArch64 y
+//! con enteros de 128 bits en el resto de arquitecturas (el host).
+//!
+//! El kernel corre con la MMU desactivada, así que toda la memoria es de tipo
+//! Device y un acceso desalineado provoca una excepción: las cabeceras y colas
+//! se escriben pixel a pixel y solo se usan accesos anchos sobre direcciones
+//! alineadas a su tamaño.
+
+/// Alineación de los accesos anchos
+const ALIGN: usize = 16;
+
+/// Bytes por iteración del bucle principal (4 registros de 128 bits)
+const CHUNK: usize = 64;
+
+/// Valor crudo de un pixel repetido hasta llenar 64 bits
+#[inline]
+fn pattern(raw: u32, bytes_per_pixel: usize) -> u64 {
+    if bytes_per_pixel == 2 {
+        (raw as u16 as u64) * 0x0001_0001_0001_0001
+    } else {
+        (raw as u64) | ((raw as u64) << 32)
+    }
+}
+
+/// Escribir un pixel de 2 o 4 bytes
+///
+/// # Safety
+/// `dst` debe estar alineado al tamaño del pixel y ser escribible.
+#[inline]
+unsafe fn write_pixel(dst: *mut u8, raw: u32, bytes_per_pixel: usize) {
+    unsafe {
+        if bytes_per_pixel == 2 {
+            *(dst as *mut u16) = raw as u16;
+        } else {
+            *(dst as *mut u32) = raw;
+        }
+    }
+}
+
+/// Rellenar `count` pixels consecutivos desde `dst` con el valor crudo `raw`
+///
+/// # Safety
+/// `dst` debe estar alineado al tamaño del pixel (2 o 4 bytes) y tener sitio
+/// para `count` pixels.
+pub unsafe fn fill_span(dst: *mut u8, raw: u32, bytes_per_pixel: u32, count: usize) {
+    let bpp = bytes_per_pixel as usize;
+    let mut dst = dst;
+    let mut count = count;
+    unsafe {
+        // Cabecera pixel a pixel hasta la alineación de los accesos anchos
+        while count > 0 && !(dst as usize).is_multiple_of(ALIGN) {
+            write_pixel(dst, raw, bpp);
+            dst = dst.add(bpp);
+            count -= 1;
+        }
+        let body = (count * bpp) & !(ALIGN - 1);
+        fill_aligned(dst, pattern(raw, bpp), body);
+        dst = dst.add(body);
+        count -= body / bpp;
+        for _ in 0..count {
+            write_pixel(dst, raw, bpp);
+            dst = dst.add(bpp);
+        }
+    }
+}
+
+/// Copiar `bytes` bytes de `src` a `dst`, de principio a fin
+///
+/// Si las zonas se solapan `dst` debe estar antes que `src` (scroll hacia arriba).
+///
+/// # Safety
+/// Las dos zonas deben ser válidas para `bytes` bytes y estar alineadas al
+/// tamaño del pixel (2 o 4 bytes); `bytes` debe ser múltiplo de ese tamaño.
+pub unsafe fn copy_span(dst: *mut u8, src: *const u8, bytes_per_pixel: u32, bytes: usize) {
+    let bpp = bytes_per_pixel as usize;
+    unsafe {
+        // Con alineaciones distintas no hay accesos anchos posibles en los dos lados
+        if bytes < CHUNK || !((dst as usize) ^ (src as usize)).is_multiple_of(ALIGN) {
+            copy_pixels(dst, src, bpp, bytes);
+            return;
+        }
+        let head = ((ALIGN - dst as usize % ALIGN) % ALIGN).min(bytes);
+        copy_pixels(dst, src, bpp, head);
+        let body = (bytes - head) & !(ALIGN - 1);
+        copy_aligned(dst.add(head), src.add(head), body);
+        let done = head + body;
+        copy_pixels(dst.add(done), src.add(done), bpp, bytes - done);
+    }
+}
+
+/// Copiar `bytes` bytes pixel a pixel, hacia delante
+///
+/// Los accesos son volátiles para que el compilador no los convierta en una
+/// llamada a `memmove`, que no garantiza el tamaño ni la alineación de los accesos.
+///
+/// # Safety
+/// Las mismas condiciones que `copy_span`.
+#[inline]
+unsafe fn copy_pixels(dst: *mut u8, src: *const u8, bytes_per_pixel: usize, bytes: usize) {
+    unsafe {
+        if bytes_per_pixel == 2 {
+            let (d, s) = (dst as *mut u16, src as *const u16);
+            for i in 0..bytes / 2 {
+                d.add(i).write_volatile(s.add(i).read_volatile());
+            }
+        } else {
+            let (d, s) = (dst as *mut u32, src as *const u32);
+            for i in 0..bytes / 4 {
+                d.add(i).write_volatile(s.add(i).read_volatile());
+            }
+        }
+    }
+}
+
+/// Rellenar `bytes` bytes (múltiplo de 16) desde `dst`, alineado a 16, con `pattern`
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+unsafe fn fill_aligned(dst: *mut u8, pattern: u64, bytes: usize) {
+    use core::arch::aarch64::{vdupq_n_u64, vst1q_u64};
+    unsafe {
+        let value = vdupq_n_u64(pattern);
+        let mut p = dst as *mut u64;
+        let mut remaining = bytes;
+        while remaining >= CHUNK {
+            vst1q_u64(p, value);
+            vst1q_u64(p.add(2), value);
+            vst1q_u64(p.add(4), value);
+            vst1q_u64(p.add(6), value);
+            p = p.add(8);
+            remaining -= CHUNK;
+        }
+        while remaining > 0 {
+            vst1q_u64(p, value);
+            p = p.add(2);
+            remaining -= ALIGN;
+        }
+    }
+}
+
+/// Rellenar `bytes` bytes (múltiplo de 16) desde `dst`, alineado a 16, con `pattern`
+#[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+unsafe fn fill_aligned(dst: *mut u8, pattern: u64, bytes: usize) {
+    let value = ((pattern as u128) << 64) | pattern as u128;
+    let mut p = dst as *mut u128;
+    let mut remaining = bytes;
+    unsafe {
+        while remaining >= CHUNK {
+            p.write(value);
+            p.add(1).write(value);
+            p.add(2).write(value);
+            p.add(3).write(value);
+            p = p.add(4);
+            remaining -= CHUNK;
+        }
+        while remaining > 0 {
+            p.write(value);
+            p = p.add(1);
+            remaining -= ALIGN;
+        }
+    }
+}
+
+/// Copiar `bytes` bytes (múltiplo de 16) entre zonas alineadas a 16, hacia delante
+///
+/// Cada bloque se lee entero antes de escribirlo, así que vale para zonas
+/// solapadas con `dst` antes que `src`.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+unsafe fn copy_aligned(dst: *mut u8, src: *const u8, bytes: usize) {
+    use core::arch::aarch64::{vld1q_u8, vst1q_u8};
+    let (mut d, mut s) = (dst, src);
+    let mut remaining = bytes;
+    unsafe {
+        while remaining >= CHUNK {
+            let a = vld1q_u8(s);
+            let b = vld1q_u8(s.add(16));
+            let c = vld1q_u8(s.add(32));
+            let e = vld1q_u8(s.add(48));
+            vst1q_u8(d, a);
+            vst1q_u8(d.add(16), b);
+            vst1q_u8(d.add(32), c);
+            vst1q_u8(d.add(48), e);
+            d = d.add(CHUNK);
+            s = s.add(CHUNK);
+            remaining -= CHUNK;
+        }
+        while remaining > 0 {
+            vst1q_u8(d, vld1q_u8(s));
+            d = d.add(ALIGN);
+            s = s.add(ALIGN);
+            remaining -= ALIGN;
+        }
+    }
+}
+
+/// Copiar `bytes` bytes (múltiplo de 16) entre zonas alineadas a 16, hacia delante
+///
+/// Cada bloque se lee entero antes de escribirlo, así que vale para zonas
+/// solapadas con `dst` antes que `src`.
+#[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+unsafe fn copy_aligned(dst: *mut u8, src: *const u8, bytes: usize) {
+    let (mut d, mut s) = (dst as *mut u128, src as *const u128);
+    let mut remaining = bytes;
+    unsafe {
+        while remaining >= CHUNK {
+            let block = [s.read(), s.add(1).read(), s.add(2).read(), s.add(3).read()];
+            for (i, value) in block.into_iter().enumerate() {
+                d.add(i).write(value);
+            }
+            d = d.add(4);
+            s = s.add(4);
+            remaining -= CHUNK;
+        }
+        while remaining > 0 {
+            d.write(s.read());
+            d = d.add(1);
+            s = s.add(1);
+            remaining -= ALIGN;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn fills_spans_at_any_offset() {
+        for offset in 0..8 {
+            for count in [0, 1, 7, 33, 100] {
+                let mut buffer = [0u32; 128];
+                unsafe { fill_span(buffer.as_mut_ptr().add(offset).cast(), 0xAABBCCDD, 4, count) };
+                assert!(buffer[offset..offset + count].iter().all(|&p| p == 0xAABBCCDD));
+                assert!(buffer[..offset].iter().chain(&buffer[offset + count..]).all(|&p| p == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn copies_spans_forward_with_overlap() {
+        for bpp in [2usize, 4] {
+            for (dst_offset, src_offset) in [(0, 16), (2, 18), (0, 3), (1, 40)] {
+                let mut buffer: Vec<u32> = (0..256).collect();
+                let expected: Vec<u32> = buffer.clone();
+                let base = buffer.as_mut_ptr().cast::<u8>();
+                let (dst, src) = (dst_offset * bpp, src_offset * bpp);
+                let bytes = 150 * bpp;
+                unsafe { copy_span(base.add(dst), base.add(src), bpp as u32, bytes) };
+
+                let bytes_view = unsafe { core::slice::from_raw_parts(base, 1024) };
+                let original = unsafe { core::slice::from_raw_parts(expected.as_ptr().cast::<u8>(), 1024) };
+                assert_eq!(&bytes_view[dst..dst + bytes], &original[src..src + bytes], "bpp {} {}→{}", bpp, src, dst);
+                assert_eq!(&bytes_view[dst + bytes..], &original[dst + bytes..]);
+            }
+        }
+    }
+}
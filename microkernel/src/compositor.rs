@@ -0,0 +1,206 @@
+//! FerroOS Mobile - Compositor de superficies
+//!
+//! Cada app y cada elemento del sistema (shell, barra de estado, notificaciones)
+//! dibuja en su propia superficie fuera de pantalla. En cada `present()` el
+//! compositor copia a la pantalla, por orden de z, solo la zona que ha cambiado:
+//! así una app nunca pisa a otra ni a la interfaz del sistema.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+
+use crate::graphics::{self, FrameBuffer};
+use crate::theme;
+
+/// Orden de apilado de las superficies del sistema (mayor = más arriba)
+pub const Z_SHELL: i32 = 0;
+pub const Z_APP: i32 = 10;
+pub const Z_STATUS_BAR: i32 = 100;
+pub const Z_NOTIFICATIONS: i32 = 200;
+
+/// Identificador de una superficie del compositor
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SurfaceId(u32);
+
+/// Superficie fuera de pantalla con su posición y orden de apilado
+struct Surface {
+    id: SurfaceId,
+    name: String,
+    /// Posición y tamaño en pantalla
+    frame: Rectangle,
+    z: i32,
+    visible: bool,
+    /// `None` mientras el `GraphicsManager` la tiene activa para dibujar
+    canvas: Option<FrameBuffer>,
+    /// Cursor de texto guardado mientras la superficie no está activa
+    cursor: Point,
+}
+
+pub struct Compositor {
+    screen: FrameBuffer,
+    /// Superficies ordenadas de abajo arriba
+    surfaces: Vec<Surface>,
+    next_id: u32,
+    /// Zona de pantalla a recomponer por cambios de superficies (crear, mostrar, apilar...)
+    damage: Option<Rectangle>,
+}
+
+impl Compositor {
+    pub fn new(screen: FrameBuffer) -> Self {
+        Self { screen, surfaces: Vec::new(), next_id: 0, damage: None }
+    }
+
+    /// Framebuffer de la pantalla, para consultar el modo de vídeo
+    pub fn screen(&self) -> &FrameBuffer {
+        &self.screen
+    }
+
+    /// Crear una superficie visible en `frame` (recortado a la pantalla)
+    ///
+    /// Devuelve `None` si el rectángulo queda vacío o no hay memoria para el buffer.
+    pub fn create_surface(&mut self, name: &str, frame: Rectangle, z: i32, cursor: Point) -> Option<SurfaceId> {
+        let frame = frame.intersection(&self.screen.bounds());
+        if frame.is_zero_sized() {
+            return None;
+        }
+        let mut canvas = FrameBuffer::offscreen(frame.size.width, frame.size.height, self.screen.format(), self.screen.depth())?;
+        // La superficie entera se compone al mostrarse: no hace falta su daño inicial
+        canvas.take_damage();
+
+        let id = SurfaceId(self.next_id);
+        self.next_id += 1;
+        self.insert(Surface { id, name: String::from(name), frame, z, visible: true, canvas: Some(canvas), cursor });
+        self.add_damage(frame);
+        Some(id)
+    }
+
+    /// Eliminar una superficie y liberar su buffer; su zona se recompone en el próximo `present()`
+    pub fn destroy_surface(&mut self, id: SurfaceId) {
+        if let Some(index) = self.index(id) {
+            let surface = self.surfaces.remove(index);
+            if surface.visible {
+                self.add_damage(surface.frame);
+            }
+        }
+    }
+
+    pub fn set_visible(&mut self, id: SurfaceId, visible: bool) {
+        if let Some(index) = self.index(id) {
+            let surface = &mut self.surfaces[index];
+            if surface.visible != visible {
+                surface.visible = visible;
+                let frame = surface.frame;
+                self.add_damage(frame);
+            }
+        }
+    }
+
+    pub fn is_visible(&self, id: SurfaceId) -> bool {
+        self.index(id).is_some_and(|index| self.surfaces[index].visible)
+    }
+
+    /// Subir la superficie por encima de las de su mismo z
+    pub fn raise(&mut self, id: SurfaceId) {
+        if let Some(index) = self.index(id) {
+            let surface = self.surfaces.remove(index);
+            if surface.visible {
+                self.add_damage(surface.frame);
+            }
+            self.insert(surface);
+        }
+    }
+
+    /// Posición de la superficie en pantalla
+    pub fn frame(&self, id: SurfaceId) -> Option<Rectangle> {
+        self.index(id).map(|index| self.surfaces[index].frame)
+    }
+
+    /// Superficies de abajo arriba: (nombre, posición, z, visible)
+    pub fn surfaces(&self) -> impl Iterator<Item = (&str, Rectangle, i32, bool)> {
+        self.surfaces.iter().map(|s| (s.name.as_str(), s.frame, s.z, s.visible))
+    }
+
+    /// Sacar el buffer de una superficie para dibujar en él, con su cursor guardado
+    pub fn take_canvas(&mut self, id: SurfaceId) -> Option<(FrameBuffer, Point)> {
+        let index = self.index(id)?;
+        let surface = &mut self.surfaces[index];
+        surface.canvas.take().map(|canvas| (canvas, surface.cursor))
+    }
+
+    /// Devolver el buffer de una superficie junto con su cursor de texto
+    pub fn put_canvas(&mut self, id: SurfaceId, canvas: FrameBuffer, cursor: Point) {
+        if let Some(index) = self.index(id) {
+            let surface = &mut self.surfaces[index];
+            surface.canvas = Some(canvas);
+            surface.cursor = cursor;
+        }
+    }
+
+    /// Benchmark de relleno y copia sobre la memoria de vídeo; la pantalla entera
+    /// se recompone en el próximo `present()`
+    pub fn benchmark_screen(&mut self) -> Vec<graphics::BenchmarkResult> {
+        let results = self.screen.benchmark_scanout();
+        self.screen.take_damage();
+        self.add_damage(self.screen.bounds());
+        results
+    }
+
+    /// Recomponer la zona dañada y mostrar el frame
+    ///
+    /// `active` es la superficie que el `GraphicsManager` tiene fuera para dibujar.
+    pub fn present(&mut self, active: SurfaceId, canvas: &mut FrameBuffer) {
+        self.compose(active, canvas);
+        self.screen.present();
+    }
+
+    fn compose(&mut self, active: SurfaceId, active_canvas: &mut FrameBuffer) {
+        // Daño de cada superficie, pasado a coordenadas de pantalla
+        let mut damage = self.damage.take();
+        for surface in self.surfaces.iter_mut() {
+            let canvas = if surface.id == active { Some(&mut *active_canvas) } else { surface.canvas.as_mut() };
+            let Some(local) = canvas.and_then(|canvas| canvas.take_damage()) else {
+                continue;
+            };
+            if surface.visible {
+                let rect = Rectangle::new(surface.frame.top_left + local.top_left, local.size);
+                damage = Some(damage.map_or(rect, |damage| graphics::union(damage, rect)));
+            }
+        }
+        let Some(damage) = damage else {
+            return;
+        };
+        let damage = damage.intersection(&self.screen.bounds());
+        if damage.is_zero_sized() {
+            return;
+        }
+
+        let (x, y) = (damage.top_left.x as u32, damage.top_left.y as u32);
+        self.screen.fill_rect(x, y, damage.size.width, damage.size.height, theme::current().background);
+        for surface in self.surfaces.iter().filter(|surface| surface.visible) {
+            let area = damage.intersection(&surface.frame);
+            if area.is_zero_sized() {
+                continue;
+            }
+            let canvas = if surface.id == active { Some(&*active_canvas) } else { surface.canvas.as_ref() };
+            if let Some(canvas) = canvas {
+                let local = Rectangle::new(area.top_left - surface.frame.top_left, area.size);
+                self.screen.copy_from(canvas, local, area.top_left);
+            }
+        }
+        // El daño de la pantalla queda para que `FrameBuffer::present` copie solo esta zona
+    }
+
+    fn index(&self, id: SurfaceId) -> Option<usize> {
+        self.surfaces.iter().position(|surface| surface.id == id)
+    }
+
+    /// Insertar por encima de todas las superficies con z menor o igual
+    fn insert(&mut self, surface: Surface) {
+        let position = self.surfaces.iter().position(|other| other.z > surface.z).unwrap_or(self.surfaces.len());
+        self.surfaces.insert(position, surface);
+    }
+
+    fn add_damage(&mut self, rect: Rectangle) {
+        self.damage = Some(self.damage.map_or(rect, |damage| graphics::union(damage, rect)));
+    }
+}
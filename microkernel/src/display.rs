@@ -0,0 +1,522 @@
+//! FerroOS Mobile - Pantallas
+//!
+//! El framebuffer no habla directamente con el hardware: dibuja en la memoria
+//! que le da una `Display` y le avisa al presentar. En la Raspberry Pi es el
+//! framebuffer que asigna el firmware por el mailbox; en los tests del host,
+//! un buffer en memoria cuyos pixels se comparan.
+
+use alloc::vec::Vec;
+
+use crate::graphics::PixelFormat;
+use crate::mailbox;
+use crate::uart_send_str;
+use fos_microkernel::print_number;
+
+#[cfg(test)]
+use embedded_graphics::pixelcolor::Rgb888;
+
+/// Resolución de pantalla por defecto (Safe Mode para QEMU), usada si falla la negociación
+pub const SCREEN_WIDTH: u32 = 640;
+pub const SCREEN_HEIGHT: u32 = 480;
+
+/// Modos que se prueban, en orden, después del tamaño físico del display
+const PREFERRED_MODES: &[(u32, u32)] = &[(800, 600), (1024, 768), (SCREEN_WIDTH, SCREEN_HEIGHT)];
+
+/// Mayor modo que se acepta del tamaño físico (limita la memoria de las dos páginas)
+const MAX_MODE: (u32, u32) = (1920, 1080);
+
+/// Dirección del framebuffer cuando el mailbox no asigna uno (QEMU)
+const FALLBACK_FRAMEBUFFER: u32 = 0x3C10_0000;
+
+/// Modo de vídeo de una pantalla
+#[derive(Clone, Copy)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    /// Bits por pixel
+    pub depth: u32,
+    pub pitch: u32,
+    pub format: PixelFormat,
+    /// La memoria de la pantalla tiene dos páginas y `flip` cambia la visible
+    pub page_flip: bool,
+}
+
+impl DisplayMode {
+    /// Modo seguro de QEMU: 640x480 RGB565 sin page flipping
+    fn fallback() -> Self {
+        Self {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            depth: 16,
+            pitch: SCREEN_WIDTH * 2,
+            format: PixelFormat::Rgb565,
+            page_flip: false,
+        }
+    }
+}
+
+/// Destino final de lo que dibuja el framebuffer
+pub trait Display {
+    /// Nombre para el log de arranque
+    fn name(&self) -> &'static str;
+
+    fn mode(&self) -> DisplayMode;
+
+    /// Memoria de la primera página (`mode().pitch * mode().height` bytes, o el doble con page flip)
+    fn scanout(&mut self) -> *mut u8;
+
+    /// Mostrar la página `page` (0 o 1); `false` si no se pudo cambiar
+    fn flip(&mut self, _page: u32) -> bool {
+        false
+    }
+
+    /// El framebuffer terminó de escribir un frame en la página visible
+    fn frame_ready(&mut self) {}
+
+    /// Color del pixel (x, y) que se ve ahora, si la pantalla permite leerlo
+    #[cfg(test)]
+    fn pixel(&self, _x: u32, _y: u32) -> Option<Rgb888> {
+        None
+    }
+}
+
+/// Framebuffer que asigna el firmware de la GPU por el mailbox
+pub struct MailboxDisplay {
+    mode: DisplayMode,
+    address: u32,
+}
+
+impl MailboxDisplay {
+    /// Negociar el modo con el firmware: el tamaño físico del display y luego
+    /// `PREFERRED_MODES`; si ninguno se asigna, el modo seguro de QEMU
+    pub fn new() -> Self {
+        // Tamaño físico del display y modos preferidos, en orden
+        let mut modes: Vec<(u32, u32)> = Vec::new();
+        if let Ok(Some((width, height))) = mailbox::get_physical_display_size() {
+            uart_send_str("  Display físico: ");
+            print_number(width as u64);
+            uart_send_str("x");
+            print_number(height as u64);
+            uart_send_str("\n");
+            if width <= MAX_MODE.0 && height <= MAX_MODE.1 {
+                modes.push((width, height));
+            }
+        }
+        for mode in PREFERRED_MODES {
+            if !modes.contains(mode) {
+                modes.push(*mode);
+            }
+        }
+
+        let mut negotiated = None;
+        for (width, height) in modes {
+            uart_send_str("  Probando modo ");
+            print_number(width as u64);
+            uart_send_str("x");
+            print_number(height as u64);
+            uart_send_str("...\n");
+            if let Some(display) = Self::request_mode(width, height) {
+                negotiated = Some(display);
+                break;
+            }
+        }
+
+        let display = negotiated.unwrap_or_else(|| {
+            uart_send_str("  ⚠️ Ningún modo asignado (QEMU VC4 falla allocation).\n");
+            uart_send_str("  🔧 Usando dirección fallback: 0x3C100000 (640x480, 16-bit)\n");
+            Self { mode: DisplayMode::fallback(), address: FALLBACK_FRAMEBUFFER }
+        });
+        uart_send_str("  Framebuffer Address: ");
+        print_number(display.address as u64);
+        uart_send_str("\n");
+        display
+    }
+
+    /// Pedir al firmware un framebuffer de `width`x`height` a 32 bits con dos páginas
+    ///
+    /// Devuelve `None` si el mailbox falla, no asigna memoria o ajusta el modo a otro tamaño.
+    fn request_mode(width: u32, height: u32) -> Option<Self> {
+        // Define the mailbox message buffer
+        // The buffer must be 16-byte aligned
+        #[repr(C, align(16))]
+        struct MailboxBuffer {
+            header: mailbox::MailboxMessage,
+            // Set physical display size
+            tag_set_physical_display_size: u32,
+            value_buf_size_set_physical_display_size: u32,
+            request_response_code_set_physical_display_size: u32,
+            width: u32,
+            height: u32,
+            // Set virtual display size
+            tag_set_virtual_display_size: u32,
+            value_buf_size_set_virtual_display_size: u32,
+            request_response_code_set_virtual_display_size: u32,
+            vwidth: u32,
+            vheight: u32,
+            // Set depth
+            tag_set_depth: u32,
+            value_buf_size_set_depth: u32,
+            request_response_code_set_depth: u32,
+            depth: u32,
+            // Set pixel order
+            tag_set_pixel_order: u32,
+            value_buf_size_set_pixel_order: u32,
+            request_response_code_set_pixel_order: u32,
+            pixel_order: u32,
+            // Get alpha mode
+            tag_get_alpha_mode: u32,
+            value_buf_size_get_alpha_mode: u32,
+            request_response_code_get_alpha_mode: u32,
+            alpha_mode: u32,
+            // Allocate buffer
+            tag_allocate_buffer: u32,
+            value_buf_size_allocate_buffer: u32,
+            request_response_code_allocate_buffer: u32,
+            alignment: u32,
+            // Response values for allocate buffer
+            fb_address: u32,
+            fb_size: u32,
+            // Get pitch
+            tag_get_pitch: u32,
+            value_buf_size_get_pitch: u32,
+            request_response_code_get_pitch: u32,
+            pitch: u32,
+            // End tag
+            end_tag: u32,
+        }
+
+        let mut mbox_buffer = MailboxBuffer {
+            header: mailbox::MailboxMessage {
+                buffer_size: core::mem::size_of::<MailboxBuffer>() as u32,
+                request_response_code: mailbox::MBOX_REQUEST,
+            },
+            // Set physical display size
+            tag_set_physical_display_size: mailbox::MBOX_TAG_SET_PHYSICAL_DISPLAY_SIZE,
+            value_buf_size_set_physical_display_size: 8,
+            request_response_code_set_physical_display_size: 8,
+            width,
+            height,
+            // Set virtual display size
+            tag_set_virtual_display_size: mailbox::MBOX_TAG_SET_VIRTUAL_DISPLAY_SIZE,
+            value_buf_size_set_virtual_display_size: 8,
+            request_response_code_set_virtual_display_size: 8,
+            vwidth: width,
+            // Dos páginas para el doble buffer por page flipping
+            vheight: height * 2,
+            // Set depth
+            tag_set_depth: mailbox::MBOX_TAG_SET_DEPTH,
+            value_buf_size_set_depth: 4,
+            request_response_code_set_depth: 4,
+            depth: 32, // 32 bits per pixel
+            // Set pixel order (the firmware answers with the order it actually uses)
+            tag_set_pixel_order: mailbox::MBOX_TAG_SET_PIXEL_ORDER,
+            value_buf_size_set_pixel_order: 4,
+            request_response_code_set_pixel_order: 4,
+            pixel_order: mailbox::MBOX_PIXEL_ORDER_BGR,
+            // Get alpha mode
+            tag_get_alpha_mode: mailbox::MBOX_TAG_GET_ALPHA_MODE,
+            value_buf_size_get_alpha_mode: 4,
+            request_response_code_get_alpha_mode: 0,
+            alpha_mode: 0,
+            // Allocate buffer
+            tag_allocate_buffer: mailbox::MBOX_TAG_ALLOCATE_BUFFER,
+            value_buf_size_allocate_buffer: 8,
+            request_response_code_allocate_buffer: 4, // Send 4 bytes (alignment)
+            alignment: 4096, // 4096-byte alignment
+            fb_address: 0,
+            fb_size: 0,
+            // Get pitch
+            tag_get_pitch: mailbox::MBOX_TAG_GET_PITCH,
+            value_buf_size_get_pitch: 4,
+            request_response_code_get_pitch: 0,
+            pitch: 0,
+            // End tag
+            end_tag: 0,
+        };
+
+        uart_send_str("  Enviando mensaje al mailbox...\n");
+        if mailbox::send_mailbox_message(&mut mbox_buffer.header).is_err() {
+            uart_send_str("  ❌ Error al enviar mensaje al mailbox.\n");
+            return None;
+        }
+
+        // The GPU returns the address with the high bit set if it's a cached address.
+        // We need to clear it to get the physical address.
+        let address = mbox_buffer.fb_address & 0x3FFF_FFFF;
+        if address == 0 || mbox_buffer.width != width || mbox_buffer.height != height {
+            return None;
+        }
+        uart_send_str("  ✅ Mensaje de mailbox enviado y procesado.\n");
+
+        let pitch = mbox_buffer.pitch;
+        // El page flipping necesita la segunda página y el tag de offset virtual
+        let page_flip = mbox_buffer.vheight >= height * 2
+            && mbox_buffer.fb_size >= pitch * height * 2
+            && mailbox::set_virtual_offset(0, 0) == Ok(true);
+
+        let mode = DisplayMode {
+            width,
+            height,
+            depth: mbox_buffer.depth,
+            pitch,
+            format: PixelFormat::from_mailbox(mbox_buffer.depth, mbox_buffer.pixel_order, mbox_buffer.alpha_mode),
+            page_flip,
+        };
+        Some(Self { mode, address })
+    }
+}
+
+impl Display for MailboxDisplay {
+    fn name(&self) -> &'static str {
+        "mailbox"
+    }
+
+    fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    fn scanout(&mut self) -> *mut u8 {
+        self.address as *mut u8
+    }
+
+    fn flip(&mut self, page: u32) -> bool {
+        mailbox::set_virtual_offset(0, page * self.mode.height) == Ok(true)
+    }
+}
+
+/// Pantalla en el heap, sin hardware: para renderizar y comparar pixels en el host
+#[cfg(test)]
+pub struct MemoryDisplay {
+    mode: DisplayMode,
+    pixels: Vec<u8>,
+    /// Página que se ve (siempre 0 sin page flip)
+    page: u32,
+}
+
+#[cfg(test)]
+impl MemoryDisplay {
+    /// Pantalla de `width`x`height` en `format`, en negro y con una sola página
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        let pitch = width * format.bytes_per_pixel();
+        let mode = DisplayMode {
+            width,
+            height,
+            depth: format.bytes_per_pixel() * 8,
+            pitch,
+            format,
+            page_flip: false,
+        };
+        let pixels = alloc::vec![0; (pitch * height) as usize];
+        Self { mode, pixels, page: 0 }
+    }
+
+    /// Como `new`, pero con dos páginas entre las que se cambia con `flip`
+    pub fn with_pages(width: u32, height: u32, format: PixelFormat) -> Self {
+        let mut display = Self::new(width, height, format);
+        display.mode.page_flip = true;
+        display.pixels.resize(display.pixels.len() * 2, 0);
+        display
+    }
+}
+
+#[cfg(test)]
+impl Display for MemoryDisplay {
+    fn name(&self) -> &'static str {
+        "memoria"
+    }
+
+    fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    fn scanout(&mut self) -> *mut u8 {
+        self.pixels.as_mut_ptr()
+    }
+
+    fn flip(&mut self, page: u32) -> bool {
+        if !self.mode.page_flip || page > 1 {
+            return false;
+        }
+        self.page = page;
+        true
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Option<Rgb888> {
+        if x >= self.mode.width || y >= self.mode.height {
+            return None;
+        }
+        let bpp = self.mode.format.bytes_per_pixel() as usize;
+        let offset = ((self.page * self.mode.height + y) * self.mode.pitch) as usize + x as usize * bpp;
+        let bytes = &self.pixels[offset..offset + bpp];
+        let raw = bytes.iter().rev().fold(0u32, |raw, byte| (raw << 8) | *byte as u32);
+        Some(self.mode.format.decode(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use embedded_graphics::pixelcolor::RgbColor;
+    use embedded_graphics::prelude::{Point, Size};
+    use embedded_graphics::primitives::Rectangle;
+
+    use crate::graphics::GraphicsManager;
+    use crate::theme;
+
+    fn graphics(format: PixelFormat) -> GraphicsManager {
+        GraphicsManager::with_display(Box::new(MemoryDisplay::new(320, 240, format)))
+    }
+
+    #[test]
+    fn presents_the_cleared_screen() {
+        let graphics = graphics(PixelFormat::Bgra8888);
+        let background = theme::current().background;
+        assert_eq!(graphics.display_pixel(0, 239), Some(background));
+        assert_eq!(graphics.display_pixel(319, 100), Some(background));
+        assert_eq!(graphics.display_pixel(320, 0), None);
+    }
+
+    #[test]
+    fn renders_a_filled_rectangle() {
+        for format in [PixelFormat::Rgb565, PixelFormat::Bgra8888, PixelFormat::Rgba8888, PixelFormat::Xrgb8888] {
+            let mut graphics = graphics(format);
+            graphics.set_color(Rgb888::RED);
+            graphics.draw_rect(10, 50, 20, 10, true);
+            graphics.present();
+
+            let background = theme::current().background;
+            assert_eq!(graphics.display_pixel(10, 50), Some(Rgb888::RED), "{}", format.name());
+            assert_eq!(graphics.display_pixel(29, 59), Some(Rgb888::RED), "{}", format.name());
+            assert_eq!(graphics.display_pixel(30, 59), Some(background), "{}", format.name());
+            assert_eq!(graphics.display_pixel(29, 60), Some(background), "{}", format.name());
+        }
+    }
+
+    #[test]
+    fn clips_drawing_to_the_viewport() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        graphics.set_viewport(Rectangle::new(Point::new(100, 100), Size::new(50, 50)));
+        graphics.set_color(Rgb888::GREEN);
+        graphics.draw_rect(-10, -10, 100, 100, true);
+        graphics.present();
+
+        let background = theme::current().background;
+        assert_eq!(graphics.display_pixel(100, 100), Some(Rgb888::GREEN));
+        assert_eq!(graphics.display_pixel(149, 149), Some(Rgb888::GREEN));
+        assert_eq!(graphics.display_pixel(99, 120), Some(background));
+        assert_eq!(graphics.display_pixel(150, 120), Some(background));
+    }
+
+    #[test]
+    fn keeps_earlier_frames_when_presenting_only_the_damage() {
+        let displays: [Box<dyn Display>; 2] = [
+            Box::new(MemoryDisplay::new(320, 240, PixelFormat::Bgra8888)),
+            Box::new(MemoryDisplay::with_pages(320, 240, PixelFormat::Bgra8888)),
+        ];
+        for display in displays {
+            let mut graphics = GraphicsManager::with_display(display);
+            let buffering = graphics.screen().buffering().name();
+            let rects = [(10, 50, Rgb888::RED), (200, 60, Rgb888::GREEN), (100, 150, Rgb888::BLUE)];
+            for (index, (x, y, color)) in rects.into_iter().enumerate() {
+                graphics.set_color(color);
+                graphics.draw_rect(x, y, 20, 10, true);
+                graphics.present();
+                // Un frame sin cambios no debe mostrar una página desactualizada
+                graphics.present();
+                for (x, y, color) in &rects[..=index] {
+                    assert_eq!(graphics.display_pixel(*x as u32 + 5, *y as u32 + 5), Some(*color), "{}", buffering);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn presents_each_primitive_whole() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        graphics.set_color(Rgb888::RED);
+        graphics.draw_circle(100, 100, 20, true, 1);
+        graphics.draw_line(200, 20, 260, 80, 1);
+        graphics.draw_polygon(&[(20, 180), (60, 180), (40, 220)], true, 1);
+        graphics.present();
+
+        for (x, y) in [(80, 100), (120, 100), (100, 80), (100, 120), (200, 20), (230, 50), (260, 80), (21, 181), (40, 215)] {
+            assert_eq!(graphics.display_pixel(x, y), Some(Rgb888::RED), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn clamps_huge_radii() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        graphics.set_color(Rgb888::RED);
+        graphics.draw_circle(160, 120, u32::MAX, true, 1);
+        graphics.draw_arc(160, 120, u32::MAX, 0.0, 90.0, 1);
+        graphics.present();
+        assert_eq!(graphics.display_pixel(0, 0), Some(Rgb888::RED));
+        assert_eq!(graphics.display_pixel(319, 239), Some(Rgb888::RED));
+    }
+
+    #[test]
+    fn destroying_a_surface_uncovers_the_ones_below() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        let surface = graphics.create_surface("app", graphics.app_area(), crate::compositor::Z_APP).expect("superficie");
+        graphics.set_surface(surface);
+        graphics.set_color(Rgb888::BLUE);
+        graphics.draw_rect(0, 0, 40, 40, true);
+        graphics.present();
+        let origin = graphics.app_area().top_left;
+        let (x, y) = (origin.x as u32 + 5, origin.y as u32 + 5);
+        assert_eq!(graphics.display_pixel(x, y), Some(Rgb888::BLUE));
+
+        graphics.destroy_surface(surface);
+        graphics.present();
+        assert_eq!(graphics.surfaces().filter(|(name, ..)| *name == "app").count(), 0);
+        assert_ne!(graphics.display_pixel(x, y), Some(Rgb888::BLUE));
+    }
+
+    #[test]
+    fn renders_builtin_font_glyphs() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        graphics.set_color(Rgb888::WHITE);
+        graphics.draw_text_at("|", 100, 100);
+        graphics.present();
+
+        let lit = (100..108)
+            .flat_map(|x| (100..108).map(move |y| (x, y)))
+            .filter(|&(x, y)| graphics.display_pixel(x, y) == Some(Rgb888::WHITE))
+            .count();
+        assert!(lit > 0 && lit < 64, "pixels encendidos: {}", lit);
+    }
+
+    #[test]
+    fn draws_combining_marks_precomposed() {
+        let mut graphics = graphics(PixelFormat::Bgra8888);
+        graphics.set_color(Rgb888::WHITE);
+        for (row, (decomposed, composed)) in [("e\u{301}x", "éx"), ("i\u{308}x", "ïx"), ("N\u{303}x", "Ñx")].into_iter().enumerate() {
+            assert_eq!(graphics.text_size(decomposed), graphics.text_size(composed));
+            let y = 20 + row as i32 * 20;
+            graphics.draw_text_at(decomposed, 100, y);
+            graphics.draw_text_at(composed, 200, y);
+        }
+        graphics.present();
+
+        for y in 20..68 {
+            for x in 0..16 {
+                assert_eq!(graphics.display_pixel(100 + x, y), graphics.display_pixel(200 + x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn measures_wrapped_text_with_the_current_font() {
+        let graphics = graphics(PixelFormat::Bgra8888);
+        let (advance, height) = graphics.text_size("a");
+        assert!(advance > 0 && height > 0);
+
+        assert_eq!(graphics.measure_text("", 100, 2), (0, height, 1));
+        assert_eq!(graphics.measure_text("ab cd", 0, 2), (5 * advance, height, 1));
+        assert_eq!(graphics.measure_text("ab cd", 5 * advance, 2), (5 * advance, height, 1));
+        assert_eq!(graphics.measure_text("ab cd", 5 * advance - 1, 2), (2 * advance, 2 * height + 2, 2));
+        assert_eq!(graphics.measure_text("abcdef", 4 * advance, 0), (4 * advance, 2 * height, 2));
+    }
+}
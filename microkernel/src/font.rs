@@ -0,0 +1,741 @@
+//! FerroOS Mobile - Fuente bitmap 8x8 con cobertura UTF-8
+//!
+//! Cubre ASCII imprimible, Latin-1, Latin Extended-A, puntuación tipográfica,
+//! flechas, bloques y dibujo de cajas. Las letras con diacrítico se componen a
+//! partir de la letra base y el acento; los caracteres sin glifo se dibujan con
+//! un glifo de sustitución visible en lugar de quedar en blanco.
+//!
+//! Además de la fuente integrada se pueden cargar fuentes bitmap PSF2 y BDF
+//! (recursos del kernel o assets del `.wpk`) en un [`FontRegistry`].
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Glifo de 8x8: una fila por byte, el bit más alto es la columna izquierda
+pub type Glyph = [u8; 8];
+
+/// Ancho y alto de una celda de texto en pixels
+pub const GLYPH_WIDTH: i32 = 8;
+pub const GLYPH_HEIGHT: i32 = 8;
+
+/// Nombre de la fuente 8x8 integrada
+pub const BUILTIN_FONT: &str = "system";
+/// Fuente de 8x8 básica (ASCII 32-127)
+const FONT_8X8: [u8; 768] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Space (32)
+    0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00, // !
+    0x66, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, // "
+    0x6C, 0x6C, 0xFE, 0x6C, 0xFE, 0x6C, 0x6C, 0x00, // #
+    0x18, 0x3E, 0x60, 0x3C, 0x06, 0x7C, 0x18, 0x00, // $
+    0x00, 0xC6, 0xCC, 0x18, 0x30, 0x66, 0xC6, 0x00, // %
+    0x38, 0x6C, 0x38, 0x76, 0xDC, 0xCC, 0x76, 0x00, // &
+    0x18, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, // '
+    0x0C, 0x18, 0x30, 0x30, 0x30, 0x18, 0x0C, 0x00, // (
+    0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x18, 0x30, 0x00, // )
+    0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00, // *
+    0x00, 0x18, 0x18, 0x7E, 0x18, 0x18, 0x00, 0x00, // +
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x30, // ,
+    0x00, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00, // -
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, // .
+    0x00, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x00, 0x00, // /
+    0x3C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // 0 (48)
+    0x18, 0x38, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // 1
+    0x3C, 0x66, 0x06, 0x0C, 0x30, 0x60, 0x7E, 0x00, // 2
+    0x3C, 0x66, 0x06, 0x1C, 0x06, 0x66, 0x3C, 0x00, // 3
+    0x0C, 0x1C, 0x3C, 0x6C, 0xCC, 0xFE, 0x0C, 0x00, // 4
+    0x7E, 0x60, 0x7C, 0x06, 0x06, 0x66, 0x3C, 0x00, // 5
+    0x3C, 0x60, 0xFC, 0x66, 0x66, 0x66, 0x3C, 0x00, // 6
+    0x7E, 0x06, 0x0C, 0x18, 0x30, 0x30, 0x30, 0x00, // 7
+    0x3C, 0x66, 0x66, 0x3C, 0x66, 0x66, 0x3C, 0x00, // 8
+    0x3C, 0x66, 0x66, 0x66, 0x3E, 0x06, 0x3C, 0x00, // 9
+    0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x00, // :
+    0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x30, // ;
+    0x0C, 0x18, 0x30, 0x60, 0x30, 0x18, 0x0C, 0x00, // <
+    0x00, 0x00, 0x7E, 0x00, 0x7E, 0x00, 0x00, 0x00, // =
+    0x30, 0x18, 0x0C, 0x06, 0x0C, 0x18, 0x30, 0x00, // >
+    0x3C, 0x66, 0x06, 0x0C, 0x18, 0x00, 0x18, 0x00, // ?
+    0x3C, 0x66, 0x6E, 0x6E, 0x60, 0x62, 0x3C, 0x00, // @
+    0x18, 0x3C, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x00, // A
+    0xFC, 0x66, 0x66, 0x7C, 0x66, 0x66, 0xFC, 0x00, // B
+    0x3C, 0x66, 0x60, 0x60, 0x60, 0x66, 0x3C, 0x00, // C
+    0xF8, 0x6C, 0x66, 0x66, 0x66, 0x6C, 0xF8, 0x00, // D
+    0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x7E, 0x00, // E
+    0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x60, 0x00, // F
+    0x3C, 0x66, 0x60, 0x6E, 0x66, 0x66, 0x3C, 0x00, // G
+    0x66, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x66, 0x00, // H
+    0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // I
+    0x1E, 0x0C, 0x0C, 0x0C, 0xCC, 0xCC, 0x78, 0x00, // J
+    0xE6, 0x66, 0x6C, 0x78, 0x6C, 0x66, 0xE6, 0x00, // K
+    0xF0, 0x60, 0x60, 0x60, 0x60, 0x60, 0xF0, 0x00, // L
+    0xC6, 0xEE, 0xFE, 0xD6, 0xC6, 0xC6, 0xC6, 0x00, // M
+    0xC6, 0xE6, 0xF6, 0xDE, 0xCE, 0xC6, 0xC6, 0x00, // N
+    0x3C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // O
+    0xFC, 0x66, 0x66, 0x7C, 0x60, 0x60, 0x60, 0x00, // P
+    0x3C, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x0E, 0x00, // Q
+    0xFC, 0x66, 0x66, 0x7C, 0x6C, 0x66, 0xE6, 0x00, // R
+    0x3C, 0x66, 0x60, 0x3C, 0x06, 0x66, 0x3C, 0x00, // S
+    0x7E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x00, // T
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // U
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x18, 0x00, // V
+    0xC6, 0xC6, 0xC6, 0xD6, 0xFE, 0xEE, 0xC6, 0x00, // W
+    0xC6, 0xC6, 0x6C, 0x38, 0x6C, 0xC6, 0xC6, 0x00, // X
+    0x66, 0x66, 0x66, 0x3C, 0x18, 0x18, 0x18, 0x00, // Y
+    0xFE, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFE, 0x00, // Z
+    0x3C, 0x30, 0x30, 0x30, 0x30, 0x30, 0x3C, 0x00, // [
+    0x00, 0x60, 0x30, 0x18, 0x0C, 0x06, 0x00, 0x00, // Backslash
+    0x3C, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x3C, 0x00, // ]
+    0x10, 0x38, 0x6C, 0xC6, 0x00, 0x00, 0x00, 0x00, // ^
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, // _
+    0x18, 0x18, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, // `
+    0x00, 0x00, 0x3C, 0x06, 0x3E, 0x66, 0x3E, 0x00, // a (97)
+    0x60, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x7C, 0x00, // b
+    0x00, 0x00, 0x3C, 0x60, 0x60, 0x60, 0x3C, 0x00, // c
+    0x06, 0x06, 0x3E, 0x66, 0x66, 0x66, 0x3E, 0x00, // d
+    0x00, 0x00, 0x3C, 0x66, 0x7E, 0x60, 0x3C, 0x00, // e
+    0x1C, 0x36, 0x30, 0x78, 0x30, 0x30, 0x30, 0x00, // f
+    0x00, 0x00, 0x3E, 0x66, 0x66, 0x3E, 0x06, 0x3C, // g
+    0x60, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x66, 0x00, // h
+    0x18, 0x00, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // i
+    0x06, 0x00, 0x06, 0x06, 0x06, 0x66, 0x66, 0x3C, // j
+    0x60, 0x60, 0x66, 0x6C, 0x78, 0x6C, 0x66, 0x00, // k
+    0x38, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // l
+    0x00, 0x00, 0xEC, 0xFE, 0xFE, 0xD6, 0xC6, 0x00, // m
+    0x00, 0x00, 0xDC, 0x66, 0x66, 0x66, 0x66, 0x00, // n
+    0x00, 0x00, 0x3C, 0x66, 0x66, 0x66, 0x3C, 0x00, // o
+    0x00, 0x00, 0xDC, 0x66, 0x66, 0x7C, 0x60, 0xF0, // p
+    0x00, 0x00, 0x76, 0x66, 0x66, 0x7C, 0x06, 0x1E, // q
+    0x00, 0x00, 0xDC, 0x66, 0x60, 0x60, 0xF0, 0x00, // r
+    0x00, 0x00, 0x3C, 0x60, 0x3C, 0x06, 0x7C, 0x00, // s
+    0x30, 0x30, 0x7C, 0x30, 0x30, 0x30, 0x1C, 0x00, // t
+    0x00, 0x00, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // u
+    0x00, 0x00, 0x66, 0x66, 0x66, 0x3C, 0x18, 0x00, // v
+    0x00, 0x00, 0xC6, 0xD6, 0xFE, 0xEE, 0xC4, 0x00, // w
+    0x00, 0x00, 0x66, 0x3C, 0x18, 0x3C, 0x66, 0x00, // x
+    0x00, 0x00, 0x66, 0x66, 0x66, 0x3E, 0x0C, 0x78, // y
+    0x00, 0x00, 0x7E, 0x0C, 0x18, 0x30, 0x7E, 0x00, // z
+    0x0C, 0x18, 0x18, 0x30, 0x18, 0x18, 0x0C, 0x00, // {
+    0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, // |
+    0x30, 0x18, 0x18, 0x0C, 0x18, 0x18, 0x30, 0x00, // }
+    0x36, 0x5C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // ~
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00  // DEL
+];
+/// Glifo de sustitución: '?' invertido dentro de un recuadro
+const REPLACEMENT: Glyph = [0xFF, 0xC3, 0x99, 0xF9, 0xF3, 0xFF, 0xF3, 0xFF];
+
+/// Diacrítico que se superpone a una letra base
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Grave,
+    Acute,
+    Circumflex,
+    Tilde,
+    Macron,
+    Breve,
+    DotAbove,
+    Diaeresis,
+    Ring,
+    DoubleAcute,
+    Caron,
+    Cedilla,
+    Ogonek,
+}
+
+impl Mark {
+    /// Filas del diacrítico: las dos superiores del glifo o, si va debajo, la última
+    fn rows(self) -> [u8; 2] {
+        match self {
+            Mark::Grave => [0x30, 0x18],
+            Mark::Acute => [0x0C, 0x18],
+            Mark::Circumflex => [0x18, 0x66],
+            Mark::Tilde => [0x36, 0x5C],
+            Mark::Macron => [0x7E, 0x00],
+            Mark::Breve => [0x42, 0x3C],
+            Mark::DotAbove => [0x18, 0x00],
+            Mark::Diaeresis => [0x66, 0x00],
+            Mark::Ring => [0x3C, 0x24],
+            Mark::DoubleAcute => [0x36, 0x6C],
+            Mark::Caron => [0x66, 0x18],
+            Mark::Cedilla => [0x18, 0x00],
+            Mark::Ogonek => [0x06, 0x00],
+        }
+    }
+
+    fn is_below(self) -> bool {
+        matches!(self, Mark::Cedilla | Mark::Ogonek)
+    }
+
+    /// Diacrítico de un carácter combinante (U+0300..U+036F)
+    fn from_combining(c: char) -> Option<Self> {
+        Some(match c {
+            '\u{0300}' => Mark::Grave,
+            '\u{0301}' => Mark::Acute,
+            '\u{0302}' => Mark::Circumflex,
+            '\u{0303}' => Mark::Tilde,
+            '\u{0304}' => Mark::Macron,
+            '\u{0306}' => Mark::Breve,
+            '\u{0307}' => Mark::DotAbove,
+            '\u{0308}' => Mark::Diaeresis,
+            '\u{030A}' => Mark::Ring,
+            '\u{030B}' => Mark::DoubleAcute,
+            '\u{030C}' => Mark::Caron,
+            '\u{0327}' => Mark::Cedilla,
+            '\u{0328}' => Mark::Ogonek,
+            _ => return None,
+        })
+    }
+}
+/// Letras de Latin-1 y Latin Extended-A compuestas como (letra, base, diacrítico),
+/// ordenadas por código para la búsqueda binaria.
+/// Con diacríticos superiores la `i` y la `j` usan su forma sin punto.
+const COMPOSED: &[(char, char, Mark)] = &[
+    ('À', 'A', Mark::Grave), ('Á', 'A', Mark::Acute), ('Â', 'A', Mark::Circumflex),
+    ('Ã', 'A', Mark::Tilde), ('Ä', 'A', Mark::Diaeresis), ('Å', 'A', Mark::Ring),
+    ('Ç', 'C', Mark::Cedilla), ('È', 'E', Mark::Grave), ('É', 'E', Mark::Acute),
+    ('Ê', 'E', Mark::Circumflex), ('Ë', 'E', Mark::Diaeresis), ('Ì', 'I', Mark::Grave),
+    ('Í', 'I', Mark::Acute), ('Î', 'I', Mark::Circumflex), ('Ï', 'I', Mark::Diaeresis),
+    ('Ñ', 'N', Mark::Tilde), ('Ò', 'O', Mark::Grave), ('Ó', 'O', Mark::Acute),
+    ('Ô', 'O', Mark::Circumflex), ('Õ', 'O', Mark::Tilde), ('Ö', 'O', Mark::Diaeresis),
+    ('Ù', 'U', Mark::Grave), ('Ú', 'U', Mark::Acute), ('Û', 'U', Mark::Circumflex),
+    ('Ü', 'U', Mark::Diaeresis), ('Ý', 'Y', Mark::Acute), ('à', 'a', Mark::Grave),
+    ('á', 'a', Mark::Acute), ('â', 'a', Mark::Circumflex), ('ã', 'a', Mark::Tilde),
+    ('ä', 'a', Mark::Diaeresis), ('å', 'a', Mark::Ring), ('ç', 'c', Mark::Cedilla),
+    ('è', 'e', Mark::Grave), ('é', 'e', Mark::Acute), ('ê', 'e', Mark::Circumflex),
+    ('ë', 'e', Mark::Diaeresis), ('ì', 'ı', Mark::Grave), ('í', 'ı', Mark::Acute),
+    ('î', 'ı', Mark::Circumflex), ('ï', 'ı', Mark::Diaeresis), ('ñ', 'n', Mark::Tilde),
+    ('ò', 'o', Mark::Grave), ('ó', 'o', Mark::Acute), ('ô', 'o', Mark::Circumflex),
+    ('õ', 'o', Mark::Tilde), ('ö', 'o', Mark::Diaeresis), ('ù', 'u', Mark::Grave),
+    ('ú', 'u', Mark::Acute), ('û', 'u', Mark::Circumflex), ('ü', 'u', Mark::Diaeresis),
+    ('ý', 'y', Mark::Acute), ('ÿ', 'y', Mark::Diaeresis), ('Ā', 'A', Mark::Macron),
+    ('ā', 'a', Mark::Macron), ('Ă', 'A', Mark::Breve), ('ă', 'a', Mark::Breve),
+    ('Ą', 'A', Mark::Ogonek), ('ą', 'a', Mark::Ogonek), ('Ć', 'C', Mark::Acute),
+    ('ć', 'c', Mark::Acute), ('Ĉ', 'C', Mark::Circumflex), ('ĉ', 'c', Mark::Circumflex),
+    ('Ċ', 'C', Mark::DotAbove), ('ċ', 'c', Mark::DotAbove), ('Č', 'C', Mark::Caron),
+    ('č', 'c', Mark::Caron), ('Ď', 'D', Mark::Caron), ('ď', 'd', Mark::Caron),
+    ('Ē', 'E', Mark::Macron), ('ē', 'e', Mark::Macron), ('Ĕ', 'E', Mark::Breve),
+    ('ĕ', 'e', Mark::Breve), ('Ė', 'E', Mark::DotAbove), ('ė', 'e', Mark::DotAbove),
+    ('Ę', 'E', Mark::Ogonek), ('ę', 'e', Mark::Ogonek), ('Ě', 'E', Mark::Caron),
+    ('ě', 'e', Mark::Caron), ('Ĝ', 'G', Mark::Circumflex), ('ĝ', 'g', Mark::Circumflex),
+    ('Ğ', 'G', Mark::Breve), ('ğ', 'g', Mark::Breve), ('Ġ', 'G', Mark::DotAbove),
+    ('ġ', 'g', Mark::DotAbove), ('Ģ', 'G', Mark::Cedilla), ('ģ', 'g', Mark::Cedilla),
+    ('Ĥ', 'H', Mark::Circumflex), ('ĥ', 'h', Mark::Circumflex), ('Ĩ', 'I', Mark::Tilde),
+    ('ĩ', 'ı', Mark::Tilde), ('Ī', 'I', Mark::Macron), ('ī', 'ı', Mark::Macron),
+    ('Ĭ', 'I', Mark::Breve), ('ĭ', 'ı', Mark::Breve), ('Į', 'I', Mark::Ogonek),
+    ('į', 'i', Mark::Ogonek), ('İ', 'I', Mark::DotAbove), ('Ĵ', 'J', Mark::Circumflex),
+    ('ĵ', 'ȷ', Mark::Circumflex), ('Ķ', 'K', Mark::Cedilla), ('ķ', 'k', Mark::Cedilla),
+    ('Ĺ', 'L', Mark::Acute), ('ĺ', 'l', Mark::Acute), ('Ļ', 'L', Mark::Cedilla),
+    ('ļ', 'l', Mark::Cedilla), ('Ľ', 'L', Mark::Caron), ('ľ', 'l', Mark::Caron),
+    ('Ń', 'N', Mark::Acute), ('ń', 'n', Mark::Acute), ('Ņ', 'N', Mark::Cedilla),
+    ('ņ', 'n', Mark::Cedilla), ('Ň', 'N', Mark::Caron), ('ň', 'n', Mark::Caron),
+    ('Ō', 'O', Mark::Macron), ('ō', 'o', Mark::Macron), ('Ŏ', 'O', Mark::Breve),
+    ('ŏ', 'o', Mark::Breve), ('Ő', 'O', Mark::DoubleAcute), ('ő', 'o', Mark::DoubleAcute),
+    ('Ŕ', 'R', Mark::Acute), ('ŕ', 'r', Mark::Acute), ('Ŗ', 'R', Mark::Cedilla),
+    ('ŗ', 'r', Mark::Cedilla), ('Ř', 'R', Mark::Caron), ('ř', 'r', Mark::Caron),
+    ('Ś', 'S', Mark::Acute), ('ś', 's', Mark::Acute), ('Ŝ', 'S', Mark::Circumflex),
+    ('ŝ', 's', Mark::Circumflex), ('Ş', 'S', Mark::Cedilla), ('ş', 's', Mark::Cedilla),
+    ('Š', 'S', Mark::Caron), ('š', 's', Mark::Caron), ('Ţ', 'T', Mark::Cedilla),
+    ('ţ', 't', Mark::Cedilla), ('Ť', 'T', Mark::Caron), ('ť', 't', Mark::Caron),
+    ('Ũ', 'U', Mark::Tilde), ('ũ', 'u', Mark::Tilde), ('Ū', 'U', Mark::Macron),
+    ('ū', 'u', Mark::Macron), ('Ŭ', 'U', Mark::Breve), ('ŭ', 'u', Mark::Breve),
+    ('Ů', 'U', Mark::Ring), ('ů', 'u', Mark::Ring), ('Ű', 'U', Mark::DoubleAcute),
+    ('ű', 'u', Mark::DoubleAcute), ('Ų', 'U', Mark::Ogonek), ('ų', 'u', Mark::Ogonek),
+    ('Ŵ', 'W', Mark::Circumflex), ('ŵ', 'w', Mark::Circumflex), ('Ŷ', 'Y', Mark::Circumflex),
+    ('ŷ', 'y', Mark::Circumflex), ('Ÿ', 'Y', Mark::Diaeresis), ('Ź', 'Z', Mark::Acute),
+    ('ź', 'z', Mark::Acute), ('Ż', 'Z', Mark::DotAbove), ('ż', 'z', Mark::DotAbove),
+    ('Ž', 'Z', Mark::Caron), ('ž', 'z', Mark::Caron),
+];
+
+/// Glifos dibujados a mano (letras sin descomposición, símbolos, flechas y cajas),
+/// ordenados por código para la búsqueda binaria
+const SYMBOLS: &[(char, Glyph)] = &[
+    ('¡', [0x18, 0x00, 0x18, 0x18, 0x3C, 0x3C, 0x18, 0x00]), // U+00A1 inverted exclamation mark
+    ('¢', [0x18, 0x3C, 0x66, 0x60, 0x66, 0x3C, 0x18, 0x00]), // U+00A2 cent sign
+    ('£', [0x38, 0x6C, 0x60, 0xF0, 0x60, 0x66, 0xFC, 0x00]), // U+00A3 pound sign
+    ('¤', [0x00, 0x66, 0x3C, 0x24, 0x3C, 0x66, 0x00, 0x00]), // U+00A4 currency sign
+    ('¥', [0x66, 0x66, 0x3C, 0x7E, 0x18, 0x7E, 0x18, 0x00]), // U+00A5 yen sign
+    ('¦', [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00]), // U+00A6 broken bar
+    ('§', [0x3C, 0x60, 0x3C, 0x66, 0x3C, 0x06, 0x3C, 0x00]), // U+00A7 section sign
+    ('¨', [0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+00A8 diaeresis
+    ('©', [0x3C, 0x42, 0x99, 0xA1, 0x99, 0x42, 0x3C, 0x00]), // U+00A9 copyright sign
+    ('ª', [0x3C, 0x06, 0x3E, 0x66, 0x3E, 0x00, 0x7E, 0x00]), // U+00AA feminine ordinal indicator
+    ('«', [0x00, 0x33, 0x66, 0xCC, 0x66, 0x33, 0x00, 0x00]), // U+00AB left-pointing double angle quotation mark
+    ('¬', [0x00, 0x00, 0x7E, 0x06, 0x06, 0x00, 0x00, 0x00]), // U+00AC not sign
+    ('®', [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0x52, 0x3C, 0x00]), // U+00AE registered sign
+    ('¯', [0x7E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+00AF macron
+    ('°', [0x38, 0x6C, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+00B0 degree sign
+    ('±', [0x18, 0x18, 0x7E, 0x18, 0x18, 0x00, 0x7E, 0x00]), // U+00B1 plus-minus sign
+    ('²', [0x78, 0x0C, 0x30, 0x7C, 0x00, 0x00, 0x00, 0x00]), // U+00B2 superscript two
+    ('³', [0x78, 0x38, 0x0C, 0x78, 0x00, 0x00, 0x00, 0x00]), // U+00B3 superscript three
+    ('´', [0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+00B4 acute accent
+    ('µ', [0x00, 0x00, 0x66, 0x66, 0x66, 0x7C, 0x60, 0xC0]), // U+00B5 micro sign
+    ('¶', [0x3F, 0x7B, 0x7B, 0x3B, 0x0B, 0x0B, 0x0B, 0x00]), // U+00B6 pilcrow sign
+    ('·', [0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00]), // U+00B7 middle dot
+    ('¸', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x30]), // U+00B8 cedilla
+    ('¹', [0x30, 0x70, 0x30, 0x78, 0x00, 0x00, 0x00, 0x00]), // U+00B9 superscript one
+    ('º', [0x3C, 0x66, 0x66, 0x3C, 0x00, 0x7E, 0x00, 0x00]), // U+00BA masculine ordinal indicator
+    ('»', [0x00, 0xCC, 0x66, 0x33, 0x66, 0xCC, 0x00, 0x00]), // U+00BB right-pointing double angle quotation mark
+    ('¼', [0x60, 0xE2, 0x64, 0x0A, 0x16, 0x2F, 0x02, 0x00]), // U+00BC vulgar fraction one quarter
+    ('½', [0x60, 0xE2, 0x64, 0x0F, 0x11, 0x22, 0x07, 0x00]), // U+00BD vulgar fraction one half
+    ('¾', [0xE0, 0x62, 0xE4, 0x0A, 0x16, 0x2F, 0x02, 0x00]), // U+00BE vulgar fraction three quarters
+    ('¿', [0x18, 0x00, 0x18, 0x30, 0x60, 0x66, 0x3C, 0x00]), // U+00BF inverted question mark
+    ('Æ', [0x3E, 0x6C, 0xCC, 0xFE, 0xCC, 0xCC, 0xCE, 0x00]), // U+00C6 latin capital letter ae
+    ('Ð', [0x78, 0x6C, 0x66, 0xF6, 0x66, 0x6C, 0x78, 0x00]), // U+00D0 latin capital letter eth
+    ('×', [0x00, 0x66, 0x3C, 0x18, 0x3C, 0x66, 0x00, 0x00]), // U+00D7 multiplication sign
+    ('Ø', [0x3D, 0x66, 0x6E, 0x7E, 0x76, 0x66, 0xBC, 0x00]), // U+00D8 latin capital letter o with stroke
+    ('Þ', [0x60, 0x7C, 0x66, 0x66, 0x7C, 0x60, 0x60, 0x00]), // U+00DE latin capital letter thorn
+    ('ß', [0x3C, 0x66, 0x66, 0x6C, 0x66, 0x66, 0x6C, 0x00]), // U+00DF latin small letter sharp s
+    ('æ', [0x00, 0x00, 0x6C, 0x1A, 0x7F, 0xD8, 0x76, 0x00]), // U+00E6 latin small letter ae
+    ('ð', [0x28, 0x10, 0x2C, 0x06, 0x3E, 0x66, 0x3C, 0x00]), // U+00F0 latin small letter eth
+    ('÷', [0x00, 0x18, 0x00, 0x7E, 0x00, 0x18, 0x00, 0x00]), // U+00F7 division sign
+    ('ø', [0x00, 0x02, 0x3C, 0x6E, 0x76, 0x66, 0xBC, 0x00]), // U+00F8 latin small letter o with stroke
+    ('þ', [0x00, 0x60, 0x7C, 0x66, 0x66, 0x7C, 0x60, 0x60]), // U+00FE latin small letter thorn
+    ('Đ', [0x78, 0x6C, 0x66, 0xF6, 0x66, 0x6C, 0x78, 0x00]), // U+0110 latin capital letter d with stroke
+    ('đ', [0x0C, 0x1F, 0x0C, 0x3E, 0x66, 0x66, 0x3E, 0x00]), // U+0111 latin small letter d with stroke
+    ('Ħ', [0x66, 0xFF, 0x66, 0x7E, 0x66, 0x66, 0x66, 0x00]), // U+0126 latin capital letter h with stroke
+    ('ħ', [0x60, 0xF0, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x00]), // U+0127 latin small letter h with stroke
+    ('ı', [0x00, 0x00, 0x38, 0x18, 0x18, 0x18, 0x3C, 0x00]), // U+0131 latin small letter dotless i
+    ('Ĳ', [0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xD6, 0xCC, 0x00]), // U+0132 latin capital ligature ij
+    ('ĳ', [0x63, 0x00, 0x63, 0x63, 0x63, 0x63, 0x6B, 0x0C]), // U+0133 latin small ligature ij
+    ('ĸ', [0x00, 0x00, 0x66, 0x6C, 0x78, 0x6C, 0x66, 0x00]), // U+0138 latin small letter kra
+    ('Ŀ', [0x60, 0x60, 0x60, 0x66, 0x60, 0x60, 0x7E, 0x00]), // U+013F latin capital letter l with middle dot
+    ('ŀ', [0x60, 0x60, 0x60, 0x6C, 0x60, 0x60, 0x30, 0x00]), // U+0140 latin small letter l with middle dot
+    ('Ł', [0x60, 0x60, 0x68, 0x70, 0xC0, 0x60, 0x7E, 0x00]), // U+0141 latin capital letter l with stroke
+    ('ł', [0x30, 0x30, 0x34, 0x38, 0x70, 0x30, 0x18, 0x00]), // U+0142 latin small letter l with stroke
+    ('ŉ', [0x60, 0x20, 0x9C, 0x66, 0x66, 0x66, 0x66, 0x00]), // U+0149 latin small letter n preceded by apostrophe
+    ('Ŋ', [0x66, 0x76, 0x7E, 0x6E, 0x66, 0x66, 0x6C, 0x08]), // U+014A latin capital letter eng
+    ('ŋ', [0x00, 0x00, 0x7C, 0x66, 0x66, 0x66, 0x66, 0x06]), // U+014B latin small letter eng
+    ('Œ', [0x3F, 0x6C, 0xCC, 0xCF, 0xCC, 0x6C, 0x3F, 0x00]), // U+0152 latin capital ligature oe
+    ('œ', [0x00, 0x00, 0x6C, 0x92, 0x9E, 0x90, 0x6E, 0x00]), // U+0153 latin small ligature oe
+    ('Ŧ', [0x7E, 0x18, 0x18, 0x7E, 0x18, 0x18, 0x18, 0x00]), // U+0166 latin capital letter t with stroke
+    ('ŧ', [0x18, 0x18, 0x7E, 0x18, 0x7E, 0x18, 0x0C, 0x00]), // U+0167 latin small letter t with stroke
+    ('ſ', [0x1C, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x00]), // U+017F latin small letter long s
+    ('ȷ', [0x00, 0x00, 0x0C, 0x0C, 0x0C, 0x6C, 0x6C, 0x38]), // U+0237 latin small letter dotless j
+    ('‐', [0x00, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00]), // U+2010 hyphen
+    ('–', [0x00, 0x00, 0x00, 0xFE, 0x00, 0x00, 0x00, 0x00]), // U+2013 en dash
+    ('—', [0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00]), // U+2014 em dash
+    ('‘', [0x18, 0x30, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+2018 left single quotation mark
+    ('’', [0x18, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+2019 right single quotation mark
+    ('‚', [0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x30]), // U+201A single low-9 quotation mark
+    ('“', [0x66, 0xCC, 0xCC, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+201C left double quotation mark
+    ('”', [0x66, 0x66, 0xCC, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+201D right double quotation mark
+    ('„', [0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x66, 0xCC]), // U+201E double low-9 quotation mark
+    ('†', [0x18, 0x18, 0x7E, 0x18, 0x18, 0x18, 0x18, 0x00]), // U+2020 dagger
+    ('‡', [0x18, 0x7E, 0x18, 0x18, 0x18, 0x7E, 0x18, 0x00]), // U+2021 double dagger
+    ('•', [0x00, 0x00, 0x3C, 0x3C, 0x3C, 0x3C, 0x00, 0x00]), // U+2022 bullet
+    ('…', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xDB, 0x00]), // U+2026 horizontal ellipsis
+    ('‰', [0xC4, 0xC8, 0x10, 0x20, 0x40, 0x9B, 0x1B, 0x00]), // U+2030 per mille sign
+    ('′', [0x18, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+2032 prime
+    ('″', [0x6C, 0x6C, 0xD8, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+2033 double prime
+    ('‹', [0x00, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x00, 0x00]), // U+2039 single left-pointing angle quotation mark
+    ('›', [0x00, 0x30, 0x18, 0x0C, 0x18, 0x30, 0x00, 0x00]), // U+203A single right-pointing angle quotation mark
+    ('€', [0x1E, 0x30, 0xFC, 0x30, 0xFC, 0x30, 0x1E, 0x00]), // U+20AC euro sign
+    ('™', [0xE9, 0x4F, 0x49, 0x00, 0x00, 0x00, 0x00, 0x00]), // U+2122 trade mark sign
+    ('←', [0x00, 0x30, 0x60, 0xFF, 0x60, 0x30, 0x00, 0x00]), // U+2190 leftwards arrow
+    ('↑', [0x18, 0x3C, 0x7E, 0x18, 0x18, 0x18, 0x18, 0x00]), // U+2191 upwards arrow
+    ('→', [0x00, 0x0C, 0x06, 0xFF, 0x06, 0x0C, 0x00, 0x00]), // U+2192 rightwards arrow
+    ('↓', [0x18, 0x18, 0x18, 0x18, 0x7E, 0x3C, 0x18, 0x00]), // U+2193 downwards arrow
+    ('↔', [0x00, 0x24, 0x66, 0xFF, 0x66, 0x24, 0x00, 0x00]), // U+2194 left right arrow
+    ('↕', [0x18, 0x3C, 0x7E, 0x18, 0x7E, 0x3C, 0x18, 0x00]), // U+2195 up down arrow
+    ('⇐', [0x00, 0x20, 0x7F, 0x80, 0x7F, 0x20, 0x00, 0x00]), // U+21D0 leftwards double arrow
+    ('⇒', [0x00, 0x04, 0xFE, 0x01, 0xFE, 0x04, 0x00, 0x00]), // U+21D2 rightwards double arrow
+    ('─', [0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00]), // U+2500 box drawings light horizontal
+    ('│', [0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18]), // U+2502 box drawings light vertical
+    ('┌', [0x00, 0x00, 0x00, 0x1F, 0x1F, 0x18, 0x18, 0x18]), // U+250C box drawings light down and right
+    ('┐', [0x00, 0x00, 0x00, 0xF8, 0xF8, 0x18, 0x18, 0x18]), // U+2510 box drawings light down and left
+    ('└', [0x18, 0x18, 0x18, 0x1F, 0x1F, 0x00, 0x00, 0x00]), // U+2514 box drawings light up and right
+    ('┘', [0x18, 0x18, 0x18, 0xF8, 0xF8, 0x00, 0x00, 0x00]), // U+2518 box drawings light up and left
+    ('├', [0x18, 0x18, 0x18, 0x1F, 0x1F, 0x18, 0x18, 0x18]), // U+251C box drawings light vertical and right
+    ('┤', [0x18, 0x18, 0x18, 0xF8, 0xF8, 0x18, 0x18, 0x18]), // U+2524 box drawings light vertical and left
+    ('┬', [0x00, 0x00, 0x00, 0xFF, 0xFF, 0x18, 0x18, 0x18]), // U+252C box drawings light down and horizontal
+    ('┴', [0x18, 0x18, 0x18, 0xFF, 0xFF, 0x00, 0x00, 0x00]), // U+2534 box drawings light up and horizontal
+    ('┼', [0x18, 0x18, 0x18, 0xFF, 0xFF, 0x18, 0x18, 0x18]), // U+253C box drawings light vertical and horizontal
+    ('═', [0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00]), // U+2550 box drawings double horizontal
+    ('║', [0x24, 0x24, 0x24, 0x24, 0x24, 0x24, 0x24, 0x24]), // U+2551 box drawings double vertical
+    ('╔', [0x00, 0x00, 0x3F, 0x20, 0x20, 0x27, 0x24, 0x24]), // U+2554 box drawings double down and right
+    ('╗', [0x00, 0x00, 0xFC, 0x04, 0x04, 0xE4, 0x24, 0x24]), // U+2557 box drawings double down and left
+    ('╚', [0x24, 0x24, 0x27, 0x20, 0x20, 0x3F, 0x00, 0x00]), // U+255A box drawings double up and right
+    ('╝', [0x24, 0x24, 0xE4, 0x04, 0x04, 0xFC, 0x00, 0x00]), // U+255D box drawings double up and left
+    ('╠', [0x24, 0x24, 0x27, 0x20, 0x20, 0x27, 0x24, 0x24]), // U+2560 box drawings double vertical and right
+    ('╣', [0x24, 0x24, 0xE4, 0x04, 0x04, 0xE4, 0x24, 0x24]), // U+2563 box drawings double vertical and left
+    ('╦', [0x00, 0x00, 0xFF, 0x00, 0x00, 0xE7, 0x24, 0x24]), // U+2566 box drawings double down and horizontal
+    ('╩', [0x24, 0x24, 0xE7, 0x00, 0x00, 0xFF, 0x00, 0x00]), // U+2569 box drawings double up and horizontal
+    ('╬', [0x24, 0x24, 0xE7, 0x00, 0x00, 0xE7, 0x24, 0x24]), // U+256C box drawings double vertical and horizontal
+    ('▀', [0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]), // U+2580 upper half block
+    ('▄', [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]), // U+2584 lower half block
+    ('█', [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]), // U+2588 full block
+    ('▌', [0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0]), // U+258C left half block
+    ('▐', [0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 0x0F]), // U+2590 right half block
+    ('░', [0x88, 0x22, 0x88, 0x22, 0x88, 0x22, 0x88, 0x22]), // U+2591 light shade
+    ('▒', [0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55]), // U+2592 medium shade
+    ('▓', [0xEE, 0x55, 0xBB, 0x55, 0xEE, 0x55, 0xBB, 0x55]), // U+2593 dark shade
+    ('■', [0x00, 0x7E, 0x7E, 0x7E, 0x7E, 0x7E, 0x7E, 0x00]), // U+25A0 black square
+    ('□', [0x00, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x00]), // U+25A1 white square
+    ('▲', [0x00, 0x18, 0x3C, 0x3C, 0x7E, 0x7E, 0x00, 0x00]), // U+25B2 black up-pointing triangle
+    ('►', [0x00, 0x60, 0x78, 0x7E, 0x78, 0x60, 0x00, 0x00]), // U+25BA black right-pointing pointer
+    ('▼', [0x00, 0x7E, 0x7E, 0x3C, 0x3C, 0x18, 0x00, 0x00]), // U+25BC black down-pointing triangle
+    ('◄', [0x00, 0x06, 0x1E, 0x7E, 0x1E, 0x06, 0x00, 0x00]), // U+25C4 black left-pointing pointer
+    ('◆', [0x18, 0x3C, 0x7E, 0xFF, 0x7E, 0x3C, 0x18, 0x00]), // U+25C6 black diamond
+    ('○', [0x00, 0x3C, 0x66, 0x42, 0x42, 0x66, 0x3C, 0x00]), // U+25CB white circle
+    ('●', [0x00, 0x3C, 0x7E, 0x7E, 0x7E, 0x7E, 0x3C, 0x00]), // U+25CF black circle
+    ('★', [0x18, 0x18, 0xFF, 0x7E, 0x3C, 0x66, 0x42, 0x00]), // U+2605 black star
+    ('♥', [0x00, 0x6C, 0xFE, 0xFE, 0x7C, 0x38, 0x10, 0x00]), // U+2665 black heart suit
+    ('⚡', [0x0E, 0x1C, 0x38, 0x7E, 0x1C, 0x30, 0x40, 0x00]), // U+26A1 high voltage sign
+    ('✓', [0x00, 0x03, 0x06, 0x8C, 0xD8, 0x70, 0x20, 0x00]), // U+2713 check mark
+    ('✔', [0x03, 0x07, 0x8E, 0xDC, 0xF8, 0x70, 0x20, 0x00]), // U+2714 heavy check mark
+    ('✗', [0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00, 0x00]), // U+2717 ballot x
+    ('✘', [0x63, 0x77, 0x3E, 0x1C, 0x3E, 0x77, 0x63, 0x00]), // U+2718 heavy ballot x
+];
+
+/// Glifo de un carácter, con el de sustitución si la fuente no lo cubre
+pub fn glyph(c: char) -> Glyph {
+    if let Some(glyph) = lookup(c) {
+        return glyph;
+    }
+    if let Ok(index) = COMPOSED.binary_search_by_key(&c, |&(ch, _, _)| ch) {
+        let (_, base, mark) = COMPOSED[index];
+        if let Some(base) = lookup(base) {
+            return compose(base, mark);
+        }
+    }
+    REPLACEMENT
+}
+
+/// Glifo con solo el diacrítico de un carácter combinante, para dibujarlo sobre la celda anterior
+pub fn combining_mark(c: char) -> Option<Glyph> {
+    Mark::from_combining(c).map(|mark| compose([0; 8], mark))
+}
+
+/// Caracteres de `text` con cada letra seguida de un diacrítico combinante
+/// sustituida por su forma precompuesta, si existe y `available` la acepta
+pub fn precompose<'a>(text: &'a str, available: impl Fn(char) -> bool + 'a) -> impl Iterator<Item = char> + 'a {
+    let mut chars = text.chars().peekable();
+    core::iter::from_fn(move || {
+        let c = chars.next()?;
+        if let Some(&next) = chars.peek()
+            && let Some(composed) = composed_char(c, next)
+            && available(composed)
+        {
+            chars.next();
+            return Some(composed);
+        }
+        Some(c)
+    })
+}
+
+/// Letra precompuesta de `base` con el diacrítico combinante `combining`
+///
+/// En la tabla la `i` y la `j` con diacrítico superior tienen como base su forma sin punto.
+fn composed_char(base: char, combining: char) -> Option<char> {
+    let mark = Mark::from_combining(combining)?;
+    let dotless = match base {
+        'i' => 'ı',
+        'j' => 'ȷ',
+        other => other,
+    };
+    COMPOSED
+        .iter()
+        .find(|&&(_, letter, composed_mark)| composed_mark == mark && (letter == base || letter == dotless))
+        .map(|&(composed, _, _)| composed)
+}
+
+/// Caracteres que no ocupan celda: espacios de ancho cero, selectores de variación y BOM
+pub fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FE00}'..='\u{FE0F}' | '\u{FEFF}')
+}
+
+/// Glifos almacenados tal cual: ASCII y tabla de símbolos
+fn lookup(c: char) -> Option<Glyph> {
+    match c {
+        ' '..='~' => {
+            let index = (c as usize - 32) * 8;
+            let mut glyph = [0; 8];
+            glyph.copy_from_slice(&FONT_8X8[index..index + 8]);
+            Some(glyph)
+        }
+        // Espacio de no separación y guion opcional
+        '\u{00A0}' => lookup(' '),
+        '\u{00AD}' => lookup('-'),
+        _ => SYMBOLS
+            .binary_search_by_key(&c, |&(ch, _)| ch)
+            .ok()
+            .map(|index| SYMBOLS[index].1),
+    }
+}
+
+/// Superponer un diacrítico a una letra base
+///
+/// Los diacríticos superiores ocupan las dos primeras filas. Si la base ya las usa
+/// (mayúsculas y letras con ascendente), se baja dos filas quitando una fila
+/// repetida para que quepa en las seis inferiores.
+fn compose(mut glyph: Glyph, mark: Mark) -> Glyph {
+    let [top, bottom] = mark.rows();
+    if mark.is_below() {
+        glyph[7] |= top;
+        return glyph;
+    }
+    if glyph[0] | glyph[1] != 0 {
+        let drop = (1..7).find(|&row| glyph[row] == glyph[row - 1]).unwrap_or(1);
+        let mut squeezed = [0; 8];
+        let mut target = 2;
+        for (row, bits) in glyph.iter().enumerate().take(7) {
+            if row != drop {
+                squeezed[target] = *bits;
+                target += 1;
+            }
+        }
+        glyph = squeezed;
+    }
+    glyph[0] |= top;
+    glyph[1] |= bottom;
+    glyph
+}
+
+// ===== FUENTES CARGABLES (PSF2 / BDF) =====
+
+const PSF2_MAGIC: u32 = 0x864A_B572;
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+
+/// Fuente bitmap de ancho fijo cargada desde un fichero PSF2 o BDF
+///
+/// Cada glifo ocupa `height` filas de `(width + 7) / 8` bytes, con el bit más
+/// alto de cada fila como columna izquierda.
+pub struct BitmapFont {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    bitmaps: Vec<u8>,
+    index: BTreeMap<char, u32>,
+}
+
+impl BitmapFont {
+    /// Cargar una fuente detectando el formato por su cabecera
+    pub fn parse(name: &str, data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() >= 4 && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == PSF2_MAGIC {
+            Self::parse_psf2(name, data)
+        } else if data.starts_with(b"STARTFONT") {
+            Self::parse_bdf(name, data)
+        } else {
+            Err("formato de fuente desconocido (se espera PSF2 o BDF)")
+        }
+    }
+
+    /// Cargar una fuente PSF2 (consola de Linux), con su tabla Unicode si la tiene
+    pub fn parse_psf2(name: &str, data: &[u8]) -> Result<Self, &'static str> {
+        let field = |i: usize| -> Result<u32, &'static str> {
+            data.get(i * 4..i * 4 + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or("cabecera PSF2 truncada")
+        };
+        let header_size = field(2)? as usize;
+        let flags = field(3)?;
+        let glyph_count = field(4)? as usize;
+        let glyph_size = field(5)? as usize;
+        let height = field(6)?;
+        let width = field(7)?;
+
+        let row_bytes = width.div_ceil(8) as usize;
+        if width == 0 || height == 0 || glyph_size != row_bytes * height as usize {
+            return Err("dimensiones PSF2 inválidas");
+        }
+        let glyphs_end = header_size + glyph_count * glyph_size;
+        let bitmaps = data.get(header_size..glyphs_end).ok_or("glifos PSF2 truncados")?.to_vec();
+
+        let mut index = BTreeMap::new();
+        if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            // Por glifo: caracteres UTF-8, secuencias tras 0xFE (se ignoran) y fin en 0xFF
+            for (glyph, entry) in data[glyphs_end..].split(|&b| b == 0xFF).take(glyph_count).enumerate() {
+                let singles = entry.split(|&b| b == 0xFE).next().unwrap_or(&[]);
+                if let Ok(chars) = core::str::from_utf8(singles) {
+                    for c in chars.chars() {
+                        index.entry(c).or_insert(glyph as u32);
+                    }
+                }
+            }
+        } else {
+            for glyph in 0..glyph_count as u32 {
+                if let Some(c) = char::from_u32(glyph) {
+                    index.insert(c, glyph);
+                }
+            }
+        }
+
+        Ok(Self { name: String::from(name), width, height, bitmaps, index })
+    }
+
+    /// Cargar una fuente BDF, colocando cada glifo en la celda de `FONTBOUNDINGBOX`
+    pub fn parse_bdf(name: &str, data: &[u8]) -> Result<Self, &'static str> {
+        let text = core::str::from_utf8(data).map_err(|_| "BDF no es texto válido")?;
+        let mut lines = text.lines();
+
+        let mut cell: Option<(u32, u32, i32, i32)> = None;
+        let mut font = Self { name: String::from(name), width: 0, height: 0, bitmaps: Vec::new(), index: BTreeMap::new() };
+
+        while let Some(line) = lines.next() {
+            let mut words = line.split_ascii_whitespace();
+            match words.next() {
+                Some("FONTBOUNDINGBOX") => {
+                    let nums: Vec<i32> = words.filter_map(|w| w.parse().ok()).collect();
+                    let [w, h, x, y] = nums[..] else {
+                        return Err("FONTBOUNDINGBOX inválido");
+                    };
+                    if w <= 0 || h <= 0 {
+                        return Err("FONTBOUNDINGBOX inválido");
+                    }
+                    cell = Some((w as u32, h as u32, x, y));
+                    font.width = w as u32;
+                    font.height = h as u32;
+                }
+                Some("STARTCHAR") => {
+                    let (cell_w, cell_h, cell_x, cell_y) = cell.ok_or("STARTCHAR antes de FONTBOUNDINGBOX")?;
+                    let row_bytes = cell_w.div_ceil(8) as usize;
+                    let mut encoding = -1i64;
+                    let mut bbx = (0i32, 0i32, 0i32, 0i32);
+
+                    // Propiedades del glifo hasta BITMAP
+                    for line in lines.by_ref() {
+                        let mut words = line.split_ascii_whitespace();
+                        match words.next() {
+                            Some("ENCODING") => encoding = words.next().and_then(|w| w.parse().ok()).unwrap_or(-1),
+                            Some("BBX") => {
+                                let nums: Vec<i32> = words.filter_map(|w| w.parse().ok()).collect();
+                                if let [w, h, x, y] = nums[..] {
+                                    bbx = (w, h, x, y);
+                                }
+                            }
+                            Some("BITMAP") => break,
+                            _ => {}
+                        }
+                    }
+
+                    let mut glyph = alloc::vec![0u8; row_bytes * cell_h as usize];
+                    let (_, bbx_h, bbx_x, bbx_y) = bbx;
+                    // Fila superior del glifo dentro de la celda, medida desde la línea base
+                    let ascent = cell_h as i32 + cell_y;
+                    let top = ascent - (bbx_y + bbx_h);
+                    let left = bbx_x - cell_x;
+
+                    for (row, line) in lines.by_ref().enumerate() {
+                        let line = line.trim();
+                        if line == "ENDCHAR" {
+                            break;
+                        }
+                        let y = top + row as i32;
+                        if y < 0 || y >= cell_h as i32 {
+                            continue;
+                        }
+                        for (nibble_idx, nibble) in line.chars().filter_map(|c| c.to_digit(16)).enumerate() {
+                            for bit in 0..4 {
+                                if nibble & (0x8 >> bit) == 0 {
+                                    continue;
+                                }
+                                let x = left + (nibble_idx * 4 + bit) as i32;
+                                if x >= 0 && x < cell_w as i32 {
+                                    glyph[y as usize * row_bytes + x as usize / 8] |= 0x80 >> (x % 8);
+                                }
+                            }
+                        }
+                    }
+
+                    let Some(c) = u32::try_from(encoding).ok().and_then(char::from_u32) else {
+                        continue;
+                    };
+                    let slot = (font.bitmaps.len() / glyph.len()) as u32;
+                    font.bitmaps.extend_from_slice(&glyph);
+                    font.index.insert(c, slot);
+                }
+                _ => {}
+            }
+        }
+
+        if font.index.is_empty() {
+            return Err("la fuente BDF no tiene glifos");
+        }
+        Ok(font)
+    }
+
+    /// Bytes por fila de un glifo
+    pub fn row_bytes(&self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+
+    /// Bitmap de un carácter, si la fuente lo incluye
+    pub fn glyph(&self, c: char) -> Option<&[u8]> {
+        let size = self.row_bytes() * self.height as usize;
+        let slot = *self.index.get(&c)? as usize;
+        self.bitmaps.get(slot * size..(slot + 1) * size)
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.index.len()
+    }
+}
+
+/// Fuente elegida para dibujar texto
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FontSelection {
+    /// Fuente 8x8 integrada, ampliada `scale` veces
+    Builtin { scale: u32 },
+    /// Fuente bitmap del registro, ampliada `scale` veces
+    Bitmap { index: usize, scale: u32 },
+    /// Fuente vectorial (TrueType/OpenType) rasterizada a `size` pixels
+    Vector { index: usize, size: u32 },
+}
+
+impl FontSelection {
+    pub const BUILTIN: Self = FontSelection::Builtin { scale: 1 };
+
+    /// Escala entera de las fuentes bitmap (1 para las vectoriales)
+    pub fn scale(self) -> u32 {
+        match self {
+            FontSelection::Builtin { scale } | FontSelection::Bitmap { scale, .. } => scale,
+            FontSelection::Vector { .. } => 1,
+        }
+    }
+}
+
+/// Fuentes cargadas en el sistema, seleccionables por nombre y tamaño
+pub struct FontRegistry {
+    fonts: Vec<BitmapFont>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        Self { fonts: Vec::new() }
+    }
+
+    /// Añadir una fuente; varias fuentes con el mismo nombre son tamaños distintos
+    pub fn add(&mut self, font: BitmapFont) {
+        self.fonts.push(font);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BitmapFont> {
+        self.fonts.get(index)
+    }
+
+    pub fn fonts(&self) -> &[BitmapFont] {
+        &self.fonts
+    }
+
+    /// Elegir fuente y escala para `name` a `size` pixels de alto
+    ///
+    /// Se toma la fuente más alta que no supere el tamaño pedido (o la más baja si
+    /// todas lo superan) y se escala por el factor entero que más se acerque.
+    pub fn select(&self, name: &str, size: u32) -> Option<FontSelection> {
+        let scale_for = |height: u32| ((size + height / 2) / height).max(1);
+
+        if name == BUILTIN_FONT {
+            return Some(FontSelection::Builtin { scale: scale_for(GLYPH_HEIGHT as u32) });
+        }
+
+        let candidates = self.fonts.iter().enumerate().filter(|(_, f)| f.name == name);
+        let best = candidates
+            .clone()
+            .filter(|(_, f)| f.height <= size)
+            .max_by_key(|(_, f)| f.height)
+            .or_else(|| candidates.min_by_key(|(_, f)| f.height))?;
+
+        Some(FontSelection::Bitmap { index: best.0, scale: scale_for(best.1.height) })
+    }
+}
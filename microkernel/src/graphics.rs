@@ -1,81 +1,669 @@
 //! FerroOS Mobile - Módulo de gráficos embebido
 //! Sistema de gráficos básico para mostrar UI en pantalla
 
-use embedded_graphics::{mono_font::{ascii::FONT_9X18_BOLD, MonoTextStyleBuilder}, pixelcolor::Rgb888, prelude::*, primitives::{Rectangle, PrimitiveStyleBuilder}, text::{Baseline, Text}};
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{
+        Arc, Circle, Ellipse, Line, Polyline, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle,
+        Triangle,
+    },
+};
 
+use ab_glyph::{point, Font, FontRef, GlyphId, PxScale, ScaleFont};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::assets;
+use crate::blit;
+use crate::display::{Display, MailboxDisplay};
+use crate::compositor::{Compositor, SurfaceId, Z_NOTIFICATIONS, Z_SHELL, Z_STATUS_BAR};
+use crate::font::{self, BitmapFont, FontRegistry, FontSelection};
+use crate::image::{Image, ImageError};
 use crate::mailbox;
+use crate::terminal::Terminal;
+use crate::text::{self, TextLayout, TextStyle};
+use crate::theme;
 use crate::uart_send_str;
-use fos_microkernel::print_number;
+use crate::widgets::{Align, Layout, Ui};
+use fos_microkernel::{print_number, uptime_micros};
 
-/// Resolución de pantalla por defecto (Safe Mode para QEMU)
-pub const SCREEN_WIDTH: u32 = 640;
-pub const SCREEN_HEIGHT: u32 = 480;
+/// Primera fila de la consola de texto; lo que queda encima (cabecera) no se desplaza
+const CONSOLE_TOP: i32 = 30;
 
-/// Colores básicos para la UI
-pub mod colors {
-    use embedded_graphics::pixelcolor::Rgb888;
-    
-    pub const BLACK: Rgb888 = Rgb888::new(0, 0, 0);
-    pub const WHITE: Rgb888 = Rgb888::new(255, 255, 255);
-    pub const BLUE: Rgb888 = Rgb888::new(0, 100, 255);
-    pub const GREEN: Rgb888 = Rgb888::new(0, 255, 0);
-    pub const RED: Rgb888 = Rgb888::new(255, 0, 0);
-    pub const YELLOW: Rgb888 = Rgb888::new(255, 255, 0);
-    pub const PURPLE: Rgb888 = Rgb888::new(128, 0, 128);
-    pub const ORANGE: Rgb888 = Rgb888::new(255, 165, 0);
-    pub const CYAN: Rgb888 = Rgb888::new(0, 255, 255);
+/// Alto del aviso de la superficie de notificaciones
+const NOTIFICATION_HEIGHT: u32 = 40;
+
+/// Líneas de texto que se guardan en el historial de la consola
+const SCROLLBACK_LINES: usize = 512;
+
+/// Repeticiones de cada prueba del benchmark de relleno y copia
+const BENCHMARK_REPEATS: u32 = 8;
+
+/// Columnas mínimas del terminal: con menos se usa la fuente integrada sin escalar
+const TERMINAL_MIN_COLUMNS: u32 = 80;
+
+/// Radio máximo de círculos y arcos
+///
+/// Cubre cualquier pantalla y evita desbordar las distancias al cuadrado de
+/// embedded-graphics, que además recorre todo el cuadrado del arco.
+const MAX_RADIUS: u32 = 2048;
+
+/// Formato de pixel del framebuffer (orden de bytes en memoria)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 16 bits: RRRRRGGG GGGBBBBB
+    Rgb565,
+    /// 32 bits: bytes B, G, R, A
+    Bgra8888,
+    /// 32 bits: bytes R, G, B, A
+    Rgba8888,
+    /// 32 bits: bytes B, G, R y un byte de relleno que el display ignora
+    Xrgb8888,
+}
+
+impl PixelFormat {
+    /// Negociar el formato a partir de la respuesta del mailbox
+    ///
+    /// `pixel_order` es el valor del tag de orden de pixel (0 = BGR, 1 = RGB) y
+    /// `alpha_mode` el del tag de modo alfa (2 = canal alfa ignorado).
+    pub fn from_mailbox(depth: u32, pixel_order: u32, alpha_mode: u32) -> Self {
+        match (depth, pixel_order) {
+            (16, _) => PixelFormat::Rgb565,
+            (_, mailbox::MBOX_PIXEL_ORDER_RGB) => PixelFormat::Rgba8888,
+            _ if alpha_mode == mailbox::MBOX_ALPHA_MODE_IGNORED => PixelFormat::Xrgb8888,
+            _ => PixelFormat::Bgra8888,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            _ => 4,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelFormat::Rgb565 => "RGB565",
+            PixelFormat::Bgra8888 => "BGRA8888",
+            PixelFormat::Rgba8888 => "RGBA8888",
+            PixelFormat::Xrgb8888 => "XRGB8888",
+        }
+    }
+
+    /// Codificar un color al valor crudo que se escribe en memoria (little-endian)
+    #[inline]
+    pub fn encode(self, color: Rgb888) -> u32 {
+        let (r, g, b) = (color.r() as u32, color.g() as u32, color.b() as u32);
+        match self {
+            PixelFormat::Rgb565 => ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3),
+            PixelFormat::Bgra8888 => 0xFF00_0000 | (r << 16) | (g << 8) | b,
+            PixelFormat::Rgba8888 => 0xFF00_0000 | (b << 16) | (g << 8) | r,
+            PixelFormat::Xrgb8888 => (r << 16) | (g << 8) | b,
+        }
+    }
+
+    /// Decodificar un valor crudo del framebuffer (inverso de `encode`)
+    #[inline]
+    pub fn decode(self, raw: u32) -> Rgb888 {
+        match self {
+            PixelFormat::Rgb565 => {
+                let (r, g, b) = ((raw >> 11) & 0x1F, (raw >> 5) & 0x3F, raw & 0x1F);
+                Rgb888::new(((r << 3) | (r >> 2)) as u8, ((g << 2) | (g >> 4)) as u8, ((b << 3) | (b >> 2)) as u8)
+            }
+            PixelFormat::Bgra8888 | PixelFormat::Xrgb8888 => Rgb888::new((raw >> 16) as u8, (raw >> 8) as u8, raw as u8),
+            PixelFormat::Rgba8888 => Rgb888::new(raw as u8, (raw >> 8) as u8, (raw >> 16) as u8),
+        }
+    }
+}
+
+/// Estrategia de doble buffer del framebuffer
+pub enum Buffering {
+    /// Sin back buffer: se dibuja directamente en la página visible
+    Single,
+    /// Dos páginas en la memoria de vídeo (altura virtual 2x);
+    /// `present()` cambia la página visible con `Display::flip`
+    PageFlip { base: *mut u8, back_page: u32 },
+    /// Back buffer en el heap; `present()` lo copia a la página visible
+    Software { scanout: *mut u8, buffer: Vec<u8> },
+    /// Superficie del compositor en el heap: no se muestra por sí misma
+    Offscreen { buffer: Vec<u8> },
+}
+
+impl Buffering {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Buffering::Single => "simple",
+            Buffering::PageFlip { .. } => "page flip",
+            Buffering::Software { .. } => "software",
+            Buffering::Offscreen { .. } => "offscreen",
+        }
+    }
+}
+
+/// Resultado de una prueba del benchmark de relleno y copia
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    /// Memoria medida: "heap" (superficies y back buffer) o "vídeo" (la que lee la pantalla)
+    pub memory: &'static str,
+    /// Bytes escritos en cada repetición
+    pub bytes: u64,
+    /// Tiempo medio por repetición pixel a pixel y con `blit`, en microsegundos
+    pub per_pixel_us: u64,
+    pub fast_us: u64,
+}
+
+impl BenchmarkResult {
+    /// Veces que es más rápido `blit` que pixel a pixel
+    pub fn speedup(&self) -> f32 {
+        self.per_pixel_us as f32 / self.fast_us.max(1) as f32
+    }
+
+    /// Ancho de banda con `blit`, en MB/s
+    pub fn megabytes_per_second(&self) -> u64 {
+        self.bytes / self.fast_us.max(1)
+    }
+}
+
+/// Relleno o contorno de una figura, con el color de dibujo actual
+#[derive(Clone, Copy)]
+pub struct ShapeStyle {
+    pub filled: bool,
+    /// Grosor del contorno en pixels (mínimo 1); no se usa si `filled`
+    pub stroke_width: u32,
+}
+
+impl ShapeStyle {
+    pub fn new(filled: bool, stroke_width: u32) -> Self {
+        Self { filled, stroke_width }
+    }
 }
 
 /// Framebuffer virtual para renderizar gráficos
 pub struct FrameBuffer {
+    /// Página en la que se dibuja (el back buffer si hay doble buffer)
     pixels: *mut u8,
     width: u32,
     height: u32,
-    bytes_per_pixel: u32,
+    /// Bits por pixel
+    depth: u32,
+    format: PixelFormat,
     pitch: u32,
+    buffering: Buffering,
+    /// Alfa con el que se mezcla lo que se dibuja (color × opacidad global)
+    alpha: u8,
+    /// Ventana de la app en coordenadas de pantalla: su esquina es el origen local
+    viewport: Rectangle,
+    /// Recorte activo en coordenadas de pantalla, siempre dentro del viewport
+    clip: Rectangle,
+    /// Zona modificada desde el último `take_damage` o `present`: la que recompone
+    /// el compositor y la que copia `present`
+    damage: Option<Rectangle>,
+    /// Pantalla en la que se presenta; `None` en las superficies offscreen
+    display: Option<Box<dyn Display>>,
 }
 
 impl FrameBuffer {
-    /// Crear nuevo framebuffer usando la dirección y el pitch obtenidos del mailbox
-    pub fn new(framebuffer_address: u32, pitch: u32) -> Self {
-        // Guess depth from pitch
-        let bytes_per_pixel = if pitch == SCREEN_WIDTH * 2 { 2 } else { 4 };
-        
+    /// Crear nuevo framebuffer que presenta en `display`
+    ///
+    /// Con `page_flip` se dibuja en la segunda página; si no, se intenta un back
+    /// buffer en el heap y, sin memoria, se dibuja directamente.
+    pub fn new(mut display: Box<dyn Display>) -> Self {
+        let mode = display.mode();
+        let scanout = display.scanout();
+        let page_size = (mode.pitch * mode.height) as usize;
+
+        let buffering = if mode.page_flip {
+            Buffering::PageFlip { base: scanout, back_page: 1 }
+        } else {
+            match zeroed_buffer(page_size) {
+                Some(buffer) => Buffering::Software { scanout, buffer },
+                None => Buffering::Single,
+            }
+        };
+
+        let pixels = match &buffering {
+            Buffering::Single => scanout,
+            Buffering::PageFlip { base, back_page } => unsafe { base.add(*back_page as usize * page_size) },
+            Buffering::Software { buffer, .. } | Buffering::Offscreen { buffer } => buffer.as_ptr() as *mut u8,
+        };
+
         Self {
-            pixels: framebuffer_address as *mut u8,
-            width: SCREEN_WIDTH,
-            height: SCREEN_HEIGHT,
-            bytes_per_pixel,
+            pixels,
+            width: mode.width,
+            height: mode.height,
+            depth: mode.depth,
+            format: mode.format,
+            pitch: mode.pitch,
+            buffering,
+            alpha: 255,
+            viewport: Rectangle::new(Point::zero(), Size::new(mode.width, mode.height)),
+            clip: Rectangle::new(Point::zero(), Size::new(mode.width, mode.height)),
+            damage: None,
+            display: Some(display),
+        }
+    }
+
+    /// Crear un buffer en el heap con el formato de la pantalla, para el compositor
+    ///
+    /// Devuelve `None` si no hay memoria. Empieza en negro.
+    pub fn offscreen(width: u32, height: u32, format: PixelFormat, depth: u32) -> Option<Self> {
+        let pitch = width * format.bytes_per_pixel();
+        let size = (pitch * height) as usize;
+        let buffer = zeroed_buffer(size)?;
+
+        let mut framebuffer = Self {
+            pixels: buffer.as_ptr() as *mut u8,
+            width,
+            height,
+            depth,
+            format,
             pitch,
+            buffering: Buffering::Offscreen { buffer },
+            alpha: 255,
+            viewport: Rectangle::new(Point::zero(), Size::new(width, height)),
+            clip: Rectangle::new(Point::zero(), Size::new(width, height)),
+            damage: None,
+            display: None,
+        };
+        framebuffer.fill_rect(0, 0, width, height, theme::current().background);
+        Some(framebuffer)
+    }
+
+    /// Zona modificada desde la última llamada, en coordenadas del buffer
+    pub fn take_damage(&mut self) -> Option<Rectangle> {
+        self.damage.take()
+    }
+
+    /// Añadir un rectángulo (coordenadas del buffer) a la zona modificada
+    #[inline]
+    fn add_damage(&mut self, rect: Rectangle) {
+        self.damage = Some(match self.damage {
+            Some(damage) => union(damage, rect),
+            None => rect,
+        });
+    }
+
+    /// Añadir a la zona modificada la caja (en coordenadas locales) de una primitiva
+    ///
+    /// Lo que se dibuja pixel a pixel (`plot`, `plot_alpha` y el `DrawTarget`) no
+    /// marca daño: quien dibuja la primitiva lo marca una vez, recortado al clip.
+    pub fn add_local_damage(&mut self, rect: Rectangle) {
+        let area = Rectangle::new(self.viewport.top_left + rect.top_left, rect.size).intersection(&self.clip);
+        if !area.is_zero_sized() {
+            self.add_damage(area);
+        }
+    }
+
+    /// Copiar `area` de `source` a la posición `dest` de este buffer
+    ///
+    /// Ambos buffers deben tener el mismo formato; las zonas se recortan a los dos.
+    pub fn copy_from(&mut self, source: &FrameBuffer, area: Rectangle, dest: Point) {
+        let area = area.intersection(&source.bounds());
+        let target = Rectangle::new(dest, area.size).intersection(&self.bounds());
+        if target.is_zero_sized() || source.format != self.format {
+            return;
+        }
+        let src_origin = area.top_left + (target.top_left - dest);
+        let bpp = self.format.bytes_per_pixel();
+        let row_bytes = (target.size.width * bpp) as usize;
+        for row in 0..target.size.height {
+            unsafe {
+                let src = source
+                    .pixels
+                    .add(((src_origin.y as u32 + row) * source.pitch + src_origin.x as u32 * bpp) as usize);
+                let dst = self
+                    .pixels
+                    .add(((target.top_left.y as u32 + row) * self.pitch + target.top_left.x as u32 * bpp) as usize);
+                blit::copy_span(dst, src, bpp, row_bytes);
+            }
+        }
+        self.add_damage(target);
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Alfa de dibujo que usan `plot`, `plot_alpha`, `blend_rect` y el `DrawTarget`
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// Rectángulo de toda la pantalla
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(self.width, self.height))
+    }
+
+    /// Fijar la ventana (en coordenadas de pantalla) y recortar a ella
+    ///
+    /// `plot`, `plot_alpha`, `blend_rect` y el `DrawTarget` reciben coordenadas
+    /// locales a esta ventana; el resto de métodos trabaja en coordenadas de pantalla.
+    pub fn set_viewport(&mut self, viewport: Rectangle) {
+        self.viewport = viewport.intersection(&self.bounds());
+        self.clip = self.viewport;
+    }
+
+    pub fn viewport(&self) -> Rectangle {
+        self.viewport
+    }
+
+    /// Fijar el recorte (en coordenadas de pantalla), limitado al viewport
+    pub fn set_clip(&mut self, clip: Rectangle) {
+        self.clip = clip.intersection(&self.viewport);
+    }
+
+    pub fn clip(&self) -> Rectangle {
+        self.clip
+    }
+
+    /// Recorte activo en coordenadas locales al viewport
+    pub fn local_clip(&self) -> Rectangle {
+        Rectangle::new(self.clip.top_left - self.viewport.top_left, self.clip.size)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pitch(&self) -> u32 {
+        self.pitch
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn buffering(&self) -> &Buffering {
+        &self.buffering
+    }
+
+    /// Mostrar en pantalla lo dibujado en el back buffer
+    ///
+    /// Solo se copia la zona modificada desde el último `present`: con page flip,
+    /// de la página visible al nuevo back buffer (las apps dibujan de forma
+    /// incremental sobre el frame anterior); con back buffer por software, del
+    /// heap a la página visible.
+    pub fn present(&mut self) {
+        let page_size = (self.pitch * self.height) as usize;
+        let Some(display) = self.display.as_mut() else {
+            return;
+        };
+        let bpp = self.format.bytes_per_pixel();
+        match &mut self.buffering {
+            Buffering::Single => {
+                self.damage = None;
+                display.frame_ready();
+            }
+            Buffering::PageFlip { base, back_page } => {
+                let front = *back_page;
+                // Si no se puede cambiar de página, el daño sigue pendiente para el siguiente intento
+                if display.flip(front) {
+                    *back_page = 1 - front;
+                    unsafe {
+                        let front_ptr = base.add(front as usize * page_size);
+                        self.pixels = base.add(*back_page as usize * page_size);
+                        if let Some(damage) = self.damage.take() {
+                            copy_rect(self.pixels, front_ptr, self.pitch, bpp, damage);
+                        }
+                    }
+                }
+            }
+            Buffering::Software { scanout, buffer } => {
+                if let Some(damage) = self.damage.take() {
+                    unsafe { copy_rect(*scanout, buffer.as_ptr(), self.pitch, bpp, damage) };
+                }
+                display.frame_ready();
+            }
+            Buffering::Offscreen { .. } => {}
+        }
+    }
+
+    /// Escribir un valor ya codificado en el pixel (x, y) sin comprobar límites
+    ///
+    /// # Safety
+    /// `x` e `y` deben estar dentro del framebuffer.
+    #[inline]
+    unsafe fn write_raw(&mut self, x: u32, y: u32, raw: u32) {
+        let offset = (y * self.pitch + x * self.format.bytes_per_pixel()) as usize;
+        unsafe {
+            if self.format.bytes_per_pixel() == 2 {
+                *(self.pixels.add(offset) as *mut u16) = raw as u16;
+            } else {
+                *(self.pixels.add(offset) as *mut u32) = raw;
+            }
+        }
+    }
+
+    /// Leer el valor crudo del pixel (x, y) sin comprobar límites
+    ///
+    /// # Safety
+    /// `x` e `y` deben estar dentro del framebuffer.
+    #[inline]
+    unsafe fn read_raw(&self, x: u32, y: u32) -> u32 {
+        let offset = (y * self.pitch + x * self.format.bytes_per_pixel()) as usize;
+        unsafe {
+            if self.format.bytes_per_pixel() == 2 {
+                *(self.pixels.add(offset) as *const u16) as u32
+            } else {
+                *(self.pixels.add(offset) as *const u32)
+            }
+        }
+    }
+
+    /// Color del pixel (x, y) en el buffer de dibujo
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<Rgb888> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return None;
+        }
+        Some(self.format.decode(unsafe { self.read_raw(x as u32, y as u32) }))
+    }
+
+    /// Mezclar un color sobre el pixel con una cobertura/alfa de 0 a 255
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Rgb888, alpha: u8) {
+        match alpha {
+            0 => {}
+            255 => self.set_pixel(x, y, color),
+            _ => {
+                let Some(dst) = self.get_pixel(x, y) else {
+                    return;
+                };
+                let a = alpha as u32;
+                let mix = |src: u8, dst: u8| ((src as u32 * a + dst as u32 * (255 - a) + 127) / 255) as u8;
+                self.set_pixel(x, y, Rgb888::new(mix(color.r(), dst.r()), mix(color.g(), dst.g()), mix(color.b(), dst.b())));
+            }
+        }
+    }
+
+    /// Dibujar un pixel, ignorando los que caen fuera de la pantalla
+    ///
+    /// No marca daño: lo hace la primitiva que lo dibuja (ver `add_local_damage`).
+    #[inline]
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Rgb888) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
         }
+        let raw = self.format.encode(color);
+        unsafe { self.write_raw(x as u32, y as u32, raw) };
     }
     
-    /// Limpiar pantalla con un color
-    pub fn clear(&mut self, color: Rgb888) {
-        let (r, g, b) = (color.r(), color.g(), color.b());
-        
-        // Pre-calculate 16-bit color
-        let r5 = (r >> 3) as u16;
-        let g6 = (g >> 2) as u16;
-        let b5 = (b >> 3) as u16;
-        let rgb565 = (r5 << 11) | (g6 << 5) | b5;
-        
+    /// Dibujar un pixel en coordenadas locales mezclándolo con el alfa de dibujo (source-over)
+    pub fn plot(&mut self, x: i32, y: i32, color: Rgb888) {
+        let point = self.viewport.top_left + Point::new(x, y);
+        if self.clip.contains(point) {
+            self.blend_pixel(point.x, point.y, color, self.alpha);
+        }
+    }
+
+    /// Dibujar un pixel en coordenadas locales con cobertura/alfa propia, combinada con el alfa de dibujo
+    pub fn plot_alpha(&mut self, x: i32, y: i32, color: Rgb888, alpha: u8) {
+        let point = self.viewport.top_left + Point::new(x, y);
+        if self.clip.contains(point) {
+            self.blend_pixel(point.x, point.y, color, mul_alpha(alpha, self.alpha));
+        }
+    }
+
+    /// Rellenar un rectángulo, recortado a la pantalla
+    ///
+    /// Cada fila se rellena con `blit::fill_span` (escrituras de 128 bits); si el
+    /// rectángulo ocupa filas completas, todo él es un único tramo.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgb888) {
+        let raw = self.format.encode(color);
+        let x_end = x.saturating_add(width).min(self.width);
+        let y_end = y.saturating_add(height).min(self.height);
+        if x >= x_end || y >= y_end {
+            return;
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let row_pixels = (x_end - x) as usize;
         unsafe {
-            for y in 0..self.height {
-                for x in 0..self.width {
-                    if self.bytes_per_pixel == 2 {
-                        let offset = (y * self.pitch + x * 2) as usize;
-                        *(self.pixels.add(offset) as *mut u16) = rgb565;
-                    } else {
-                        let offset = (y * self.pitch + x * 4) as usize;
-                        *self.pixels.add(offset) = b; // Blue (BGRA)
-                        *self.pixels.add(offset + 1) = g; // Green
-                        *self.pixels.add(offset + 2) = r; // Red
-                        *self.pixels.add(offset + 3) = 255; // Alpha
+            let start = self.pixels.add((y * self.pitch + x * bpp) as usize);
+            if row_pixels as u32 * bpp == self.pitch {
+                blit::fill_span(start, raw, bpp, row_pixels * (y_end - y) as usize);
+            } else {
+                for row in 0..(y_end - y) as usize {
+                    blit::fill_span(start.add(row * self.pitch as usize), raw, bpp, row_pixels);
+                }
+            }
+        }
+        self.add_damage(Rectangle::new(Point::new(x as i32, y as i32), Size::new(x_end - x, y_end - y)));
+    }
+
+    /// Medir el relleno de toda la pantalla, de un rectángulo y una copia de media
+    /// pantalla, pixel a pixel y con `blit`
+    ///
+    /// Deja el buffer con basura: hay que redibujarlo después.
+    pub fn benchmark(&mut self) -> Vec<BenchmarkResult> {
+        let (width, height) = (self.width, self.height);
+        let bpp = self.format.bytes_per_pixel();
+        let memory = match &self.buffering {
+            Buffering::Software { scanout, .. } if self.pixels == *scanout => "vídeo",
+            Buffering::Software { .. } | Buffering::Offscreen { .. } => "heap",
+            Buffering::Single | Buffering::PageFlip { .. } => "vídeo",
+        };
+        let colors = [Rgb888::RED, Rgb888::GREEN, Rgb888::BLUE, Rgb888::WHITE];
+        let mut results = Vec::new();
+
+        // Toda la pantalla, como `clear_screen`
+        let bytes = (width * height * bpp) as u64;
+        let per_pixel_us = time_repeats(|i| self.fill_rect_per_pixel(0, 0, width, height, colors[i % 4]));
+        let fast_us = time_repeats(|i| self.fill_rect(0, 0, width, height, colors[i % 4]));
+        results.push(BenchmarkResult { name: "clear", memory, bytes, per_pixel_us, fast_us });
+
+        // Rectángulo en una columna impar, con cabecera y cola desalineadas
+        let (rect_width, rect_height) = (width.saturating_sub(2).min(301), height.saturating_sub(2).min(200));
+        let bytes = (rect_width * rect_height * bpp) as u64;
+        let per_pixel_us = time_repeats(|i| self.fill_rect_per_pixel(1, 1, rect_width, rect_height, colors[i % 4]));
+        let fast_us = time_repeats(|i| self.fill_rect(1, 1, rect_width, rect_height, colors[i % 4]));
+        results.push(BenchmarkResult { name: "rect", memory, bytes, per_pixel_us, fast_us });
+
+        // Mitad superior sobre la inferior, fila a fila como `copy_from`
+        let half = height / 2;
+        let bytes = (width * half * bpp) as u64;
+        let per_pixel_us = time_repeats(|_| {
+            for y in 0..half {
+                for x in 0..width {
+                    unsafe { self.write_raw(x, y + half, self.read_raw(x, y)) };
+                }
+            }
+        });
+        let fast_us = time_repeats(|_| {
+            for y in 0..half {
+                unsafe {
+                    let src = self.pixels.add((y * self.pitch) as usize);
+                    blit::copy_span(src.add((half * self.pitch) as usize), src, bpp, (width * bpp) as usize);
+                }
+            }
+        });
+        results.push(BenchmarkResult { name: "blit", memory, bytes, per_pixel_us, fast_us });
+
+        self.add_damage(self.bounds());
+        results
+    }
+
+    /// `benchmark` sobre la memoria de vídeo que lee la pantalla
+    ///
+    /// Con back buffer por software se mide sobre la página visible en vez de
+    /// sobre el heap; el contenido de la pantalla queda destruido hasta recomponerla.
+    pub fn benchmark_scanout(&mut self) -> Vec<BenchmarkResult> {
+        let pixels = self.pixels;
+        if let Buffering::Software { scanout, .. } = &self.buffering {
+            self.pixels = *scanout;
+        }
+        let results = self.benchmark();
+        self.pixels = pixels;
+        results
+    }
+
+    /// Rellenar un rectángulo pixel a pixel, como antes de `blit`: referencia de `benchmark`
+    fn fill_rect_per_pixel(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgb888) {
+        let raw = self.format.encode(color);
+        let x_end = x.saturating_add(width).min(self.width);
+        let y_end = y.saturating_add(height).min(self.height);
+        for py in y..y_end {
+            for px in x..x_end {
+                unsafe { self.write_raw(px, py, raw) };
+            }
+        }
+    }
+
+    /// Rellenar un rectángulo en coordenadas locales, recortado y mezclado con el alfa de dibujo
+    pub fn blend_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Rgb888) {
+        let area = Rectangle::new(self.viewport.top_left + Point::new(x, y), Size::new(width, height)).intersection(&self.clip);
+        if area.is_zero_sized() {
+            return;
+        }
+        let (x, y) = (area.top_left.x as u32, area.top_left.y as u32);
+        if self.alpha == 255 {
+            self.fill_rect(x, y, area.size.width, area.size.height, color);
+            return;
+        }
+        self.add_damage(area);
+        for py in y..y + area.size.height {
+            for px in x..x + area.size.width {
+                self.blend_pixel(px as i32, py as i32, color, self.alpha);
+            }
+        }
+    }
+
+    /// Desplazar hacia arriba `dy` filas el contenido de `area` (en coordenadas de pantalla)
+    /// y rellenar la franja liberada
+    pub fn scroll_up(&mut self, area: Rectangle, dy: u32, fill: Rgb888) {
+        let area = area.intersection(&self.bounds());
+        if area.is_zero_sized() {
+            return;
+        }
+        self.add_damage(area);
+        let (left, top) = (area.top_left.x as u32, area.top_left.y as u32);
+        let (width, height) = (area.size.width, area.size.height);
+        let dy = dy.min(height);
+        let moved_rows = height - dy;
+        if moved_rows > 0 {
+            let bpp = self.format.bytes_per_pixel();
+            unsafe {
+                let dst = self.pixels.add((top * self.pitch + left * bpp) as usize);
+                let src = dst.add((dy * self.pitch) as usize);
+                // Las zonas se solapan con el destino antes que el origen: copia hacia delante
+                if width == self.width {
+                    // Filas completas: una sola copia
+                    blit::copy_span(dst, src, bpp, (moved_rows * self.pitch) as usize);
+                } else {
+                    let row_bytes = (width * bpp) as usize;
+                    for row in 0..moved_rows as usize {
+                        let offset = row * self.pitch as usize;
+                        blit::copy_span(dst.add(offset), src.add(offset), bpp, row_bytes);
                     }
                 }
             }
         }
+        self.fill_rect(left, top + height - dy, width, dy, fill);
     }
     
 }
@@ -89,359 +677,735 @@ impl DrawTarget for FrameBuffer {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for Pixel(coord, color) in pixels.into_iter() {
-            let x = coord.x as u32;
-            let y = coord.y as u32;
-            
-            if x < self.width && y < self.height {
-                let index = (y * self.pitch + x * self.bytes_per_pixel) as usize;
-                
-                unsafe {
-                    *self.pixels.add(index) = color.r();     // Red
-                    *self.pixels.add(index + 1) = color.g(); // Green
-                    *self.pixels.add(index + 2) = color.b(); // Blue
-                    *self.pixels.add(index + 3) = 255;       // Alpha
-                }
-            }
+            self.plot(coord.x, coord.y, color);
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.blend_rect(area.top_left.x, area.top_left.y, area.size.width, area.size.height, color);
+        Ok(())
+    }
 }
 
+/// El `DrawTarget` trabaja en coordenadas locales al viewport
 impl OriginDimensions for FrameBuffer {
     fn size(&self) -> Size {
-        Size::new(self.width, self.height)
+        self.viewport.size
     }
 }
 
 /// Manejador de gráficos para FerroOS
 pub struct GraphicsManager {
+    /// Buffer de la superficie activa, sacado del compositor mientras se dibuja en ella
     framebuffer: FrameBuffer,
+    compositor: Compositor,
+    /// Superficie en la que se dibuja ahora
+    surface: SurfaceId,
+    shell_surface: SurfaceId,
+    status_bar_surface: SurfaceId,
+    notification_surface: SurfaceId,
     current_color: Rgb888,
+    /// Alfa del color actual (0 transparente, 255 opaco)
+    current_alpha: u8,
+    /// Opacidad global que multiplica el alfa de todo lo que se dibuja
+    opacity: u8,
     cursor_x: i32,
     cursor_y: i32,
     line_height: i32,
+    /// Historial de líneas de la consola, de la más antigua a la más reciente
+    scrollback: VecDeque<ConsoleLine>,
+    fonts: FontRegistry,
+    /// Fuentes TrueType/OpenType de los assets del paquete
+    vector_fonts: Vec<VectorFont>,
+    glyph_cache: GlyphCache,
+    font: FontSelection,
+    image_cache: ImageCache,
+    /// Pila de recortes en coordenadas de pantalla; cada uno ya está dentro del anterior
+    clip_stack: Vec<Rectangle>,
+    /// Terminal ANSI sobre la zona de la consola, creado la primera vez que se usa
+    terminal: Option<Terminal>,
+}
+
+/// Línea de texto de la consola guardada en el historial
+struct ConsoleLine {
+    color: Rgb888,
+    font: FontSelection,
+    text: String,
 }
 
 impl GraphicsManager {
+    /// Gráficos sobre la pantalla del dispositivo, negociada por el mailbox
     pub fn new() -> Self {
-        // Define the mailbox message buffer
-        // The buffer must be 16-byte aligned
-        #[repr(C, align(16))]
-        struct MailboxBuffer {
-            header: mailbox::MailboxMessage,
-            // Set physical display size
-            tag_set_physical_display_size: u32,
-            value_buf_size_set_physical_display_size: u32,
-            request_response_code_set_physical_display_size: u32,
-            width: u32,
-            height: u32,
-            // Set virtual display size
-            tag_set_virtual_display_size: u32,
-            value_buf_size_set_virtual_display_size: u32,
-            request_response_code_set_virtual_display_size: u32,
-            vwidth: u32,
-            vheight: u32,
-            // Set depth
-            tag_set_depth: u32,
-            value_buf_size_set_depth: u32,
-            request_response_code_set_depth: u32,
-            depth: u32,
-            // Allocate buffer
-            tag_allocate_buffer: u32,
-            value_buf_size_allocate_buffer: u32,
-            request_response_code_allocate_buffer: u32,
-            alignment: u32,
-            // Response values for allocate buffer
-            fb_address: u32,
-            fb_size: u32,
-            // Get pitch
-            tag_get_pitch: u32,
-            value_buf_size_get_pitch: u32,
-            request_response_code_get_pitch: u32,
-            pitch: u32,
-            // End tag
-            end_tag: u32,
-        }
-
-        let mut mbox_buffer = MailboxBuffer {
-            header: mailbox::MailboxMessage {
-                buffer_size: core::mem::size_of::<MailboxBuffer>() as u32,
-                request_response_code: mailbox::MBOX_REQUEST,
-            },
-            // Set physical display size
-            tag_set_physical_display_size: mailbox::MBOX_TAG_SET_PHYSICAL_DISPLAY_SIZE,
-            value_buf_size_set_physical_display_size: 8,
-            request_response_code_set_physical_display_size: 8,
-            width: SCREEN_WIDTH,
-            height: SCREEN_HEIGHT,
-            // Set virtual display size
-            tag_set_virtual_display_size: mailbox::MBOX_TAG_SET_VIRTUAL_DISPLAY_SIZE,
-            value_buf_size_set_virtual_display_size: 8,
-            request_response_code_set_virtual_display_size: 8,
-            vwidth: SCREEN_WIDTH,
-            vheight: SCREEN_HEIGHT,
-            // Set depth
-            tag_set_depth: mailbox::MBOX_TAG_SET_DEPTH,
-            value_buf_size_set_depth: 4,
-            request_response_code_set_depth: 4,
-            depth: 32, // 32 bits per pixel (RGBA8888)
-            // Allocate buffer
-            tag_allocate_buffer: mailbox::MBOX_TAG_ALLOCATE_BUFFER,
-            value_buf_size_allocate_buffer: 8,
-            request_response_code_allocate_buffer: 4, // Send 4 bytes (alignment)
-            alignment: 4096, // 4096-byte alignment
-            fb_address: 0,
-            fb_size: 0,
-            // Get pitch
-            tag_get_pitch: mailbox::MBOX_TAG_GET_PITCH,
-            value_buf_size_get_pitch: 4,
-            request_response_code_get_pitch: 0,
-            pitch: 0,
-            // End tag
-            end_tag: 0,
-        };
+        Self::with_display(Box::new(MailboxDisplay::new()))
+    }
 
-        let mut framebuffer_address: u32 = 0;
-        let mut pitch: u32 = 0;
-
-        uart_send_str("  Enviando mensaje al mailbox...\n");
-        // Send the mailbox message
-        let result = mailbox::send_mailbox_message(&mut mbox_buffer.header);
-
-        match result {
-            Ok(_) => {
-                uart_send_str("  ✅ Mensaje de mailbox enviado y procesado.\n");
-                framebuffer_address = mbox_buffer.fb_address;
-                pitch = mbox_buffer.pitch;
-                
-                // The GPU returns the address with the high bit set if it's a cached address.
-                // We need to clear it to get the physical address.
-                framebuffer_address &= 0x3FFF_FFFF;
-
-                if framebuffer_address == 0 {
-                    uart_send_str("  ⚠️ Framebuffer 0 detectado (QEMU VC4 falla allocation).\n");
-                    uart_send_str("  🔧 Usando dirección fallback: 0x3C100000 (16-bit)\n");
-                    framebuffer_address = 0x3C100000;
-                    // Force pitch to 1280 (640 width * 2 bytes/pixel for RGB565)
-                    pitch = 640 * 2;
-                }
+    /// Gráficos sobre cualquier pantalla (la del mailbox, una en memoria para los tests...)
+    pub fn with_display(display: Box<dyn Display>) -> Self {
+        let mode = display.mode();
+        uart_send_str("  Pantalla: ");
+        uart_send_str(display.name());
+        uart_send_str("\n");
+        uart_send_str("  Modo: ");
+        print_number(mode.width as u64);
+        uart_send_str("x");
+        print_number(mode.height as u64);
+        uart_send_str("x");
+        print_number(mode.depth as u64);
+        uart_send_str("\n");
+        uart_send_str("  Pitch: ");
+        print_number(mode.pitch as u64);
+        uart_send_str("\n");
+        uart_send_str("  Formato: ");
+        uart_send_str(mode.format.name());
+        uart_send_str("\n");
 
-                uart_send_str("  Framebuffer Address: ");
-                print_number(framebuffer_address as u64);
-                uart_send_str("\n");
-                uart_send_str("  Pitch: ");
-                print_number(pitch as u64);
-                uart_send_str("\n");
-            }
-            Err(_) => {
-                uart_send_str("  ❌ Error al enviar mensaje al mailbox.\n");
-                // Fallback to a known address if mailbox fails
-                framebuffer_address = 0x3C100000; 
-                pitch = 640 * 2;
-            }
-        }
+        // Superficies del sistema: el shell ocupa toda la pantalla y la barra de
+        // estado y las notificaciones quedan ocultas hasta que se dibujan
+        let mut compositor = Compositor::new(FrameBuffer::new(display));
+        let screen = compositor.screen().bounds();
+        let cursor = Point::new(10, 30);
+        let status_bar = Rectangle::new(Point::zero(), Size::new(mode.width, CONSOLE_TOP as u32));
+        let notification = Rectangle::new(Point::new(0, CONSOLE_TOP), Size::new(mode.width, NOTIFICATION_HEIGHT));
+        let (Some(shell_surface), Some(status_bar_surface), Some(notification_surface)) = (
+            compositor.create_surface("shell", screen, Z_SHELL, cursor),
+            compositor.create_surface("status_bar", status_bar, Z_STATUS_BAR, Point::zero()),
+            compositor.create_surface("notifications", notification, Z_NOTIFICATIONS, Point::zero()),
+        ) else {
+            panic!("sin memoria para las superficies del sistema");
+        };
+        compositor.set_visible(status_bar_surface, false);
+        compositor.set_visible(notification_surface, false);
+        let (framebuffer, _) = compositor.take_canvas(shell_surface).expect("superficie del shell recién creada");
 
         let mut manager = Self {
-            framebuffer: FrameBuffer::new(framebuffer_address, pitch),
-            current_color: colors::WHITE,
+            framebuffer,
+            compositor,
+            surface: shell_surface,
+            shell_surface,
+            status_bar_surface,
+            notification_surface,
+            current_color: theme::current().text,
+            current_alpha: 255,
+            opacity: 255,
             cursor_x: 10,
             cursor_y: 30,
             line_height: 25,
+            scrollback: VecDeque::new(),
+            fonts: FontRegistry::new(),
+            vector_fonts: Vec::new(),
+            glyph_cache: GlyphCache::new(),
+            font: FontSelection::BUILTIN,
+            image_cache: ImageCache::new(),
+            clip_stack: Vec::new(),
+            terminal: None,
         };
+        manager.load_fonts();
         
+        uart_send_str("  Doble buffer: ");
+        uart_send_str(manager.buffering().name());
+        uart_send_str("\n");
+
         // Inicializar con pantalla negra
         manager.clear_screen();
+        manager.present();
         manager
     }
-    
-    /// Limpiar pantalla
+
+    /// Limpiar pantalla (el recorte activo, que sin viewport ni recortes es toda la superficie)
     pub fn clear_screen(&mut self) {
-        self.framebuffer.clear(colors::BLACK);
+        let clip = self.framebuffer.clip();
+        if !clip.is_zero_sized() {
+            let (x, y) = (clip.top_left.x as u32, clip.top_left.y as u32);
+            self.framebuffer.fill_rect(x, y, clip.size.width, clip.size.height, theme::current().background);
+        }
         self.cursor_x = 10;
-        self.cursor_y = 30;
+        self.cursor_y = self.text_top();
     }
-    
-    /// Cambiar color actual
-    pub fn set_color(&mut self, color: Rgb888) {
-        self.current_color = color;
+
+    /// Asignar la ventana de una app: desde aquí todo se dibuja en coordenadas
+    /// locales a ella y recortado a sus bordes
+    ///
+    /// Descarta la pila de recortes. El cursor de texto conserva su posición en
+    /// pantalla, así la consola continúa donde estaba al entrar y salir de la app.
+    pub fn set_viewport(&mut self, viewport: Rectangle) {
+        let old_origin = self.framebuffer.viewport().top_left;
+        self.clip_stack.clear();
+        self.framebuffer.set_viewport(viewport);
+        let offset = old_origin - self.framebuffer.viewport().top_left;
+        self.cursor_x += offset.x;
+        self.cursor_y = (self.cursor_y + offset.y).max(self.text_top());
+    }
+
+    /// Volver a dibujar en toda la pantalla
+    pub fn reset_viewport(&mut self) {
+        let bounds = self.framebuffer.bounds();
+        self.set_viewport(bounds);
+    }
+
+    /// Zona que el shell deja a las apps: toda la pantalla bajo la cabecera
+    pub fn app_area(&self) -> Rectangle {
+        Rectangle::new(Point::new(0, CONSOLE_TOP), Size::new(self.width(), self.height() - CONSOLE_TOP as u32))
+    }
+
+    /// Tamaño del viewport actual: lo que una app ve como su pantalla
+    pub fn viewport_size(&self) -> (u32, u32) {
+        let size = self.framebuffer.viewport().size;
+        (size.width, size.height)
+    }
+
+    /// Recortar el dibujo a `rect` (en coordenadas locales) dentro del recorte actual
+    pub fn push_clip(&mut self, rect: Rectangle) {
+        let current = self.framebuffer.clip();
+        self.clip_stack.push(current);
+        let rect = Rectangle::new(self.framebuffer.viewport().top_left + rect.top_left, rect.size);
+        self.framebuffer.set_clip(rect.intersection(&current));
+    }
+
+    /// Restaurar el recorte anterior; devuelve `false` si la pila estaba vacía
+    pub fn pop_clip(&mut self) -> bool {
+        match self.clip_stack.pop() {
+            Some(clip) => {
+                self.framebuffer.set_clip(clip);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Primera fila de la consola de texto en coordenadas locales: bajo la
+    /// cabecera del shell si la superficie y el viewport la incluyen
+    fn text_top(&self) -> i32 {
+        let surface_y = self.compositor.frame(self.surface).map_or(0, |frame| frame.top_left.y);
+        (CONSOLE_TOP - surface_y - self.framebuffer.viewport().top_left.y).max(0)
+    }
+
+    /// Última fila en la que puede empezar una línea antes de hacer scroll
+    fn text_bottom(&self) -> i32 {
+        self.framebuffer.viewport().size.height as i32 - 50
+    }
+
+    /// Zona de la consola en coordenadas de pantalla
+    fn console_area(&self) -> Rectangle {
+        let viewport = self.framebuffer.viewport();
+        let top = self.text_top();
+        Rectangle::new(
+            viewport.top_left + Point::new(0, top),
+            Size::new(viewport.size.width, viewport.size.height.saturating_sub(top as u32)),
+        )
     }
     
-    /// Fuente de 8x8 básica (ASCII 32-127)
-    /// Para depuración sin dependencias externas
-    const FONT_8X8: [u8; 768] = [
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Space (32)
-        0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00, // !
-        0x66, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, // "
-        0x6C, 0x6C, 0xFE, 0x6C, 0xFE, 0x6C, 0x6C, 0x00, // #
-        0x18, 0x3E, 0x60, 0x3C, 0x06, 0x7C, 0x18, 0x00, // $
-        0x00, 0xC6, 0xCC, 0x18, 0x30, 0x66, 0xC6, 0x00, // %
-        0x38, 0x6C, 0x38, 0x76, 0xDC, 0xCC, 0x76, 0x00, // &
-        0x18, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, // '
-        0x0C, 0x18, 0x30, 0x30, 0x30, 0x18, 0x0C, 0x00, // (
-        0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x18, 0x30, 0x00, // )
-        0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00, // *
-        0x00, 0x18, 0x18, 0x7E, 0x18, 0x18, 0x00, 0x00, // +
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x30, // ,
-        0x00, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00, // -
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, // .
-        0x00, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x00, 0x00, // /
-        0x3C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // 0 (48)
-        0x18, 0x38, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // 1
-        0x3C, 0x66, 0x06, 0x0C, 0x30, 0x60, 0x7E, 0x00, // 2
-        0x3C, 0x66, 0x06, 0x1C, 0x06, 0x66, 0x3C, 0x00, // 3
-        0x0C, 0x1C, 0x3C, 0x6C, 0xCC, 0xFE, 0x0C, 0x00, // 4
-        0x7E, 0x60, 0x7C, 0x06, 0x06, 0x66, 0x3C, 0x00, // 5
-        0x3C, 0x60, 0xFC, 0x66, 0x66, 0x66, 0x3C, 0x00, // 6
-        0x7E, 0x06, 0x0C, 0x18, 0x30, 0x30, 0x30, 0x00, // 7
-        0x3C, 0x66, 0x66, 0x3C, 0x66, 0x66, 0x3C, 0x00, // 8
-        0x3C, 0x66, 0x66, 0x66, 0x3E, 0x06, 0x3C, 0x00, // 9
-        0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x00, // :
-        0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x30, // ;
-        0x0C, 0x18, 0x30, 0x60, 0x30, 0x18, 0x0C, 0x00, // <
-        0x00, 0x00, 0x7E, 0x00, 0x7E, 0x00, 0x00, 0x00, // =
-        0x30, 0x18, 0x0C, 0x06, 0x0C, 0x18, 0x30, 0x00, // >
-        0x3C, 0x66, 0x06, 0x0C, 0x18, 0x00, 0x18, 0x00, // ?
-        0x3C, 0x66, 0x6E, 0x6E, 0x60, 0x62, 0x3C, 0x00, // @
-        0x18, 0x3C, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x00, // A
-        0xFC, 0x66, 0x66, 0x7C, 0x66, 0x66, 0xFC, 0x00, // B
-        0x3C, 0x66, 0x60, 0x60, 0x60, 0x66, 0x3C, 0x00, // C
-        0xF8, 0x6C, 0x66, 0x66, 0x66, 0x6C, 0xF8, 0x00, // D
-        0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x7E, 0x00, // E
-        0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x60, 0x00, // F
-        0x3C, 0x66, 0x60, 0x6E, 0x66, 0x66, 0x3C, 0x00, // G
-        0x66, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x66, 0x00, // H
-        0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // I
-        0x1E, 0x0C, 0x0C, 0x0C, 0xCC, 0xCC, 0x78, 0x00, // J
-        0xE6, 0x66, 0x6C, 0x78, 0x6C, 0x66, 0xE6, 0x00, // K
-        0xF0, 0x60, 0x60, 0x60, 0x60, 0x60, 0xF0, 0x00, // L
-        0xC6, 0xEE, 0xFE, 0xD6, 0xC6, 0xC6, 0xC6, 0x00, // M
-        0xC6, 0xE6, 0xF6, 0xDE, 0xCE, 0xC6, 0xC6, 0x00, // N
-        0x3C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // O
-        0xFC, 0x66, 0x66, 0x7C, 0x60, 0x60, 0x60, 0x00, // P
-        0x3C, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x0E, 0x00, // Q
-        0xFC, 0x66, 0x66, 0x7C, 0x6C, 0x66, 0xE6, 0x00, // R
-        0x3C, 0x66, 0x60, 0x3C, 0x06, 0x66, 0x3C, 0x00, // S
-        0x7E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x00, // T
-        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // U
-        0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x18, 0x00, // V
-        0xC6, 0xC6, 0xC6, 0xD6, 0xFE, 0xEE, 0xC6, 0x00, // W
-        0xC6, 0xC6, 0x6C, 0x38, 0x6C, 0xC6, 0xC6, 0x00, // X
-        0x66, 0x66, 0x66, 0x3C, 0x18, 0x18, 0x18, 0x00, // Y
-        0xFE, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFE, 0x00, // Z
-        0x3C, 0x30, 0x30, 0x30, 0x30, 0x30, 0x3C, 0x00, // [
-        0x00, 0x60, 0x30, 0x18, 0x0C, 0x06, 0x00, 0x00, // Backslash
-        0x3C, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x3C, 0x00, // ]
-        0x10, 0x38, 0x6C, 0xC6, 0x00, 0x00, 0x00, 0x00, // ^
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, // _
-        0x18, 0x18, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, // `
-        0x00, 0x00, 0x3C, 0x06, 0x3E, 0x66, 0x3E, 0x00, // a (97)
-        0x60, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x7C, 0x00, // b
-        0x00, 0x00, 0x3C, 0x60, 0x60, 0x60, 0x3C, 0x00, // c
-        0x06, 0x06, 0x3E, 0x66, 0x66, 0x66, 0x3E, 0x00, // d
-        0x00, 0x00, 0x3C, 0x66, 0x7E, 0x60, 0x3C, 0x00, // e
-        0x1C, 0x36, 0x30, 0x78, 0x30, 0x30, 0x30, 0x00, // f
-        0x00, 0x00, 0x3E, 0x66, 0x66, 0x3E, 0x06, 0x3C, // g
-        0x60, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x66, 0x00, // h
-        0x18, 0x00, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // i
-        0x06, 0x00, 0x06, 0x06, 0x06, 0x66, 0x66, 0x3C, // j
-        0x60, 0x60, 0x66, 0x6C, 0x78, 0x6C, 0x66, 0x00, // k
-        0x38, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, // l
-        0x00, 0x00, 0xEC, 0xFE, 0xFE, 0xD6, 0xC6, 0x00, // m
-        0x00, 0x00, 0xDC, 0x66, 0x66, 0x66, 0x66, 0x00, // n
-        0x00, 0x00, 0x3C, 0x66, 0x66, 0x66, 0x3C, 0x00, // o
-        0x00, 0x00, 0xDC, 0x66, 0x66, 0x7C, 0x60, 0xF0, // p
-        0x00, 0x00, 0x76, 0x66, 0x66, 0x7C, 0x06, 0x1E, // q
-        0x00, 0x00, 0xDC, 0x66, 0x60, 0x60, 0xF0, 0x00, // r
-        0x00, 0x00, 0x3C, 0x60, 0x3C, 0x06, 0x7C, 0x00, // s
-        0x30, 0x30, 0x7C, 0x30, 0x30, 0x30, 0x1C, 0x00, // t
-        0x00, 0x00, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00, // u
-        0x00, 0x00, 0x66, 0x66, 0x66, 0x3C, 0x18, 0x00, // v
-        0x00, 0x00, 0xC6, 0xD6, 0xFE, 0xEE, 0xC4, 0x00, // w
-        0x00, 0x00, 0x66, 0x3C, 0x18, 0x3C, 0x66, 0x00, // x
-        0x00, 0x00, 0x66, 0x66, 0x66, 0x3E, 0x0C, 0x78, // y
-        0x00, 0x00, 0x7E, 0x0C, 0x18, 0x30, 0x7E, 0x00, // z
-        0x0C, 0x18, 0x18, 0x30, 0x18, 0x18, 0x0C, 0x00, // {
-        0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, // |
-        0x30, 0x18, 0x18, 0x0C, 0x18, 0x18, 0x30, 0x00, // }
-        0x36, 0x5C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // ~
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00  // DEL
-    ];
-
-    /// Dibujar un carácter manualmente
-    pub fn draw_char_manual(&mut self, c: char, x: i32, y: i32) {
-        let ascii_val = c as usize;
-        let index = if ascii_val >= 32 && ascii_val <= 127 {
-            ascii_val - 32
-        } else {
-            0
-        };
+    /// Restablecer el estado de dibujo (color, fuente, cursor y pantalla) antes de relanzar una app
+    pub fn reset(&mut self) {
+        self.set_color(theme::current().text);
+        self.set_opacity(255);
+        self.font = FontSelection::BUILTIN;
+        self.clear_screen();
+    }
 
-        let bitmap = &Self::FONT_8X8[index * 8..(index + 1) * 8];
-        let color = self.current_color;
+    /// Cargar las fuentes del kernel y las fuentes `.psf`/`.bdf`/`.ttf`/`.otf` de los assets del paquete
+    ///
+    /// Las fuentes del paquete toman el nombre del fichero sin extensión.
+    fn load_fonts(&mut self) {
+        for (path, data) in assets::WPK_ASSETS.iter() {
+            let file = path.rsplit('/').next().unwrap_or(path);
+            let Some(name) = file.strip_suffix(".ttf").or_else(|| file.strip_suffix(".otf")) else {
+                continue;
+            };
+            match FontRef::try_from_slice(data) {
+                Ok(font) => {
+                    uart_send_str("  🔤 Fuente vectorial ");
+                    uart_send_str(name);
+                    uart_send_str(": ");
+                    print_number(font.glyph_count() as u64);
+                    uart_send_str(" glifos\n");
+                    self.vector_fonts.push(VectorFont { name: String::from(name), font });
+                }
+                Err(_) => {
+                    uart_send_str("  ⚠️ Fuente ");
+                    uart_send_str(name);
+                    uart_send_str(" no cargada: TrueType/OpenType inválido\n");
+                }
+            }
+        }
 
-        for (row_idx, row) in bitmap.iter().enumerate() {
-            for col_idx in 0..8 {
-                if (row >> (7 - col_idx)) & 1 == 1 {
-                    let px = x + col_idx;
-                    let py = y + row_idx as i32;
-                    self.draw_pixel_manual(px, py, color);
+        let wpk_fonts = assets::WPK_ASSETS.iter().filter_map(|(path, data)| {
+            let file = path.rsplit('/').next().unwrap_or(path);
+            let stem = file.strip_suffix(".psf").or_else(|| file.strip_suffix(".bdf"))?;
+            Some((stem, *data))
+        });
+
+        for (name, data) in assets::KERNEL_FONTS.iter().copied().chain(wpk_fonts) {
+            match BitmapFont::parse(name, data) {
+                Ok(font) => {
+                    uart_send_str("  🔤 Fuente ");
+                    uart_send_str(name);
+                    uart_send_str(": ");
+                    print_number(font.width as u64);
+                    uart_send_str("x");
+                    print_number(font.height as u64);
+                    uart_send_str(", ");
+                    print_number(font.glyph_count() as u64);
+                    uart_send_str(" glifos\n");
+                    self.fonts.add(font);
+                }
+                Err(e) => {
+                    uart_send_str("  ⚠️ Fuente ");
+                    uart_send_str(name);
+                    uart_send_str(" no cargada: ");
+                    uart_send_str(e);
+                    uart_send_str("\n");
                 }
             }
         }
     }
 
-    /// Dibujar un pixel manualmente
-    fn draw_pixel_manual(&mut self, x: i32, y: i32, color: Rgb888) {
-        if x < 0 || x >= SCREEN_WIDTH as i32 || y < 0 || y >= SCREEN_HEIGHT as i32 {
+    /// Seleccionar la fuente del texto por nombre y altura en pixels
+    ///
+    /// Las fuentes vectoriales se rasterizan al tamaño exacto (16 px si es 0).
+    /// Devuelve `false` si no hay ninguna fuente con ese nombre.
+    pub fn set_font(&mut self, name: &str, size: u32) -> bool {
+        if let Some(index) = self.vector_fonts.iter().position(|f| f.name == name) {
+            let size = if size == 0 { DEFAULT_VECTOR_SIZE } else { size.min(MAX_VECTOR_SIZE) };
+            self.font = FontSelection::Vector { index, size };
+            return true;
+        }
+        match self.fonts.select(name, size.max(1)) {
+            Some(selection) => {
+                self.font = selection;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Nombres de las fuentes disponibles con sus alturas nativas (0 = escalable)
+    pub fn font_names(&self) -> Vec<(&str, u32)> {
+        let mut names = Vec::new();
+        names.push((font::BUILTIN_FONT, font::GLYPH_HEIGHT as u32));
+        names.extend(self.fonts.fonts().iter().map(|f| (f.name.as_str(), f.height)));
+        names.extend(self.vector_fonts.iter().map(|f| (f.name.as_str(), 0)));
+        names
+    }
+
+    /// Tamaño en pixels de una celda de texto con la fuente actual
+    ///
+    /// Con fuentes vectoriales el ancho es orientativo (el avance es proporcional)
+    /// y el alto es la distancia entre ascendente y descendente.
+    fn cell_size(&self) -> (i32, i32) {
+        match self.font {
+            FontSelection::Builtin { scale } => (font::GLYPH_WIDTH * scale as i32, font::GLYPH_HEIGHT * scale as i32),
+            FontSelection::Bitmap { index, scale } => match self.fonts.get(index) {
+                Some(font) => (font.width as i32 * scale as i32, font.height as i32 * scale as i32),
+                None => (font::GLYPH_WIDTH, font::GLYPH_HEIGHT),
+            },
+            FontSelection::Vector { index, size } => match self.vector_fonts.get(index) {
+                Some(font) => {
+                    let scaled = font.font.as_scaled(PxScale::from(size as f32));
+                    ((size / 2) as i32, libm::ceilf(scaled.ascent() - scaled.descent()) as i32)
+                }
+                None => (font::GLYPH_WIDTH, font::GLYPH_HEIGHT),
+            },
+        }
+    }
+
+    /// Componer las superficies y mostrar el frame (cambio de página o copia del back buffer)
+    pub fn present(&mut self) {
+        self.compositor.present(self.surface, &mut self.framebuffer);
+    }
+
+    /// Color que muestra la pantalla en (x, y) tras el último `present`, si se puede leer
+    #[cfg(test)]
+    pub fn display_pixel(&self, x: u32, y: u32) -> Option<Rgb888> {
+        self.compositor.screen().display.as_ref()?.pixel(x, y)
+    }
+
+    /// Framebuffer de la pantalla con lo último presentado
+    pub fn screen(&self) -> &FrameBuffer {
+        self.compositor.screen()
+    }
+
+    /// Benchmark de relleno y copia sobre la superficie actual; después redibuja la consola
+    pub fn benchmark(&mut self) -> Vec<BenchmarkResult> {
+        let mut results = self.compositor.benchmark_screen();
+        results.extend(self.framebuffer.benchmark());
+        let viewport = self.framebuffer.viewport();
+        let (x, y) = (viewport.top_left.x as u32, viewport.top_left.y as u32);
+        self.framebuffer.fill_rect(x, y, viewport.size.width, viewport.size.height, theme::current().background);
+        self.show_scrollback(0);
+        self.present();
+        results
+    }
+
+    /// Estrategia de doble buffer en uso
+    pub fn buffering(&self) -> &Buffering {
+        self.compositor.screen().buffering()
+    }
+
+    /// Ancho de la pantalla en pixels
+    pub fn width(&self) -> u32 {
+        self.compositor.screen().width()
+    }
+
+    /// Alto de la pantalla en pixels
+    pub fn height(&self) -> u32 {
+        self.compositor.screen().height()
+    }
+
+    /// Pitch (bytes por fila) y profundidad (bits por pixel) del framebuffer
+    pub fn pitch_and_depth(&self) -> (u32, u32) {
+        let screen = self.compositor.screen();
+        (screen.pitch(), screen.depth())
+    }
+
+    /// Formato de pixel negociado con el firmware
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.compositor.screen().format()
+    }
+
+    // ===== Superficies del compositor =====
+
+    /// Crear una superficie para una app en `frame` (coordenadas de pantalla)
+    ///
+    /// La barra de estado queda reservada: la superficie se recorta a `app_area`
+    /// y siempre queda por debajo de ella. Devuelve `None` si no hay memoria o el
+    /// marco queda vacío; la superficie empieza visible y en negro.
+    pub fn create_surface(&mut self, name: &str, frame: Rectangle, z: i32) -> Option<SurfaceId> {
+        let frame = frame.intersection(&self.app_area());
+        self.compositor.create_surface(name, frame, z.min(Z_STATUS_BAR - 1), Point::new(10, 0))
+    }
+
+    /// Dibujar a partir de ahora en la superficie `id`; devuelve la que estaba activa
+    ///
+    /// Cada superficie conserva su cursor de texto, su viewport y su contenido.
+    /// La pila de recortes se descarta; el color, la opacidad y la fuente se mantienen.
+    pub fn set_surface(&mut self, id: SurfaceId) -> SurfaceId {
+        let previous = self.surface;
+        if id == previous {
+            return previous;
+        }
+        let Some((canvas, cursor)) = self.compositor.take_canvas(id) else {
+            return previous;
+        };
+        self.clip_stack.clear();
+        let viewport = self.framebuffer.viewport();
+        self.framebuffer.set_viewport(viewport);
+        let old = core::mem::replace(&mut self.framebuffer, canvas);
+        self.compositor.put_canvas(previous, old, Point::new(self.cursor_x, self.cursor_y));
+
+        self.surface = id;
+        self.cursor_x = cursor.x;
+        self.cursor_y = cursor.y;
+        self.framebuffer.set_alpha(mul_alpha(self.current_alpha, self.opacity));
+        previous
+    }
+
+    /// Eliminar una superficie y liberar su buffer
+    ///
+    /// Si era la activa se vuelve a dibujar en la del shell, que no se puede eliminar.
+    pub fn destroy_surface(&mut self, id: SurfaceId) {
+        if id == self.shell_surface {
             return;
         }
-        
-        unsafe {
-            if self.framebuffer.bytes_per_pixel == 2 {
-                // RGB565 (16-bit)
-                // RRRR RGGG GGGB BBBB
-                let r5 = (color.r() >> 3) as u16;
-                let g6 = (color.g() >> 2) as u16;
-                let b5 = (color.b() >> 3) as u16;
-                let rgb565 = (r5 << 11) | (g6 << 5) | b5;
-                
-                let offset = (y as u32 * self.framebuffer.pitch + x as u32 * 2) as usize;
-                *(self.framebuffer.pixels.add(offset) as *mut u16) = rgb565;
-            } else {
-                // BGRA8888 (32-bit)
-                let offset = (y as u32 * self.framebuffer.pitch + x as u32 * 4) as usize;
-                *self.framebuffer.pixels.add(offset) = color.b();     // Blue
-                *self.framebuffer.pixels.add(offset + 1) = color.g(); // Green
-                *self.framebuffer.pixels.add(offset + 2) = color.r(); // Red
-                *self.framebuffer.pixels.add(offset + 3) = 255;       // Alpha
+        if id == self.surface {
+            self.set_surface(self.shell_surface);
+            if id == self.surface {
+                return;
+            }
+        }
+        self.compositor.destroy_surface(id);
+    }
+
+    pub fn set_surface_visible(&mut self, id: SurfaceId, visible: bool) {
+        self.compositor.set_visible(id, visible);
+    }
+
+    pub fn surface_visible(&self, id: SurfaceId) -> bool {
+        self.compositor.is_visible(id)
+    }
+
+    /// Traer la superficie al frente de las de su mismo nivel
+    pub fn raise_surface(&mut self, id: SurfaceId) {
+        self.compositor.raise(id);
+    }
+
+    /// Superficie de la consola del shell
+    pub fn shell_surface(&self) -> SurfaceId {
+        self.shell_surface
+    }
+
+    /// Superficies de abajo arriba: (nombre, posición, z, visible)
+    pub fn surfaces(&self) -> impl Iterator<Item = (&str, Rectangle, i32, bool)> {
+        self.compositor.surfaces()
+    }
+
+    /// Mostrar un aviso del sistema por encima de las apps hasta `dismiss_notification`
+    pub fn show_notification(&mut self, text: &str) {
+        let (color, alpha, opacity, font) = (self.current_color, self.current_alpha, self.opacity, self.font);
+        let previous = self.set_surface(self.notification_surface);
+        self.set_opacity(255);
+        self.font = FontSelection::BUILTIN;
+
+        let (width, height) = self.viewport_size();
+        self.set_color(theme::current().secondary);
+        self.draw_rect(0, 0, width, height, true);
+        self.set_color(theme::current().on_primary);
+        let (_, cell_height) = self.cell_size();
+        self.draw_text_at(text, 10, (height as i32 - cell_height) / 2);
+
+        self.set_surface(previous);
+        self.font = font;
+        self.set_opacity(opacity);
+        self.set_color_alpha(color, alpha);
+        self.compositor.set_visible(self.notification_surface, true);
+    }
+
+    /// Ocultar el aviso del sistema
+    pub fn dismiss_notification(&mut self) {
+        self.compositor.set_visible(self.notification_surface, false);
+    }
+
+    /// Dibujar la barra de estado: `title` a la izquierda y los textos de `items`
+    /// alineados a la derecha, con su color (los vacíos se omiten)
+    pub fn show_status_bar(&mut self, title: &str, items: &[(&str, Rgb888)]) {
+        let (color, alpha, opacity, font) = (self.current_color, self.current_alpha, self.opacity, self.font);
+        let previous = self.set_surface(self.status_bar_surface);
+        self.set_opacity(255);
+        self.font = FontSelection::BUILTIN;
+
+        let theme = theme::current();
+        let (width, height) = self.viewport_size();
+        self.set_color(theme.surface);
+        self.draw_rect(0, 0, width, height, true);
+        let (_, cell_height) = self.cell_size();
+        let y = (height as i32 - cell_height) / 2;
+        self.set_color(theme.primary);
+        self.draw_text_at(title, 10, y);
+
+        let mut x = width as i32 - 10;
+        for (text, color) in items.iter().rev().filter(|(text, _)| !text.is_empty()) {
+            x -= self.text_size(text).0 as i32;
+            self.set_color(*color);
+            self.draw_text_at(text, x, y);
+            x -= 16;
+        }
+
+        self.set_surface(previous);
+        self.font = font;
+        self.set_opacity(opacity);
+        self.set_color_alpha(color, alpha);
+        self.compositor.set_visible(self.status_bar_surface, true);
+    }
+
+    /// Color actual y su alfa
+    pub fn color(&self) -> (Rgb888, u8) {
+        (self.current_color, self.current_alpha)
+    }
+
+    /// Cambiar color actual (opaco)
+    pub fn set_color(&mut self, color: Rgb888) {
+        self.set_color_alpha(color, 255);
+    }
+
+    /// Cambiar color actual con alfa: lo que se dibuje se mezcla con el fondo
+    pub fn set_color_alpha(&mut self, color: Rgb888, alpha: u8) {
+        self.current_color = color;
+        self.current_alpha = alpha;
+        self.framebuffer.set_alpha(mul_alpha(alpha, self.opacity));
+    }
+
+    /// Opacidad global (0-255) para overlays, sombras y fundidos
+    ///
+    /// Se aplica a todas las primitivas, texto e imágenes hasta que se cambie;
+    /// `clear_screen` siempre pinta opaco.
+    pub fn set_opacity(&mut self, opacity: u8) {
+        self.opacity = opacity;
+        self.framebuffer.set_alpha(mul_alpha(self.current_alpha, opacity));
+    }
+
+    
+    /// Dibujar un carácter manualmente con la fuente actual
+    ///
+    /// Si la fuente cargada no tiene el carácter se usa el glifo de la integrada.
+    pub fn draw_char_manual(&mut self, c: char, x: i32, y: i32) {
+        if !self.draw_font_glyph(c, x, y) {
+            self.draw_glyph(&font::glyph(c), x, y);
+        }
+    }
+
+    /// Dibujar el glifo de `c` de la fuente cargada; `false` si no lo tiene (o es la integrada)
+    fn draw_font_glyph(&mut self, c: char, x: i32, y: i32) -> bool {
+        let color = self.current_color;
+        match self.font {
+            FontSelection::Bitmap { index, scale } => {
+                if let Some(font) = self.fonts.get(index)
+                    && let Some(bitmap) = font.glyph(c)
+                {
+                    blit_glyph(&mut self.framebuffer, bitmap, font.row_bytes(), font.width as i32, x, y, scale as i32, color);
+                    return true;
+                }
             }
+            FontSelection::Vector { index, size } => {
+                if let Some(font) = self.vector_fonts.get(index) {
+                    let glyph = self.glyph_cache.get(index, &font.font, c, size);
+                    blend_coverage(&mut self.framebuffer, glyph, x, y, color);
+                    return true;
+                }
+            }
+            FontSelection::Builtin { .. } => {}
         }
+        false
+    }
+
+    /// Dibujar un glifo 8x8 de la fuente integrada con el color y la escala actuales
+    fn draw_glyph(&mut self, bitmap: &font::Glyph, x: i32, y: i32) {
+        let scale = self.font.scale() as i32;
+        blit_glyph(&mut self.framebuffer, bitmap, 1, font::GLYPH_WIDTH, x, y, scale, self.current_color);
     }
 
     /// Dibujar texto en la posición del cursor
+    ///
+    /// Los saltos de línea y las líneas más anchas que la ventana pasan a la línea siguiente.
     pub fn draw_text(&mut self, text: &str) {
-        for c in text.chars() {
-            self.draw_char_manual(c, self.cursor_x, self.cursor_y);
-            self.cursor_x += 8;
+        let max_width = (self.viewport_size().0 as i32 - self.cursor_x - 10).max(1) as u32;
+        for line in text::wrap(text, max_width, &|s| self.text_size(s).0) {
+            self.record_line(&line.text);
+            self.put_line(&line.text);
         }
+    }
+
+    /// Dibujar una línea en el cursor y avanzar, sin guardarla en el historial
+    fn put_line(&mut self, text: &str) {
+        self.draw_string(text, self.cursor_x, self.cursor_y);
         
-        // Avanzar cursor
-        self.cursor_y += self.line_height;
+        // Avanzar cursor (las fuentes grandes ocupan más de una línea de consola)
+        let (_, cell_height) = self.cell_size();
+        self.cursor_y += self.line_height.max(cell_height + 4);
         self.cursor_x = 10;
         
         // Verificar si necesitamos hacer scroll
-        if self.cursor_y > (SCREEN_HEIGHT as i32 - 50) {
+        while self.cursor_y > self.text_bottom() {
             self.scroll_up();
         }
     }
     
     /// Dibujar texto en posición específica
     pub fn draw_text_at(&mut self, text: &str, x: i32, y: i32) {
+        self.draw_string(text, x, y);
+    }
+
+    /// Partir un texto en líneas para una caja de `max_width` x `max_height` (0 = sin límite)
+    pub fn layout_text(&self, text: &str, max_width: u32, max_height: u32, style: &TextStyle) -> TextLayout {
+        let (_, line_height) = self.cell_size();
+        text::layout(text, max_width, max_height, line_height.max(1) as u32, style, &|s| self.text_size(s).0)
+    }
+
+    /// Ancho, alto y número de líneas de un texto partido a `max_width` (0 = sin partir)
+    pub fn measure_text(&self, text: &str, max_width: u32, line_spacing: u32) -> (u32, u32, usize) {
+        let style = TextStyle { line_spacing, ellipsis: false, ..TextStyle::default() };
+        let layout = self.layout_text(text, max_width, 0, &style);
+        (layout.width(), layout.height(), layout.lines.len())
+    }
+
+    /// Dibujar un texto partido en líneas y alineado dentro de `rect`, sin salirse de él
+    ///
+    /// Devuelve la composición usada: líneas dibujadas y si hubo que recortar.
+    pub fn draw_text_box(&mut self, text: &str, rect: Rectangle, style: &TextStyle) -> TextLayout {
+        let layout = self.layout_text(text, rect.size.width, rect.size.height, style);
+        self.push_clip(rect);
+        for (index, line) in layout.lines.iter().enumerate() {
+            let (dx, dy) = layout.line_offset(index, rect.size.width, style.align);
+            self.draw_string(&line.text, rect.top_left.x + dx, rect.top_left.y + dy);
+        }
+        self.pop_clip();
+        layout
+    }
+
+    /// Ancho y alto en pixels de una línea de texto con la fuente actual
+    pub fn text_size(&self, text: &str) -> (u32, u32) {
+        let (advance, height) = self.cell_size();
+        let width = match self.font {
+            FontSelection::Vector { index, size } => match self.vector_fonts.get(index) {
+                Some(font) => {
+                    let scaled = font.font.as_scaled(PxScale::from(size as f32));
+                    let mut width = 0.0;
+                    let mut previous: Option<GlyphId> = None;
+                    let precomposed = font::precompose(text, |c| scaled.glyph_id(c).0 != 0);
+                    for c in precomposed.filter(|c| !font::is_zero_width(*c)) {
+                        let id = scaled.glyph_id(c);
+                        if let Some(previous) = previous {
+                            width += scaled.kern(previous, id);
+                        }
+                        width += scaled.h_advance(id);
+                        previous = Some(id);
+                    }
+                    libm::roundf(width) as i32
+                }
+                None => 0,
+            },
+            _ => {
+                let cells = font::precompose(text, |_| true)
+                    .filter(|c| !font::is_zero_width(*c) && font::combining_mark(*c).is_none())
+                    .count();
+                cells as i32 * advance
+            }
+        };
+        (width.max(0) as u32, height.max(0) as u32)
+    }
+
+    /// Dibujar una cadena desde (x, y) y devolver la x final
+    ///
+    /// Los caracteres de ancho cero no avanzan. Una letra seguida de un diacrítico
+    /// combinante se dibuja con su forma precompuesta; si no la hay, el diacrítico
+    /// se dibuja sobre la celda anterior con el glifo de la fuente actual o, si no
+    /// lo tiene, con el de la integrada.
+    fn draw_string(&mut self, text: &str, x: i32, y: i32) -> i32 {
+        if let FontSelection::Vector { index, size } = self.font {
+            return self.draw_string_vector(text, x, y, index, size);
+        }
+        let (advance, _) = self.cell_size();
         let mut curr_x = x;
-        for c in text.chars() {
+        for c in font::precompose(text, |_| true) {
+            if font::is_zero_width(c) {
+                continue;
+            }
+            if let Some(mark) = font::combining_mark(c) {
+                if !self.draw_font_glyph(c, curr_x - advance, y) {
+                    self.draw_glyph(&mark, curr_x - advance, y);
+                }
+                continue;
+            }
             self.draw_char_manual(c, curr_x, y);
-            curr_x += 8;
+            curr_x += advance;
+        }
+        curr_x
+    }
+    
+    /// Dibujar una cadena con una fuente vectorial: avance proporcional, kerning y antialiasing
+    fn draw_string_vector(&mut self, text: &str, x: i32, y: i32, index: usize, size: u32) -> i32 {
+        let Some(font) = self.vector_fonts.get(index) else {
+            return x;
+        };
+        let scaled = font.font.as_scaled(PxScale::from(size as f32));
+        let color = self.current_color;
+        let mut pen = x as f32;
+        let mut previous: Option<GlyphId> = None;
+
+        // Las formas precompuestas solo si la fuente las tiene; si no, su glifo combinante
+        for c in font::precompose(text, |c| scaled.glyph_id(c).0 != 0) {
+            if font::is_zero_width(c) {
+                continue;
+            }
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                pen += scaled.kern(previous, id);
+            }
+            let glyph = self.glyph_cache.get(index, &font.font, c, size);
+            blend_coverage(&mut self.framebuffer, glyph, libm::roundf(pen) as i32, y, color);
+            pen += glyph.advance;
+            previous = Some(id);
         }
+        libm::roundf(pen) as i32
     }
     
     /// Dibujar rectángulo
@@ -452,68 +1416,593 @@ impl GraphicsManager {
             let style = PrimitiveStyleBuilder::new()
                 .fill_color(self.current_color)
                 .build();
-            rect.into_styled(style).draw(&mut self.framebuffer).ok();
+            self.draw_primitive(rect.into_styled(style));
         } else {
             let style = PrimitiveStyleBuilder::new()
                 .stroke_color(self.current_color)
                 .stroke_width(2)
                 .build();
-            rect.into_styled(style).draw(&mut self.framebuffer).ok();
+            self.draw_primitive(rect.into_styled(style));
         }
     }
+
+    /// Dibujar una primitiva de embedded-graphics marcando su caja como zona modificada
+    fn draw_primitive(&mut self, primitive: impl Drawable<Color = Rgb888> + Dimensions) {
+        self.framebuffer.add_local_damage(primitive.bounding_box());
+        primitive.draw(&mut self.framebuffer).ok();
+    }
     
+    /// Estilo de las primitivas: relleno con el color actual o contorno de `stroke_width` pixels
+    fn shape_style(&self, filled: bool, stroke_width: u32) -> PrimitiveStyle<Rgb888> {
+        if filled {
+            PrimitiveStyle::with_fill(self.current_color)
+        } else {
+            PrimitiveStyle::with_stroke(self.current_color, stroke_width.max(1))
+        }
+    }
+
+    /// Dibujar línea de (x1, y1) a (x2, y2)
+    pub fn draw_line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, stroke_width: u32) {
+        let line = Line::new(Point::new(x1, y1), Point::new(x2, y2));
+        self.draw_primitive(line.into_styled(self.shape_style(false, stroke_width)));
+    }
+
+    /// Dibujar círculo con centro en (cx, cy)
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: u32, filled: bool, stroke_width: u32) {
+        let circle = Circle::with_center(Point::new(cx, cy), diameter(radius));
+        self.draw_primitive(circle.into_styled(self.shape_style(filled, stroke_width)));
+    }
+
+    /// Dibujar elipse inscrita en el rectángulo (x, y, width, height)
+    pub fn draw_ellipse(&mut self, x: i32, y: i32, width: u32, height: u32, filled: bool, stroke_width: u32) {
+        let ellipse = Ellipse::new(Point::new(x, y), Size::new(width, height));
+        self.draw_primitive(ellipse.into_styled(self.shape_style(filled, stroke_width)));
+    }
+
+    /// Dibujar arco de circunferencia con centro en (cx, cy)
+    ///
+    /// Los ángulos van en grados: 0 apunta a la derecha y crecen en sentido horario.
+    pub fn draw_arc(&mut self, cx: i32, cy: i32, radius: u32, start_deg: f32, sweep_deg: f32, stroke_width: u32) {
+        let arc = Arc::with_center(
+            Point::new(cx, cy),
+            diameter(radius),
+            Angle::from_degrees(start_deg),
+            Angle::from_degrees(sweep_deg),
+        );
+        self.draw_primitive(arc.into_styled(self.shape_style(false, stroke_width)));
+    }
+
+    /// Dibujar triángulo
+    pub fn draw_triangle(&mut self, points: [(i32, i32); 3], filled: bool, stroke_width: u32) {
+        let [a, b, c] = points.map(|(x, y)| Point::new(x, y));
+        self.draw_primitive(Triangle::new(a, b, c).into_styled(self.shape_style(filled, stroke_width)));
+    }
+
+    /// Dibujar rectángulo con las esquinas redondeadas
+    pub fn draw_round_rect(&mut self, rect: Rectangle, radius: u32, style: ShapeStyle) {
+        let shape = RoundedRectangle::with_equal_corners(rect, Size::new(radius, radius));
+        self.draw_primitive(shape.into_styled(self.shape_style(style.filled, style.stroke_width)));
+    }
+
+    /// Dibujar polígono cerrado
+    ///
+    /// El relleno usa la regla par-impar, así que los polígonos que se cruzan
+    /// dejan huecos como en SVG con `fill-rule: evenodd`.
+    pub fn draw_polygon(&mut self, points: &[(i32, i32)], filled: bool, stroke_width: u32) {
+        if points.len() < 2 {
+            return;
+        }
+        if filled && points.len() >= 3 {
+            fill_polygon(&mut self.framebuffer, points, self.current_color);
+            return;
+        }
+
+        let mut outline: Vec<Point> = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+        outline.push(outline[0]);
+        self.draw_primitive(Polyline::new(&outline).into_styled(self.shape_style(false, stroke_width)));
+    }
+
+    /// Dibujar una imagen de los assets del paquete (BMP, QOI o PNG)
+    ///
+    /// Con `width` y `height` a 0 se dibuja a tamaño natural; si solo uno es 0 se
+    /// calcula para conservar la proporción. El escalado es por vecino más cercano
+    /// y el canal alfa se mezcla con el fondo.
+    pub fn draw_image(&mut self, name: &str, x: i32, y: i32, width: u32, height: u32) -> Result<(), ImageError> {
+        let image = self.image_cache.get(name)?;
+        let (width, height) = match (width, height) {
+            (0, 0) => (image.width, image.height),
+            (0, h) => ((image.width as u64 * h as u64 / image.height as u64).max(1) as u32, h),
+            (w, 0) => (w, (image.height as u64 * w as u64 / image.width as u64).max(1) as u32),
+            size => size,
+        };
+        blit_image(&mut self.framebuffer, image, x, y, width, height);
+        Ok(())
+    }
+
     /// Nueva línea
     pub fn new_line(&mut self) {
+        self.record_line("");
         self.cursor_x = 10;
         self.cursor_y += self.line_height;
         
-        if self.cursor_y > (SCREEN_HEIGHT as i32 - 50) {
+        if self.cursor_y > self.text_bottom() {
             self.scroll_up();
         }
     }
     
-    /// Scroll hacia arriba (simulado)
+    /// Scroll hacia arriba de una línea de la consola
+    ///
+    /// Mueve las filas del viewport y limpia solo la franja inferior liberada;
+    /// la cabecera por encima de `CONSOLE_TOP` se conserva.
     fn scroll_up(&mut self) {
-        // Simular scroll limpiando la pantalla y reiniciando
-        // En una implementación real, moveríamos el contenido del buffer
-        self.clear_screen();
+        let area = self.console_area();
+        self.framebuffer.scroll_up(area, self.line_height as u32, theme::current().background);
+        self.cursor_y -= self.line_height;
+    }
+
+    /// Guardar una línea en el historial, descartando la más antigua si está lleno
+    fn record_line(&mut self, text: &str) {
+        if self.scrollback.len() == SCROLLBACK_LINES {
+            self.scrollback.pop_front();
+        }
+        self.scrollback.push_back(ConsoleLine { color: self.current_color, font: self.font, text: String::from(text) });
+    }
+
+    /// Líneas guardadas en el historial de la consola
+    pub fn scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    /// Líneas que caben en una página de la consola
+    ///
+    /// La última fila queda libre para la siguiente línea, como tras un scroll.
+    pub fn console_page_lines(&self) -> usize {
+        ((self.text_bottom() - self.text_top()) / self.line_height).max(0) as usize
+    }
+
+    /// Redibujar la consola con una página del historial
+    ///
+    /// `offset` es el número de líneas que se retrocede desde la más reciente;
+    /// con 0 se vuelve a la vista normal. Devuelve el offset ajustado al historial.
+    pub fn show_scrollback(&mut self, offset: usize) -> usize {
+        let page = self.console_page_lines();
+        let offset = offset.min(self.scrollback.len().saturating_sub(page));
+        let end = self.scrollback.len() - offset;
+        let start = end.saturating_sub(page);
+
+        let area = self.console_area();
+        let (x, y) = (area.top_left.x as u32, area.top_left.y as u32);
+        self.framebuffer.fill_rect(x, y, area.size.width, area.size.height, theme::current().background);
+        self.cursor_x = 10;
+        self.cursor_y = self.text_top();
+
+        let (color, font) = (self.current_color, self.font);
+        for index in start..end {
+            let line = &self.scrollback[index];
+            let text = line.text.clone();
+            self.current_color = line.color;
+            self.font = line.font;
+            self.put_line(&text);
+        }
+        self.current_color = color;
+        self.font = font;
+        offset
     }
     
+    /// Mostrar el terminal ANSI en la zona de la consola
+    ///
+    /// Conserva el contenido de la vez anterior; `show_scrollback(0)` vuelve a
+    /// la consola normal.
+    pub fn open_terminal(&mut self) {
+        let theme = theme::current();
+        let (_, (cols, rows)) = self.terminal_scale();
+        let terminal = self.terminal.get_or_insert_with(|| Terminal::new(cols, rows, theme.text, theme.background));
+        // Con el tema actual y entero: la consola estaba dibujada encima
+        terminal.set_default_colors(theme.text, theme.background);
+
+        let area = self.console_area();
+        let (x, y) = (area.top_left.x as u32, area.top_left.y as u32);
+        self.framebuffer.fill_rect(x, y, area.size.width, area.size.height, theme.background);
+        self.render_terminal();
+    }
+
+    /// Pasar bytes (texto UTF-8 con controles y secuencias ANSI) al terminal y dibujar lo que cambió
+    pub fn terminal_write(&mut self, bytes: &[u8]) {
+        if self.terminal.is_none() {
+            self.open_terminal();
+        }
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.write(bytes);
+        }
+        self.render_terminal();
+    }
+
+    /// Borrar el terminal y volver a los colores y la posición iniciales
+    pub fn terminal_reset(&mut self) {
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.reset();
+        }
+        self.render_terminal();
+    }
+
+    /// Tamaño del terminal (columnas, filas)
+    pub fn terminal_size(&self) -> Option<(usize, usize)> {
+        self.terminal.as_ref().map(|terminal| (terminal.cols(), terminal.rows()))
+    }
+
+    /// Escala de la fuente integrada para el terminal y la rejilla que cabe en la consola
+    ///
+    /// Se usa escala 2 si con ella caben al menos `TERMINAL_MIN_COLUMNS` columnas.
+    fn terminal_scale(&self) -> (u32, (usize, usize)) {
+        let area = self.console_area();
+        let scale = if area.size.width / (font::GLYPH_WIDTH as u32 * 2) >= TERMINAL_MIN_COLUMNS { 2 } else { 1 };
+        let cols = area.size.width / (font::GLYPH_WIDTH as u32 * scale);
+        let rows = area.size.height / (font::GLYPH_HEIGHT as u32 * scale);
+        (scale, (cols as usize, rows as usize))
+    }
+
+    /// Dibujar las filas del terminal que cambiaron y el cursor, como una celda invertida
+    fn render_terminal(&mut self) {
+        let Some(mut terminal) = self.terminal.take() else {
+            return;
+        };
+        let (color, alpha, opacity, font) = (self.current_color, self.current_alpha, self.opacity, self.font);
+        self.set_opacity(255);
+        let scale = self.terminal_scale().0;
+        self.font = FontSelection::Builtin { scale };
+        let (cell_width, cell_height) = self.cell_size();
+        let top = self.text_top();
+
+        let cursor = terminal.cursor();
+        for row in terminal.take_dirty() {
+            let y = top + row as i32 * cell_height;
+            for col in 0..terminal.cols() {
+                let cell = terminal.cell(col, row);
+                let (fg, bg) = if cursor == Some((col, row)) { (cell.bg, cell.fg) } else { (cell.fg, cell.bg) };
+                let x = col as i32 * cell_width;
+                self.framebuffer.blend_rect(x, y, cell_width as u32, cell_height as u32, bg);
+                if cell.ch != ' ' {
+                    self.set_color(fg);
+                    self.draw_char_manual(cell.ch, x, y);
+                }
+            }
+        }
+
+        self.terminal = Some(terminal);
+        self.font = font;
+        self.set_opacity(opacity);
+        self.set_color_alpha(color, alpha);
+    }
+
     /// Mostrar splash screen de FerroOS
     pub fn show_splash_screen(&mut self) {
         self.clear_screen();
-        
-        // Título principal
-        self.set_color(colors::BLUE);
-        self.draw_text_at("FerroOS Mobile", 280, 100);
-        
-        // Subtítulo
-        self.set_color(colors::WHITE);
-        self.draw_text_at("Sistema Operativo Nativo", 230, 140);
-        
-        // Versión
-        self.set_color(colors::GREEN);
-        self.draw_text_at("v1.0.0 - Zero Dependencies", 220, 180);
-        
-        // Pipeline
-        self.set_color(colors::YELLOW);
-        self.draw_text_at("Lua → Zig → WASM → Rust", 240, 220);
-        
-        // Borde decorativo
-        self.set_color(colors::PURPLE);
-        self.draw_rect(50, 50, SCREEN_WIDTH - 100, SCREEN_HEIGHT - 100, false);
-        
+        let mut ui = Ui::new();
+        let theme = theme::current();
+
+        // Título, subtítulo, versión y pipeline
+        let texts = [
+            ("FerroOS Mobile", theme.primary),
+            ("Sistema Operativo Nativo", theme.text),
+            ("v1.0.0 - Zero Dependencies", theme.success),
+            ("Lua → Zig → WASM → Rust", theme.warning),
+        ];
+        let mut lines: Vec<_> = texts
+            .iter()
+            .map(|(text, color)| {
+                let label = ui.label(text);
+                ui.set_color(label, *color);
+                label
+            })
+            .collect();
+
         // Logo ASCII (simple)
-        self.set_color(colors::ORANGE);
-        self.draw_text_at("   ███████", 320, 280);
-        self.draw_text_at("   █     █", 320, 300);
-        self.draw_text_at("   █  ○  █", 320, 320);
-        self.draw_text_at("   █     █", 320, 340);
-        self.draw_text_at("   ███████", 320, 360);
+        let logo_rows = ["███████", "█     █", "█  ○  █", "█     █", "███████"];
+        let logo_lines: Vec<_> = logo_rows
+            .iter()
+            .map(|row| {
+                let label = ui.label(row);
+                ui.set_color(label, theme.accent);
+                label
+            })
+            .collect();
+        let Some(logo) = ui.container(Layout::Column, &logo_lines) else {
+            return;
+        };
+        ui.set_spacing(logo, 4);
+        ui.set_align(logo, Align::Start);
+        lines.push(logo);
+
+        // Columna centrada con borde decorativo
+        let Some(content) = ui.container(Layout::Column, &lines) else {
+            return;
+        };
+        ui.set_padding(content, 40);
+        ui.set_spacing(content, 20);
+        ui.set_align(content, Align::Center);
+        ui.set_border(content, theme.secondary);
+        let area = Rectangle::new(Point::new(50, 50), Size::new(self.width().saturating_sub(100), self.height().saturating_sub(100)));
+        ui.show(content, area);
+        ui.render(self);
         
-        // Reset cursor para contenido normal
+        // Reset cursor para contenido normal: última línea dentro del marco del splash
         self.cursor_x = 10;
-        self.cursor_y = 420;
-        self.set_color(colors::WHITE);
+        self.cursor_y = (area.top_left.y + area.size.height as i32 - 10).max(0);
+        self.set_color(theme.text);
+    }
+}
+
+/// Dibujar un glifo de 1 bit por pixel ampliado `scale` veces
+///
+/// `rows` contiene filas de `row_bytes` bytes con el bit más alto a la izquierda.
+#[allow(clippy::too_many_arguments)]
+fn blit_glyph(framebuffer: &mut FrameBuffer, rows: &[u8], row_bytes: usize, width: i32, x: i32, y: i32, scale: i32, color: Rgb888) {
+    let height = (rows.len() / row_bytes.max(1)) as i32;
+    framebuffer.add_local_damage(Rectangle::new(Point::new(x, y), Size::new((width * scale) as u32, (height * scale) as u32)));
+    for (row_idx, row) in rows.chunks(row_bytes).enumerate() {
+        for col_idx in 0..width {
+            if row[col_idx as usize / 8] & (0x80 >> (col_idx % 8)) == 0 {
+                continue;
+            }
+            let px = x + col_idx * scale;
+            let py = y + row_idx as i32 * scale;
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    framebuffer.plot(px + dx, py + dy, color);
+                }
+            }
+        }
+    }
+}
+
+/// Buffer de `len` bytes a cero, o `None` si no hay memoria
+///
+/// Se pide ya a cero al asignador en vez de reservarlo y rellenarlo después.
+fn zeroed_buffer(len: usize) -> Option<Vec<u8>> {
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    let layout = alloc::alloc::Layout::array::<u8>(len).ok()?;
+    let ptr = unsafe { alloc::alloc::alloc_zeroed(layout) };
+    if ptr.is_null() {
+        return None;
+    }
+    // Reservado con el asignador global y el layout de un `Vec<u8>` de `len` elementos
+    Some(unsafe { Vec::from_raw_parts(ptr, len, len) })
+}
+
+/// Diámetro de un círculo de radio `radius`, limitado a `MAX_RADIUS`
+fn diameter(radius: u32) -> u32 {
+    radius.min(MAX_RADIUS) * 2 + 1
+}
+
+/// Copiar `rect` entre dos buffers de pantalla con el mismo `pitch`
+///
+/// Si el rectángulo ocupa filas completas se copia como un único tramo.
+///
+/// # Safety
+/// Los dos buffers deben contener `rect`, que ya está recortado a la pantalla.
+unsafe fn copy_rect(dst: *mut u8, src: *const u8, pitch: u32, bpp: u32, rect: Rectangle) {
+    let offset = (rect.top_left.y as u32 * pitch + rect.top_left.x as u32 * bpp) as usize;
+    let row_bytes = (rect.size.width * bpp) as usize;
+    unsafe {
+        let (dst, src) = (dst.add(offset), src.add(offset));
+        if row_bytes == pitch as usize {
+            blit::copy_span(dst, src, bpp, row_bytes * rect.size.height as usize);
+        } else {
+            for row in 0..rect.size.height as usize {
+                let offset = row * pitch as usize;
+                blit::copy_span(dst.add(offset), src.add(offset), bpp, row_bytes);
+            }
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Tiempo medio de `BENCHMARK_REPEATS` repeticiones de `f`, en microsegundos
+fn time_repeats(mut f: impl FnMut(usize)) -> u64 {
+    let start = uptime_micros();
+    for i in 0..BENCHMARK_REPEATS as usize {
+        f(i);
+    }
+    (uptime_micros() - start) / BENCHMARK_REPEATS as u64
+}
+
+/// Menor rectángulo que contiene a `a` y a `b`
+pub fn union(a: Rectangle, b: Rectangle) -> Rectangle {
+    if a.is_zero_sized() {
+        return b;
+    }
+    if b.is_zero_sized() {
+        return a;
+    }
+    let left = a.top_left.x.min(b.top_left.x);
+    let top = a.top_left.y.min(b.top_left.y);
+    let right = (a.top_left.x + a.size.width as i32).max(b.top_left.x + b.size.width as i32);
+    let bottom = (a.top_left.y + a.size.height as i32).max(b.top_left.y + b.size.height as i32);
+    Rectangle::new(Point::new(left, top), Size::new((right - left) as u32, (bottom - top) as u32))
+}
+
+/// Producto de dos alfas de 0 a 255, redondeado
+pub(crate) fn mul_alpha(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+/// Rellenar un polígono por scanlines con la regla par-impar
+///
+/// Cada fila se muestrea en su centro (y + 0.5), así los lados horizontales
+/// no generan cruces y dos polígonos que comparten lado no se solapan.
+fn fill_polygon(fb: &mut FrameBuffer, points: &[(i32, i32)], color: Rgb888) {
+    let clip = fb.local_clip();
+    let min_y = points.iter().map(|p| p.1).min().unwrap_or(0).max(clip.top_left.y);
+    let max_y = points.iter().map(|p| p.1).max().unwrap_or(0).min(clip.top_left.y + clip.size.height as i32);
+    let mut crossings: Vec<i32> = Vec::with_capacity(points.len());
+    // Cada tramo marca su propio daño en `blend_rect`
+
+    for y in min_y..max_y {
+        let scan = y as f32 + 0.5;
+        crossings.clear();
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            let (top, bottom) = if y0 < y1 { ((x0, y0), (x1, y1)) } else { ((x1, y1), (x0, y0)) };
+            if scan < top.1 as f32 || scan >= bottom.1 as f32 {
+                continue;
+            }
+            let t = (scan - top.1 as f32) / (bottom.1 - top.1) as f32;
+            let x = top.0 as f32 + t * (bottom.0 - top.0) as f32;
+            crossings.push(libm::roundf(x) as i32);
+        }
+        crossings.sort_unstable();
+
+        for &[start, end] in crossings.as_chunks::<2>().0 {
+            if end > start {
+                fb.blend_rect(start, y, (end - start) as u32, 1, color);
+            }
+        }
+    }
+}
+
+/// Copiar una imagen al rectángulo destino (x, y, width, height), recortada al clip activo
+fn blit_image(fb: &mut FrameBuffer, image: &Image, x: i32, y: i32, width: u32, height: u32) {
+    let clip = fb.local_clip();
+    let (left, top) = (clip.top_left.x as i64, clip.top_left.y as i64);
+    let dx_start = (left - x as i64).clamp(0, width as i64) as u32;
+    let dy_start = (top - y as i64).clamp(0, height as i64) as u32;
+    let dx_end = (left + clip.size.width as i64 - x as i64).clamp(0, width as i64) as u32;
+    let dy_end = (top + clip.size.height as i64 - y as i64).clamp(0, height as i64) as u32;
+    fb.add_local_damage(Rectangle::new(
+        Point::new(x + dx_start as i32, y + dy_start as i32),
+        Size::new(dx_end - dx_start, dy_end - dy_start),
+    ));
+
+    for dy in dy_start..dy_end {
+        let sy = (dy as u64 * image.height as u64 / height as u64) as u32;
+        for dx in dx_start..dx_end {
+            let sx = (dx as u64 * image.width as u64 / width as u64) as u32;
+            let [r, g, b, a] = image.pixel(sx, sy);
+            fb.plot_alpha(x + dx as i32, y + dy as i32, Rgb888::new(r, g, b), a);
+        }
+    }
+}
+
+// ===== IMÁGENES =====
+
+/// Memoria máxima de imágenes decodificadas en caché
+const IMAGE_CACHE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Imágenes decodificadas de los assets, por nombre
+struct ImageCache {
+    images: BTreeMap<String, Image>,
+    bytes: usize,
+}
+
+impl ImageCache {
+    fn new() -> Self {
+        Self { images: BTreeMap::new(), bytes: 0 }
+    }
+
+    /// Imagen decodificada, cargándola del paquete si no está en caché
+    ///
+    /// Como la caché de glifos, se vacía entera si la nueva imagen no cabe.
+    fn get(&mut self, name: &str) -> Result<&Image, ImageError> {
+        if !self.images.contains_key(name) {
+            let data = assets::wpk_asset(name).ok_or(ImageError::NotFound)?;
+            let image = Image::decode(data).map_err(ImageError::Decode)?;
+            if self.bytes + image.byte_size() > IMAGE_CACHE_BYTES {
+                self.images.clear();
+                self.bytes = 0;
+            }
+            self.bytes += image.byte_size();
+            self.images.insert(String::from(name), image);
+        }
+        Ok(&self.images[name])
+    }
+}
+
+// ===== FUENTES VECTORIALES =====
+
+/// Tamaño por defecto y máximo (en pixels) del texto con fuentes vectoriales
+const DEFAULT_VECTOR_SIZE: u32 = 16;
+const MAX_VECTOR_SIZE: u32 = 128;
+
+/// Glifos rasterizados que se guardan antes de vaciar la caché
+const GLYPH_CACHE_CAPACITY: usize = 512;
+
+/// Fuente TrueType/OpenType cargada desde los assets del paquete
+struct VectorFont {
+    name: String,
+    font: FontRef<'static>,
+}
+
+/// Glifo rasterizado: cobertura de 0 a 255 por pixel y posición respecto al
+/// origen de la línea (esquina superior izquierda del texto)
+struct CachedGlyph {
+    left: i32,
+    top: i32,
+    width: u32,
+    advance: f32,
+    coverage: Vec<u8>,
+}
+
+/// Caché de glifos rasterizados en el heap, por (fuente, carácter, tamaño)
+struct GlyphCache {
+    glyphs: BTreeMap<(usize, char, u32), CachedGlyph>,
+}
+
+impl GlyphCache {
+    fn new() -> Self {
+        Self { glyphs: BTreeMap::new() }
+    }
+
+    /// Glifo rasterizado, generándolo si no está en caché
+    ///
+    /// Al llenarse, la caché se vacía entera: el texto de una pantalla usa pocos glifos.
+    fn get(&mut self, index: usize, font: &FontRef<'static>, c: char, size: u32) -> &CachedGlyph {
+        let key = (index, c, size);
+        if !self.glyphs.contains_key(&key) {
+            if self.glyphs.len() >= GLYPH_CACHE_CAPACITY {
+                self.glyphs.clear();
+            }
+            self.glyphs.insert(key, rasterize_glyph(font, c, size));
+        }
+        &self.glyphs[&key]
+    }
+}
+
+/// Rasterizar un glifo con la línea base a `ascent` pixels del origen
+fn rasterize_glyph(font: &FontRef<'static>, c: char, size: u32) -> CachedGlyph {
+    let scaled = font.as_scaled(PxScale::from(size as f32));
+    let id = scaled.glyph_id(c);
+    let advance = scaled.h_advance(id);
+    let glyph = id.with_scale_and_position(size as f32, point(0.0, scaled.ascent()));
+
+    let Some(outline) = font.outline_glyph(glyph) else {
+        // Espacios y glifos sin contorno: solo avanzan
+        return CachedGlyph { left: 0, top: 0, width: 0, advance, coverage: Vec::new() };
+    };
+
+    let bounds = outline.px_bounds();
+    let width = bounds.width() as u32;
+    let height = bounds.height() as u32;
+    let mut coverage = alloc::vec![0u8; (width * height) as usize];
+    outline.draw(|px, py, value| {
+        if let Some(pixel) = coverage.get_mut((py * width + px) as usize) {
+            *pixel = (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        }
+    });
+
+    CachedGlyph { left: bounds.min.x as i32, top: bounds.min.y as i32, width, advance, coverage }
+}
+
+/// Mezclar la cobertura de un glifo sobre el framebuffer con el color dado
+fn blend_coverage(framebuffer: &mut FrameBuffer, glyph: &CachedGlyph, x: i32, y: i32, color: Rgb888) {
+    if glyph.width == 0 {
+        return;
+    }
+    let height = (glyph.coverage.len() / glyph.width as usize) as u32;
+    framebuffer.add_local_damage(Rectangle::new(Point::new(x + glyph.left, y + glyph.top), Size::new(glyph.width, height)));
+    for (row, line) in glyph.coverage.chunks(glyph.width as usize).enumerate() {
+        for (col, alpha) in line.iter().enumerate() {
+            framebuffer.plot_alpha(x + glyph.left + col as i32, y + glyph.top + row as i32, color, *alpha);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! FerroOS Mobile - Decodificación de imágenes
+//!
+//! Formatos soportados para los assets de las apps:
+//! - BMP (1/4/8/16/24/32 bits) mediante `tinybmp`
+//! - QOI completo
+//! - PNG no entrelazado, de 1 a 16 bits por canal (IDAT inflado con `miniz_oxide`)
+//!
+//! Todas las imágenes se decodifican a RGBA de 8 bits por canal.
+
+use alloc::vec::Vec;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use fos_microkernel::crc32;
+use tinybmp::Bmp;
+
+/// Error al cargar una imagen de los assets
+#[derive(Clone, Copy, Debug)]
+pub enum ImageError {
+    /// No hay ningún asset con ese nombre
+    NotFound,
+    /// El asset existe pero no se pudo decodificar
+    Decode(&'static str),
+}
+
+impl ImageError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            ImageError::NotFound => "imagen no encontrada",
+            ImageError::Decode(reason) => reason,
+        }
+    }
+}
+
+/// Imagen decodificada en RGBA, fila a fila de arriba abajo
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+/// Límite de pixels de una imagen decodificada (16 MB en RGBA)
+const MAX_PIXELS: u64 = 4 * 1024 * 1024;
+
+impl Image {
+    /// Decodificar detectando el formato por su cabecera
+    pub fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        if data.starts_with(b"BM") {
+            Self::decode_bmp(data)
+        } else if data.starts_with(b"qoif") {
+            Self::decode_qoi(data)
+        } else if data.starts_with(PNG_SIGNATURE) {
+            Self::decode_png(data)
+        } else {
+            Err("formato de imagen desconocido")
+        }
+    }
+
+    fn with_size(width: u32, height: u32) -> Result<Self, &'static str> {
+        if width == 0 || height == 0 {
+            return Err("imagen vacía");
+        }
+        if width as u64 * height as u64 > MAX_PIXELS {
+            return Err("imagen demasiado grande");
+        }
+        let mut pixels = Vec::new();
+        pixels
+            .try_reserve_exact((width * height) as usize)
+            .map_err(|_| "sin memoria para la imagen")?;
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Pixel (x, y) en RGBA
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Memoria que ocupan los pixels, para la caché
+    pub fn byte_size(&self) -> usize {
+        self.pixels.len() * 4
+    }
+
+    // ===== BMP =====
+
+    fn decode_bmp(data: &[u8]) -> Result<Self, &'static str> {
+        let bmp = Bmp::<Rgb888>::from_slice(data).map_err(|_| "BMP inválido o no soportado")?;
+        let size = bmp.size();
+        let mut image = Self::with_size(size.width, size.height)?;
+        image.pixels.resize((size.width * size.height) as usize, [0, 0, 0, 255]);
+        for Pixel(point, color) in bmp.pixels() {
+            if point.x >= 0 && point.y >= 0 && (point.x as u32) < size.width && (point.y as u32) < size.height {
+                image.pixels[(point.y as u32 * size.width + point.x as u32) as usize] =
+                    [color.r(), color.g(), color.b(), 255];
+            }
+        }
+        Ok(image)
+    }
+
+    // ===== QOI =====
+
+    fn decode_qoi(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < 14 + 8 {
+            return Err("QOI truncado");
+        }
+        let width = read_u32_be(data, 4);
+        let height = read_u32_be(data, 8);
+        let mut image = Self::with_size(width, height)?;
+        let total = (width * height) as usize;
+
+        let mut index = [[0u8; 4]; 64];
+        let mut px = [0u8, 0, 0, 255];
+        let mut pos = 14;
+        let end = data.len() - 8;
+        let mut run = 0u32;
+
+        while image.pixels.len() < total {
+            if run > 0 {
+                run -= 1;
+            } else {
+                if pos >= end {
+                    return Err("QOI truncado");
+                }
+                let tag = data[pos];
+                pos += 1;
+                match tag {
+                    0xFE => {
+                        px[..3].copy_from_slice(data.get(pos..pos + 3).ok_or("QOI truncado")?);
+                        pos += 3;
+                    }
+                    0xFF => {
+                        px.copy_from_slice(data.get(pos..pos + 4).ok_or("QOI truncado")?);
+                        pos += 4;
+                    }
+                    _ => match tag >> 6 {
+                        0 => px = index[tag as usize & 0x3F],
+                        1 => {
+                            px[0] = px[0].wrapping_add((tag >> 4) & 3).wrapping_sub(2);
+                            px[1] = px[1].wrapping_add((tag >> 2) & 3).wrapping_sub(2);
+                            px[2] = px[2].wrapping_add(tag & 3).wrapping_sub(2);
+                        }
+                        2 => {
+                            let second = *data.get(pos).ok_or("QOI truncado")?;
+                            pos += 1;
+                            let dg = (tag & 0x3F).wrapping_sub(32);
+                            px[0] = px[0].wrapping_add(dg.wrapping_sub(8).wrapping_add(second >> 4));
+                            px[1] = px[1].wrapping_add(dg);
+                            px[2] = px[2].wrapping_add(dg.wrapping_sub(8).wrapping_add(second & 0x0F));
+                        }
+                        _ => run = (tag & 0x3F) as u32,
+                    },
+                }
+                let hash = (px[0] as usize * 3 + px[1] as usize * 5 + px[2] as usize * 7 + px[3] as usize * 11) % 64;
+                index[hash] = px;
+            }
+            image.pixels.push(px);
+        }
+        Ok(image)
+    }
+
+    // ===== PNG =====
+
+    fn decode_png(data: &[u8]) -> Result<Self, &'static str> {
+        let mut pos = PNG_SIGNATURE.len();
+        let mut header: Option<PngHeader> = None;
+        let mut palette: &[u8] = &[];
+        let mut transparency: &[u8] = &[];
+        let mut compressed = Vec::new();
+
+        while pos + 8 <= data.len() {
+            let length = read_u32_be(data, pos) as usize;
+            let kind = &data[pos + 4..pos + 8];
+            let body = data.get(pos + 8..pos + 8 + length).ok_or("PNG truncado")?;
+            // El CRC cubre el tipo y el cuerpo del chunk
+            if pos + 12 + length > data.len() {
+                return Err("PNG truncado");
+            }
+            if crc32(&data[pos + 4..pos + 8 + length]) != read_u32_be(data, pos + 8 + length) {
+                return Err("PNG con CRC de chunk incorrecto");
+            }
+            pos += 12 + length;
+
+            match kind {
+                b"IHDR" => header = Some(PngHeader::parse(body)?),
+                b"PLTE" => palette = body,
+                b"tRNS" => transparency = body,
+                b"IDAT" => compressed.extend_from_slice(body),
+                b"IEND" => break,
+                _ => {}
+            }
+        }
+
+        let header = header.ok_or("PNG sin IHDR")?;
+        let mut image = Self::with_size(header.width, header.height)?;
+
+        let bpp = header.bits_per_pixel();
+        let stride = (header.width as usize * bpp).div_ceil(8);
+        // Distancia en bytes al pixel anterior para los filtros
+        let filter_step = bpp.div_ceil(8);
+        // Cada fila lleva delante su byte de filtro. El inflado se limita a ese
+        // tamaño: unos KB de zlib podrían expandirse hasta agotar el heap
+        let expected = (stride + 1) * header.height as usize;
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&compressed, expected)
+            .map_err(|_| "PNG con datos comprimidos inválidos o demasiado grandes")?;
+        if raw.len() != expected {
+            return Err("PNG con datos de imagen de tamaño incorrecto");
+        }
+
+        let mut previous = alloc::vec![0u8; stride];
+        let mut current = alloc::vec![0u8; stride];
+        for row in 0..header.height as usize {
+            let line = &raw[row * (stride + 1)..(row + 1) * (stride + 1)];
+            current.copy_from_slice(&line[1..]);
+            unfilter(line[0], &mut current, &previous, filter_step)?;
+            for x in 0..header.width as usize {
+                image.pixels.push(header.pixel(&current, x, palette, transparency));
+            }
+            core::mem::swap(&mut previous, &mut current);
+        }
+        Ok(image)
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Cabecera IHDR de un PNG
+struct PngHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl PngHeader {
+    fn parse(body: &[u8]) -> Result<Self, &'static str> {
+        if body.len() < 13 {
+            return Err("PNG con IHDR inválido");
+        }
+        let header = Self {
+            width: read_u32_be(body, 0),
+            height: read_u32_be(body, 4),
+            bit_depth: body[8],
+            color_type: body[9],
+        };
+        if body[12] != 0 {
+            return Err("PNG entrelazado no soportado");
+        }
+        let valid_depth = match header.color_type {
+            0 => matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(header.bit_depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(header.bit_depth, 8 | 16),
+            _ => false,
+        };
+        if !valid_depth {
+            return Err("PNG con formato de color inválido");
+        }
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    /// Muestra `channel` del pixel `x` reducida a 8 bits (o el índice de paleta)
+    fn sample(&self, row: &[u8], x: usize, channel: usize) -> u8 {
+        let depth = self.bit_depth as usize;
+        match depth {
+            // 16 bits: el byte alto basta para la pantalla
+            16 => row[(x * self.channels() + channel) * 2],
+            8 => row[x * self.channels() + channel],
+            _ => {
+                let bit = x * depth;
+                let value = (row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1);
+                if self.color_type == 3 {
+                    value
+                } else {
+                    // Escalar gris de 1/2/4 bits a 0..255
+                    (value as u32 * 255 / ((1 << depth) - 1)) as u8
+                }
+            }
+        }
+    }
+
+    fn pixel(&self, row: &[u8], x: usize, palette: &[u8], transparency: &[u8]) -> [u8; 4] {
+        match self.color_type {
+            0 => {
+                let gray = self.sample(row, x, 0);
+                [gray, gray, gray, 255]
+            }
+            2 => [self.sample(row, x, 0), self.sample(row, x, 1), self.sample(row, x, 2), 255],
+            3 => {
+                let index = self.sample(row, x, 0) as usize;
+                let rgb = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0, 0, 0]);
+                let alpha = transparency.get(index).copied().unwrap_or(255);
+                [rgb[0], rgb[1], rgb[2], alpha]
+            }
+            4 => {
+                let gray = self.sample(row, x, 0);
+                [gray, gray, gray, self.sample(row, x, 1)]
+            }
+            _ => [self.sample(row, x, 0), self.sample(row, x, 1), self.sample(row, x, 2), self.sample(row, x, 3)],
+        }
+    }
+}
+
+/// Deshacer el filtro PNG de una fila usando la fila anterior ya reconstruida
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], step: usize) -> Result<(), &'static str> {
+    for i in 0..row.len() {
+        let left = if i >= step { row[i - step] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= step { previous[i - step] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err("PNG con filtro inválido"),
+        };
+        row[i] = row[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    /// PNG RGB de 8 bits con `raw` (filas ya filtradas) como datos de imagen
+    fn png(width: u32, height: u32, raw: &[u8]) -> Vec<u8> {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut data = Vec::from(PNG_SIGNATURE);
+        for (kind, body) in [(b"IHDR", ihdr), (b"IDAT", compress_to_vec_zlib(raw, 6)), (b"IEND", Vec::new())] {
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            let mut chunk = Vec::from(&kind[..]);
+            chunk.extend_from_slice(&body);
+            data.extend_from_slice(&chunk);
+            data.extend_from_slice(&crc32(&chunk).to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_png_pixels() {
+        let image = Image::decode(&png(2, 1, &[0, 255, 0, 0, 0, 0, 255])).expect("PNG válido");
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn rejects_png_that_inflates_past_its_size() {
+        let bomb = alloc::vec![0u8; 16 * 1024 * 1024];
+        assert!(Image::decode(&png(1, 1, &bomb)).is_err());
+        assert!(Image::decode(&png(1, 1, &[0, 1, 2, 3, 4])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_png_data() {
+        assert!(Image::decode(&png(2, 2, &[0, 1, 2, 3, 4, 5, 6])).is_err());
+    }
+
+    #[test]
+    fn rejects_png_with_corrupt_chunk() {
+        let mut data = png(2, 1, &[0, 255, 0, 0, 0, 0, 255]);
+        // Primer byte del ancho en IHDR
+        data[PNG_SIGNATURE.len() + 8] ^= 0x80;
+        assert!(Image::decode(&data).is_err());
+    }
+}
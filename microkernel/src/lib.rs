@@ -1,6 +1,10 @@
 #![no_std]
 
 // Microkernel móvil minimalista
+//
+// Los periféricos solo existen en el dispositivo (`target_os = "none"`). En el
+// host (tests) la UART descarta lo enviado sin guardarlo en el registro del
+// kernel y no recibe nada, y el System Timer avanza 1 µs en cada lectura.
 
 use core::ptr;
 
@@ -9,8 +13,35 @@ const UART0_BASE: usize = 0x3F20_1000;  // PL011 UART
 const UART0_DR: *mut u32 = (UART0_BASE + 0x00) as *mut u32; // Data Register
 const UART0_FR: *mut u32 = (UART0_BASE + 0x18) as *mut u32; // Flag Register
 
-// Enviar un byte por UART PL011
+// System Timer del BCM2837: contador libre de 64 bits a 1 MHz
+const SYSTEM_TIMER_BASE: usize = 0x3F00_3000;
+const SYSTEM_TIMER_CLO: *const u32 = (SYSTEM_TIMER_BASE + 0x04) as *const u32;
+const SYSTEM_TIMER_CHI: *const u32 = (SYSTEM_TIMER_BASE + 0x08) as *const u32;
+
+// Registro del kernel: los últimos bytes enviados por UART, en un buffer circular
+const KERNEL_LOG_SIZE: usize = 16 * 1024;
+static mut KERNEL_LOG: [u8; KERNEL_LOG_SIZE] = [0; KERNEL_LOG_SIZE];
+// Bytes escritos en total; la posición en el buffer es el resto
+static mut KERNEL_LOG_WRITTEN: usize = 0;
+
+// Enviar un byte por UART PL011 y guardarlo en el registro del kernel
 pub fn uart_send(byte: u8) {
+    if !cfg!(target_os = "none") {
+        return;
+    }
+    unsafe {
+        let written = ptr::addr_of_mut!(KERNEL_LOG_WRITTEN);
+        (*ptr::addr_of_mut!(KERNEL_LOG))[*written % KERNEL_LOG_SIZE] = byte;
+        *written = (*written).wrapping_add(1);
+    }
+    uart_send_unlogged(byte);
+}
+
+// Enviar un byte por UART sin guardarlo en el registro (datos binarios, capturas...)
+pub fn uart_send_unlogged(byte: u8) {
+    if !cfg!(target_os = "none") {
+        return;
+    }
     unsafe {
         // Esperar que el FIFO de transmisión no esté lleno (bit 5 = TXFF)
         while UART0_FR.read_volatile() & (1 << 5) != 0 {}
@@ -21,6 +52,9 @@ pub fn uart_send(byte: u8) {
 
 // Recibir un byte por UART (bloqueante)
 pub fn uart_receive() -> u8 {
+    if !cfg!(target_os = "none") {
+        panic!("UART sin datos en el host");
+    }
     unsafe {
         // Esperar que el FIFO de recepción no esté vacío (bit 4 = RXFE)
         while UART0_FR.read_volatile() & (1 << 4) != 0 {}
@@ -31,6 +65,9 @@ pub fn uart_receive() -> u8 {
 
 // Recibir un byte por UART (no bloqueante)
 pub fn uart_receive_non_blocking() -> Option<u8> {
+    if !cfg!(target_os = "none") {
+        return None;
+    }
     unsafe {
         // Comprobar si el FIFO de recepción está vacío (bit 4 = RXFE)
         if UART0_FR.read_volatile() & (1 << 4) != 0 {
@@ -41,6 +78,39 @@ pub fn uart_receive_non_blocking() -> Option<u8> {
     }
 }
 
+// Recibir un byte por UART con límite de espera (en iteraciones de sondeo)
+pub fn uart_receive_timeout(mut spins: u32) -> Option<u8> {
+    while spins > 0 {
+        if let Some(byte) = uart_receive_non_blocking() {
+            return Some(byte);
+        }
+        spins -= 1;
+    }
+    None
+}
+
+// Recibir un entero u32 little-endian por UART con límite de espera por byte
+pub fn uart_receive_u32_le(spins: u32) -> Option<u32> {
+    let mut bytes = [0u8; 4];
+    for b in bytes.iter_mut() {
+        *b = uart_receive_timeout(spins)?;
+    }
+    Some(u32::from_le_bytes(bytes))
+}
+
+// Recorrer el registro del kernel, del byte más antiguo guardado al más reciente
+pub fn kernel_log(mut f: impl FnMut(&[u8])) {
+    unsafe {
+        let written = *ptr::addr_of!(KERNEL_LOG_WRITTEN);
+        let log = &*ptr::addr_of!(KERNEL_LOG);
+        let end = written % KERNEL_LOG_SIZE;
+        if written >= KERNEL_LOG_SIZE {
+            f(&log[end..]);
+        }
+        f(&log[..end]);
+    }
+}
+
 // Enviar string por UART
 pub fn uart_send_str(s: &str) {
     for b in s.as_bytes() { 
@@ -67,4 +137,47 @@ pub fn print_number(mut n: u64) {
         i -= 1;
         uart_send(buf[i]);
     }
-}
\ No newline at end of file
+}
+
+// Microsegundos desde el arranque, según el System Timer
+pub fn uptime_micros() -> u64 {
+    if !cfg!(target_os = "none") {
+        static HOST_CLOCK: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+        return HOST_CLOCK.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+    }
+    unsafe {
+        // Releer si la parte alta cambió mientras se leía la baja
+        loop {
+            let high = SYSTEM_TIMER_CHI.read_volatile();
+            let low = SYSTEM_TIMER_CLO.read_volatile();
+            if SYSTEM_TIMER_CHI.read_volatile() == high {
+                return ((high as u64) << 32) | low as u64;
+            }
+        }
+    }
+}
+
+// Esperar `micros` microsegundos con el System Timer
+pub fn delay_micros(micros: u64) {
+    let end = uptime_micros() + micros;
+    while uptime_micros() < end {
+        core::hint::spin_loop();
+    }
+}
+
+// CRC-32 (IEEE 802.3, polinomio reflejado 0xEDB88320), el mismo que calcula zlib
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, data)
+}
+
+// Continuar un CRC-32 calculado por partes: empezar en 0xFFFFFFFF e invertir el resultado final
+pub fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
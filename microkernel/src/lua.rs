@@ -0,0 +1,2382 @@
+//! FerroOS Mobile - Intérprete Lua embebido
+//!
+//! Subconjunto de Lua 5.x interpretado directamente sobre el AST. Cubre lo que
+//! necesitan los scripts de UI y el REPL del shell: variables locales y globales,
+//! tablas, funciones y closures, control de flujo, `pcall`/`error` y una biblioteca
+//! base reducida (`string`, `table`, `math`). No hay metatablas ni corrutinas.
+
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::BTreeMap, format, rc::{Rc, Weak}, string::String, vec, vec::Vec};
+use core::cell::RefCell;
+
+use fos_microkernel::uart_send_str;
+use crate::manifest::{self, Permission, PermissionSet};
+
+/// Profundidad máxima de llamadas anidadas antes de abortar con error
+const MAX_CALL_DEPTH: usize = 96;
+
+/// Anidamiento máximo de expresiones y bloques al analizar el código
+///
+/// El parser y el intérprete son recursivos: sin límite, una entrada como
+/// `((((...))))` desborda la pila del kernel.
+const MAX_PARSE_DEPTH: usize = 100;
+
+/// Longitud máxima (bytes) de las cadenas que construyen `..`, `string.rep`,
+/// `string.format` y `table.concat`: sin límite, un `string.rep("a", 1e9)`
+/// agota el heap y el kernel entero entra en pánico
+const MAX_STRING_LEN: usize = 1024 * 1024;
+
+/// Pasos (iteraciones de bucle y llamadas) que puede dar una ejecución desde el
+/// host antes de abortarla: un `while true do end` no puede colgar el shell
+const DEFAULT_STEP_LIMIT: u64 = 20_000_000;
+
+/// Objetos registrados a partir de los cuales se purgan las referencias muertas
+const MIN_TRACKED: usize = 256;
+
+// ===== VALORES =====
+
+pub type TableRef = Rc<RefCell<Table>>;
+pub type LuaResult<T> = Result<T, LuaError>;
+pub type NativeFn = fn(&mut Lua, Vec<Value>) -> LuaResult<Vec<Value>>;
+
+/// Función nativa (Rust) invocable desde Lua
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub func: NativeFn,
+    /// Permiso del manifiesto necesario para llamarla (`None`: siempre permitida)
+    pub permission: Option<Permission>,
+}
+
+impl NativeFunction {
+    pub const fn new(name: &'static str, func: NativeFn) -> Self {
+        Self { name, func, permission: None }
+    }
+}
+
+/// Valor Lua
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Rc<str>),
+    Table(TableRef),
+    Function(Rc<Closure>),
+    Native(NativeFunction),
+}
+
+impl Value {
+    pub fn str(s: &str) -> Self {
+        Value::Str(Rc::from(s))
+    }
+
+    pub fn new_table() -> Self {
+        Value::Table(Rc::new(RefCell::new(Table::default())))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Table(_) => "table",
+            Value::Function(_) | Value::Native(_) => "function",
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    pub fn truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// Conversión a número con coerción de strings, como en Lua
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Str(s) => parse_number(s.trim()),
+            _ => None,
+        }
+    }
+
+    /// Representación textual equivalente a `tostring`
+    pub fn to_display(&self) -> String {
+        match self {
+            Value::Nil => String::from("nil"),
+            Value::Bool(b) => String::from(if *b { "true" } else { "false" }),
+            Value::Number(n) => number_to_string(*n),
+            Value::Str(s) => String::from(&**s),
+            Value::Table(t) => format!("table: 0x{:x}", Rc::as_ptr(t) as usize),
+            Value::Function(f) => format!("function: {}: 0x{:x}", f.proto.name, Rc::as_ptr(f) as usize),
+            Value::Native(n) => format!("function: builtin: {}", n.name),
+        }
+    }
+
+    /// Igualdad primitiva (`==` sin metamétodos)
+    pub fn raw_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => a.name == b.name,
+            _ => false,
+        }
+    }
+}
+
+/// Formatear un número como lo hace Lua: enteros sin parte decimal
+pub fn number_to_string(n: f64) -> String {
+    if n == (n as i64) as f64 && libm::fabs(n) < 1e15 {
+        format!("{}", n as i64)
+    } else if n.is_nan() {
+        String::from("nan")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 { "inf" } else { "-inf" })
+    } else {
+        format!("{}", n)
+    }
+}
+
+/// Interpretar un literal numérico (decimal o hexadecimal)
+fn parse_number(s: &str) -> Option<f64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()? as f64
+    } else {
+        // `parse` acepta "inf"/"nan", que no son literales Lua
+        if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return None;
+        }
+        digits.parse::<f64>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+// ===== TABLAS =====
+
+/// Clave de tabla normalizada (los números enteros se guardan como `Int`)
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Bool(bool),
+    Int(i64),
+    Float(u64),
+    Str(Rc<str>),
+}
+
+impl Key {
+    fn from_value(value: &Value) -> LuaResult<Key> {
+        match value {
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Number(n) if n.is_nan() => Err(LuaError::new("índice de tabla es NaN")),
+            Value::Number(n) if *n == (*n as i64) as f64 => Ok(Key::Int(*n as i64)),
+            Value::Number(n) => Ok(Key::Float(n.to_bits())),
+            Value::Str(s) => Ok(Key::Str(s.clone())),
+            Value::Nil => Err(LuaError::new("índice de tabla es nil")),
+            other => Err(LuaError::new(&format!("clave de tabla de tipo {} no soportada", other.type_name()))),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Int(i) => Value::Number(*i as f64),
+            Key::Float(bits) => Value::Number(f64::from_bits(*bits)),
+            Key::Str(s) => Value::Str(s.clone()),
+        }
+    }
+}
+
+/// Tabla Lua: parte de array (índices 1..n) y parte hash ordenada
+#[derive(Default)]
+pub struct Table {
+    array: Vec<Value>,
+    hash: BTreeMap<Key, Value>,
+}
+
+impl Table {
+    pub fn get(&self, key: &Value) -> Value {
+        match Key::from_value(key) {
+            Ok(key) => self.get_key(&key),
+            Err(_) => Value::Nil,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Value {
+        self.get_key(&Key::Str(Rc::from(key)))
+    }
+
+    fn get_key(&self, key: &Key) -> Value {
+        if let Key::Int(i) = key
+            && *i >= 1
+            && (*i as usize) <= self.array.len()
+        {
+            return self.array[*i as usize - 1].clone();
+        }
+        self.hash.get(key).cloned().unwrap_or(Value::Nil)
+    }
+
+    pub fn set(&mut self, key: Value, value: Value) -> LuaResult<()> {
+        let key = Key::from_value(&key)?;
+        self.set_key(key, value);
+        Ok(())
+    }
+
+    pub fn set_str(&mut self, key: &str, value: Value) {
+        self.set_key(Key::Str(Rc::from(key)), value);
+    }
+
+    fn set_key(&mut self, key: Key, value: Value) {
+        if let Key::Int(i) = key {
+            let len = self.array.len();
+            if i >= 1 && (i as usize) <= len {
+                self.array[i as usize - 1] = value;
+                if i as usize == len {
+                    self.trim_array();
+                }
+                return;
+            }
+            if i as usize == len + 1 && i >= 1 {
+                if value.is_nil() {
+                    self.hash.remove(&key);
+                    return;
+                }
+                self.array.push(value);
+                self.hash.remove(&key);
+                self.migrate_from_hash();
+                return;
+            }
+        }
+        if value.is_nil() {
+            self.hash.remove(&key);
+        } else {
+            self.hash.insert(key, value);
+        }
+    }
+
+    /// Mover a la parte de array las claves enteras consecutivas
+    fn migrate_from_hash(&mut self) {
+        loop {
+            let next = Key::Int(self.array.len() as i64 + 1);
+            match self.hash.remove(&next) {
+                Some(value) => self.array.push(value),
+                None => break,
+            }
+        }
+    }
+
+    fn trim_array(&mut self) {
+        while matches!(self.array.last(), Some(Value::Nil)) {
+            self.array.pop();
+        }
+    }
+
+    /// Longitud (operador `#`)
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn push(&mut self, value: Value) {
+        if !value.is_nil() {
+            self.array.push(value);
+            self.migrate_from_hash();
+        }
+    }
+
+    pub fn insert(&mut self, pos: usize, value: Value) {
+        let pos = pos.clamp(1, self.array.len() + 1);
+        self.array.insert(pos - 1, value);
+        self.trim_array();
+        self.migrate_from_hash();
+    }
+
+    pub fn remove(&mut self, pos: usize) -> Value {
+        if pos >= 1 && pos <= self.array.len() {
+            self.array.remove(pos - 1)
+        } else {
+            Value::Nil
+        }
+    }
+
+    /// Siguiente par clave/valor después de `key` (semántica de `next`)
+    pub fn next(&self, key: &Value) -> LuaResult<Option<(Value, Value)>> {
+        let mut start_hash: Option<Key> = None;
+        let mut array_index = 0usize;
+        match key {
+            Value::Nil => {}
+            _ => match Key::from_value(key)? {
+                Key::Int(i) if i >= 1 && (i as usize) <= self.array.len() => array_index = i as usize,
+                other => {
+                    array_index = usize::MAX;
+                    start_hash = Some(other);
+                }
+            },
+        }
+
+        if array_index != usize::MAX {
+            while array_index < self.array.len() {
+                let value = &self.array[array_index];
+                array_index += 1;
+                if !value.is_nil() {
+                    return Ok(Some((Value::Number(array_index as f64), value.clone())));
+                }
+            }
+        }
+
+        let mut iter: Box<dyn Iterator<Item = (&Key, &Value)>> = match &start_hash {
+            Some(start) => {
+                let mut range = self.hash.range(start.clone()..);
+                range.next();
+                Box::new(range)
+            }
+            None => Box::new(self.hash.iter()),
+        };
+        Ok(iter.next().map(|(k, v)| (k.to_value(), v.clone())))
+    }
+}
+
+// ===== ERRORES =====
+
+/// Error de ejecución o de sintaxis
+#[derive(Clone)]
+pub struct LuaError {
+    pub message: String,
+    /// El código termina antes de tiempo (útil para continuar líneas en el REPL)
+    pub incomplete: bool,
+}
+
+impl LuaError {
+    pub fn new(message: &str) -> Self {
+        Self { message: String::from(message), incomplete: false }
+    }
+}
+
+// ===== LÉXICO =====
+
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+const SYMBOLS: [&str; 32] = [
+    "...", "..", "==", "~=", "<=", ">=", "//", "::", "+", "-", "*", "/", "%", "^", "#", "&", "~", "|",
+    "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".", "@",
+];
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Name(Rc<str>),
+    Number(f64),
+    Str(Rc<str>),
+    Keyword(&'static str),
+    Symbol(&'static str),
+    Eof,
+}
+
+struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: u32,
+}
+
+impl<'a> Lexer<'a> {
+    fn tokenize(source: &'a str) -> LuaResult<Vec<(Token, u32)>> {
+        let mut lexer = Lexer { src: source.as_bytes(), pos: 0, line: 1 };
+        let mut tokens = Vec::new();
+        loop {
+            lexer.skip_whitespace_and_comments()?;
+            let line = lexer.line;
+            let token = lexer.next_token()?;
+            let done = token == Token::Eof;
+            tokens.push((token, line));
+            if done {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn peek(&self, offset: usize) -> u8 {
+        self.src.get(self.pos + offset).copied().unwrap_or(0)
+    }
+
+    fn error(&self, message: &str, incomplete: bool) -> LuaError {
+        LuaError { message: format!("[línea {}] {}", self.line, message), incomplete }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> LuaResult<()> {
+        loop {
+            match self.peek(0) {
+                b'\n' => {
+                    self.line += 1;
+                    self.pos += 1;
+                }
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'-' if self.peek(1) == b'-' => {
+                    self.pos += 2;
+                    if self.peek(0) == b'['
+                        && let Some(level) = self.long_bracket_level()
+                    {
+                        self.read_long_string(level)?;
+                        continue;
+                    }
+                    while self.pos < self.src.len() && self.peek(0) != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Nivel de un corchete largo `[==[` en la posición actual
+    fn long_bracket_level(&self) -> Option<usize> {
+        let mut level = 0;
+        while self.peek(1 + level) == b'=' {
+            level += 1;
+        }
+        if self.peek(1 + level) == b'[' { Some(level) } else { None }
+    }
+
+    fn read_long_string(&mut self, level: usize) -> LuaResult<String> {
+        self.pos += level + 2;
+        // Un salto de línea inmediato tras la apertura no forma parte del texto
+        if self.peek(0) == b'\r' {
+            self.pos += 1;
+        }
+        if self.peek(0) == b'\n' {
+            self.line += 1;
+            self.pos += 1;
+        }
+        let start = self.pos;
+        loop {
+            if self.pos >= self.src.len() {
+                return Err(self.error("string o comentario largo sin cerrar", true));
+            }
+            if self.peek(0) == b']' {
+                let mut eq = 0;
+                while self.peek(1 + eq) == b'=' {
+                    eq += 1;
+                }
+                if eq == level && self.peek(1 + eq) == b']' {
+                    let text = core::str::from_utf8(&self.src[start..self.pos])
+                        .map_err(|_| self.error("string no es UTF-8 válido", false))?;
+                    self.pos += level + 2;
+                    return Ok(String::from(text));
+                }
+            }
+            if self.peek(0) == b'\n' {
+                self.line += 1;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn next_token(&mut self) -> LuaResult<Token> {
+        let c = self.peek(0);
+        if self.pos >= self.src.len() {
+            return Ok(Token::Eof);
+        }
+        if c.is_ascii_alphabetic() || c == b'_' {
+            let start = self.pos;
+            while self.peek(0).is_ascii_alphanumeric() || self.peek(0) == b'_' {
+                self.pos += 1;
+            }
+            let word = core::str::from_utf8(&self.src[start..self.pos]).unwrap_or("");
+            if let Some(kw) = KEYWORDS.iter().find(|kw| **kw == word) {
+                return Ok(Token::Keyword(kw));
+            }
+            return Ok(Token::Name(Rc::from(word)));
+        }
+        if c.is_ascii_digit() || (c == b'.' && self.peek(1).is_ascii_digit()) {
+            return self.read_number();
+        }
+        if c == b'"' || c == b'\'' {
+            return self.read_string(c);
+        }
+        if c == b'['
+            && let Some(level) = self.long_bracket_level()
+        {
+            return Ok(Token::Str(Rc::from(self.read_long_string(level)?.as_str())));
+        }
+        for sym in SYMBOLS.iter() {
+            if self.src[self.pos..].starts_with(sym.as_bytes()) {
+                self.pos += sym.len();
+                return Ok(Token::Symbol(sym));
+            }
+        }
+        Err(self.error(&format!("símbolo inesperado '{}'", c as char), false))
+    }
+
+    fn read_number(&mut self) -> LuaResult<Token> {
+        let start = self.pos;
+        if self.peek(0) == b'0' && (self.peek(1) == b'x' || self.peek(1) == b'X') {
+            self.pos += 2;
+            while self.peek(0).is_ascii_hexdigit() {
+                self.pos += 1;
+            }
+        } else {
+            while self.peek(0).is_ascii_digit() || self.peek(0) == b'.' {
+                self.pos += 1;
+            }
+            if self.peek(0) == b'e' || self.peek(0) == b'E' {
+                self.pos += 1;
+                if self.peek(0) == b'+' || self.peek(0) == b'-' {
+                    self.pos += 1;
+                }
+                while self.peek(0).is_ascii_digit() {
+                    self.pos += 1;
+                }
+            }
+        }
+        let text = core::str::from_utf8(&self.src[start..self.pos]).unwrap_or("");
+        parse_number(text)
+            .map(Token::Number)
+            .ok_or_else(|| self.error(&format!("número mal formado '{}'", text), false))
+    }
+
+    fn read_string(&mut self, quote: u8) -> LuaResult<Token> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let c = self.peek(0);
+            if self.pos >= self.src.len() || c == b'\n' {
+                return Err(self.error("string sin cerrar", self.pos >= self.src.len()));
+            }
+            self.pos += 1;
+            if c == quote {
+                break;
+            }
+            if c != b'\\' {
+                bytes.push(c);
+                continue;
+            }
+            let esc = self.peek(0);
+            self.pos += 1;
+            match esc {
+                b'n' => bytes.push(b'\n'),
+                b't' => bytes.push(b'\t'),
+                b'r' => bytes.push(b'\r'),
+                b'a' => bytes.push(0x07),
+                b'b' => bytes.push(0x08),
+                b'0'..=b'9' => {
+                    let mut value = (esc - b'0') as u32;
+                    for _ in 0..2 {
+                        if self.peek(0).is_ascii_digit() {
+                            value = value * 10 + (self.peek(0) - b'0') as u32;
+                            self.pos += 1;
+                        }
+                    }
+                    if value > 255 {
+                        return Err(self.error("escape decimal fuera de rango", false));
+                    }
+                    bytes.push(value as u8);
+                }
+                b'x' => {
+                    let hex = core::str::from_utf8(&self.src[self.pos..(self.pos + 2).min(self.src.len())]).unwrap_or("");
+                    let value = u8::from_str_radix(hex, 16).map_err(|_| self.error("escape hexadecimal inválido", false))?;
+                    self.pos += 2;
+                    bytes.push(value);
+                }
+                b'\n' => {
+                    self.line += 1;
+                    bytes.push(b'\n');
+                }
+                other => bytes.push(other),
+            }
+        }
+        let text = String::from_utf8(bytes).map_err(|_| self.error("string no es UTF-8 válido", false))?;
+        Ok(Token::Str(Rc::from(text.as_str())))
+    }
+}
+
+// ===== AST =====
+
+#[derive(Clone, Copy, PartialEq)]
+enum BinOp {
+    Add, Sub, Mul, Div, IDiv, Mod, Pow, Concat,
+    Eq, Ne, Lt, Le, Gt, Ge, And, Or,
+}
+
+#[derive(Clone, Copy)]
+enum UnOp {
+    Neg, Not, Len,
+}
+
+enum Expr {
+    Nil,
+    True,
+    False,
+    Number(f64),
+    Str(Rc<str>),
+    Vararg,
+    Function(Rc<FuncProto>),
+    Name(Rc<str>),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>, u32),
+    Method(Box<Expr>, Rc<str>, Vec<Expr>, u32),
+    Binary(BinOp, Box<Expr>, Box<Expr>, u32),
+    Unary(UnOp, Box<Expr>, u32),
+    Table(Vec<TableField>),
+    Paren(Box<Expr>),
+}
+
+enum TableField {
+    Positional(Expr),
+    Keyed(Expr, Expr),
+}
+
+enum Stmt {
+    Local(Vec<Rc<str>>, Vec<Expr>),
+    LocalFunction(Rc<str>, Rc<FuncProto>),
+    Assign(Vec<Expr>, Vec<Expr>),
+    Call(Expr),
+    Do(Block),
+    While(Expr, Block),
+    Repeat(Block, Expr),
+    If(Vec<(Expr, Block)>, Option<Block>),
+    NumericFor(Rc<str>, Expr, Expr, Option<Expr>, Block),
+    GenericFor(Vec<Rc<str>>, Vec<Expr>, Block),
+    Return(Vec<Expr>),
+    Break,
+}
+
+type Block = Vec<Stmt>;
+
+/// Prototipo de función ya analizado
+pub struct FuncProto {
+    name: Rc<str>,
+    params: Vec<Rc<str>>,
+    vararg: bool,
+    body: Block,
+}
+
+/// Función Lua con su entorno léxico capturado
+pub struct Closure {
+    proto: Rc<FuncProto>,
+    env: Rc<Scope>,
+}
+
+/// Chunk compilado, listo para ejecutarse sentencia a sentencia
+pub struct Chunk {
+    body: Block,
+}
+
+// ===== PARSER =====
+
+struct Parser {
+    tokens: Vec<(Token, u32)>,
+    pos: usize,
+    /// Bloques y expresiones abiertos ahora mismo
+    depth: usize,
+}
+
+impl Parser {
+    fn parse_chunk(source: &str) -> LuaResult<Chunk> {
+        let mut parser = Parser { tokens: Lexer::tokenize(source)?, pos: 0, depth: 0 };
+        let body = parser.block()?;
+        if !parser.check_eof() {
+            return Err(parser.error("se esperaba fin de código"));
+        }
+        Ok(Chunk { body })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> u32 {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn check_eof(&self) -> bool {
+        *self.peek() == Token::Eof
+    }
+
+    fn check_sym(&self, sym: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == sym)
+    }
+
+    fn check_kw(&self, kw: &str) -> bool {
+        matches!(self.peek(), Token::Keyword(k) if *k == kw)
+    }
+
+    fn accept_sym(&mut self, sym: &str) -> bool {
+        if self.check_sym(sym) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn accept_kw(&mut self, kw: &str) -> bool {
+        if self.check_kw(kw) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> LuaError {
+        let near = match self.peek() {
+            Token::Eof => String::from("<eof>"),
+            Token::Name(n) => format!("'{}'", n),
+            Token::Keyword(k) | Token::Symbol(k) => format!("'{}'", k),
+            Token::Number(n) => format!("'{}'", number_to_string(*n)),
+            Token::Str(_) => String::from("string"),
+        };
+        LuaError {
+            message: format!("[línea {}] {} cerca de {}", self.line(), message, near),
+            incomplete: self.check_eof(),
+        }
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> LuaResult<()> {
+        if self.accept_sym(sym) { Ok(()) } else { Err(self.error(&format!("se esperaba '{}'", sym))) }
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> LuaResult<()> {
+        if self.accept_kw(kw) { Ok(()) } else { Err(self.error(&format!("se esperaba '{}'", kw))) }
+    }
+
+    fn expect_name(&mut self) -> LuaResult<Rc<str>> {
+        match self.peek() {
+            Token::Name(n) => {
+                let n = n.clone();
+                self.advance();
+                Ok(n)
+            }
+            _ => Err(self.error("se esperaba un nombre")),
+        }
+    }
+
+    fn block_ends(&self) -> bool {
+        self.check_eof() || self.check_kw("end") || self.check_kw("else") || self.check_kw("elseif") || self.check_kw("until")
+    }
+
+    /// Entrar en un nivel de anidamiento; error si se pasa de `MAX_PARSE_DEPTH`
+    fn enter_level(&mut self) -> LuaResult<()> {
+        if self.depth >= MAX_PARSE_DEPTH {
+            return Err(self.error("demasiados niveles de anidamiento"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn block(&mut self) -> LuaResult<Block> {
+        self.enter_level()?;
+        let block = self.block_statements();
+        self.depth -= 1;
+        block
+    }
+
+    fn block_statements(&mut self) -> LuaResult<Block> {
+        let mut stmts = Vec::new();
+        while !self.block_ends() {
+            if self.accept_sym(";") {
+                continue;
+            }
+            if self.check_kw("return") {
+                self.advance();
+                let values = if self.block_ends() || self.check_sym(";") { Vec::new() } else { self.expr_list()? };
+                self.accept_sym(";");
+                stmts.push(Stmt::Return(values));
+                if !self.block_ends() {
+                    return Err(self.error("'return' debe ser la última sentencia del bloque"));
+                }
+                break;
+            }
+            stmts.push(self.statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> LuaResult<Stmt> {
+        if self.accept_kw("local") {
+            if self.accept_kw("function") {
+                let name = self.expect_name()?;
+                let proto = self.func_body(name.clone(), false)?;
+                return Ok(Stmt::LocalFunction(name, proto));
+            }
+            let mut names = vec![self.expect_name()?];
+            while self.accept_sym(",") {
+                names.push(self.expect_name()?);
+            }
+            let values = if self.accept_sym("=") { self.expr_list()? } else { Vec::new() };
+            return Ok(Stmt::Local(names, values));
+        }
+        if self.accept_kw("function") {
+            let first = self.expect_name()?;
+            let mut full_name = String::from(&*first);
+            let mut target = Expr::Name(first);
+            let mut is_method = false;
+            while self.check_sym(".") || self.check_sym(":") {
+                is_method = self.check_sym(":");
+                self.advance();
+                let field = self.expect_name()?;
+                full_name.push(if is_method { ':' } else { '.' });
+                full_name.push_str(&field);
+                target = Expr::Index(Box::new(target), Box::new(Expr::Str(field)));
+                if is_method {
+                    break;
+                }
+            }
+            let proto = self.func_body(Rc::from(full_name.as_str()), is_method)?;
+            return Ok(Stmt::Assign(vec![target], vec![Expr::Function(proto)]));
+        }
+        if self.accept_kw("if") {
+            let mut branches = Vec::new();
+            let cond = self.expr()?;
+            self.expect_kw("then")?;
+            branches.push((cond, self.block()?));
+            let mut otherwise = None;
+            loop {
+                if self.accept_kw("elseif") {
+                    let cond = self.expr()?;
+                    self.expect_kw("then")?;
+                    branches.push((cond, self.block()?));
+                } else if self.accept_kw("else") {
+                    otherwise = Some(self.block()?);
+                    self.expect_kw("end")?;
+                    break;
+                } else {
+                    self.expect_kw("end")?;
+                    break;
+                }
+            }
+            return Ok(Stmt::If(branches, otherwise));
+        }
+        if self.accept_kw("while") {
+            let cond = self.expr()?;
+            self.expect_kw("do")?;
+            let body = self.block()?;
+            self.expect_kw("end")?;
+            return Ok(Stmt::While(cond, body));
+        }
+        if self.accept_kw("repeat") {
+            let body = self.block()?;
+            self.expect_kw("until")?;
+            let cond = self.expr()?;
+            return Ok(Stmt::Repeat(body, cond));
+        }
+        if self.accept_kw("do") {
+            let body = self.block()?;
+            self.expect_kw("end")?;
+            return Ok(Stmt::Do(body));
+        }
+        if self.accept_kw("for") {
+            let first = self.expect_name()?;
+            if self.accept_sym("=") {
+                let start = self.expr()?;
+                self.expect_sym(",")?;
+                let limit = self.expr()?;
+                let step = if self.accept_sym(",") { Some(self.expr()?) } else { None };
+                self.expect_kw("do")?;
+                let body = self.block()?;
+                self.expect_kw("end")?;
+                return Ok(Stmt::NumericFor(first, start, limit, step, body));
+            }
+            let mut names = vec![first];
+            while self.accept_sym(",") {
+                names.push(self.expect_name()?);
+            }
+            self.expect_kw("in")?;
+            let exprs = self.expr_list()?;
+            self.expect_kw("do")?;
+            let body = self.block()?;
+            self.expect_kw("end")?;
+            return Ok(Stmt::GenericFor(names, exprs, body));
+        }
+        if self.accept_kw("break") {
+            return Ok(Stmt::Break);
+        }
+
+        // Sentencia de expresión: llamada o asignación
+        let first = self.suffixed_expr()?;
+        if self.check_sym("=") || self.check_sym(",") {
+            let mut targets = vec![first];
+            while self.accept_sym(",") {
+                targets.push(self.suffixed_expr()?);
+            }
+            self.expect_sym("=")?;
+            for target in targets.iter() {
+                if !matches!(target, Expr::Name(_) | Expr::Index(..)) {
+                    return Err(self.error("no se puede asignar a esta expresión"));
+                }
+            }
+            let values = self.expr_list()?;
+            return Ok(Stmt::Assign(targets, values));
+        }
+        match first {
+            Expr::Call(..) | Expr::Method(..) => Ok(Stmt::Call(first)),
+            _ => Err(self.error("error de sintaxis")),
+        }
+    }
+
+    fn func_body(&mut self, name: Rc<str>, is_method: bool) -> LuaResult<Rc<FuncProto>> {
+        self.expect_sym("(")?;
+        let mut params = Vec::new();
+        if is_method {
+            params.push(Rc::from("self"));
+        }
+        let mut vararg = false;
+        if !self.check_sym(")") {
+            loop {
+                if self.accept_sym("...") {
+                    vararg = true;
+                    break;
+                }
+                params.push(self.expect_name()?);
+                if !self.accept_sym(",") {
+                    break;
+                }
+            }
+        }
+        self.expect_sym(")")?;
+        let body = self.block()?;
+        self.expect_kw("end")?;
+        Ok(Rc::new(FuncProto { name, params, vararg, body }))
+    }
+
+    fn expr_list(&mut self) -> LuaResult<Vec<Expr>> {
+        let mut exprs = vec![self.expr()?];
+        while self.accept_sym(",") {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+
+    fn expr(&mut self) -> LuaResult<Expr> {
+        self.sub_expr(0)
+    }
+
+    fn binary_op(&self) -> Option<(BinOp, u8, u8)> {
+        let op = match self.peek() {
+            Token::Keyword("or") => (BinOp::Or, 1, 1),
+            Token::Keyword("and") => (BinOp::And, 2, 2),
+            Token::Symbol("<") => (BinOp::Lt, 3, 3),
+            Token::Symbol(">") => (BinOp::Gt, 3, 3),
+            Token::Symbol("<=") => (BinOp::Le, 3, 3),
+            Token::Symbol(">=") => (BinOp::Ge, 3, 3),
+            Token::Symbol("~=") => (BinOp::Ne, 3, 3),
+            Token::Symbol("==") => (BinOp::Eq, 3, 3),
+            Token::Symbol("..") => (BinOp::Concat, 9, 8),
+            Token::Symbol("+") => (BinOp::Add, 10, 10),
+            Token::Symbol("-") => (BinOp::Sub, 10, 10),
+            Token::Symbol("*") => (BinOp::Mul, 11, 11),
+            Token::Symbol("/") => (BinOp::Div, 11, 11),
+            Token::Symbol("//") => (BinOp::IDiv, 11, 11),
+            Token::Symbol("%") => (BinOp::Mod, 11, 11),
+            Token::Symbol("^") => (BinOp::Pow, 14, 13),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn sub_expr(&mut self, limit: u8) -> LuaResult<Expr> {
+        self.enter_level()?;
+        let expr = self.sub_expr_operators(limit);
+        self.depth -= 1;
+        expr
+    }
+
+    fn sub_expr_operators(&mut self, limit: u8) -> LuaResult<Expr> {
+        const UNARY_PRIORITY: u8 = 12;
+        let line = self.line();
+        let unary = match self.peek() {
+            Token::Keyword("not") => Some(UnOp::Not),
+            Token::Symbol("-") => Some(UnOp::Neg),
+            Token::Symbol("#") => Some(UnOp::Len),
+            _ => None,
+        };
+        let mut left = if let Some(op) = unary {
+            self.advance();
+            let operand = self.sub_expr(UNARY_PRIORITY)?;
+            match (op, operand) {
+                (UnOp::Neg, Expr::Number(n)) => Expr::Number(-n),
+                (op, operand) => Expr::Unary(op, Box::new(operand), line),
+            }
+        } else {
+            self.simple_expr()?
+        };
+        while let Some((op, left_prio, right_prio)) = self.binary_op() {
+            if left_prio <= limit {
+                break;
+            }
+            let line = self.line();
+            self.advance();
+            let right = self.sub_expr(right_prio)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right), line);
+        }
+        Ok(left)
+    }
+
+    fn simple_expr(&mut self) -> LuaResult<Expr> {
+        let expr = match self.peek().clone() {
+            Token::Number(n) => Expr::Number(n),
+            Token::Str(s) => Expr::Str(s),
+            Token::Keyword("nil") => Expr::Nil,
+            Token::Keyword("true") => Expr::True,
+            Token::Keyword("false") => Expr::False,
+            Token::Symbol("...") => Expr::Vararg,
+            Token::Symbol("{") => return self.table_constructor(),
+            Token::Keyword("function") => {
+                self.advance();
+                return Ok(Expr::Function(self.func_body(Rc::from("anónima"), false)?));
+            }
+            _ => return self.suffixed_expr(),
+        };
+        self.advance();
+        Ok(expr)
+    }
+
+    fn primary_expr(&mut self) -> LuaResult<Expr> {
+        match self.peek().clone() {
+            Token::Name(n) => {
+                self.advance();
+                Ok(Expr::Name(n))
+            }
+            Token::Symbol("(") => {
+                self.advance();
+                let inner = self.expr()?;
+                self.expect_sym(")")?;
+                Ok(Expr::Paren(Box::new(inner)))
+            }
+            _ => Err(self.error("expresión inesperada")),
+        }
+    }
+
+    fn suffixed_expr(&mut self) -> LuaResult<Expr> {
+        let mut expr = self.primary_expr()?;
+        loop {
+            let line = self.line();
+            if self.accept_sym(".") {
+                let name = self.expect_name()?;
+                expr = Expr::Index(Box::new(expr), Box::new(Expr::Str(name)));
+            } else if self.accept_sym("[") {
+                let key = self.expr()?;
+                self.expect_sym("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(key));
+            } else if self.accept_sym(":") {
+                let name = self.expect_name()?;
+                let args = self.call_args()?;
+                expr = Expr::Method(Box::new(expr), name, args, line);
+            } else if self.check_sym("(") || self.check_sym("{") || matches!(self.peek(), Token::Str(_)) {
+                let args = self.call_args()?;
+                expr = Expr::Call(Box::new(expr), args, line);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn call_args(&mut self) -> LuaResult<Vec<Expr>> {
+        match self.peek().clone() {
+            Token::Str(s) => {
+                self.advance();
+                Ok(vec![Expr::Str(s)])
+            }
+            Token::Symbol("{") => Ok(vec![self.table_constructor()?]),
+            _ => {
+                self.expect_sym("(")?;
+                if self.accept_sym(")") {
+                    return Ok(Vec::new());
+                }
+                let args = self.expr_list()?;
+                self.expect_sym(")")?;
+                Ok(args)
+            }
+        }
+    }
+
+    fn table_constructor(&mut self) -> LuaResult<Expr> {
+        self.expect_sym("{")?;
+        let mut fields = Vec::new();
+        while !self.check_sym("}") {
+            if self.accept_sym("[") {
+                let key = self.expr()?;
+                self.expect_sym("]")?;
+                self.expect_sym("=")?;
+                fields.push(TableField::Keyed(key, self.expr()?));
+            } else if matches!(self.peek(), Token::Name(_))
+                && matches!(self.tokens.get(self.pos + 1), Some((Token::Symbol("="), _)))
+            {
+                let name = self.expect_name()?;
+                self.advance();
+                fields.push(TableField::Keyed(Expr::Str(name), self.expr()?));
+            } else {
+                fields.push(TableField::Positional(self.expr()?));
+            }
+            if !self.accept_sym(",") && !self.accept_sym(";") {
+                break;
+            }
+        }
+        self.expect_sym("}")?;
+        Ok(Expr::Table(fields))
+    }
+}
+
+// ===== ÁMBITOS =====
+
+/// Celda compartida de una variable local
+type Slot = Rc<RefCell<Value>>;
+
+/// Ámbito léxico: variables locales con enlace al ámbito padre
+pub struct Scope {
+    vars: RefCell<Vec<(Rc<str>, Slot)>>,
+    parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+    fn new(parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope { vars: RefCell::new(Vec::new()), parent })
+    }
+
+    fn declare(&self, name: Rc<str>, value: Value) {
+        self.vars.borrow_mut().push((name, Rc::new(RefCell::new(value))));
+    }
+
+    fn lookup(&self, name: &str) -> Option<Slot> {
+        if let Some((_, cell)) = self.vars.borrow().iter().rev().find(|(n, _)| &**n == name) {
+            return Some(cell.clone());
+        }
+        self.parent.as_ref().and_then(|p| p.lookup(name))
+    }
+}
+
+/// Resultado de ejecutar una sentencia
+enum Flow {
+    Normal,
+    Break,
+    Return(Vec<Value>),
+}
+
+// ===== ESTADO DEL INTÉRPRETE =====
+
+/// Objetos que pueden formar ciclos de `Rc`: tablas y ámbitos capturados por closures
+///
+/// No hay recolector de basura: al destruir el estado se vacían los que siguen
+/// vivos, lo que rompe los ciclos (funciones locales recursivas, `t.self = t`...)
+/// y libera su memoria.
+#[derive(Default)]
+struct Tracked {
+    tables: Vec<Weak<RefCell<Table>>>,
+    scopes: Vec<Weak<Scope>>,
+    /// Tamaño a partir del cual se purgan las referencias muertas
+    prune_at: usize,
+}
+
+impl Tracked {
+    fn add_table(&mut self, table: &TableRef) {
+        self.tables.push(Rc::downgrade(table));
+        self.prune();
+    }
+
+    fn add_scope(&mut self, scope: &Rc<Scope>) {
+        self.scopes.push(Rc::downgrade(scope));
+        self.prune();
+    }
+
+    /// Descartar las referencias a objetos ya liberados cuando la lista dobla su tamaño
+    fn prune(&mut self) {
+        if self.tables.len() + self.scopes.len() < self.prune_at.max(MIN_TRACKED) {
+            return;
+        }
+        self.tables.retain(|table| table.strong_count() > 0);
+        self.scopes.retain(|scope| scope.strong_count() > 0);
+        self.prune_at = 2 * (self.tables.len() + self.scopes.len());
+    }
+}
+
+/// Estado Lua persistente: tabla de globales y pila de llamadas
+pub struct Lua {
+    globals: TableRef,
+    permissions: PermissionSet,
+    depth: usize,
+    varargs: Vec<Rc<[Value]>>,
+    rng_state: u64,
+    tracked: Tracked,
+    /// Pasos dados desde que el host empezó la ejecución actual
+    steps: u64,
+    step_limit: u64,
+}
+
+/// Vaciar los ámbitos y tablas que siguen vivos para romper los ciclos y liberarlos
+impl Drop for Lua {
+    fn drop(&mut self) {
+        let tracked = core::mem::take(&mut self.tracked);
+        for scope in tracked.scopes.iter().filter_map(Weak::upgrade) {
+            let mut current = Some(scope);
+            while let Some(scope) = current {
+                // Se sacan antes de soltarlas: al liberarse pueden liberar otros ámbitos
+                let vars = core::mem::take(&mut *scope.vars.borrow_mut());
+                drop(vars);
+                current = scope.parent.clone();
+            }
+        }
+        for table in tracked.tables.iter().filter_map(Weak::upgrade).chain([self.globals.clone()]) {
+            let contents = core::mem::take(&mut *table.borrow_mut());
+            drop(contents);
+        }
+    }
+}
+
+impl Lua {
+    /// Crear un estado nuevo con la biblioteca base cargada
+    pub fn new() -> Self {
+        let mut lua = Self {
+            globals: Rc::new(RefCell::new(Table::default())),
+            permissions: PermissionSet::all(),
+            depth: 0,
+            varargs: Vec::new(),
+            rng_state: 0x2545_F491_4F6C_DD1D,
+            tracked: Tracked::default(),
+            steps: 0,
+            step_limit: DEFAULT_STEP_LIMIT,
+        };
+        open_base_library(&mut lua);
+        lua
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().set_str(name, value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Value {
+        self.globals.borrow().get_str(name)
+    }
+
+    /// Registrar una función nativa como global
+    pub fn register(&mut self, name: &'static str, func: NativeFn) {
+        self.set_global(name, Value::Native(NativeFunction::new(name, func)));
+    }
+
+    /// Registrar una función nativa que sólo pueden llamar apps con `permission`
+    pub fn register_with_permission(&mut self, name: &'static str, permission: Permission, func: NativeFn) {
+        self.set_global(name, Value::Native(NativeFunction { name, func, permission: Some(permission) }));
+    }
+
+    /// Crear una tabla vacía de este estado (se vacía al destruirlo)
+    pub fn new_table(&mut self) -> Value {
+        let table = Rc::new(RefCell::new(Table::default()));
+        self.tracked.add_table(&table);
+        Value::Table(table)
+    }
+
+    /// Contar un paso (iteración de bucle o llamada) y abortar si se agota el presupuesto
+    fn step(&mut self) -> LuaResult<()> {
+        self.steps += 1;
+        if self.steps > self.step_limit {
+            return Err(LuaError::new("tiempo de ejecución agotado (¿bucle infinito?)"));
+        }
+        Ok(())
+    }
+
+    /// Empezar a contar pasos si la ejecución llega desde el host y no desde Lua
+    fn start_steps(&mut self) {
+        if self.depth == 0 {
+            self.steps = 0;
+        }
+    }
+
+    /// Fijar los permisos concedidos al código que ejecuta este estado
+    pub fn set_permissions(&mut self, permissions: PermissionSet) {
+        self.permissions = permissions;
+    }
+
+    /// Comprobar que el estado tiene `permission` para usar `api`; si no, se
+    /// registra la denegación y se devuelve un error capturable
+    pub fn check_permission(&self, api: &str, permission: Permission) -> LuaResult<()> {
+        if self.permissions.allows(permission) {
+            return Ok(());
+        }
+        manifest::log_denied(api, permission);
+        Err(LuaError::new(&format!("permiso denegado: '{}' requiere el permiso '{}'", api, permission.name())))
+    }
+
+    /// Compilar código fuente sin ejecutarlo
+    pub fn load(&self, source: &str) -> LuaResult<Chunk> {
+        Parser::parse_chunk(source)
+    }
+
+    /// Compilar y ejecutar código fuente
+    pub fn exec(&mut self, source: &str) -> LuaResult<Vec<Value>> {
+        let chunk = self.load(source)?;
+        self.exec_chunk_with(&chunk, |_| {})
+    }
+
+    /// Ejecutar un chunk llamando a `on_statement` tras cada sentencia de nivel superior
+    pub fn exec_chunk_with(&mut self, chunk: &Chunk, mut on_statement: impl FnMut(&mut Lua)) -> LuaResult<Vec<Value>> {
+        self.start_steps();
+        let scope = Scope::new(None);
+        self.varargs.push(Rc::from(Vec::new()));
+        let mut result = Ok(Vec::new());
+        for stmt in chunk.body.iter() {
+            match self.exec_stmt(stmt, &scope) {
+                Ok(Flow::Normal) => on_statement(self),
+                Ok(Flow::Return(values)) => {
+                    result = Ok(values);
+                    break;
+                }
+                Ok(Flow::Break) => {
+                    result = Err(LuaError::new("'break' fuera de un bucle"));
+                    break;
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.varargs.pop();
+        result
+    }
+
+    /// Evaluar una entrada del REPL: primero como expresión, si no como sentencias
+    pub fn eval_repl(&mut self, source: &str) -> LuaResult<Vec<Value>> {
+        let mut as_expr = String::from("return ");
+        as_expr.push_str(source);
+        match self.load(&as_expr) {
+            Ok(chunk) => self.exec_chunk_with(&chunk, |_| {}),
+            Err(_) => self.exec(source),
+        }
+    }
+
+    /// Llamar a un valor invocable con los argumentos dados
+    pub fn call(&mut self, func: &Value, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(LuaError::new("desbordamiento de pila (demasiadas llamadas anidadas)"));
+        }
+        self.start_steps();
+        self.step()?;
+        self.depth += 1;
+        let result = match func {
+            Value::Native(native) => match native.permission {
+                Some(permission) => self.check_permission(native.name, permission).and_then(|()| (native.func)(self, args)),
+                None => (native.func)(self, args),
+            },
+            Value::Function(closure) => self.call_closure(closure.clone(), args),
+            other => Err(LuaError::new(&format!("intento de llamar a un valor {}", other.type_name()))),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    fn call_closure(&mut self, closure: Rc<Closure>, mut args: Vec<Value>) -> LuaResult<Vec<Value>> {
+        let proto = &closure.proto;
+        let scope = Scope::new(Some(closure.env.clone()));
+        let extra: Vec<Value> = if args.len() > proto.params.len() { args.split_off(proto.params.len()) } else { Vec::new() };
+        let mut args = args.into_iter();
+        for param in proto.params.iter() {
+            scope.declare(param.clone(), args.next().unwrap_or(Value::Nil));
+        }
+        self.varargs.push(if proto.vararg { Rc::from(extra) } else { Rc::from(Vec::new()) });
+        let result = self.exec_block_in(&proto.body, &scope);
+        self.varargs.pop();
+        match result {
+            Ok(Flow::Return(values)) => Ok(values),
+            Ok(_) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Closure sobre `scope`; el ámbito queda registrado porque la closure puede
+    /// acabar guardada en él mismo o en un ámbito padre (ciclo)
+    fn closure(&mut self, proto: &Rc<FuncProto>, scope: &Rc<Scope>) -> Value {
+        self.tracked.add_scope(scope);
+        Value::Function(Rc::new(Closure { proto: proto.clone(), env: scope.clone() }))
+    }
+
+    fn exec_block(&mut self, block: &Block, parent: &Rc<Scope>) -> LuaResult<Flow> {
+        let scope = Scope::new(Some(parent.clone()));
+        self.exec_block_in(block, &scope)
+    }
+
+    fn exec_block_in(&mut self, block: &Block, scope: &Rc<Scope>) -> LuaResult<Flow> {
+        for stmt in block.iter() {
+            match self.exec_stmt(stmt, scope)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt, scope: &Rc<Scope>) -> LuaResult<Flow> {
+        match stmt {
+            Stmt::Local(names, exprs) => {
+                let mut values = self.eval_list(exprs, scope)?.into_iter();
+                for name in names.iter() {
+                    scope.declare(name.clone(), values.next().unwrap_or(Value::Nil));
+                }
+            }
+            Stmt::LocalFunction(name, proto) => {
+                // Declarar antes de crear la closure para permitir recursión
+                scope.declare(name.clone(), Value::Nil);
+                let closure = self.closure(proto, scope);
+                if let Some(cell) = scope.lookup(name) {
+                    *cell.borrow_mut() = closure;
+                }
+            }
+            Stmt::Assign(targets, exprs) => {
+                let mut values = self.eval_list(exprs, scope)?.into_iter();
+                for target in targets.iter() {
+                    let value = values.next().unwrap_or(Value::Nil);
+                    self.assign(target, value, scope)?;
+                }
+            }
+            Stmt::Call(expr) => {
+                self.eval_multi(expr, scope)?;
+            }
+            Stmt::Do(body) => return self.exec_block(body, scope),
+            Stmt::While(cond, body) => {
+                while self.eval(cond, scope)?.truthy() {
+                    self.step()?;
+                    match self.exec_block(body, scope)? {
+                        Flow::Break => break,
+                        Flow::Return(values) => return Ok(Flow::Return(values)),
+                        Flow::Normal => {}
+                    }
+                }
+            }
+            Stmt::Repeat(body, cond) => loop {
+                self.step()?;
+                // La condición de `until` ve las locales del cuerpo
+                let inner = Scope::new(Some(scope.clone()));
+                match self.exec_block_in(body, &inner)? {
+                    Flow::Break => break,
+                    Flow::Return(values) => return Ok(Flow::Return(values)),
+                    Flow::Normal => {}
+                }
+                if self.eval(cond, &inner)?.truthy() {
+                    break;
+                }
+            },
+            Stmt::If(branches, otherwise) => {
+                for (cond, body) in branches.iter() {
+                    if self.eval(cond, scope)?.truthy() {
+                        return self.exec_block(body, scope);
+                    }
+                }
+                if let Some(body) = otherwise {
+                    return self.exec_block(body, scope);
+                }
+            }
+            Stmt::NumericFor(name, start, limit, step, body) => {
+                let start = self.eval_number(start, scope, "'for' valor inicial")?;
+                let limit = self.eval_number(limit, scope, "'for' límite")?;
+                let step = match step {
+                    Some(step) => self.eval_number(step, scope, "'for' paso")?,
+                    None => 1.0,
+                };
+                if step == 0.0 {
+                    return Err(LuaError::new("'for' con paso 0"));
+                }
+                let mut i = start;
+                while (step > 0.0 && i <= limit) || (step < 0.0 && i >= limit) {
+                    self.step()?;
+                    let inner = Scope::new(Some(scope.clone()));
+                    inner.declare(name.clone(), Value::Number(i));
+                    match self.exec_block_in(body, &inner)? {
+                        Flow::Break => break,
+                        Flow::Return(values) => return Ok(Flow::Return(values)),
+                        Flow::Normal => {}
+                    }
+                    i += step;
+                }
+            }
+            Stmt::GenericFor(names, exprs, body) => {
+                let mut init = self.eval_list(exprs, scope)?.into_iter();
+                let func = init.next().unwrap_or(Value::Nil);
+                let state = init.next().unwrap_or(Value::Nil);
+                let mut control = init.next().unwrap_or(Value::Nil);
+                loop {
+                    self.step()?;
+                    let results = self.call(&func, vec![state.clone(), control.clone()])?;
+                    let first = results.first().cloned().unwrap_or(Value::Nil);
+                    if first.is_nil() {
+                        break;
+                    }
+                    control = first;
+                    let inner = Scope::new(Some(scope.clone()));
+                    let mut results = results.into_iter();
+                    for name in names.iter() {
+                        inner.declare(name.clone(), results.next().unwrap_or(Value::Nil));
+                    }
+                    match self.exec_block_in(body, &inner)? {
+                        Flow::Break => break,
+                        Flow::Return(values) => return Ok(Flow::Return(values)),
+                        Flow::Normal => {}
+                    }
+                }
+            }
+            Stmt::Return(exprs) => return Ok(Flow::Return(self.eval_list(exprs, scope)?)),
+            Stmt::Break => return Ok(Flow::Break),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, scope: &Rc<Scope>) -> LuaResult<()> {
+        match target {
+            Expr::Name(name) => {
+                match scope.lookup(name) {
+                    Some(cell) => *cell.borrow_mut() = value,
+                    None => self.globals.borrow_mut().set_str(name, value),
+                }
+                Ok(())
+            }
+            Expr::Index(obj, key) => {
+                let obj = self.eval(obj, scope)?;
+                let key = self.eval(key, scope)?;
+                match obj {
+                    Value::Table(t) => t.borrow_mut().set(key, value),
+                    other => Err(LuaError::new(&format!("intento de indexar un valor {}", other.type_name()))),
+                }
+            }
+            _ => Err(LuaError::new("asignación inválida")),
+        }
+    }
+
+    fn eval_number(&mut self, expr: &Expr, scope: &Rc<Scope>, what: &str) -> LuaResult<f64> {
+        self.eval(expr, scope)?
+            .to_number()
+            .ok_or_else(|| LuaError::new(&format!("{} debe ser un número", what)))
+    }
+
+    /// Evaluar una lista de expresiones expandiendo la última (llamadas y `...`)
+    fn eval_list(&mut self, exprs: &[Expr], scope: &Rc<Scope>) -> LuaResult<Vec<Value>> {
+        let mut values = Vec::with_capacity(exprs.len());
+        for (i, expr) in exprs.iter().enumerate() {
+            if i + 1 == exprs.len() {
+                values.extend(self.eval_multi(expr, scope)?);
+            } else {
+                values.push(self.eval(expr, scope)?);
+            }
+        }
+        Ok(values)
+    }
+
+    fn eval_multi(&mut self, expr: &Expr, scope: &Rc<Scope>) -> LuaResult<Vec<Value>> {
+        match expr {
+            Expr::Call(func, args, line) => {
+                let func = self.eval(func, scope)?;
+                let args = self.eval_list(args, scope)?;
+                self.call(&func, args).map_err(|e| with_line(e, *line))
+            }
+            Expr::Method(obj, name, args, line) => {
+                let obj = self.eval(obj, scope)?;
+                let func = match &obj {
+                    Value::Table(t) => t.borrow().get_str(name),
+                    // Los strings usan la tabla `string` como en Lua (`s:upper()`)
+                    Value::Str(_) => match self.get_global("string") {
+                        Value::Table(t) => t.borrow().get_str(name),
+                        _ => Value::Nil,
+                    },
+                    other => return Err(with_line(LuaError::new(&format!("intento de indexar un valor {}", other.type_name())), *line)),
+                };
+                if func.is_nil() {
+                    return Err(with_line(LuaError::new(&format!("método '{}' no existe", name)), *line));
+                }
+                let mut call_args = vec![obj];
+                call_args.extend(self.eval_list(args, scope)?);
+                self.call(&func, call_args).map_err(|e| with_line(e, *line))
+            }
+            Expr::Vararg => Ok(self.varargs.last().map(|v| v.to_vec()).unwrap_or_default()),
+            other => Ok(vec![self.eval(other, scope)?]),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, scope: &Rc<Scope>) -> LuaResult<Value> {
+        Ok(match expr {
+            Expr::Nil => Value::Nil,
+            Expr::True => Value::Bool(true),
+            Expr::False => Value::Bool(false),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Function(proto) => self.closure(proto, scope),
+            Expr::Name(name) => match scope.lookup(name) {
+                Some(cell) => cell.borrow().clone(),
+                None => self.globals.borrow().get_str(name),
+            },
+            Expr::Index(obj, key) => {
+                let obj_value = self.eval(obj, scope)?;
+                let key = self.eval(key, scope)?;
+                match obj_value {
+                    Value::Table(t) => t.borrow().get(&key),
+                    other => {
+                        let what = match &**obj {
+                            Expr::Name(n) => format!(" (variable '{}')", n),
+                            _ => String::new(),
+                        };
+                        return Err(LuaError::new(&format!("intento de indexar un valor {}{}", other.type_name(), what)));
+                    }
+                }
+            }
+            Expr::Call(..) | Expr::Method(..) | Expr::Vararg => {
+                self.eval_multi(expr, scope)?.into_iter().next().unwrap_or(Value::Nil)
+            }
+            Expr::Paren(inner) => self.eval(inner, scope)?,
+            Expr::Table(fields) => {
+                let table = Rc::new(RefCell::new(Table::default()));
+                self.tracked.add_table(&table);
+                let count = fields.len();
+                for (i, field) in fields.iter().enumerate() {
+                    match field {
+                        TableField::Keyed(key, value) => {
+                            let key = self.eval(key, scope)?;
+                            let value = self.eval(value, scope)?;
+                            table.borrow_mut().set(key, value)?;
+                        }
+                        TableField::Positional(value) if i + 1 == count => {
+                            for v in self.eval_multi(value, scope)? {
+                                table.borrow_mut().push(v);
+                            }
+                        }
+                        TableField::Positional(value) => {
+                            let value = self.eval(value, scope)?;
+                            let index = table.borrow().len() + 1;
+                            table.borrow_mut().set(Value::Number(index as f64), value)?;
+                        }
+                    }
+                }
+                Value::Table(table)
+            }
+            Expr::Unary(op, operand, line) => {
+                let value = self.eval(operand, scope)?;
+                unary_op(*op, value).map_err(|e| with_line(e, *line))?
+            }
+            Expr::Binary(BinOp::And, left, right, _) => {
+                let left = self.eval(left, scope)?;
+                if left.truthy() { self.eval(right, scope)? } else { left }
+            }
+            Expr::Binary(BinOp::Or, left, right, _) => {
+                let left = self.eval(left, scope)?;
+                if left.truthy() { left } else { self.eval(right, scope)? }
+            }
+            Expr::Binary(op, left, right, line) => {
+                let left = self.eval(left, scope)?;
+                let right = self.eval(right, scope)?;
+                binary_op(*op, left, right).map_err(|e| with_line(e, *line))?
+            }
+        })
+    }
+}
+
+/// Añadir la línea de origen a un error de ejecución que aún no la tiene
+fn with_line(mut error: LuaError, line: u32) -> LuaError {
+    if !error.message.starts_with("[línea") {
+        error.message = format!("[línea {}] {}", line, error.message);
+    }
+    error
+}
+
+fn unary_op(op: UnOp, value: Value) -> LuaResult<Value> {
+    match op {
+        UnOp::Not => Ok(Value::Bool(!value.truthy())),
+        UnOp::Neg => value
+            .to_number()
+            .map(|n| Value::Number(-n))
+            .ok_or_else(|| LuaError::new(&format!("intento de operar aritméticamente un valor {}", value.type_name()))),
+        UnOp::Len => match &value {
+            Value::Str(s) => Ok(Value::Number(s.len() as f64)),
+            Value::Table(t) => Ok(Value::Number(t.borrow().len() as f64)),
+            other => Err(LuaError::new(&format!("intento de obtener la longitud de un valor {}", other.type_name()))),
+        },
+    }
+}
+
+fn binary_op(op: BinOp, left: Value, right: Value) -> LuaResult<Value> {
+    match op {
+        BinOp::Eq => return Ok(Value::Bool(left.raw_equals(&right))),
+        BinOp::Ne => return Ok(Value::Bool(!left.raw_equals(&right))),
+        BinOp::Concat => {
+            return match (&left, &right) {
+                (Value::Str(_) | Value::Number(_), Value::Str(_) | Value::Number(_)) => {
+                    let mut s = left.to_display();
+                    let right = right.to_display();
+                    check_string_len(s.len() + right.len(), "..")?;
+                    s.push_str(&right);
+                    Ok(Value::Str(Rc::from(s.as_str())))
+                }
+                _ => {
+                    let bad = if matches!(left, Value::Str(_) | Value::Number(_)) { &right } else { &left };
+                    Err(LuaError::new(&format!("intento de concatenar un valor {}", bad.type_name())))
+                }
+            };
+        }
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            let ordering = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+                _ => {
+                    return Err(LuaError::new(&format!(
+                        "intento de comparar {} con {}",
+                        left.type_name(),
+                        right.type_name()
+                    )))
+                }
+            };
+            let result = match ordering {
+                None => false,
+                Some(ord) => match op {
+                    BinOp::Lt => ord.is_lt(),
+                    BinOp::Le => ord.is_le(),
+                    BinOp::Gt => ord.is_gt(),
+                    _ => ord.is_ge(),
+                },
+            };
+            return Ok(Value::Bool(result));
+        }
+        _ => {}
+    }
+
+    let (a, b) = match (left.to_number(), right.to_number()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            let bad = if left.to_number().is_none() { &left } else { &right };
+            return Err(LuaError::new(&format!("intento de operar aritméticamente un valor {}", bad.type_name())));
+        }
+    };
+    let result = match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        BinOp::IDiv => libm::floor(a / b),
+        BinOp::Mod => a - libm::floor(a / b) * b,
+        BinOp::Pow => libm::pow(a, b),
+        _ => unreachable!(),
+    };
+    Ok(Value::Number(result))
+}
+
+// ===== AYUDANTES PARA FUNCIONES NATIVAS =====
+
+fn arg_error(index: usize, func: &str, expected: &str, got: &Value) -> LuaError {
+    LuaError::new(&format!(
+        "argumento #{} inválido para '{}' (se esperaba {}, se recibió {})",
+        index + 1,
+        func,
+        expected,
+        got.type_name()
+    ))
+}
+
+/// Obtener el argumento `index` como string (acepta números, como Lua)
+pub fn check_str(args: &[Value], index: usize, func: &str) -> LuaResult<Rc<str>> {
+    match args.get(index).unwrap_or(&Value::Nil) {
+        Value::Str(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(Rc::from(number_to_string(*n).as_str())),
+        other => Err(arg_error(index, func, "string", other)),
+    }
+}
+
+/// Obtener el argumento `index` como número
+pub fn check_number(args: &[Value], index: usize, func: &str) -> LuaResult<f64> {
+    let value = args.get(index).unwrap_or(&Value::Nil);
+    value.to_number().ok_or_else(|| arg_error(index, func, "number", value))
+}
+
+/// Obtener el argumento `index` como entero (trunca la parte decimal)
+pub fn check_int(args: &[Value], index: usize, func: &str) -> LuaResult<i64> {
+    check_number(args, index, func).map(|n| n as i64)
+}
+
+/// Obtener el argumento `index` como entero de 32 bits (coordenadas)
+///
+/// Un valor que no cabe en un `i32` es un error, no se trunca.
+pub fn check_i32(args: &[Value], index: usize, func: &str) -> LuaResult<i32> {
+    let n = check_int(args, index, func)?;
+    i32::try_from(n).map_err(|_| range_error(index, func, n))
+}
+
+/// Obtener el argumento `index` como entero sin signo de 32 bits (tamaños e identificadores)
+///
+/// Los negativos valen 0; un valor mayor que `u32::MAX` es un error.
+pub fn check_u32(args: &[Value], index: usize, func: &str) -> LuaResult<u32> {
+    let n = check_int(args, index, func)?;
+    u32::try_from(n.max(0)).map_err(|_| range_error(index, func, n))
+}
+
+fn range_error(index: usize, func: &str, value: i64) -> LuaError {
+    LuaError::new(&format!("argumento #{} inválido para '{}' ({} fuera de rango)", index + 1, func, value))
+}
+
+/// Argumento numérico opcional con valor por defecto
+pub fn opt_number(args: &[Value], index: usize, func: &str, default: f64) -> LuaResult<f64> {
+    match args.get(index) {
+        None | Some(Value::Nil) => Ok(default),
+        Some(_) => check_number(args, index, func),
+    }
+}
+
+/// Obtener el argumento `index` como tabla
+pub fn check_table(args: &[Value], index: usize, func: &str) -> LuaResult<TableRef> {
+    match args.get(index).unwrap_or(&Value::Nil) {
+        Value::Table(t) => Ok(t.clone()),
+        other => Err(arg_error(index, func, "table", other)),
+    }
+}
+
+/// Argumento booleano opcional (cualquier valor se interpreta por su veracidad)
+pub fn opt_bool(args: &[Value], index: usize, default: bool) -> bool {
+    match args.get(index) {
+        None | Some(Value::Nil) => default,
+        Some(value) => value.truthy(),
+    }
+}
+
+// ===== BIBLIOTECA BASE =====
+
+fn open_base_library(lua: &mut Lua) {
+    lua.register("print", lua_print);
+    lua.register("type", lua_type);
+    lua.register("tostring", lua_tostring);
+    lua.register("tonumber", lua_tonumber);
+    lua.register("pairs", lua_pairs);
+    lua.register("ipairs", lua_ipairs);
+    lua.register("next", lua_next);
+    lua.register("select", lua_select);
+    lua.register("pcall", lua_pcall);
+    lua.register("error", lua_error);
+    lua.register("assert", lua_assert);
+    lua.register("unpack", lua_unpack);
+
+    let string = library_table(&[
+        ("len", lua_string_len),
+        ("sub", lua_string_sub),
+        ("upper", lua_string_upper),
+        ("lower", lua_string_lower),
+        ("rep", lua_string_rep),
+        ("format", lua_string_format),
+    ]);
+    lua.set_global("string", string);
+
+    let table = library_table(&[
+        ("insert", lua_table_insert),
+        ("remove", lua_table_remove),
+        ("concat", lua_table_concat),
+        ("unpack", lua_unpack),
+    ]);
+    lua.set_global("table", table);
+
+    let math = library_table(&[
+        ("floor", lua_math_floor),
+        ("ceil", lua_math_ceil),
+        ("abs", lua_math_abs),
+        ("sqrt", lua_math_sqrt),
+        ("sin", lua_math_sin),
+        ("cos", lua_math_cos),
+        ("min", lua_math_min),
+        ("max", lua_math_max),
+        ("random", lua_math_random),
+    ]);
+    if let Value::Table(t) = &math {
+        t.borrow_mut().set_str("pi", Value::Number(core::f64::consts::PI));
+        t.borrow_mut().set_str("huge", Value::Number(f64::INFINITY));
+    }
+    lua.set_global("math", math);
+}
+
+/// Construir una tabla de biblioteca a partir de funciones nativas
+pub fn library_table(entries: &[(&'static str, NativeFn)]) -> Value {
+    let table = Value::new_table();
+    if let Value::Table(t) = &table {
+        let mut t = t.borrow_mut();
+        for (name, func) in entries.iter() {
+            t.set_str(name, Value::Native(NativeFunction::new(name, *func)));
+        }
+    }
+    table
+}
+
+pub fn lua_print(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            uart_send_str("\t");
+        }
+        uart_send_str(&arg.to_display());
+    }
+    uart_send_str("\n");
+    Ok(Vec::new())
+}
+
+fn lua_type(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    match args.first() {
+        Some(value) => Ok(vec![Value::str(value.type_name())]),
+        None => Err(LuaError::new("argumento #1 inválido para 'type' (se esperaba un valor)")),
+    }
+}
+
+fn lua_tostring(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let value = args.first().cloned().unwrap_or(Value::Nil);
+    Ok(vec![Value::str(&value.to_display())])
+}
+
+fn lua_tonumber(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let value = args.first().cloned().unwrap_or(Value::Nil);
+    Ok(vec![value.to_number().map(Value::Number).unwrap_or(Value::Nil)])
+}
+
+fn lua_next(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "next")?;
+    let key = args.get(1).cloned().unwrap_or(Value::Nil);
+    let entry = table.borrow().next(&key)?;
+    Ok(match entry {
+        Some((k, v)) => vec![k, v],
+        None => vec![Value::Nil],
+    })
+}
+
+fn lua_pairs(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "pairs")?;
+    Ok(vec![
+        Value::Native(NativeFunction::new("next", lua_next)),
+        Value::Table(table),
+        Value::Nil,
+    ])
+}
+
+fn lua_ipairs_iter(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "ipairs")?;
+    let index = check_number(&args, 1, "ipairs")? + 1.0;
+    let value = table.borrow().get(&Value::Number(index));
+    Ok(if value.is_nil() { vec![Value::Nil] } else { vec![Value::Number(index), value] })
+}
+
+fn lua_ipairs(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "ipairs")?;
+    Ok(vec![
+        Value::Native(NativeFunction::new("ipairs_iter", lua_ipairs_iter)),
+        Value::Table(table),
+        Value::Number(0.0),
+    ])
+}
+
+fn lua_select(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    if let Some(Value::Str(s)) = args.first()
+        && &**s == "#"
+    {
+        return Ok(vec![Value::Number((args.len() - 1) as f64)]);
+    }
+    let n = check_int(&args, 0, "select")?;
+    if n < 1 {
+        return Err(LuaError::new("argumento #1 inválido para 'select' (índice fuera de rango)"));
+    }
+    Ok(args.into_iter().skip(n as usize).collect())
+}
+
+fn lua_pcall(lua: &mut Lua, mut args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    if args.is_empty() {
+        return Err(LuaError::new("argumento #1 inválido para 'pcall' (se esperaba un valor)"));
+    }
+    let func = args.remove(0);
+    match lua.call(&func, args) {
+        Ok(mut results) => {
+            results.insert(0, Value::Bool(true));
+            Ok(results)
+        }
+        Err(e) => Ok(vec![Value::Bool(false), Value::str(&e.message)]),
+    }
+}
+
+fn lua_error(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let message = args.first().cloned().unwrap_or(Value::Nil);
+    Err(LuaError::new(&message.to_display()))
+}
+
+fn lua_assert(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    match args.first() {
+        Some(value) if value.truthy() => Ok(args),
+        _ => {
+            let message = match args.get(1) {
+                Some(m) => m.to_display(),
+                None => String::from("assertion failed!"),
+            };
+            Err(LuaError::new(&message))
+        }
+    }
+}
+
+fn lua_unpack(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "unpack")?;
+    let table = table.borrow();
+    let start = opt_number(&args, 1, "unpack", 1.0)? as i64;
+    let end = opt_number(&args, 2, "unpack", table.len() as f64)? as i64;
+    Ok((start..=end).map(|i| table.get(&Value::Number(i as f64))).collect())
+}
+
+// --- string ---
+
+fn lua_string_len(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let s = check_str(&args, 0, "len")?;
+    Ok(vec![Value::Number(s.len() as f64)])
+}
+
+/// Convertir índices Lua (1-based, negativos desde el final) a un rango de bytes
+fn byte_range(len: usize, start: i64, end: i64) -> (usize, usize) {
+    let len = len as i64;
+    let start = if start < 0 { (len + start + 1).max(1) } else { start.max(1) };
+    let end = if end < 0 { len + end + 1 } else { end.min(len) };
+    if start > end { (0, 0) } else { ((start - 1) as usize, end as usize) }
+}
+
+fn lua_string_sub(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let s = check_str(&args, 0, "sub")?;
+    let start = check_int(&args, 1, "sub")?;
+    let end = opt_number(&args, 2, "sub", -1.0)? as i64;
+    let (from, to) = byte_range(s.len(), start, end);
+    // Cortar sobre bytes y descartar secuencias UTF-8 incompletas
+    let bytes = &s.as_bytes()[from..to];
+    Ok(vec![Value::str(&String::from_utf8_lossy(bytes))])
+}
+
+fn lua_string_upper(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let s = check_str(&args, 0, "upper")?;
+    Ok(vec![Value::str(&s.to_uppercase())])
+}
+
+fn lua_string_lower(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let s = check_str(&args, 0, "lower")?;
+    Ok(vec![Value::str(&s.to_lowercase())])
+}
+
+/// Error si una cadena de `len` bytes pasa de `MAX_STRING_LEN`
+fn check_string_len(len: usize, func: &str) -> LuaResult<()> {
+    if len > MAX_STRING_LEN {
+        return Err(LuaError::new(&format!("cadena demasiado larga en '{}' (máximo {} bytes)", func, MAX_STRING_LEN)));
+    }
+    Ok(())
+}
+
+fn lua_string_rep(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let s = check_str(&args, 0, "rep")?;
+    let n = check_int(&args, 1, "rep")?.max(0) as usize;
+    check_string_len(s.len().saturating_mul(n), "rep")?;
+    Ok(vec![Value::str(&s.repeat(n))])
+}
+
+fn lua_string_format(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let fmt = check_str(&args, 0, "format")?;
+    let mut out = String::new();
+    let mut arg_index = 1;
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+        // %[-0][ancho][.precisión]conversión
+        let mut left_align = false;
+        let mut zero_pad = false;
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left_align = true,
+                '0' => zero_pad = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let mut width = 0usize;
+        while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+            width = width.saturating_mul(10).saturating_add(d as usize);
+            chars.next();
+        }
+        check_string_len(width, "format")?;
+        let mut precision: Option<usize> = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut p = 0usize;
+            while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                p = p.saturating_mul(10).saturating_add(d as usize);
+                chars.next();
+            }
+            check_string_len(p, "format")?;
+            precision = Some(p);
+        }
+        let conv = chars.next().ok_or_else(|| LuaError::new("formato inválido en 'format'"))?;
+        let text = match conv {
+            'd' | 'i' => format!("{}", check_number(&args, arg_index, "format")? as i64),
+            'x' => format!("{:x}", check_number(&args, arg_index, "format")? as i64),
+            'X' => format!("{:X}", check_number(&args, arg_index, "format")? as i64),
+            'c' => {
+                let code = check_u32(&args, arg_index, "format")?;
+                String::from(char::from_u32(code).unwrap_or('?'))
+            }
+            'f' => format!("{:.*}", precision.unwrap_or(6), check_number(&args, arg_index, "format")?),
+            'g' => number_to_string(check_number(&args, arg_index, "format")?),
+            's' => {
+                let s = args.get(arg_index).cloned().unwrap_or(Value::Nil).to_display();
+                match precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None => s,
+                }
+            }
+            'q' => format!("\"{}\"", check_str(&args, arg_index, "format")?),
+            other => return Err(LuaError::new(&format!("conversión '%{}' no soportada en 'format'", other))),
+        };
+        arg_index += 1;
+        let pad = width.saturating_sub(text.chars().count());
+        check_string_len(out.len() + text.len() + pad, "format")?;
+        if left_align {
+            out.push_str(&text);
+            (0..pad).for_each(|_| out.push(' '));
+        } else if zero_pad && matches!(conv, 'd' | 'i' | 'f' | 'x' | 'X') {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(rest) => ("-", rest),
+                None => ("", text.as_str()),
+            };
+            out.push_str(sign);
+            (0..pad).for_each(|_| out.push('0'));
+            out.push_str(digits);
+        } else {
+            (0..pad).for_each(|_| out.push(' '));
+            out.push_str(&text);
+        }
+    }
+    Ok(vec![Value::str(&out)])
+}
+
+// --- table ---
+
+fn lua_table_insert(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "insert")?;
+    match args.len() {
+        2 => table.borrow_mut().push(args[1].clone()),
+        3 => {
+            let pos = check_int(&args, 1, "insert")?;
+            table.borrow_mut().insert(pos.max(1) as usize, args[2].clone());
+        }
+        _ => return Err(LuaError::new("número de argumentos incorrecto para 'insert'")),
+    }
+    Ok(Vec::new())
+}
+
+fn lua_table_remove(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "remove")?;
+    let len = table.borrow().len();
+    let pos = opt_number(&args, 1, "remove", len as f64)? as usize;
+    Ok(vec![table.borrow_mut().remove(pos)])
+}
+
+fn lua_table_concat(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = check_table(&args, 0, "concat")?;
+    let sep = match args.get(1) {
+        None | Some(Value::Nil) => Rc::from(""),
+        Some(_) => check_str(&args, 1, "concat")?,
+    };
+    let table = table.borrow();
+    let mut out = String::new();
+    for i in 1..=table.len() {
+        if i > 1 {
+            check_string_len(out.len() + sep.len(), "concat")?;
+            out.push_str(&sep);
+        }
+        match table.get(&Value::Number(i as f64)) {
+            value @ (Value::Str(_) | Value::Number(_)) => {
+                let text = value.to_display();
+                check_string_len(out.len() + text.len(), "concat")?;
+                out.push_str(&text);
+            }
+            other => {
+                return Err(LuaError::new(&format!(
+                    "valor inválido (en el índice {}) para 'concat': {}",
+                    i,
+                    other.type_name()
+                )))
+            }
+        }
+    }
+    Ok(vec![Value::str(&out)])
+}
+
+// --- math ---
+
+fn lua_math_floor(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::floor(check_number(&args, 0, "floor")?))])
+}
+
+fn lua_math_ceil(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::ceil(check_number(&args, 0, "ceil")?))])
+}
+
+fn lua_math_abs(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::fabs(check_number(&args, 0, "abs")?))])
+}
+
+fn lua_math_sqrt(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::sqrt(check_number(&args, 0, "sqrt")?))])
+}
+
+fn lua_math_sin(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::sin(check_number(&args, 0, "sin")?))])
+}
+
+fn lua_math_cos(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(vec![Value::Number(libm::cos(check_number(&args, 0, "cos")?))])
+}
+
+fn lua_math_min(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let mut best = check_number(&args, 0, "min")?;
+    for i in 1..args.len() {
+        best = best.min(check_number(&args, i, "min")?);
+    }
+    Ok(vec![Value::Number(best)])
+}
+
+fn lua_math_max(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let mut best = check_number(&args, 0, "max")?;
+    for i in 1..args.len() {
+        best = best.max(check_number(&args, i, "max")?);
+    }
+    Ok(vec![Value::Number(best)])
+}
+
+fn lua_math_random(lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    // xorshift64: suficiente para animaciones y demos
+    let mut x = lua.rng_state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    lua.rng_state = x;
+    let unit = (x >> 11) as f64 / (1u64 << 53) as f64;
+    let result = match args.len() {
+        0 => unit,
+        1 => libm::floor(unit * check_number(&args, 0, "random")?) + 1.0,
+        _ => {
+            let low = check_number(&args, 0, "random")?;
+            let high = check_number(&args, 1, "random")?;
+            libm::floor(unit * (high - low + 1.0)) + low
+        }
+    };
+    Ok(vec![Value::Number(result)])
+}
+
+/// Escribir valores separados por tabuladores (formato de salida del REPL)
+pub fn format_values(values: &[Value]) -> String {
+    let mut out = String::new();
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push('\t');
+        }
+        out.push_str(&value.to_display());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> LuaResult<String> {
+        let mut lua = Lua::new();
+        let values = lua.exec(source)?;
+        Ok(values.first().map(Value::to_display).unwrap_or_default())
+    }
+
+    #[test]
+    fn aborts_runaway_loops() {
+        let mut lua = Lua::new();
+        lua.step_limit = 10_000;
+        for source in ["while true do end", "repeat until false", "for i = 1, 1e12 do end", "local function f() return f() end f()"] {
+            let error = lua.exec(source).err().expect(source);
+            assert!(error.message.contains("agotado") || error.message.contains("desbordamiento"), "{}", error.message);
+        }
+        // Cada ejecución desde el host tiene su propio presupuesto y pcall no lo renueva
+        assert!(lua.exec("pcall(function() while true do end end) while true do end").is_err());
+        assert_eq!(lua.exec("local n = 0 for i = 1, 100 do n = n + i end return n").ok().map(|v| v[0].to_display()).as_deref(), Some("5050"));
+    }
+
+    #[test]
+    fn dropping_the_state_frees_reference_cycles() {
+        let mut lua = Lua::new();
+        let script = "t = {} t.self = t local function f() return f end g = f h = function() return t end";
+        assert!(lua.exec(script).is_ok());
+        let table = match lua.get_global("t") {
+            Value::Table(table) => Rc::downgrade(&table),
+            _ => panic!("t debería ser una tabla"),
+        };
+        let closure = match lua.get_global("g") {
+            Value::Function(closure) => Rc::downgrade(&closure),
+            _ => panic!("g debería ser una función"),
+        };
+        drop(lua);
+        assert!(table.upgrade().is_none());
+        assert!(closure.upgrade().is_none());
+    }
+
+    #[test]
+    fn range_checks_32_bit_arguments() {
+        let args = [Value::Number(-5.0), Value::Number(4_294_967_296.0), Value::Number(-3e9), Value::Number(70.9)];
+        assert_eq!(check_i32(&args, 0, "f").ok(), Some(-5));
+        assert_eq!(check_u32(&args, 0, "f").ok(), Some(0));
+        assert!(check_u32(&args, 1, "f").is_err());
+        assert!(check_i32(&args, 1, "f").is_err());
+        assert!(check_i32(&args, 2, "f").is_err());
+        assert_eq!(check_u32(&args, 3, "f").ok(), Some(70));
+        let error = check_i32(&args, 1, "f").err().map(|e| e.message).unwrap_or_default();
+        assert!(error.contains("fuera de rango"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_huge_string_rep() {
+        let error = eval("return string.rep('a', 1e9)").expect_err("debería fallar");
+        assert!(error.message.contains("demasiado larga"), "{}", error.message);
+        assert_eq!(eval("return #string.rep('ab', 1000)").ok().as_deref(), Some("2000"));
+    }
+
+    #[test]
+    fn caught_string_errors_leave_the_state_usable() {
+        let result = eval("local ok = pcall(string.rep, 'a', 1e9) return ok");
+        assert_eq!(result.ok().as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn rejects_huge_concat_and_format() {
+        let concat = "local s = string.rep('a', 1024 * 1024) return s .. 'b'";
+        assert!(eval(concat).is_err());
+        assert!(eval("return string.format('%999999999d', 1)").is_err());
+        assert!(eval("return string.format('%.99999999999999999999f', 1)").is_err());
+        let table = "local t = {} for i = 1, 5 do t[i] = string.rep('a', 300000) end return table.concat(t)";
+        assert!(eval(table).is_err());
+        assert_eq!(eval("return string.format('%05d', 42)").ok().as_deref(), Some("00042"));
+    }
+
+    #[test]
+    fn limits_parser_nesting() {
+        let deep = format!("return {}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        let error = eval(&deep).expect_err("debería fallar");
+        assert!(error.message.contains("anidamiento"), "{}", error.message);
+        let blocks = format!("{}x = 1{}", "do ".repeat(100_000), " end".repeat(100_000));
+        assert!(eval(&blocks).is_err());
+
+        let parens = format!("return {}1{}", "(".repeat(90), ")".repeat(90));
+        assert_eq!(eval(&parens).ok().as_deref(), Some("1"));
+        let blocks = format!("{}x = 1{} return x", "do ".repeat(90), " end".repeat(90));
+        assert_eq!(eval(&blocks).ok().as_deref(), Some("1"));
+    }
+}
@@ -24,16 +24,29 @@ const MAILBOX_EMPTY: u32 = 0x4000_0000;
 const MAILBOX_CHANNEL_PROPERTY_TAGS_ARM_TO_VC: u32 = 8;
 
 // Property tags for framebuffer
+pub const MBOX_TAG_GET_PHYSICAL_DISPLAY_SIZE: u32 = 0x00040003;
 pub const MBOX_TAG_SET_PHYSICAL_DISPLAY_SIZE: u32 = 0x00048003;
 pub const MBOX_TAG_SET_VIRTUAL_DISPLAY_SIZE: u32 = 0x00048004;
 pub const MBOX_TAG_SET_DEPTH: u32 = 0x00048005;
+pub const MBOX_TAG_SET_PIXEL_ORDER: u32 = 0x00048006;
+pub const MBOX_TAG_GET_ALPHA_MODE: u32 = 0x00040007;
 pub const MBOX_TAG_ALLOCATE_BUFFER: u32 = 0x00040001;
 pub const MBOX_TAG_GET_PITCH: u32 = 0x00040008;
+pub const MBOX_TAG_SET_VIRTUAL_OFFSET: u32 = 0x00048009;
+
+// Pixel order values (tag 0x00048006)
+pub const MBOX_PIXEL_ORDER_BGR: u32 = 0;
+pub const MBOX_PIXEL_ORDER_RGB: u32 = 1;
+
+// Alpha mode values (tag 0x00040007)
+pub const MBOX_ALPHA_MODE_IGNORED: u32 = 2;
 
 // Request and response codes
 pub const MBOX_REQUEST: u32 = 0;
 const MBOX_RESPONSE_SUCCESS: u32 = 0x8000_0000;
 const MBOX_RESPONSE_ERROR: u32 = 0x8000_0001;
+// Bit set by the firmware in a tag's request/response code once it has processed it
+const MBOX_TAG_RESPONSE: u32 = 0x8000_0000;
 
 /// A mailbox message buffer.
 ///
@@ -84,3 +97,71 @@ pub fn send_mailbox_message(message_ptr: *mut MailboxMessage) -> Result<(), ()>
         }
     }
 }
+
+/// Sets the virtual framebuffer offset, which selects the page being scanned out.
+///
+/// Returns `Ok(true)` if the firmware processed the tag and applied the offset,
+/// `Ok(false)` if the message went through but the tag is unsupported.
+pub fn set_virtual_offset(x: u32, y: u32) -> Result<bool, ()> {
+    #[repr(C, align(16))]
+    struct VirtualOffsetMessage {
+        header: MailboxMessage,
+        tag: u32,
+        value_buf_size: u32,
+        request_response_code: u32,
+        x: u32,
+        y: u32,
+        end_tag: u32,
+    }
+
+    let mut message = VirtualOffsetMessage {
+        header: MailboxMessage {
+            buffer_size: core::mem::size_of::<VirtualOffsetMessage>() as u32,
+            request_response_code: MBOX_REQUEST,
+        },
+        tag: MBOX_TAG_SET_VIRTUAL_OFFSET,
+        value_buf_size: 8,
+        request_response_code: 8,
+        x,
+        y,
+        end_tag: 0,
+    };
+
+    send_mailbox_message(&mut message.header)?;
+    Ok(message.request_response_code & MBOX_TAG_RESPONSE != 0 && message.x == x && message.y == y)
+}
+
+/// Queries the physical (display) width and height reported by the firmware.
+///
+/// Returns `Ok(None)` if the tag is unsupported or the firmware reports a zero size.
+pub fn get_physical_display_size() -> Result<Option<(u32, u32)>, ()> {
+    #[repr(C, align(16))]
+    struct PhysicalSizeMessage {
+        header: MailboxMessage,
+        tag: u32,
+        value_buf_size: u32,
+        request_response_code: u32,
+        width: u32,
+        height: u32,
+        end_tag: u32,
+    }
+
+    let mut message = PhysicalSizeMessage {
+        header: MailboxMessage {
+            buffer_size: core::mem::size_of::<PhysicalSizeMessage>() as u32,
+            request_response_code: MBOX_REQUEST,
+        },
+        tag: MBOX_TAG_GET_PHYSICAL_DISPLAY_SIZE,
+        value_buf_size: 8,
+        request_response_code: 0,
+        width: 0,
+        height: 0,
+        end_tag: 0,
+    };
+
+    send_mailbox_message(&mut message.header)?;
+    if message.request_response_code & MBOX_TAG_RESPONSE == 0 || message.width == 0 || message.height == 0 {
+        return Ok(None);
+    }
+    Ok(Some((message.width, message.height)))
+}
@@ -1,16 +1,35 @@
-#![no_std]
-#![no_main]
-#![feature(alloc_error_handler)]
+// En los tests del host el binario se compila con `std`: sin arranque,
+// asignador ni manejadores de pánico propios
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+#![cfg_attr(not(test), feature(alloc_error_handler))]
+
+extern crate alloc;
 
 mod mobile_os;
+mod lua;
+mod manifest;
 mod wasm_runner;
 mod graphics;
+mod blit;
+mod terminal;
+mod display;
+mod screenshot;
+mod status_bar;
+mod animation;
+mod compositor;
+mod theme;
+mod text;
+mod widgets;
+mod font;
+mod image;
+mod assets;
 mod mailbox;
 
 use linked_list_allocator::LockedHeap;
 
-// Asignador de memoria global para `alloc`
-#[global_allocator]
+// Asignador de memoria global para `alloc` (en los tests, el de `std`)
+#[cfg_attr(not(test), global_allocator)]
 static ALLOCATOR: LockedHeap = LockedHeap::empty();
 
 // Inicializar UART para comunicación
@@ -21,12 +40,19 @@ fn uart_init() {
 use mobile_os::MobileSystem;
 use wasm_runner::WasmRunner;
 use graphics::GraphicsManager;
-use fos_microkernel::{uart_send, uart_send_str, print_number, uart_receive_non_blocking};
+use status_bar::{StatusBar, StatusInfo};
+use widgets::Key;
+use fos_microkernel::{uart_send, uart_send_str, print_number, uart_receive, uart_receive_non_blocking, uart_receive_timeout, uart_receive_u32_le, crc32, delay_micros, kernel_log};
+use alloc::{string::String, vec::Vec};
 
 // WASM de la aplicación embebida (generada por el SDK de Zig)
 #[unsafe(link_section = ".rodata.wasm")]
 static APP_WASM: &[u8] = include_bytes!("../../app.wasm");
 
+// Manifiesto del paquete .wpk (nombre, id y permisos de la app)
+static APP_MANIFEST: &str = include_str!("../../wpk/manifest.toml");
+
+#[cfg(not(test))]
 core::arch::global_asm!(
     ".section .text._start",
     ".global _start",
@@ -70,6 +96,69 @@ core::arch::global_asm!(
     "    b ."
 );
 
+/// Tamaño máximo aceptado para un script Lua recibido por hot-reload
+const HOT_RELOAD_MAX_SIZE: usize = 32 * 1024;
+/// Iteraciones de sondeo por byte antes de abortar una transferencia de hot-reload
+const HOT_RELOAD_BYTE_TIMEOUT: u32 = 50_000_000;
+/// Pausa tras la pantalla de bienvenida para que la ventana de QEMU la muestre
+const SPLASH_SYNC_DELAY_US: u64 = 500_000;
+/// Iteraciones de sondeo tras un Esc para distinguir una tecla especial de un Esc suelto
+const ESCAPE_SEQUENCE_TIMEOUT: u32 = 200_000;
+
+/// Bytes del terminal que se dibujan de una vez al llegar seguidos por UART
+const TERMINAL_BURST: usize = 256;
+
+/// Recibir un script Lua por UART para hot-reload
+///
+/// Trama: longitud `u32` little-endian, el código fuente Lua y un CRC-32 `u32`
+/// little-endian calculado sobre el código fuente.
+fn receive_hot_reload_script() -> Option<String> {
+    let len = match uart_receive_u32_le(HOT_RELOAD_BYTE_TIMEOUT) {
+        Some(len) => len as usize,
+        None => {
+            uart_send_str("❌ Tiempo de espera agotado (longitud)\n");
+            return None;
+        }
+    };
+
+    if len == 0 || len > HOT_RELOAD_MAX_SIZE {
+        uart_send_str("❌ Longitud inválida: ");
+        print_number(len as u64);
+        uart_send_str(" bytes\n");
+        return None;
+    }
+
+    let mut source = Vec::with_capacity(len);
+    for _ in 0..len {
+        match uart_receive_timeout(HOT_RELOAD_BYTE_TIMEOUT) {
+            Some(byte) => source.push(byte),
+            None => {
+                uart_send_str("❌ Tiempo de espera agotado tras ");
+                print_number(source.len() as u64);
+                uart_send_str(" bytes\n");
+                return None;
+            }
+        }
+    }
+
+    let Some(expected) = uart_receive_u32_le(HOT_RELOAD_BYTE_TIMEOUT) else {
+        uart_send_str("❌ Tiempo de espera agotado (checksum)\n");
+        return None;
+    };
+    if crc32(&source) != expected {
+        uart_send_str("❌ Checksum CRC-32 incorrecto, script descartado\n");
+        return None;
+    }
+
+    match String::from_utf8(source) {
+        Ok(script) => Some(script),
+        Err(_) => {
+            uart_send_str("❌ El script no es UTF-8 válido\n");
+            None
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn kernel_main() -> ! {
     // Inicializar UART para comunicación
@@ -77,6 +166,20 @@ pub extern "C" fn kernel_main() -> ! {
     
     uart_send_str("🔌 UART OK\n\n");
     
+    // Inicializar el asignador de memoria antes que los gráficos:
+    // el back buffer por software y las superficies del compositor se reservan en el heap
+    //
+    // Presupuesto en el modo máximo (1920x1080 a 32 bits, ~8 MB por pantalla):
+    // back buffer por software, superficie del shell y superficie de la app a
+    // pantalla completa (~24 MB), barra de estado y notificaciones (<1 MB),
+    // caché de imágenes (4 MB) y el resto para Lua, los módulos WASM y los
+    // buffers de cada app. El array va en `.bss`: no ocupa sitio en la imagen
+    // del kernel y cabe de sobra en los 128 MB de RAM de `linker.ld`.
+    const HEAP_SIZE: usize = 1024 * 1024 * 64; // 64 MB
+    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+    unsafe { ALLOCATOR.lock().init(core::ptr::addr_of_mut!(HEAP).cast(), HEAP_SIZE); }
+    uart_send_str("🧠 Heap inicializado\n");
+    
     // Inicializar sistema gráfico
     uart_send_str("🎨 Inicializando sistema gráfico...\n");
     let mut graphics = GraphicsManager::new();
@@ -87,19 +190,14 @@ pub extern "C" fn kernel_main() -> ! {
     uart_send_str("Sistema Operativo Móvil\n");
     uart_send_str("Pipeline: Lua → Zig → WASM → Rust\n\n");
 
-    // Inicializar el asignador de memoria
-    const HEAP_SIZE: usize = 1024 * 128; // 128 KB
-    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
-    unsafe { ALLOCATOR.lock().init(core::ptr::addr_of_mut!(HEAP).cast(), HEAP_SIZE); }
-    uart_send_str("🧠 Heap inicializado\n");
-    
     // Mostrar una pantalla de bienvenida. Esto escribe en el framebuffer por primera vez.
     graphics.show_splash_screen();
+    graphics.present();
 
     // Introducir una pausa CRÍTICA para la sincronización con QEMU.
     // Sin esto, el kernel dibuja tan rápido que la ventana de QEMU no se actualiza a tiempo.
     uart_send_str("⏳ Sincronizando display...\n");
-    for _ in 0..5_000_000 { unsafe { core::ptr::read_volatile(&0u32); } }
+    delay_micros(SPLASH_SYNC_DELAY_US);
 
     // Inicializar sistema móvil básico
     let mut mobile_system = MobileSystem::new();
@@ -114,10 +212,16 @@ pub extern "C" fn kernel_main() -> ! {
     
     // Ejecutar la aplicación WASM con script Lua embebido
     let mut wasm_runner = WasmRunner::new();
+    wasm_runner.load_manifest(APP_MANIFEST);
+
+    // La barra de estado queda fija encima de las apps desde el primer frame de la app
+    let mut status_bar = StatusBar::new();
+    refresh_status_bar(&mut status_bar, &mobile_system, &wasm_runner, &mut graphics);
     let success = wasm_runner.run_wasm_app_with_graphics(APP_WASM, &mut graphics);
     
     if success {
         uart_send_str("\n✅ Aplicación ejecutada correctamente\n");
+        mobile_system.apps_running = 1;
         mobile_system.show_final_status();
     } else {
         uart_send_str("\n❌ Error ejecutando aplicación\n");
@@ -130,72 +234,238 @@ pub extern "C" fn kernel_main() -> ! {
     // En un OS real, aquí se iniciaría el planificador (scheduler).
     // Para esta demo, entramos en un bucle interactivo (Kernel Shell).
     uart_send_str("💻 KERNEL SHELL ACTIVO\n");
-    uart_send_str("  [h] Ayuda  [c] Limpiar  [r] Re-ejecutar  [i] Info\n\n");
+    uart_send_str("  [h] Ayuda  [c] Limpiar  [r] Re-ejecutar  [u] Hot-reload Lua  [lua] REPL  [ui] Widgets  [t] Tema  [p] Captura  [n] Avisos  [f] Frames  [k] Terminal  [b] Benchmark  [s] Scrollback  [i] Info\n\n");
     
-    // UI del Shell
-    graphics.set_color(graphics::colors::BLUE);
+    // UI del Shell: la app pasa al fondo y queda a la vista la consola
+    wasm_runner.send_to_background(&mut graphics);
+    graphics.set_color(theme::current().primary);
     graphics.clear_screen(); // Fill with blue
-    
-    // Header
-    graphics.set_color(graphics::colors::WHITE);
-    graphics.draw_rect(0, 0, 640, 30, true); // White header bar
-    graphics.set_color(graphics::colors::BLUE);
-    graphics.draw_text_at("FerroOS Mobile Shell", 10, 5); // Blue text on white
+    refresh_status_bar(&mut status_bar, &mobile_system, &wasm_runner, &mut graphics);
     
     // Content
-    graphics.set_color(graphics::colors::WHITE);
+    graphics.set_color(theme::current().text);
     graphics.draw_text("\n\n> KERNEL SHELL ACTIVO");
     graphics.draw_text("> Escuchando UART (Escribe en tu terminal)...");
+    graphics.present();
     
+    let mut line: Vec<u8> = Vec::new();
+    uart_send_str("fos> ");
+
     loop {
         if let Some(c) = uart_receive_non_blocking() {
-            // Echo en pantalla (simple)
-            // En un sistema real usaríamos un buffer circular para la consola
-            
-            match c {
-                b'h' => {
-                    uart_send_str("\n--- COMANDOS DISPONIBLES ---\n");
-                    graphics.set_color(graphics::colors::YELLOW);
-                    graphics.draw_text("\n> [h] Ayuda:");
-                    graphics.set_color(graphics::colors::WHITE);
+            // Acumular la línea de comandos hasta Enter
+            if !line_editor_feed(&mut line, c) {
+                continue;
+            }
+            let input = String::from_utf8_lossy(&line).into_owned();
+            line.clear();
+            let command = input.trim();
+
+            if !command.is_empty() {
+                // Volver a la consola del shell y hacer echo del comando completo
+                wasm_runner.send_to_background(&mut graphics);
+                graphics.dismiss_notification();
+                graphics.set_color(theme::current().text);
+                graphics.draw_text(&alloc::format!("> {}", command));
+            }
+
+            match command {
+                "" => {}
+                "h" | "help" => {
+                    uart_send_str("--- COMANDOS DISPONIBLES ---\n");
+                    uart_send_str("  h    Ayuda\n  c    Limpiar pantalla\n  r    Re-ejecutar app\n");
+                    uart_send_str("  u    Hot-reload de script Lua\n  lua  REPL de Lua\n  ui   Navegar los widgets de la app\n");
+                    uart_send_str("  t    Cambiar tema claro/oscuro (theme light|dark)\n");
+                    uart_send_str("  p    Captura de pantalla por UART (scripts/screenshot.sh)\n");
+                    uart_send_str("  n    Avisos sin leer\n  time HH:MM  Poner el reloj en hora\n");
+                    uart_send_str("  f    Estadísticas de frames de la app\n");
+                    uart_send_str("  k    Terminal ANSI en pantalla con el registro del kernel (term)\n");
+                    uart_send_str("  b    Benchmark de relleno y copia de pixels\n");
+                    uart_send_str("  s    Historial de la consola\n  i    Info sistema\n");
+                    graphics.set_color(theme::current().warning);
+                    graphics.draw_text("> [h] Ayuda:");
+                    graphics.set_color(theme::current().text);
                     graphics.draw_text("  c: Limpiar pantalla");
                     graphics.draw_text("  r: Re-ejecutar app");
+                    graphics.draw_text("  u: Hot-reload de script Lua");
+                    graphics.draw_text("  lua: REPL de Lua");
+                    graphics.draw_text("  ui: Navegar los widgets de la app");
+                    graphics.draw_text("  t: Cambiar tema claro/oscuro");
+                    graphics.draw_text("  p: Captura de pantalla");
+                    graphics.draw_text("  n: Avisos sin leer");
+                    graphics.draw_text("  time HH:MM: Poner el reloj en hora");
+                    graphics.draw_text("  f: Estadísticas de frames");
+                    graphics.draw_text("  k: Terminal ANSI");
+                    graphics.draw_text("  b: Benchmark gráfico");
+                    graphics.draw_text("  s: Historial de la consola");
                     graphics.draw_text("  i: Info sistema");
                 },
-                b'c' => {
-                    uart_send_str("\n🧹 Limpiando pantalla...\n");
+                "c" | "clear" => {
+                    uart_send_str("🧹 Limpiando pantalla...\n");
                     graphics.clear_screen();
-                    graphics.set_color(graphics::colors::WHITE);
+                    graphics.set_color(theme::current().text);
                     graphics.draw_text("> Pantalla limpia.");
                 },
-                b'r' => {
-                    uart_send_str("\n🔄 Re-ejecutando aplicación...\n");
-                    graphics.clear_screen();
+                "r" | "run" => {
+                    uart_send_str("🔄 Re-ejecutando aplicación...\n");
                     let success = wasm_runner.run_wasm_app_with_graphics(APP_WASM, &mut graphics);
                     if success {
                         uart_send_str("✅ Re-ejecución completada\n");
-                        graphics.set_color(graphics::colors::GREEN);
+                        graphics.set_color(theme::current().success);
                         graphics.draw_text("\n> App finalizada.");
                     }
                 },
-                b'i' => {
-                    uart_send_str("\n📊 INFO DEL SISTEMA\n");
-                    graphics.set_color(graphics::colors::CYAN);
+                "u" | "reload" => {
+                    uart_send_str("📥 HOT-RELOAD: esperando script (u32 LE longitud + Lua + u32 LE CRC-32)...\n");
+                    uart_send_str("READY\n");
+                    match receive_hot_reload_script() {
+                        Some(script) => {
+                            if wasm_runner.hot_reload_lua(script, &mut graphics) {
+                                uart_send_str("OK\n");
+                                notify(&mut mobile_system, &mut graphics, "App recargada en caliente");
+                            } else {
+                                uart_send_str("ERR\n");
+                                notify(&mut mobile_system, &mut graphics, "Error al recargar la app");
+                            }
+                        }
+                        None => uart_send_str("ERR\n"),
+                    }
+                },
+                "lua" => run_lua_repl(&mut wasm_runner, &mut graphics),
+                "ui" => run_ui_navigation(&mut wasm_runner, &mut graphics),
+                "t" | "theme" => {
+                    let variant = match theme::current().variant {
+                        theme::Variant::Light => theme::Variant::Dark,
+                        theme::Variant::Dark => theme::Variant::Light,
+                    };
+                    apply_theme(variant, &mut graphics);
+                }
+                "theme light" => apply_theme(theme::Variant::Light, &mut graphics),
+                "theme dark" => apply_theme(theme::Variant::Dark, &mut graphics),
+                "p" | "screenshot" => {
+                    // Capturar lo que se ve ahora, con el eco del comando ya en pantalla
+                    graphics.present();
+                    let bytes = screenshot::send(graphics.screen());
+                    graphics.set_color(theme::current().text);
+                    graphics.draw_text(&alloc::format!("> Captura enviada por UART ({} bytes QOI)", bytes));
+                }
+                "n" | "notifications" => {
+                    let notifications = mobile_system.take_notifications();
+                    graphics.set_color(theme::current().info);
+                    graphics.draw_text(&alloc::format!("> Avisos sin leer: {}", notifications.len()));
+                    graphics.set_color(theme::current().text);
+                    for text in &notifications {
+                        uart_send_str("🔔 ");
+                        uart_send_str(text);
+                        uart_send_str("\n");
+                        graphics.draw_text(&alloc::format!("  {}", text));
+                    }
+                }
+                "f" | "frames" => {
+                    let stats = wasm_runner.frame_stats();
+                    let lines = [
+                        alloc::format!("  Frames: {} ({} perdidos)", stats.frames, stats.dropped),
+                        alloc::format!("  FPS medios: {:.1}", stats.fps()),
+                        alloc::format!(
+                            "  Tiempo por frame: último {} us, medio {} us, mín {} us, máx {} us",
+                            stats.last_us,
+                            stats.average_us(),
+                            stats.min_us,
+                            stats.max_us
+                        ),
+                    ];
+                    uart_send_str("⏱️ FRAMES DE LA APP\n");
+                    graphics.set_color(theme::current().info);
+                    graphics.draw_text("> Frames de la app:");
+                    graphics.set_color(theme::current().text);
+                    for line in &lines {
+                        uart_send_str(line);
+                        uart_send_str("\n");
+                        graphics.draw_text(line);
+                    }
+                }
+                "k" | "term" => run_terminal(&mut graphics),
+                "b" | "bench" => {
+                    uart_send_str("⏱️  BENCHMARK de relleno y copia (pixel a pixel → blit)\n");
+                    let results = graphics.benchmark();
+                    graphics.set_color(theme::current().info);
+                    graphics.draw_text("> Benchmark (pixel a pixel → blit):");
+                    graphics.set_color(theme::current().text);
+                    for result in &results {
+                        let line = alloc::format!(
+                            "  {} ({}): {} us → {} us (x{:.1}, {} MB/s)",
+                            result.name,
+                            result.memory,
+                            result.per_pixel_us,
+                            result.fast_us,
+                            result.speedup(),
+                            result.megabytes_per_second()
+                        );
+                        uart_send_str(&line);
+                        uart_send_str("\n");
+                        graphics.draw_text(&line);
+                    }
+                }
+                "s" | "scroll" => run_scrollback_pager(&mut graphics),
+                "i" | "info" => {
+                    uart_send_str("📊 INFO DEL SISTEMA\n");
+                    graphics.set_color(theme::current().info);
                     graphics.draw_text("\n> INFO SISTEMA:");
-                    graphics.set_color(graphics::colors::WHITE);
+                    graphics.set_color(theme::current().text);
                     graphics.draw_text("  OS: FerroOS Mobile v0.1");
-                    graphics.draw_text("  Res: 640x480 (16-bit)");
+                    let (pitch, depth) = graphics.pitch_and_depth();
+                    graphics.draw_text(&alloc::format!(
+                        "  Res: {}x{}x{} pitch {} ({})",
+                        graphics.width(),
+                        graphics.height(),
+                        depth,
+                        pitch,
+                        graphics.pixel_format().name()
+                    ));
+                    graphics.draw_text(&alloc::format!("  Doble buffer: {}", graphics.buffering().name()));
+                    let surfaces: Vec<String> = graphics
+                        .surfaces()
+                        .map(|(name, _, z, visible)| alloc::format!("{} z{}{}", name, z, if visible { "" } else { " (oculta)" }))
+                        .collect();
+                    graphics.draw_text(&alloc::format!("  Superficies: {}", surfaces.join(", ")));
+                    let fonts: Vec<String> = graphics
+                        .font_names()
+                        .iter()
+                        .map(|(name, size)| if *size == 0 { alloc::format!("{} (TTF)", name) } else { alloc::format!("{} {}px", name, size) })
+                        .collect();
+                    graphics.draw_text(&alloc::format!("  Fuentes: {}", fonts.join(", ")));
                 },
-                other => {
-                    // Echo visual de cualquier otra tecla
-                    let buf = [other];
-                    if let Ok(s) = core::str::from_utf8(&buf) {
-                        graphics.set_color(graphics::colors::WHITE);
-                        graphics.draw_text(s);
+                other if other.starts_with("time ") => {
+                    let time = other["time ".len()..].trim();
+                    let parsed = time
+                        .split_once(':')
+                        .and_then(|(hours, minutes)| Some((hours.parse::<u8>().ok()?, minutes.parse::<u8>().ok()?)));
+                    match parsed {
+                        Some((hours, minutes)) if mobile_system.set_time(hours, minutes) => {
+                            graphics.draw_text(&alloc::format!("> Hora: {:02}:{:02}", hours, minutes));
+                        }
+                        _ => {
+                            uart_send_str("❌ Hora inválida, usa time HH:MM\n");
+                            graphics.set_color(theme::current().error);
+                            graphics.draw_text("> Hora inválida (time HH:MM)");
+                        }
                     }
-                    uart_send(other); // Echo UART
+                }
+                other => {
+                    uart_send_str("❓ Comando desconocido: ");
+                    uart_send_str(other);
+                    uart_send_str(" (h para ayuda)\n");
                 }
             }
+            refresh_status_bar(&mut status_bar, &mobile_system, &wasm_runner, &mut graphics);
+            graphics.present();
+            uart_send_str("fos> ");
+        } else {
+            // Frames de las animaciones de la app y la hora de la barra de estado
+            wasm_runner.poll_frame(&mut graphics);
+            if refresh_status_bar(&mut status_bar, &mobile_system, &wasm_runner, &mut graphics) {
+                graphics.present();
+            }
         }
         
         // Pequeña pausa para no saturar CPU
@@ -203,6 +473,290 @@ pub extern "C" fn kernel_main() -> ! {
     }
 }
 
+/// Cambiar el tema del sistema y redibujar la consola con él
+///
+/// La barra de estado se redibuja sola al ver el tema nuevo; la app recoge los
+/// colores nuevos en su próxima ejecución.
+fn apply_theme(variant: theme::Variant, graphics: &mut GraphicsManager) {
+    theme::set_variant(variant);
+    uart_send_str("🎨 Tema: ");
+    uart_send_str(variant.name());
+    uart_send_str("\n");
+    graphics.clear_screen();
+    graphics.set_color(theme::current().text);
+    graphics.draw_text(&alloc::format!("> Tema {}", variant.name()));
+}
+
+/// Mostrar un aviso del sistema y guardarlo como no leído en la barra de estado
+fn notify(mobile_system: &mut MobileSystem, graphics: &mut GraphicsManager, text: &str) {
+    mobile_system.notify(text);
+    graphics.show_notification(text);
+}
+
+/// Redibujar la barra de estado si cambió algo; devuelve `true` si hace falta `present`
+fn refresh_status_bar(
+    status_bar: &mut StatusBar,
+    mobile_system: &MobileSystem,
+    wasm_runner: &WasmRunner,
+    graphics: &mut GraphicsManager,
+) -> bool {
+    let info = StatusInfo {
+        battery: mobile_system.battery_level,
+        time: mobile_system.time_of_day(),
+        notifications: mobile_system.notification_count(),
+        foreground: wasm_runner.foreground_app(graphics),
+    };
+    status_bar.update(&info, graphics)
+}
+
+/// Procesar un byte de entrada de la línea de comandos con eco por UART
+///
+/// Devuelve `true` cuando se pulsa Enter y la línea está completa.
+fn line_editor_feed(line: &mut Vec<u8>, byte: u8) -> bool {
+    match byte {
+        b'\r' | b'\n' => {
+            uart_send_str("\n");
+            true
+        }
+        // Backspace / DEL: borrar el último carácter UTF-8 completo
+        0x08 | 0x7F => {
+            while let Some(last) = line.pop() {
+                if last & 0xC0 != 0x80 {
+                    break;
+                }
+            }
+            uart_send_str("\x08 \x08");
+            false
+        }
+        _ => {
+            line.push(byte);
+            uart_send(byte); // Echo UART
+            false
+        }
+    }
+}
+
+/// Terminal ANSI en pantalla con el registro del kernel y lo que llegue por UART
+///
+/// Lo recibido se interpreta como la salida de un programa: colores, movimientos
+/// del cursor y borrados. Ctrl-D vuelve a la consola del shell.
+fn run_terminal(graphics: &mut GraphicsManager) {
+    uart_send_str("🖥️  TERMINAL: lo que se envíe por UART se muestra en pantalla (Ctrl-D para salir)\n");
+    graphics.open_terminal();
+    graphics.terminal_reset();
+    if let Some((cols, rows)) = graphics.terminal_size() {
+        uart_send_str(&alloc::format!("  {}x{} celdas\n", cols, rows));
+    }
+    kernel_log(|bytes| graphics.terminal_write(bytes));
+    graphics.present();
+
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        // Juntar lo que ya haya llegado para dibujar y presentar una vez por ráfaga
+        pending.push(uart_receive());
+        while pending.len() < TERMINAL_BURST {
+            match uart_receive_non_blocking() {
+                Some(byte) => pending.push(byte),
+                None => break,
+            }
+        }
+        let exit = pending.iter().position(|&byte| byte == 0x04);
+        graphics.terminal_write(&pending[..exit.unwrap_or(pending.len())]);
+        graphics.present();
+        pending.clear();
+        if exit.is_some() {
+            break;
+        }
+    }
+
+    graphics.show_scrollback(0);
+    uart_send_str("👋 Saliendo del terminal\n");
+}
+
+/// Paginar por UART el historial de la consola mostrado en pantalla
+///
+/// `k`/`b` retroceden una página, `j`/espacio avanzan y `q`/Esc/Ctrl-D vuelven
+/// a la vista normal.
+fn run_scrollback_pager(graphics: &mut GraphicsManager) {
+    uart_send_str("📜 HISTORIAL: [k] página arriba  [j] página abajo  [q] salir\n");
+    let page = graphics.console_page_lines();
+    let mut offset = page;
+
+    loop {
+        offset = graphics.show_scrollback(offset);
+        graphics.present();
+
+        let total = graphics.scrollback_len();
+        let end = total - offset;
+        uart_send_str("  Líneas ");
+        print_number(end.saturating_sub(page) as u64 + 1);
+        uart_send_str("-");
+        print_number(end as u64);
+        uart_send_str(" de ");
+        print_number(total as u64);
+        uart_send_str("\n");
+
+        loop {
+            match uart_receive() {
+                b'k' | b'b' => offset += page,
+                b'j' | b' ' => offset = offset.saturating_sub(page),
+                b'q' | 0x1B | 0x04 => {
+                    graphics.show_scrollback(0);
+                    return;
+                }
+                _ => continue,
+            }
+            break;
+        }
+    }
+}
+
+/// Manejar los widgets de la app con el teclado del terminal UART
+///
+/// Tab/Shift-Tab y las flechas mueven el foco (o la selección de una lista),
+/// Enter activa el widget enfocado y se escribe en los campos de texto.
+/// Se sale con Esc o Ctrl-D.
+fn run_ui_navigation(wasm_runner: &mut WasmRunner, graphics: &mut GraphicsManager) {
+    if !wasm_runner.has_ui() {
+        uart_send_str("❓ La app no muestra widgets (ui_show)\n");
+        graphics.set_color(theme::current().warning);
+        graphics.draw_text("> La app no tiene widgets");
+        return;
+    }
+    if !wasm_runner.accepts_input() {
+        uart_send_str("🚫 La app no tiene el permiso 'input'\n");
+        graphics.set_color(theme::current().warning);
+        graphics.draw_text("> La app no recibe teclas");
+        return;
+    }
+    uart_send_str("🧭 WIDGETS: [Tab/flechas] foco  [Enter] activar  [Esc] salir\n");
+    wasm_runner.bring_to_front(graphics);
+
+    loop {
+        let key = match receive_animating(wasm_runner, graphics) {
+            0x04 => break,
+            b'\t' => Key::Tab,
+            b'\r' | b'\n' => Key::Enter,
+            0x08 | 0x7F => Key::Backspace,
+            0x1B => match uart_receive_timeout(ESCAPE_SEQUENCE_TIMEOUT) {
+                // Secuencias CSI de las flechas y de Shift-Tab
+                Some(b'[') => match uart_receive_timeout(ESCAPE_SEQUENCE_TIMEOUT) {
+                    Some(b'A') => Key::Up,
+                    Some(b'B') => Key::Down,
+                    Some(b'C') => Key::Right,
+                    Some(b'D') => Key::Left,
+                    Some(b'Z') => Key::BackTab,
+                    _ => continue,
+                },
+                None => break,
+                _ => continue,
+            },
+            byte if byte >= 0x20 => match receive_utf8_char(byte) {
+                Some(c) => Key::Char(c),
+                None => continue,
+            },
+            _ => continue,
+        };
+        wasm_runner.ui_handle_key(key, graphics);
+    }
+    uart_send_str("👋 Saliendo de la navegación de widgets\n");
+}
+
+/// Esperar un byte por UART sin detener las animaciones de la app
+fn receive_animating(wasm_runner: &mut WasmRunner, graphics: &mut GraphicsManager) -> u8 {
+    loop {
+        if let Some(byte) = uart_receive_non_blocking() {
+            return byte;
+        }
+        wasm_runner.poll_frame(graphics);
+    }
+}
+
+/// Completar un carácter UTF-8 a partir de su primer byte
+fn receive_utf8_char(first: u8) -> Option<char> {
+    let len = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return None,
+    };
+    let mut bytes = [first, 0, 0, 0];
+    for byte in bytes.iter_mut().take(len).skip(1) {
+        *byte = uart_receive();
+    }
+    core::str::from_utf8(&bytes[..len]).ok()?.chars().next()
+}
+
+/// REPL de Lua sobre UART con el estado persistente de la app
+///
+/// Evalúa expresiones y sentencias con los bindings gráficos disponibles.
+/// Las entradas incompletas (p. ej. un `function` sin `end`) continúan en la
+/// línea siguiente con el prompt `>>`. Se sale con `exit` o Ctrl-D.
+fn run_lua_repl(wasm_runner: &mut WasmRunner, graphics: &mut GraphicsManager) {
+    uart_send_str("🌙 LUA REPL ('exit' o Ctrl-D para salir)\n");
+    graphics.set_color(theme::current().info);
+    graphics.draw_text("> Lua REPL activo (UART)");
+    graphics.present();
+
+    let mut line: Vec<u8> = Vec::new();
+    let mut pending = String::new();
+    uart_send_str("lua> ");
+
+    loop {
+        let byte = receive_animating(wasm_runner, graphics);
+        if byte == 0x04 {
+            uart_send_str("\n");
+            break;
+        }
+        if !line_editor_feed(&mut line, byte) {
+            continue;
+        }
+        let input = String::from_utf8_lossy(&line).into_owned();
+        line.clear();
+
+        if pending.is_empty() {
+            match input.trim() {
+                "exit" => break,
+                "" => {
+                    uart_send_str("lua> ");
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        pending.push_str(&input);
+        pending.push('\n');
+
+        match wasm_runner.lua_repl_eval(&pending, graphics) {
+            Ok(values) => {
+                if !values.is_empty() {
+                    uart_send_str(&lua::format_values(&values));
+                    uart_send_str("\n");
+                }
+            }
+            Err(e) if e.incomplete => {
+                uart_send_str(">> ");
+                continue;
+            }
+            Err(e) => {
+                uart_send_str("❌ ");
+                uart_send_str(&e.message);
+                uart_send_str("\n");
+            }
+        }
+        pending.clear();
+        graphics.present();
+        uart_send_str("lua> ");
+    }
+
+    uart_send_str("👋 Saliendo del REPL de Lua\n");
+    wasm_runner.send_to_background(graphics);
+    graphics.set_color(theme::current().text);
+    graphics.draw_text("> Lua REPL cerrado");
+}
+
+#[cfg(not(test))]
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
     uart_send_str("\n\n===== KERNEL PANIC =====\n");
@@ -234,6 +788,7 @@ fn panic(_info: &core::panic::PanicInfo) -> ! {
     loop {}
 }
 
+#[cfg(not(test))]
 #[alloc_error_handler]
 fn alloc_error(_layout: core::alloc::Layout) -> ! {
     panic!("Allocation Error");
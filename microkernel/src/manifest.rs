@@ -0,0 +1,132 @@
+//! FerroOS Mobile - Manifiesto de aplicaciones (.wpk) y permisos
+//!
+//! Lee el `manifest.toml` del paquete con un parser mínimo de `clave = valor`
+//! (strings y arrays de strings), suficiente para el formato que genera
+//! `scripts/wpk-pack.sh`.
+
+extern crate alloc;
+
+use alloc::string::String;
+use fos_microkernel::uart_send_str;
+
+/// Permiso que una app debe declarar para usar una API del host
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Log,
+    Graphics,
+    Storage,
+    Input,
+}
+
+impl Permission {
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::Log => "log",
+            Permission::Graphics => "graphics",
+            Permission::Storage => "storage",
+            Permission::Input => "input",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "log" => Some(Permission::Log),
+            "graphics" => Some(Permission::Graphics),
+            "storage" => Some(Permission::Storage),
+            "input" => Some(Permission::Input),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// Conjunto de permisos concedidos a una app
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct PermissionSet(u32);
+
+impl PermissionSet {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Todos los permisos (código del propio kernel)
+    pub const fn all() -> Self {
+        Self(u32::MAX)
+    }
+
+    pub fn insert(&mut self, permission: Permission) {
+        self.0 |= permission.bit();
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        self.0 & permission.bit() != 0
+    }
+}
+
+/// Datos del manifiesto que usa el kernel
+pub struct AppManifest {
+    pub name: String,
+    pub id: String,
+    pub permissions: PermissionSet,
+}
+
+impl AppManifest {
+    /// Interpretar el contenido de `manifest.toml`
+    ///
+    /// Las claves desconocidas se ignoran; los permisos desconocidos se avisan
+    /// por UART y no se conceden.
+    pub fn parse(source: &str) -> Self {
+        let mut manifest = Self {
+            name: String::from("app"),
+            id: String::from("com.fos.app"),
+            permissions: PermissionSet::empty(),
+        };
+
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "name" => manifest.name = String::from(unquote(value)),
+                "id" => manifest.id = String::from(unquote(value)),
+                "permissions" => {
+                    let items = value.trim_start_matches('[').trim_end_matches(']');
+                    for item in items.split(',').map(|i| unquote(i.trim())).filter(|i| !i.is_empty()) {
+                        match Permission::from_name(item) {
+                            Some(permission) => manifest.permissions.insert(permission),
+                            None => {
+                                uart_send_str("  ⚠️ Permiso desconocido en manifest: ");
+                                uart_send_str(item);
+                                uart_send_str("\n");
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        manifest
+    }
+}
+
+/// Quitar las comillas de un string TOML simple
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches('"')
+}
+
+/// Registrar por UART una llamada denegada por falta de permiso
+pub fn log_denied(api: &str, permission: Permission) {
+    uart_send_str("🚫 Permiso denegado: '");
+    uart_send_str(api);
+    uart_send_str("' requiere el permiso '");
+    uart_send_str(permission.name());
+    uart_send_str("'\n");
+}
@@ -1,11 +1,21 @@
 //! FerroOS Mobile - Sistema operativo móvil simplificado
 
-use fos_microkernel::{uart_send_str, print_number};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use fos_microkernel::{uart_send_str, print_number, uptime_micros};
+
+/// Segundos de un día, para dar la vuelta al reloj
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
 
 /// Sistema móvil básico
 pub struct MobileSystem {
     pub battery_level: u8,
     pub apps_running: u8,
+    /// Segundos que se suman al tiempo desde el arranque para dar la hora (no hay RTC)
+    clock_offset: u64,
+    /// Avisos mostrados que el usuario aún no ha consultado
+    notifications: Vec<String>,
 }
 
 impl MobileSystem {
@@ -13,6 +23,8 @@ impl MobileSystem {
         Self {
             battery_level: 85,
             apps_running: 0,
+            clock_offset: 0,
+            notifications: Vec::new(),
         }
     }
 
@@ -25,6 +37,38 @@ impl MobileSystem {
         uart_send_str("🚀 Sistema móvil listo\n\n");
     }
 
+    /// Hora actual (horas, minutos); sin ajustar cuenta desde el arranque
+    pub fn time_of_day(&self) -> (u8, u8) {
+        let seconds = (uptime_micros() / 1_000_000 + self.clock_offset) % SECONDS_PER_DAY;
+        ((seconds / 3600) as u8, (seconds / 60 % 60) as u8)
+    }
+
+    /// Poner el reloj en hora; `false` si la hora no es válida
+    pub fn set_time(&mut self, hours: u8, minutes: u8) -> bool {
+        if hours >= 24 || minutes >= 60 {
+            return false;
+        }
+        let uptime = uptime_micros() / 1_000_000 % SECONDS_PER_DAY;
+        let target = hours as u64 * 3600 + minutes as u64 * 60;
+        self.clock_offset = (target + SECONDS_PER_DAY - uptime) % SECONDS_PER_DAY;
+        true
+    }
+
+    /// Guardar un aviso como no leído
+    pub fn notify(&mut self, text: &str) {
+        self.notifications.push(String::from(text));
+    }
+
+    /// Avisos sin leer
+    pub fn notification_count(&self) -> usize {
+        self.notifications.len()
+    }
+
+    /// Sacar los avisos sin leer, del más antiguo al más reciente
+    pub fn take_notifications(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.notifications)
+    }
+
     /// Mostrar estado final del sistema
     pub fn show_final_status(&self) {
         uart_send_str("📊 ESTADO DEL SISTEMA:\n");
@@ -36,4 +80,4 @@ impl MobileSystem {
         uart_send_str("\n");
         uart_send_str("  ⚡ Estado: ACTIVO\n");
     }
-}
\ No newline at end of file
+}
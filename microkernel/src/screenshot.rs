@@ -0,0 +1,190 @@
+//! FerroOS Mobile - Capturas de pantalla por UART
+//!
+//! La pantalla se codifica en QOI (RGB, sin alfa) mientras se lee, y los bytes
+//! salen en base64 en líneas de 76 caracteres entre dos líneas de marco:
+//!
+//! ```text
+//! FOS-SCREENSHOT BEGIN <ancho>x<alto> <formato> qoi
+//! cW9pZgAAAoAAAAHgAwD+AAAA...
+//! FOS-SCREENSHOT END <bytes> <crc32>
+//! ```
+//!
+//! `<bytes>` es el tamaño del QOI y `<crc32>` su CRC-32 en hexadecimal.
+//! `scripts/screenshot.sh` recoge la captura y la convierte a PNG.
+
+use embedded_graphics::pixelcolor::RgbColor;
+
+use crate::graphics::FrameBuffer;
+use fos_microkernel::{crc32_update, print_number, uart_send_str, uart_send_unlogged};
+
+/// Caracteres por línea de base64
+const LINE_WIDTH: u32 = 76;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RUN: u8 = 0xC0;
+const QOI_OP_RGB: u8 = 0xFE;
+/// Repeticiones máximas de un `QOI_OP_RUN`
+const QOI_MAX_RUN: u8 = 62;
+const QOI_END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+/// Salida en base64 por UART que lleva la cuenta de bytes y el CRC
+///
+/// Los datos no pasan por el registro del kernel, que solo guarda los marcos.
+struct Base64Stream {
+    pending: [u8; 3],
+    pending_len: usize,
+    column: u32,
+    bytes: u32,
+    crc: u32,
+}
+
+impl Base64Stream {
+    fn new() -> Self {
+        Self { pending: [0; 3], pending_len: 0, column: 0, bytes: 0, crc: 0xFFFF_FFFF }
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        self.crc = crc32_update(self.crc, data);
+        self.bytes += data.len() as u32;
+        for &byte in data {
+            self.pending[self.pending_len] = byte;
+            self.pending_len += 1;
+            if self.pending_len == 3 {
+                self.emit_group();
+            }
+        }
+    }
+
+    /// Sacar el grupo pendiente (1 a 3 bytes) como 4 caracteres, con relleno `=`
+    fn emit_group(&mut self) {
+        let [a, b, c] = self.pending;
+        let (a, b, c) = (a as usize, if self.pending_len > 1 { b as usize } else { 0 }, if self.pending_len > 2 { c as usize } else { 0 });
+        let chars = [
+            BASE64[a >> 2],
+            BASE64[((a & 0x03) << 4) | (b >> 4)],
+            if self.pending_len > 1 { BASE64[((b & 0x0F) << 2) | (c >> 6)] } else { b'=' },
+            if self.pending_len > 2 { BASE64[c & 0x3F] } else { b'=' },
+        ];
+        for byte in chars {
+            uart_send_unlogged(byte);
+        }
+        self.pending_len = 0;
+        self.column += 4;
+        if self.column >= LINE_WIDTH {
+            uart_send_unlogged(b'\n');
+            self.column = 0;
+        }
+    }
+
+    /// Vaciar lo pendiente y devolver (bytes escritos, CRC-32)
+    fn finish(mut self) -> (u32, u32) {
+        if self.pending_len > 0 {
+            self.emit_group();
+        }
+        if self.column > 0 {
+            uart_send_unlogged(b'\n');
+        }
+        (self.bytes, !self.crc)
+    }
+}
+
+/// Codificador QOI de 3 canales que escribe en `out` pixel a pixel
+struct QoiEncoder {
+    /// Colores vistos, con alfa 255 como los guarda el decodificador (empieza a cero, con alfa 0)
+    index: [[u8; 4]; 64],
+    previous: [u8; 3],
+    run: u8,
+}
+
+impl QoiEncoder {
+    fn new(width: u32, height: u32, out: &mut Base64Stream) -> Self {
+        out.write(b"qoif");
+        out.write(&width.to_be_bytes());
+        out.write(&height.to_be_bytes());
+        // 3 canales, sRGB
+        out.write(&[3, 0]);
+        Self { index: [[0; 4]; 64], previous: [0, 0, 0], run: 0 }
+    }
+
+    fn push(&mut self, pixel: [u8; 3], out: &mut Base64Stream) {
+        if pixel == self.previous {
+            self.run += 1;
+            if self.run == QOI_MAX_RUN {
+                self.flush_run(out);
+            }
+            return;
+        }
+        self.flush_run(out);
+
+        let [r, g, b] = pixel;
+        // El alfa es siempre 255 y entra en el hash como 255 * 11
+        let slot = (r as usize * 3 + g as usize * 5 + b as usize * 7 + 255 * 11) % 64;
+        if self.index[slot] == [r, g, b, 255] {
+            out.write(&[QOI_OP_INDEX | slot as u8]);
+        } else {
+            self.index[slot] = [r, g, b, 255];
+            let [pr, pg, pb] = self.previous;
+            let dr = r.wrapping_sub(pr) as i8;
+            let dg = g.wrapping_sub(pg) as i8;
+            let db = b.wrapping_sub(pb) as i8;
+            let dr_dg = dr.wrapping_sub(dg);
+            let db_dg = db.wrapping_sub(dg);
+            if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                out.write(&[QOI_OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8]);
+            } else if (-32..=31).contains(&dg) && (-8..=7).contains(&dr_dg) && (-8..=7).contains(&db_dg) {
+                out.write(&[QOI_OP_LUMA | (dg + 32) as u8, ((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8]);
+            } else {
+                out.write(&[QOI_OP_RGB, r, g, b]);
+            }
+        }
+        self.previous = pixel;
+    }
+
+    fn flush_run(&mut self, out: &mut Base64Stream) {
+        if self.run > 0 {
+            out.write(&[QOI_OP_RUN | (self.run - 1)]);
+            self.run = 0;
+        }
+    }
+
+    fn finish(mut self, out: &mut Base64Stream) {
+        self.flush_run(out);
+        out.write(&QOI_END_MARKER);
+    }
+}
+
+/// Enviar por UART la captura de `screen` (RGB565 o de 32 bits)
+///
+/// Devuelve el tamaño del QOI enviado.
+pub fn send(screen: &FrameBuffer) -> u32 {
+    let (width, height) = (screen.width(), screen.height());
+    uart_send_str("FOS-SCREENSHOT BEGIN ");
+    print_number(width as u64);
+    uart_send_str("x");
+    print_number(height as u64);
+    uart_send_str(" ");
+    uart_send_str(screen.format().name());
+    uart_send_str(" qoi\n");
+
+    let mut out = Base64Stream::new();
+    let mut encoder = QoiEncoder::new(width, height, &mut out);
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let color = screen.get_pixel(x, y).unwrap_or_default();
+            encoder.push([color.r(), color.g(), color.b()], &mut out);
+        }
+    }
+    encoder.finish(&mut out);
+    let (bytes, crc) = out.finish();
+
+    uart_send_str("FOS-SCREENSHOT END ");
+    print_number(bytes as u64);
+    uart_send_str(" ");
+    uart_send_str(&alloc::format!("{:08x}", crc));
+    uart_send_str("\n");
+    bytes
+}
@@ -0,0 +1,95 @@
+//! FerroOS Mobile - Barra de estado del sistema
+//!
+//! Franja fija en lo alto de la pantalla con la app en primer plano, los avisos
+//! sin leer, la batería y la hora. Se dibuja en su propia superficie del
+//! compositor, por encima de las apps, y solo se redibuja cuando cambia algo.
+
+use alloc::format;
+use alloc::string::String;
+use embedded_graphics::pixelcolor::Rgb888;
+
+use crate::graphics::GraphicsManager;
+use crate::theme::{self, Variant};
+
+/// Batería a partir de la que se avisa en color (%)
+const BATTERY_LOW: u8 = 30;
+const BATTERY_CRITICAL: u8 = 15;
+
+/// Nombre que se muestra cuando no hay ninguna app en primer plano
+const SHELL_NAME: &str = "FerroOS Mobile Shell";
+
+/// Lo que muestra la barra
+pub struct StatusInfo<'a> {
+    /// Nivel de batería (%)
+    pub battery: u8,
+    /// Hora (horas, minutos)
+    pub time: (u8, u8),
+    /// Avisos sin leer
+    pub notifications: usize,
+    /// App en primer plano; `None` con la consola del shell
+    pub foreground: Option<&'a str>,
+}
+
+/// Estado ya dibujado, para saber si hay que redibujar
+struct Shown {
+    battery: u8,
+    time: (u8, u8),
+    notifications: usize,
+    foreground: Option<String>,
+    theme: Variant,
+}
+
+pub struct StatusBar {
+    shown: Option<Shown>,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self { shown: None }
+    }
+
+    /// Redibujar la barra si `info` o el tema cambiaron desde la última vez
+    ///
+    /// Devuelve `true` si se redibujó (y hace falta un `present`).
+    pub fn update(&mut self, info: &StatusInfo, graphics: &mut GraphicsManager) -> bool {
+        let theme = theme::current().variant;
+        let unchanged = self.shown.as_ref().is_some_and(|shown| {
+            shown.battery == info.battery
+                && shown.time == info.time
+                && shown.notifications == info.notifications
+                && shown.foreground.as_deref() == info.foreground
+                && shown.theme == theme
+        });
+        if unchanged {
+            return false;
+        }
+        Self::draw(info, graphics);
+        self.shown = Some(Shown {
+            battery: info.battery,
+            time: info.time,
+            notifications: info.notifications,
+            foreground: info.foreground.map(String::from),
+            theme,
+        });
+        true
+    }
+
+    fn draw(info: &StatusInfo, graphics: &mut GraphicsManager) {
+        let theme = theme::current();
+        let battery_color = match info.battery {
+            level if level <= BATTERY_CRITICAL => theme.error,
+            level if level <= BATTERY_LOW => theme.warning,
+            _ => theme.success,
+        };
+        let notifications = match info.notifications {
+            0 => String::new(),
+            1 => String::from("1 aviso"),
+            count => format!("{} avisos", count),
+        };
+        let battery = format!("{}%", info.battery);
+        let clock = format!("{:02}:{:02}", info.time.0, info.time.1);
+
+        let right: [(&str, Rgb888); 3] = [(&notifications, theme.accent), (&battery, battery_color), (&clock, theme.text)];
+        graphics.show_status_bar(info.foreground.unwrap_or(SHELL_NAME), &right);
+    }
+}
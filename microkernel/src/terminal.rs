@@ -0,0 +1,697 @@
+//! FerroOS Mobile - Emulador de terminal ANSI/VT100
+//!
+//! Rejilla de celdas (carácter y colores) con cursor que interpreta los bytes
+//! tal como llegan por UART: UTF-8, controles (CR, LF, backspace, tabulador) y
+//! las secuencias de escape ANSI de color, movimiento del cursor y borrado.
+//! Aquí solo está el estado; `GraphicsManager` dibuja las filas que cambian.
+//!
+//! Los LF vuelven también a la columna 0, como en la salida del kernel, que
+//! separa las líneas solo con `\n`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use embedded_graphics::pixelcolor::Rgb888;
+
+/// Columnas entre paradas de tabulador
+const TAB_WIDTH: usize = 8;
+
+/// Parámetros máximos de una secuencia CSI (el resto se ignora)
+const MAX_PARAMS: usize = 16;
+
+/// Paleta ANSI de 16 colores (los 8 normales y los 8 brillantes), como xterm
+const ANSI_PALETTE: [Rgb888; 16] = [
+    Rgb888::new(0, 0, 0),
+    Rgb888::new(205, 0, 0),
+    Rgb888::new(0, 205, 0),
+    Rgb888::new(205, 205, 0),
+    Rgb888::new(0, 0, 238),
+    Rgb888::new(205, 0, 205),
+    Rgb888::new(0, 205, 205),
+    Rgb888::new(229, 229, 229),
+    Rgb888::new(127, 127, 127),
+    Rgb888::new(255, 0, 0),
+    Rgb888::new(0, 255, 0),
+    Rgb888::new(255, 255, 0),
+    Rgb888::new(92, 92, 255),
+    Rgb888::new(255, 0, 255),
+    Rgb888::new(0, 255, 255),
+    Rgb888::new(255, 255, 255),
+];
+
+/// Niveles de cada canal en el cubo de 6x6x6 de la paleta de 256 colores
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Color de la paleta de 256 colores: 16 ANSI, cubo 6x6x6 y 24 grises
+pub fn palette_color(index: u8) -> Rgb888 {
+    match index {
+        0..=15 => ANSI_PALETTE[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            Rgb888::new(CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[(i / 6 % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            Rgb888::new(level, level, level)
+        }
+    }
+}
+
+/// Celda de la rejilla
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Rgb888,
+    pub bg: Rgb888,
+}
+
+/// Atributos con los que se escriben los caracteres
+#[derive(Clone, Copy)]
+struct Attributes {
+    /// `None` es el color por defecto del terminal
+    fg: Option<Rgb888>,
+    bg: Option<Rgb888>,
+    bold: bool,
+    inverse: bool,
+}
+
+impl Attributes {
+    const DEFAULT: Self = Self { fg: None, bg: None, bold: false, inverse: false };
+}
+
+/// Estado del intérprete de secuencias de escape
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    /// Tras ESC
+    Escape,
+    /// Dentro de `ESC [`
+    Csi,
+    /// Dentro de `ESC ]` (título de la ventana...): se descarta hasta BEL o `ESC \`
+    Osc,
+}
+
+pub struct Terminal {
+    cols: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    /// Filas que cambiaron desde el último `take_dirty`
+    dirty: Vec<bool>,
+    cursor_col: usize,
+    cursor_row: usize,
+    /// Se escribió en la última columna: el siguiente carácter pasa a la línea siguiente
+    wrap_pending: bool,
+    cursor_visible: bool,
+    /// Posición en que se dibujó el cursor por última vez, para borrarlo al moverse
+    drawn_cursor: Option<(usize, usize)>,
+    saved_cursor: (usize, usize),
+    attributes: Attributes,
+    default_fg: Rgb888,
+    default_bg: Rgb888,
+    state: State,
+    params: Vec<u16>,
+    /// Se está leyendo un parámetro (un `;` sin dígitos deja uno vacío, que vale 0)
+    param_started: bool,
+    /// Ya hay `MAX_PARAMS` parámetros: los siguientes se descartan
+    params_full: bool,
+    /// Secuencia privada (`ESC [ ?`)
+    private: bool,
+    /// Bytes de un carácter UTF-8 a medias y cuántos faltan
+    utf8: [u8; 4],
+    utf8_len: usize,
+    utf8_needed: usize,
+}
+
+impl Terminal {
+    pub fn new(cols: usize, rows: usize, default_fg: Rgb888, default_bg: Rgb888) -> Self {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        Self {
+            cols,
+            rows,
+            cells: vec![Cell { ch: ' ', fg: default_fg, bg: default_bg }; cols * rows],
+            dirty: vec![true; rows],
+            cursor_col: 0,
+            cursor_row: 0,
+            wrap_pending: false,
+            cursor_visible: true,
+            drawn_cursor: None,
+            saved_cursor: (0, 0),
+            attributes: Attributes::DEFAULT,
+            default_fg,
+            default_bg,
+            state: State::Ground,
+            params: Vec::new(),
+            param_started: false,
+            params_full: false,
+            private: false,
+            utf8: [0; 4],
+            utf8_len: 0,
+            utf8_needed: 0,
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Celda en (columna, fila)
+    pub fn cell(&self, col: usize, row: usize) -> Cell {
+        self.cells[row * self.cols + col]
+    }
+
+    /// Posición del cursor (columna, fila) si está visible
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor_visible.then_some((self.cursor_col.min(self.cols - 1), self.cursor_row))
+    }
+
+    /// Cambiar los colores por defecto (tema nuevo) y marcar todo para redibujar
+    ///
+    /// Las celdas escritas con los colores por defecto anteriores pasan a los nuevos.
+    pub fn set_default_colors(&mut self, fg: Rgb888, bg: Rgb888) {
+        for cell in &mut self.cells {
+            if cell.fg == self.default_fg {
+                cell.fg = fg;
+            }
+            if cell.bg == self.default_bg {
+                cell.bg = bg;
+            }
+        }
+        self.default_fg = fg;
+        self.default_bg = bg;
+        self.mark_all_dirty();
+    }
+
+    /// Volver al estado inicial: pantalla vacía, cursor arriba y atributos por defecto
+    pub fn reset(&mut self) {
+        let (fg, bg) = (self.default_fg, self.default_bg);
+        *self = Self::new(self.cols, self.rows, fg, bg);
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.fill(true);
+        self.drawn_cursor = None;
+    }
+
+    /// Filas que hay que redibujar, incluidas la del cursor y en la que estaba
+    pub fn take_dirty(&mut self) -> Vec<usize> {
+        let cursor = self.cursor();
+        if self.drawn_cursor != cursor {
+            for (_, row) in [self.drawn_cursor, cursor].into_iter().flatten() {
+                self.dirty[row] = true;
+            }
+            self.drawn_cursor = cursor;
+        }
+        let rows = (0..self.rows).filter(|&row| self.dirty[row]).collect();
+        self.dirty.fill(false);
+        rows
+    }
+
+    /// Interpretar bytes recibidos
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.feed(byte);
+        }
+    }
+
+    fn feed(&mut self, byte: u8) {
+        match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::Csi => self.csi(byte),
+            State::Osc => match byte {
+                0x07 => self.state = State::Ground,
+                0x1B => self.state = State::Escape,
+                _ => {}
+            },
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        // Continuación de un carácter UTF-8
+        if self.utf8_needed > 0 {
+            if byte & 0xC0 == 0x80 {
+                self.utf8[self.utf8_len] = byte;
+                self.utf8_len += 1;
+                self.utf8_needed -= 1;
+                if self.utf8_needed == 0 {
+                    let c = core::str::from_utf8(&self.utf8[..self.utf8_len])
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.put_char(c);
+                }
+                return;
+            }
+            // Secuencia cortada: se sustituye y el byte se procesa normalmente
+            self.utf8_needed = 0;
+            self.put_char(char::REPLACEMENT_CHARACTER);
+        }
+
+        match byte {
+            0x1B => self.state = State::Escape,
+            b'\r' => self.carriage_return(),
+            b'\n' | 0x0B | 0x0C => {
+                self.carriage_return();
+                self.line_feed();
+            }
+            0x08 => {
+                self.cursor_col = self.cursor_col.min(self.cols - 1).saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                self.cursor_col = ((self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH).min(self.cols - 1);
+                self.wrap_pending = false;
+            }
+            // BEL, DEL y el resto de controles no tienen efecto en pantalla
+            0x00..=0x1F | 0x7F => {}
+            0x20..=0x7E => self.put_char(byte as char),
+            _ => {
+                let needed = match byte {
+                    0xC0..=0xDF => 1,
+                    0xE0..=0xEF => 2,
+                    0xF0..=0xF7 => 3,
+                    _ => {
+                        self.put_char(char::REPLACEMENT_CHARACTER);
+                        return;
+                    }
+                };
+                self.utf8[0] = byte;
+                self.utf8_len = 1;
+                self.utf8_needed = needed;
+            }
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Ground;
+        match byte {
+            b'[' => {
+                self.params.clear();
+                self.param_started = false;
+                self.params_full = false;
+                self.private = false;
+                self.state = State::Csi;
+            }
+            b']' => self.state = State::Osc,
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'c' => self.reset(),
+            // Index: bajar una línea sin volver a la columna 0
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.carriage_return();
+                self.line_feed();
+            }
+            // Reverse index: subir una línea, con scroll hacia abajo en la primera
+            b'M' => {
+                if self.cursor_row == 0 {
+                    self.scroll_down(1);
+                } else {
+                    self.cursor_row -= 1;
+                }
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' => {
+                if !self.param_started {
+                    self.start_param();
+                }
+                if self.params_full {
+                    return;
+                }
+                if let Some(param) = self.params.last_mut() {
+                    *param = param.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                }
+            }
+            b';' => {
+                if !self.param_started {
+                    self.start_param();
+                }
+                self.param_started = false;
+            }
+            b'?' => self.private = true,
+            // Otros parámetros e intermedios no se usan
+            0x20..=0x3F => {}
+            0x40..=0x7E => {
+                self.state = State::Ground;
+                self.dispatch_csi(byte);
+            }
+            0x1B => self.state = State::Escape,
+            // Los controles dentro de la secuencia se ejecutan sin interrumpirla
+            _ => self.ground(byte),
+        }
+    }
+
+    /// Empezar un parámetro nuevo, salvo que ya estén todos los que se guardan
+    fn start_param(&mut self) {
+        self.param_started = true;
+        if self.params.len() == MAX_PARAMS {
+            self.params_full = true;
+        } else {
+            self.params.push(0);
+        }
+    }
+
+    /// Parámetro `index` de la secuencia; los ausentes o a 0 valen `default`
+    fn param(&self, index: usize, default: u16) -> u16 {
+        match self.params.get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+
+    fn dispatch_csi(&mut self, command: u8) {
+        let n = self.param(0, 1) as usize;
+        match command {
+            b'A' => self.move_cursor(self.cursor_row.saturating_sub(n), self.cursor_col),
+            b'B' => self.move_cursor(self.cursor_row + n, self.cursor_col),
+            b'C' => self.move_cursor(self.cursor_row, self.cursor_col + n),
+            b'D' => self.move_cursor(self.cursor_row, self.cursor_col.min(self.cols - 1).saturating_sub(n)),
+            b'E' => self.move_cursor(self.cursor_row + n, 0),
+            b'F' => self.move_cursor(self.cursor_row.saturating_sub(n), 0),
+            b'G' => self.move_cursor(self.cursor_row, n - 1),
+            b'd' => self.move_cursor(n - 1, self.cursor_col),
+            b'H' | b'f' => self.move_cursor(n - 1, self.param(1, 1) as usize - 1),
+            b'J' => self.erase_display(self.param(0, 0)),
+            b'K' => self.erase_line(self.param(0, 0)),
+            b'X' => {
+                let (row, col) = (self.cursor_row, self.cursor_col.min(self.cols - 1));
+                self.clear_cells(row, col, (col + n).min(self.cols));
+            }
+            b'S' => self.scroll_up(n),
+            b'T' => self.scroll_down(n),
+            b'm' => self.select_graphic_rendition(),
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            b'h' | b'l' if self.private => {
+                // Solo se atiende mostrar u ocultar el cursor (DECTCEM)
+                if self.params.contains(&25) {
+                    self.cursor_visible = command == b'h';
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Colores y atributos (SGR)
+    fn select_graphic_rendition(&mut self) {
+        if self.params.is_empty() {
+            self.attributes = Attributes::DEFAULT;
+            return;
+        }
+        let mut i = 0;
+        while i < self.params.len() {
+            match self.params[i] {
+                0 => self.attributes = Attributes::DEFAULT,
+                1 => self.attributes.bold = true,
+                22 => self.attributes.bold = false,
+                7 => self.attributes.inverse = true,
+                27 => self.attributes.inverse = false,
+                code @ 30..=37 => self.attributes.fg = Some(ANSI_PALETTE[(code - 30) as usize]),
+                39 => self.attributes.fg = None,
+                code @ 40..=47 => self.attributes.bg = Some(ANSI_PALETTE[(code - 40) as usize]),
+                49 => self.attributes.bg = None,
+                code @ 90..=97 => self.attributes.fg = Some(ANSI_PALETTE[(code - 90 + 8) as usize]),
+                code @ 100..=107 => self.attributes.bg = Some(ANSI_PALETTE[(code - 100 + 8) as usize]),
+                code @ (38 | 48) => {
+                    // 38;5;n (paleta de 256) o 38;2;r;g;b (color directo)
+                    let color = match self.params.get(i + 1) {
+                        Some(5) => {
+                            let index = self.params.get(i + 2).copied();
+                            i += 2;
+                            index.map(|n| palette_color(n.min(255) as u8))
+                        }
+                        Some(2) => {
+                            let channel = |offset: usize| self.params.get(i + offset).map_or(0, |&v| v.min(255) as u8);
+                            let color = Rgb888::new(channel(2), channel(3), channel(4));
+                            i += 4;
+                            Some(color)
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.attributes.fg = color.or(self.attributes.fg);
+                    } else {
+                        self.attributes.bg = color.or(self.attributes.bg);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Colores con que se escribe ahora: el negrita aclara los 8 colores normales
+    fn current_colors(&self) -> (Rgb888, Rgb888) {
+        let attributes = self.attributes;
+        let fg = match attributes.fg {
+            Some(color) if attributes.bold => ANSI_PALETTE[..8]
+                .iter()
+                .position(|&c| c == color)
+                .map_or(color, |index| ANSI_PALETTE[index + 8]),
+            Some(color) => color,
+            None => self.default_fg,
+        };
+        let bg = attributes.bg.unwrap_or(self.default_bg);
+        if attributes.inverse { (bg, fg) } else { (fg, bg) }
+    }
+
+    fn put_char(&mut self, ch: char) {
+        if self.wrap_pending {
+            self.carriage_return();
+            self.line_feed();
+        }
+        let (fg, bg) = self.current_colors();
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        self.cells[row * self.cols + col] = Cell { ch, fg, bg };
+        self.dirty[row] = true;
+        if col + 1 == self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.cursor_col += 1;
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.cursor_col = 0;
+        self.wrap_pending = false;
+    }
+
+    /// Bajar una línea, con scroll al pasar de la última
+    fn line_feed(&mut self) {
+        if self.cursor_row + 1 == self.rows {
+            self.scroll_up(1);
+        } else {
+            self.cursor_row += 1;
+        }
+        self.wrap_pending = false;
+    }
+
+    /// Mover el cursor a (fila, columna), limitado a la rejilla
+    fn move_cursor(&mut self, row: usize, col: usize) {
+        self.cursor_row = row.min(self.rows - 1);
+        self.cursor_col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = (self.cursor_row, self.cursor_col);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (row, col) = self.saved_cursor;
+        self.move_cursor(row, col);
+    }
+
+    /// Celda vacía con el fondo actual, como borran los terminales
+    fn blank(&self) -> Cell {
+        let bg = self.attributes.bg.unwrap_or(self.default_bg);
+        Cell { ch: ' ', fg: self.default_fg, bg }
+    }
+
+    /// Borrar las columnas `from..to` de una fila
+    fn clear_cells(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        let start = row * self.cols;
+        self.cells[start + from..start + to].fill(blank);
+        self.dirty[row] = true;
+    }
+
+    /// Borrar la pantalla: 0 desde el cursor, 1 hasta el cursor, 2 (y 3) entera
+    fn erase_display(&mut self, mode: u16) {
+        let (row, col) = (self.cursor_row, self.cursor_col.min(self.cols - 1));
+        let rows = match mode {
+            0 => {
+                self.clear_cells(row, col, self.cols);
+                row + 1..self.rows
+            }
+            1 => {
+                self.clear_cells(row, 0, col + 1);
+                0..row
+            }
+            _ => 0..self.rows,
+        };
+        for row in rows {
+            self.clear_cells(row, 0, self.cols);
+        }
+    }
+
+    /// Borrar la línea: 0 desde el cursor, 1 hasta el cursor, 2 entera
+    fn erase_line(&mut self, mode: u16) {
+        let (row, col) = (self.cursor_row, self.cursor_col.min(self.cols - 1));
+        match mode {
+            0 => self.clear_cells(row, col, self.cols),
+            1 => self.clear_cells(row, 0, col + 1),
+            _ => self.clear_cells(row, 0, self.cols),
+        }
+    }
+
+    /// Subir el contenido `lines` filas; las de abajo quedan vacías
+    fn scroll_up(&mut self, lines: usize) {
+        let lines = lines.min(self.rows);
+        self.cells.copy_within(lines * self.cols.., 0);
+        for row in self.rows - lines..self.rows {
+            self.clear_cells(row, 0, self.cols);
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Bajar el contenido `lines` filas; las de arriba quedan vacías
+    fn scroll_down(&mut self, lines: usize) {
+        let lines = lines.min(self.rows);
+        let end = (self.rows - lines) * self.cols;
+        self.cells.copy_within(..end, lines * self.cols);
+        for row in 0..lines {
+            self.clear_cells(row, 0, self.cols);
+        }
+        self.mark_all_dirty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use embedded_graphics::pixelcolor::RgbColor;
+
+    const FG: Rgb888 = Rgb888::WHITE;
+    const BG: Rgb888 = Rgb888::BLACK;
+
+    fn terminal(cols: usize, rows: usize) -> Terminal {
+        Terminal::new(cols, rows, FG, BG)
+    }
+
+    /// Texto de una fila, sin los espacios del final
+    fn row_text(terminal: &Terminal, row: usize) -> String {
+        let text: String = (0..terminal.cols()).map(|col| terminal.cell(col, row).ch).collect();
+        String::from(text.trim_end())
+    }
+
+    #[test]
+    fn moves_the_cursor_with_cup() {
+        let mut terminal = terminal(10, 5);
+        terminal.write(b"\x1b[3;4Hx");
+        assert_eq!(terminal.cell(3, 2).ch, 'x');
+        assert_eq!(terminal.cursor(), Some((4, 2)));
+
+        // Sin parámetros va a la esquina y fuera de rango se limita a la rejilla
+        terminal.write(b"\x1b[H");
+        assert_eq!(terminal.cursor(), Some((0, 0)));
+        terminal.write(b"\x1b[99;99H");
+        assert_eq!(terminal.cursor(), Some((9, 4)));
+    }
+
+    #[test]
+    fn erases_the_display_and_lines() {
+        let mut terminal = terminal(5, 3);
+        terminal.write(b"abcde\nfghij\nklmno");
+
+        terminal.write(b"\x1b[2;3H\x1b[K");
+        assert_eq!(row_text(&terminal, 1), "fg");
+        terminal.write(b"\x1b[1K");
+        assert_eq!(row_text(&terminal, 1), "");
+        assert_eq!(row_text(&terminal, 0), "abcde");
+
+        terminal.write(b"\x1b[1;2H\x1b[J");
+        assert_eq!(row_text(&terminal, 0), "a");
+        assert_eq!(row_text(&terminal, 2), "");
+
+        terminal.write(b"\x1b[3;1Hz\x1b[2J");
+        assert!((0..3).all(|row| row_text(&terminal, row).is_empty()));
+    }
+
+    #[test]
+    fn selects_palette_and_direct_colors() {
+        let mut terminal = terminal(10, 2);
+        terminal.write(b"\x1b[38;5;196ma\x1b[48;5;244mb\x1b[0;38;2;1;2;3mc\x1b[48;2;300;0;40md\x1b[mE");
+
+        assert_eq!(terminal.cell(0, 0).fg, Rgb888::new(255, 0, 0));
+        assert_eq!(terminal.cell(1, 0).bg, Rgb888::new(128, 128, 128));
+        assert_eq!(terminal.cell(2, 0).fg, Rgb888::new(1, 2, 3));
+        assert_eq!(terminal.cell(2, 0).bg, BG);
+        assert_eq!(terminal.cell(3, 0).bg, Rgb888::new(255, 0, 40));
+        assert_eq!(terminal.cell(4, 0).fg, FG);
+        assert_eq!(terminal.cell(4, 0).bg, BG);
+    }
+
+    #[test]
+    fn wraps_only_when_the_next_character_arrives() {
+        let mut terminal = terminal(4, 3);
+        terminal.write(b"abcd");
+        // El cursor se queda en la última columna hasta el siguiente carácter
+        assert_eq!(terminal.cursor(), Some((3, 0)));
+        assert_eq!(row_text(&terminal, 1), "");
+
+        terminal.write(b"e");
+        assert_eq!(row_text(&terminal, 0), "abcd");
+        assert_eq!(row_text(&terminal, 1), "e");
+
+        // Un CR o un movimiento cancela el salto pendiente
+        terminal.write(b"fgh\rX");
+        assert_eq!(row_text(&terminal, 1), "Xfgh");
+        assert_eq!(row_text(&terminal, 2), "");
+    }
+
+    #[test]
+    fn scrolls_past_the_last_row() {
+        let mut terminal = terminal(4, 3);
+        terminal.write(b"1\n2\n3\n4");
+        assert_eq!(row_text(&terminal, 0), "2");
+        assert_eq!(row_text(&terminal, 2), "4");
+        assert_eq!(terminal.cursor(), Some((1, 2)));
+
+        terminal.take_dirty();
+        terminal.write(b"\x1b[2S");
+        assert_eq!(row_text(&terminal, 0), "4");
+        assert_eq!(row_text(&terminal, 1), "");
+        assert_eq!(terminal.take_dirty(), [0, 1, 2]);
+
+        terminal.write(b"\x1b[T");
+        assert_eq!(row_text(&terminal, 0), "");
+        assert_eq!(row_text(&terminal, 1), "4");
+    }
+
+    #[test]
+    fn ignores_parameters_past_the_limit() {
+        let mut terminal = terminal(10, 2);
+        let mut sequence = String::from("\x1b[");
+        for _ in 0..10_000 {
+            sequence.push_str("1;");
+        }
+        sequence.push_str("31mx");
+        terminal.write(sequence.as_bytes());
+
+        assert!(terminal.params.len() <= MAX_PARAMS);
+        // El 31 queda fuera de los parámetros guardados
+        assert_eq!(terminal.cell(0, 0).ch, 'x');
+        assert_eq!(terminal.cell(0, 0).fg, FG);
+    }
+}
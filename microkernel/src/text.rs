@@ -0,0 +1,257 @@
+//! FerroOS Mobile - Composición de texto
+//!
+//! Parte el texto en líneas que quepan en un ancho (por palabras, y por
+//! caracteres cuando una palabra sola no cabe), calcula la posición de cada
+//! línea según la alineación y recorta con "…" lo que no entra en una caja.
+//! El ancho de cada trozo lo mide quien llama, con su fuente actual.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Se añade al final del texto recortado
+pub const ELLIPSIS: &str = "…";
+
+/// Alineación horizontal de las líneas dentro de su caja
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(TextAlign::Left),
+            "center" => Some(TextAlign::Center),
+            "right" => Some(TextAlign::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Cómo colocar un texto dentro de una caja
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub align: TextAlign,
+    /// Pixels extra entre líneas
+    pub line_spacing: u32,
+    /// Terminar en "…" la última línea visible si el texto no cabe entero
+    pub ellipsis: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self { align: TextAlign::Left, line_spacing: 2, ellipsis: true }
+    }
+}
+
+/// Línea ya partida, con su ancho en pixels
+pub struct TextLine {
+    pub text: String,
+    pub width: u32,
+}
+
+/// Texto partido en líneas para una caja
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    pub line_height: u32,
+    pub line_spacing: u32,
+    /// Se descartaron líneas o se recortó alguna para que cupiera
+    pub truncated: bool,
+}
+
+impl TextLayout {
+    /// Ancho de la línea más larga
+    pub fn width(&self) -> u32 {
+        self.lines.iter().map(|line| line.width).max().unwrap_or(0)
+    }
+
+    /// Alto de todas las líneas con su separación
+    pub fn height(&self) -> u32 {
+        let count = self.lines.len() as u32;
+        count * self.line_height + count.saturating_sub(1) * self.line_spacing
+    }
+
+    /// Desplazamiento de una línea desde la izquierda y desde arriba de una caja de `box_width`
+    pub fn line_offset(&self, index: usize, box_width: u32, align: TextAlign) -> (i32, i32) {
+        let width = self.lines.get(index).map_or(0, |line| line.width);
+        let free = box_width as i32 - width as i32;
+        let x = match align {
+            TextAlign::Left => 0,
+            TextAlign::Center => free / 2,
+            TextAlign::Right => free,
+        };
+        (x, index as i32 * (self.line_height + self.line_spacing) as i32)
+    }
+}
+
+/// Partir `text` en líneas de como mucho `max_width` pixels (0 = sin límite)
+///
+/// Los saltos de línea del texto siempre parten. Una línea que ya cabe se deja
+/// tal cual; al partir, la sangría inicial se conserva en la primera línea.
+pub fn wrap(text: &str, max_width: u32, measure: &impl Fn(&str) -> u32) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        wrap_paragraph(paragraph, max_width, measure, &mut lines);
+    }
+    lines.into_iter().map(|text| TextLine { width: measure(&text), text }).collect()
+}
+
+fn wrap_paragraph(paragraph: &str, max_width: u32, measure: &impl Fn(&str) -> u32, lines: &mut Vec<String>) {
+    if max_width == 0 || measure(paragraph) <= max_width {
+        lines.push(String::from(paragraph));
+        return;
+    }
+    let start = lines.len();
+    let words = paragraph.trim_start_matches(' ');
+    let mut line = String::from(&paragraph[..paragraph.len() - words.len()]);
+    // ¿Tiene ya `line` alguna palabra (y hace falta un espacio antes de la siguiente)?
+    let mut has_words = false;
+
+    for word in words.split(' ').filter(|word| !word.is_empty()) {
+        let candidate = if has_words { alloc::format!("{} {}", line, word) } else { alloc::format!("{}{}", line, word) };
+        if measure(&candidate) <= max_width {
+            line = candidate;
+            has_words = true;
+            continue;
+        }
+        if has_words {
+            lines.push(core::mem::take(&mut line));
+            if measure(word) <= max_width {
+                line.push_str(word);
+                continue;
+            }
+        }
+        // La palabra no cabe ni sola: se parte por caracteres
+        for c in word.chars() {
+            let mut candidate = line.clone();
+            candidate.push(c);
+            if measure(&candidate) > max_width && !line.trim().is_empty() {
+                lines.push(core::mem::take(&mut line));
+                candidate = String::from(c);
+            }
+            line = candidate;
+        }
+        has_words = !line.trim().is_empty();
+    }
+    if has_words || lines.len() == start {
+        lines.push(line);
+    }
+}
+
+/// Recortar `text` y terminarlo en "…" para que quepa en `max_width`
+///
+/// Con `force` se añade "…" aunque el texto ya quepa (porque sigue en líneas que no se ven).
+/// Si ni siquiera "…" cabe, la línea queda vacía.
+pub fn ellipsize(text: &str, max_width: u32, force: bool, measure: &impl Fn(&str) -> u32) -> TextLine {
+    let width = measure(text);
+    if !force && width <= max_width {
+        return TextLine { text: String::from(text), width };
+    }
+    let mut kept = String::from(text);
+    loop {
+        let candidate = alloc::format!("{}{}", kept.trim_end(), ELLIPSIS);
+        let width = measure(&candidate);
+        if width <= max_width {
+            return TextLine { text: candidate, width };
+        }
+        if kept.is_empty() {
+            return TextLine { text: String::new(), width: 0 };
+        }
+        kept.pop();
+    }
+}
+
+/// Partir `text` para una caja de `max_width` x `max_height` pixels (0 = sin límite)
+///
+/// Solo se conservan las líneas que caben en alto; si sobra texto y el estilo
+/// lo pide, la última línea visible termina en "…".
+pub fn layout(text: &str, max_width: u32, max_height: u32, line_height: u32, style: &TextStyle, measure: &impl Fn(&str) -> u32) -> TextLayout {
+    let mut lines = wrap(text, max_width, measure);
+    let mut truncated = false;
+    if max_height > 0 {
+        let fitting = ((max_height + style.line_spacing) / (line_height + style.line_spacing).max(1)) as usize;
+        if lines.len() > fitting {
+            lines.truncate(fitting);
+            truncated = true;
+        }
+    }
+    let overflows = lines.last().is_some_and(|last| truncated || last.width > max_width);
+    if style.ellipsis && max_width > 0 && overflows {
+        let last = lines.len() - 1;
+        lines[last] = ellipsize(&lines[last].text, max_width, truncated, measure);
+        truncated = true;
+    }
+    TextLayout { lines, line_height, line_spacing: style.line_spacing, truncated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fuente monoespaciada de 8 pixels por carácter
+    fn measure(text: &str) -> u32 {
+        text.chars().count() as u32 * 8
+    }
+
+    fn texts(lines: &[TextLine]) -> Vec<&str> {
+        lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn splits_words_longer_than_the_line() {
+        assert_eq!(texts(&wrap("abcdefghij", 32, &measure)), ["abcd", "efgh", "ij"]);
+        assert_eq!(texts(&wrap("ab abcdefgh cd", 32, &measure)), ["ab", "abcd", "efgh", "cd"]);
+        // Un carácter que no cabe ni solo ocupa su propia línea
+        assert_eq!(texts(&wrap("abc", 4, &measure)), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn keeps_text_that_exactly_fits() {
+        let lines = wrap("ab cd", 40, &measure);
+        assert_eq!(texts(&lines), ["ab cd"]);
+        assert_eq!(lines[0].width, 40);
+        assert_eq!(texts(&wrap("ab cd ef", 40, &measure)), ["ab cd", "ef"]);
+
+        let line = ellipsize("abcd", 32, false, &measure);
+        assert_eq!((line.text.as_str(), line.width), ("abcd", 32));
+        let layout = layout("abcd", 32, 10, 10, &TextStyle::default(), &measure);
+        assert_eq!(texts(&layout.lines), ["abcd"]);
+        assert!(!layout.truncated);
+    }
+
+    #[test]
+    fn lays_out_an_empty_string_as_one_empty_line() {
+        let lines = wrap("", 32, &measure);
+        assert_eq!(texts(&lines), [""]);
+        assert_eq!(lines[0].width, 0);
+
+        let layout = layout("", 32, 0, 10, &TextStyle::default(), &measure);
+        assert_eq!((layout.width(), layout.height()), (0, 10));
+        assert!(!layout.truncated);
+        assert_eq!(ellipsize("", 32, false, &measure).text, "");
+        assert_eq!(ellipsize("", 32, true, &measure).text, ELLIPSIS);
+    }
+
+    #[test]
+    fn treats_a_zero_width_as_unlimited() {
+        assert_eq!(texts(&wrap("ab cd ef", 0, &measure)), ["ab cd ef"]);
+        let layout = layout("ab cd ef", 0, 0, 10, &TextStyle::default(), &measure);
+        assert_eq!(texts(&layout.lines), ["ab cd ef"]);
+        assert!(!layout.truncated);
+        // Aunque ni "…" quepa, el recorte no se sale del ancho
+        let line = ellipsize("abc", 0, false, &measure);
+        assert_eq!((line.text.as_str(), line.width), ("", 0));
+    }
+
+    #[test]
+    fn ellipsizes_what_does_not_fit() {
+        let line = ellipsize("abcdef", 32, false, &measure);
+        assert_eq!((line.text.as_str(), line.width), ("abc…", 32));
+        let layout = layout("ab cd ef gh", 40, 10, 10, &TextStyle::default(), &measure);
+        assert_eq!(texts(&layout.lines), ["ab c…"]);
+        assert!(layout.truncated);
+    }
+}
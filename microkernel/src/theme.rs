@@ -0,0 +1,351 @@
+//! FerroOS Mobile - Colores y tema del sistema
+//!
+//! Los colores se escriben como texto: un nombre ("red"), un papel del tema
+//! ("primary", "error"...), hexadecimal (`#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`) o funciones
+//! `rgb()`, `rgba()`, `hsl()` y `hsla()`. El tema asigna un color a cada papel
+//! de la interfaz y tiene una variante clara y otra oscura; todo el sistema
+//! (shell, widgets, compositor) dibuja con los colores del tema actual.
+
+use embedded_graphics::pixelcolor::Rgb888;
+
+/// Variante del tema
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Variant {
+    Light,
+    Dark,
+}
+
+impl Variant {
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Light => "light",
+            Variant::Dark => "dark",
+        }
+    }
+}
+
+/// Color de cada papel de la interfaz
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub variant: Variant,
+    /// Fondo de la pantalla y de las superficies
+    pub background: Rgb888,
+    /// Fondo de elementos sobre el fondo: campos de texto, listas, cabeceras
+    pub surface: Rgb888,
+    /// Color principal del sistema: botones, títulos
+    pub primary: Rgb888,
+    /// Texto sobre `primary`
+    pub on_primary: Rgb888,
+    pub secondary: Rgb888,
+    /// Detalles que deben destacar
+    pub accent: Rgb888,
+    pub text: Rgb888,
+    /// Texto secundario: placeholders, ayudas
+    pub text_muted: Rgb888,
+    pub border: Rgb888,
+    /// Fondo del elemento seleccionado sin foco
+    pub selection: Rgb888,
+    /// Marco del widget con el foco
+    pub focus: Rgb888,
+    pub success: Rgb888,
+    pub warning: Rgb888,
+    pub error: Rgb888,
+    pub info: Rgb888,
+}
+
+pub const DARK: Theme = Theme {
+    variant: Variant::Dark,
+    background: Rgb888::new(0, 0, 0),
+    surface: Rgb888::new(30, 30, 30),
+    primary: Rgb888::new(0, 100, 255),
+    on_primary: Rgb888::new(255, 255, 255),
+    secondary: Rgb888::new(128, 0, 128),
+    accent: Rgb888::new(255, 165, 0),
+    text: Rgb888::new(255, 255, 255),
+    text_muted: Rgb888::new(128, 128, 128),
+    border: Rgb888::new(255, 255, 255),
+    selection: Rgb888::new(70, 70, 70),
+    focus: Rgb888::new(255, 255, 0),
+    success: Rgb888::new(0, 255, 0),
+    warning: Rgb888::new(255, 255, 0),
+    error: Rgb888::new(255, 0, 0),
+    info: Rgb888::new(0, 255, 255),
+};
+
+pub const LIGHT: Theme = Theme {
+    variant: Variant::Light,
+    background: Rgb888::new(245, 245, 245),
+    surface: Rgb888::new(255, 255, 255),
+    primary: Rgb888::new(0, 90, 220),
+    on_primary: Rgb888::new(255, 255, 255),
+    secondary: Rgb888::new(128, 0, 128),
+    accent: Rgb888::new(230, 120, 0),
+    text: Rgb888::new(20, 20, 20),
+    text_muted: Rgb888::new(110, 110, 110),
+    border: Rgb888::new(60, 60, 60),
+    selection: Rgb888::new(200, 215, 240),
+    focus: Rgb888::new(255, 160, 0),
+    success: Rgb888::new(0, 150, 60),
+    warning: Rgb888::new(200, 140, 0),
+    error: Rgb888::new(200, 0, 0),
+    info: Rgb888::new(0, 140, 170),
+};
+
+/// Nombres de los papeles del tema, en el orden en que se listan
+pub const ROLES: &[&str] = &[
+    "background",
+    "surface",
+    "primary",
+    "on_primary",
+    "secondary",
+    "accent",
+    "text",
+    "text_muted",
+    "border",
+    "selection",
+    "focus",
+    "success",
+    "warning",
+    "error",
+    "info",
+];
+
+impl Theme {
+    /// Color de un papel por su nombre
+    pub fn role(&self, name: &str) -> Option<Rgb888> {
+        let color = match name {
+            "background" => self.background,
+            "surface" => self.surface,
+            "primary" => self.primary,
+            "on_primary" => self.on_primary,
+            "secondary" => self.secondary,
+            "accent" => self.accent,
+            "text" => self.text,
+            "text_muted" => self.text_muted,
+            "border" => self.border,
+            "selection" => self.selection,
+            "focus" => self.focus,
+            "success" => self.success,
+            "warning" => self.warning,
+            "error" => self.error,
+            "info" => self.info,
+            _ => return None,
+        };
+        Some(color)
+    }
+}
+
+/// Colores con nombre, iguales en las dos variantes del tema
+const NAMED_COLORS: &[(&str, Rgb888)] = &[
+    ("black", Rgb888::new(0, 0, 0)),
+    ("white", Rgb888::new(255, 255, 255)),
+    ("red", Rgb888::new(255, 0, 0)),
+    ("green", Rgb888::new(0, 255, 0)),
+    ("blue", Rgb888::new(0, 100, 255)),
+    ("yellow", Rgb888::new(255, 255, 0)),
+    ("purple", Rgb888::new(128, 0, 128)),
+    ("orange", Rgb888::new(255, 165, 0)),
+    ("cyan", Rgb888::new(0, 255, 255)),
+    ("magenta", Rgb888::new(255, 0, 255)),
+    ("pink", Rgb888::new(255, 105, 180)),
+    ("brown", Rgb888::new(139, 69, 19)),
+    ("gray", Rgb888::new(128, 128, 128)),
+    ("grey", Rgb888::new(128, 128, 128)),
+];
+
+// Tema en uso; solo lo cambia el shell
+static mut CURRENT: Theme = DARK;
+
+/// Tema en uso
+pub fn current() -> Theme {
+    unsafe { *core::ptr::addr_of!(CURRENT) }
+}
+
+pub fn set_variant(variant: Variant) {
+    let theme = match variant {
+        Variant::Light => LIGHT,
+        Variant::Dark => DARK,
+    };
+    unsafe {
+        *core::ptr::addr_of_mut!(CURRENT) = theme;
+    }
+}
+
+/// Interpretar una especificación de color: devuelve el color y su alfa (255 = opaco)
+///
+/// `None` si el texto no es un color válido.
+pub fn parse_color(spec: &str) -> Option<(Rgb888, u8)> {
+    let spec = spec.trim();
+    if let Some(hex) = spec.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    if let Some((name, args)) = spec.strip_suffix(')').and_then(|call| call.split_once('(')) {
+        return parse_function(name.trim(), args);
+    }
+    if spec == "transparent" {
+        return Some((Rgb888::new(0, 0, 0), 0));
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == spec)
+        .map(|(_, color)| *color)
+        .or_else(|| current().role(spec))
+        .map(|color| (color, 255))
+}
+
+/// `RRGGBB` o `RRGGBBAA`, y las formas cortas `RGB` y `RGBA` (cada dígito se duplica)
+fn parse_hex(hex: &str) -> Option<(Rgb888, u8)> {
+    if !matches!(hex.len(), 3 | 4 | 6 | 8) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = if hex.len() <= 4 { 1 } else { 2 };
+    let byte = |index: usize| {
+        let value = u8::from_str_radix(&hex[index * digits..(index + 1) * digits], 16).ok()?;
+        Some(if digits == 1 { value * 0x11 } else { value })
+    };
+    let alpha = if hex.len() == 4 * digits { byte(3)? } else { 255 };
+    Some((Rgb888::new(byte(0)?, byte(1)?, byte(2)?), alpha))
+}
+
+/// `rgb(r, g, b)`, `rgba(r, g, b, a)`, `hsl(h, s%, l%)` y `hsla(h, s%, l%, a)`
+///
+/// Los canales RGB van de 0 a 255 (o en %), el tono en grados (sin %) y el alfa de 0 a 1 (o en %).
+fn parse_function(name: &str, args: &str) -> Option<(Rgb888, u8)> {
+    let mut values = [0.0f32; 4];
+    let mut count = 0;
+    for arg in args.split(',') {
+        if count == values.len() {
+            return None;
+        }
+        values[count] = parse_component(arg.trim(), if count == 3 { 1.0 } else { 255.0 })?;
+        count += 1;
+    }
+    let (expected, alpha) = match name {
+        "rgb" | "hsl" => (3, 1.0),
+        "rgba" | "hsla" => (4, values[3]),
+        _ => return None,
+    };
+    if count != expected {
+        return None;
+    }
+    let alpha = channel(alpha.clamp(0.0, 1.0) * 255.0);
+
+    if name.starts_with("rgb") {
+        let [r, g, b, _] = values.map(|value| channel(value.clamp(0.0, 255.0)));
+        return Some((Rgb888::new(r, g, b), alpha));
+    }
+    // En hsl() el tono va en grados y la saturación y la luminosidad en %: se leyeron sobre 255
+    let mut percents = args.split(',').map(|arg| arg.trim().ends_with('%'));
+    if percents.next() != Some(false) || !percents.take(2).all(|percent| percent) {
+        return None;
+    }
+    let saturation = (values[1] / 255.0).clamp(0.0, 1.0);
+    let lightness = (values[2] / 255.0).clamp(0.0, 1.0);
+    Some((hsl_to_rgb(values[0], saturation, lightness), alpha))
+}
+
+/// Número finito, o porcentaje de `full`
+fn parse_component(text: &str, full: f32) -> Option<f32> {
+    let value = match text.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok().map(|value| value / 100.0 * full),
+        None => text.parse::<f32>().ok(),
+    };
+    value.filter(|value| value.is_finite())
+}
+
+fn channel(value: f32) -> u8 {
+    libm::roundf(value) as u8
+}
+
+/// Tono en grados, saturación y luminosidad de 0 a 1
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> Rgb888 {
+    let hue = libm::fmodf(libm::fmodf(hue, 360.0) + 360.0, 360.0) / 60.0;
+    let chroma = (1.0 - libm::fabsf(2.0 * lightness - 1.0)) * saturation;
+    let x = chroma * (1.0 - libm::fabsf(libm::fmodf(hue, 2.0) - 1.0));
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Rgb888::new(channel((r + m) * 255.0), channel((g + m) * 255.0), channel((b + m) * 255.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Option<(Rgb888, u8)> {
+        Some((Rgb888::new(r, g, b), a))
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#ff8000"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("#FF800080"), rgba(255, 128, 0, 128));
+        assert_eq!(parse_color("#f80"), rgba(255, 136, 0, 255));
+        assert_eq!(parse_color("#f808"), rgba(255, 136, 0, 136));
+        assert_eq!(parse_color("  #000000 "), rgba(0, 0, 0, 255));
+    }
+
+    #[test]
+    fn rejects_malformed_hex_colors() {
+        for spec in ["#", "#ff", "#ff800", "#ff80000", "#ff8000800", "#gg8000", "#+f8000", "ff8000"] {
+            assert_eq!(parse_color(spec), None, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_rgb_functions() {
+        assert_eq!(parse_color("rgb(255, 128, 0)"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("rgb(100%, 50%, 0%)"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("rgba(0, 0, 255, 0.5)"), rgba(0, 0, 255, 128));
+        assert_eq!(parse_color("rgba(0, 0, 255, 25%)"), rgba(0, 0, 255, 64));
+        assert_eq!(parse_color("rgb(300, -5, 0)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_color("rgba(0, 0, 0, 2)"), rgba(0, 0, 0, 255));
+    }
+
+    #[test]
+    fn rejects_malformed_rgb_functions() {
+        for spec in [
+            "rgb(255, 128)",
+            "rgb(255, 128, 0, 1)",
+            "rgba(255, 128, 0)",
+            "rgba(1, 2, 3, 4, 5)",
+            "rgb(red, 0, 0)",
+            "rgb(nan, 0, 0)",
+            "rgb(inf, 0, 0)",
+            "rgb(255, 128, 0",
+            "cmyk(0, 0, 0)",
+        ] {
+            assert_eq!(parse_color(spec), None, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_hsl_functions() {
+        assert_eq!(parse_color("hsl(0, 100%, 50%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_color("hsl(120, 100%, 25%)"), rgba(0, 128, 0, 255));
+        assert_eq!(parse_color("hsl(-120, 100%, 50%)"), rgba(0, 0, 255, 255));
+        assert_eq!(parse_color("hsl(0, 0%, 100%)"), rgba(255, 255, 255, 255));
+        assert_eq!(parse_color("hsla(240, 100%, 50%, 0.5)"), rgba(0, 0, 255, 128));
+    }
+
+    #[test]
+    fn rejects_malformed_hsl_functions() {
+        for spec in ["hsl(50%, 100%, 50%)", "hsl(0, 100, 50%)", "hsl(0, 100%, 50)", "hsl(0, 100%)", "hsla(0, 100%, 50%)"] {
+            assert_eq!(parse_color(spec), None, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_names_and_roles() {
+        assert_eq!(parse_color("transparent"), rgba(0, 0, 0, 0));
+        assert_eq!(parse_color("gray"), rgba(128, 128, 128, 255));
+        assert_eq!(parse_color("primary").map(|(color, _)| color), Some(current().primary));
+        assert_eq!(parse_color("no-such-color"), None);
+    }
+}
@@ -2,19 +2,102 @@
 //! 
 //! Procesa aplicaciones .wpk que contienen scripts Lua embebidos en WASM
 
-use fos_microkernel::{uart_send_str, print_number};
-use crate::graphics::{GraphicsManager, colors};
+use fos_microkernel::{uart_send_str, print_number, uptime_micros};
+use crate::animation::{Easing, FrameClock, FrameStats, Tween, TweenValue, EASINGS, FRAME_INTERVAL_US};
+use crate::compositor::{SurfaceId, Z_APP};
+use crate::graphics::{mul_alpha, GraphicsManager, ShapeStyle};
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::Rectangle;
+use crate::image::ImageError;
+use crate::lua::{self, Lua, LuaError, LuaResult, NativeFn, Value};
+use crate::manifest::{self, AppManifest, Permission, PermissionSet};
+use crate::text::{TextAlign, TextStyle};
+use crate::theme;
+use crate::widgets::{Align, Key, Layout, Ui, WidgetId};
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
 
 /// Runtime WASM que extrae y ejecuta scripts Lua
 pub struct WasmRunner {
     _memory: [u8; 32 * 1024], // 32KB para apps simples (prefijo _ para evitar warning)
+    /// Script Lua recibido por hot-reload; sustituye al embebido en el WASM
+    lua_override: Option<String>,
+    /// Estado Lua de la app, compartido con el REPL del shell
+    lua: Lua,
+    /// Permisos declarados en el `manifest.toml` de la app
+    permissions: PermissionSet,
+    /// Nombre de la app según su manifiesto
+    app_name: String,
+    /// Superficie del compositor en la que dibuja la app; se crea en la primera ejecución
+    app_surface: Option<SurfaceId>,
+    /// Cuándo toca el siguiente frame de `request_frame` y `animate`
+    frame_clock: FrameClock,
 }
 
 impl WasmRunner {
     pub fn new() -> Self {
-        Self {
+        let mut runner = Self {
             _memory: [0; 32 * 1024],
+            lua_override: None,
+            lua: Lua::new(),
+            permissions: PermissionSet::empty(),
+            app_name: String::from("App"),
+            app_surface: None,
+            frame_clock: FrameClock::new(FRAME_INTERVAL_US),
+        };
+        runner.reset_lua_state();
+        runner
+    }
+
+    /// Cargar el manifiesto de la app y aplicar sus permisos a Lua y a los imports WASM
+    pub fn load_manifest(&mut self, source: &str) {
+        let manifest = AppManifest::parse(source);
+        uart_send_str("🔐 Permisos de ");
+        uart_send_str(&manifest.name);
+        uart_send_str(" (");
+        uart_send_str(&manifest.id);
+        uart_send_str("):");
+        for permission in [Permission::Log, Permission::Graphics, Permission::Storage, Permission::Input] {
+            if manifest.permissions.allows(permission) {
+                uart_send_str(" ");
+                uart_send_str(permission.name());
+            }
+        }
+        uart_send_str("\n");
+
+        self.permissions = manifest.permissions;
+        self.app_name = manifest.name;
+        set_app_permissions(self.permissions);
+        self.lua.set_permissions(self.permissions);
+    }
+
+    /// Reemplazar el script Lua de la app en caliente y re-ejecutarlo sin reiniciar
+    ///
+    /// Se reutilizan el runner y el `GraphicsManager` existentes: se descarta el
+    /// estado de la app anterior y el nuevo script queda como script activo, de modo
+    /// que una re-ejecución posterior (`r`) también usa la versión recargada.
+    pub fn hot_reload_lua(&mut self, script: String, graphics: &mut GraphicsManager) -> bool {
+        uart_send_str("♻️  HOT-RELOAD DE SCRIPT LUA\n");
+        uart_send_str("  Tamaño: ");
+        print_number(script.len() as u64);
+        uart_send_str(" bytes\n");
+
+        if script.trim().is_empty() {
+            uart_send_str("❌ Script vacío, se mantiene la app actual\n");
+            return false;
+        }
+
+        // Desmontar el estado de la app anterior, sin tocar la cabecera del shell
+        self.clear_app_surface(graphics);
+        self.reset_lua_state();
+        self.lua_override = Some(script.clone());
+
+        set_graphics_context(graphics);
+        if !self.execute_lua_script_graphics(&script) {
+            return false;
         }
+
+        uart_send_str("✅ Script recargado y en ejecución\n");
+        true
     }
 
     /// Ejecutar aplicación WASM con script Lua embebido y soporte gráfico
@@ -32,12 +115,28 @@ impl WasmRunner {
 
         uart_send_str("✅ WASM válido detectado\n");
         
+        // Cada ejecución arranca con un estado Lua y una superficie limpios
+        self.reset_lua_state();
+        self.clear_app_surface(graphics);
+
+        // Un script recargado en caliente tiene prioridad sobre el embebido
+        if let Some(lua_script) = self.lua_override.clone() {
+            uart_send_str("♻️  Usando script Lua recargado en caliente...\n\n");
+            if !self.execute_lua_script_graphics(&lua_script) {
+                return false;
+            }
+            uart_send_str("\n✅ Aplicación gráfica ejecutada exitosamente\n");
+            return true;
+        }
+
         // Extraer script Lua del WASM
         if let Some(lua_script) = self.extract_lua_from_wasm(wasm_data) {
             uart_send_str("📄 Script Lua encontrado, ejecutando con gráficos...\n\n");
             
             // Procesar script Lua con comandos gráficos
-            self.execute_lua_script_graphics(lua_script);
+            if !self.execute_lua_script_graphics(lua_script) {
+                return false;
+            }
             
             uart_send_str("\n✅ Aplicación gráfica ejecutada exitosamente\n");
             true
@@ -96,94 +195,291 @@ impl WasmRunner {
         None
     }
 
-    /// Ejecutar script Lua con funciones gráficas (versión simplificada)
-    fn execute_lua_script_graphics(&self, script: &str) {
+    /// Ejecutar script Lua con funciones gráficas
+    fn execute_lua_script_graphics(&mut self, script: &str) -> bool {
         uart_send_str("🎨 INTERPRETANDO SCRIPT LUA CON GRÁFICOS\n");
 
-        let graphics_option = get_graphics_context();
-        if graphics_option.is_none() {
+        if get_graphics_context().is_none() {
             uart_send_str("❌ Error: Contexto gráfico no disponible.\n");
-            return;
+            return false;
         }
-        let graphics = graphics_option.unwrap();
 
-        for line in script.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with("--") {
-                continue;
+        let chunk = match self.lua.load(script) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                uart_send_str("❌ Error de sintaxis Lua: ");
+                uart_send_str(&e.message);
+                uart_send_str("\n");
+                return false;
             }
+        };
 
-            self.parse_and_execute_lua_command(line, graphics);
+        // La app dibuja en su superficie, en coordenadas locales a ella
+        if let Some(graphics) = get_graphics_context() {
+            self.enter_app_surface(graphics);
+        }
+
+        // Se dibuja fuera de pantalla; el frame se compone entero al terminar el script
+        // (o antes, si la app llama a `present()`)
+        let mut result = self.lua.exec_chunk_with(&chunk, |_| {});
+        if let Some(graphics) = get_graphics_context() {
+            // Un script que devuelve una tabla describe su interfaz de forma declarativa
+            if let Some(tree @ Value::Table(_)) = result.as_ref().ok().and_then(|values| values.first()).cloned() {
+                let area = widget_area(graphics, 0, 0, 0, 0);
+                if let Err(e) = render_ui_tree(&tree, area, graphics) {
+                    result = Err(e);
+                }
+            }
+            // Los cambios en los widgets tras `ui_show` se dibujan al terminar
+            if app_ui().ui.is_dirty() {
+                app_ui().ui.render(graphics);
+            }
+            self.leave_app_surface(graphics);
+            graphics.present();
+        }
 
-            // Pequeña pausa para ver el dibujo progresivo
-            for _ in 0..200000 {
-                unsafe { core::ptr::read_volatile(&0u32) };
+        match result {
+            Ok(_) => {
+                uart_send_str("✅ Script Lua interpretado completamente\n");
+                true
             }
+            Err(e) => {
+                uart_send_str("❌ Error Lua: ");
+                uart_send_str(&e.message);
+                uart_send_str("\n");
+                false
+            }
+        }
+    }
+
+    /// Descartar el estado Lua y los widgets de la app y crear un estado nuevo con los bindings del sistema
+    fn reset_lua_state(&mut self) {
+        let app = app_ui();
+        app.ui.clear();
+        app.handlers.clear();
+        let frames = app_frames();
+        frames.callbacks.clear();
+        frames.animations.clear();
+        self.lua = Lua::new();
+        self.lua.set_permissions(self.permissions);
+        for (name, permission, func) in LUA_BINDINGS.iter() {
+            self.lua.register_with_permission(name, *permission, *func);
+        }
+    }
+
+    /// Evaluar una entrada del REPL sobre el estado Lua persistente de la app
+    ///
+    /// Las expresiones devuelven sus valores; las sentencias se ejecutan y devuelven
+    /// una lista vacía. Un error con `incomplete` indica que falta más código.
+    pub fn lua_repl_eval(&mut self, source: &str, graphics: &mut GraphicsManager) -> LuaResult<Vec<Value>> {
+        self.enter_app_surface(graphics);
+        set_graphics_context(graphics);
+        let result = self.lua.eval_repl(source);
+        if let Some(graphics) = get_graphics_context() {
+            self.leave_app_surface(graphics);
         }
+        result
+    }
+
+    /// ¿Ha mostrado la app un árbol de widgets con `ui_show`?
+    pub fn has_ui(&self) -> bool {
+        app_ui().ui.root().is_some()
+    }
 
-        uart_send_str("✅ Script Lua interpretado completamente\n");
+    /// ¿Tiene la app el permiso `input` para recibir teclas?
+    pub fn accepts_input(&self) -> bool {
+        self.permissions.allows(Permission::Input)
     }
 
-    /// Parsea y ejecuta un comando gráfico de Lua
-    fn parse_and_execute_lua_command(&self, line: &str, graphics: &mut GraphicsManager) {
-        if let Some(params) = self.get_params(line, "clear_screen") {
-            if params.is_empty() {
-                graphics.clear_screen();
+    /// Pasar una tecla a los widgets de la app, llamar a los callbacks Lua de los
+    /// widgets activados y mostrar el resultado
+    ///
+    /// Solo las apps con el permiso `input` reciben teclas.
+    pub fn ui_handle_key(&mut self, key: Key, graphics: &mut GraphicsManager) {
+        if let Err(e) = self.lua.check_permission("ui_handle_key", Permission::Input) {
+            uart_send_str("❌ Error Lua: ");
+            uart_send_str(&e.message);
+            uart_send_str("\n");
+            return;
+        }
+        if !app_ui().ui.handle_key(key) {
+            return;
+        }
+        self.enter_app_surface(graphics);
+        set_graphics_context(graphics);
+
+        while let Some(id) = app_ui().ui.poll_activated() {
+            let Some(handler) = app_ui().handlers.get(&id).cloned() else {
+                continue;
+            };
+            let ui = &app_ui().ui;
+            // `on_select(índice, elemento)`, `on_submit(texto)` u `on_click()`
+            let args = match (ui.selected(id), ui.text(id)) {
+                (Some(index), Some(item)) => alloc::vec![Value::Number(index as f64 + 1.0), Value::str(item)],
+                (None, Some(text)) if ui.is_text_field(id) => alloc::vec![Value::str(text)],
+                _ => Vec::new(),
+            };
+            if let Err(e) = self.lua.call(&handler, args) {
+                uart_send_str("❌ Error Lua: ");
+                uart_send_str(&e.message);
+                uart_send_str("\n");
             }
-        } else if let Some(params) = self.get_params(line, "set_color") {
-            if let Some(color_str) = params.get(0) {
-                graphics.set_color(parse_color(color_str));
+        }
+
+        if let Some(graphics) = get_graphics_context() {
+            if app_ui().ui.is_dirty() {
+                app_ui().ui.render(graphics);
             }
-        } else if let Some(params) = self.get_params(line, "draw_text") {
-            if let Some(text) = params.get(0) {
-                graphics.draw_text(text);
+            self.leave_app_surface(graphics);
+            graphics.present();
+        }
+    }
+
+    /// Dibujar un frame de la app si tiene callbacks de `request_frame` o
+    /// animaciones pendientes y ya toca, según el reloj de frames
+    ///
+    /// Con la app oculta tras la consola los frames se detienen. Devuelve `true`
+    /// si se dibujó (y presentó) un frame.
+    pub fn poll_frame(&mut self, graphics: &mut GraphicsManager) -> bool {
+        let frames = app_frames();
+        let pending = !frames.callbacks.is_empty() || !frames.animations.is_empty();
+        if !pending || self.foreground_app(graphics).is_none() {
+            self.frame_clock.pause();
+            return false;
+        }
+        let Some(tick) = self.frame_clock.poll(uptime_micros()) else {
+            return false;
+        };
+        self.enter_app_surface(graphics);
+        set_graphics_context(graphics);
+
+        // Los callbacks son de un solo frame: los que pidan otro se apuntan de nuevo
+        let time_ms = Value::Number(tick.time_us as f64 / 1000.0);
+        let delta_ms = Value::Number(tick.delta_us as f64 / 1000.0);
+        for (_, callback) in core::mem::take(&mut app_frames().callbacks) {
+            self.call_frame_handler(&callback, alloc::vec![time_ms.clone(), delta_ms.clone()]);
+        }
+        // Las animaciones pueden cancelarse o crear otras desde sus callbacks
+        for animation in core::mem::take(&mut app_frames().animations) {
+            let cancelled = |id| app_frames().cancelled.contains(&id);
+            if cancelled(animation.id) {
+                continue;
             }
-        } else if let Some(params) = self.get_params(line, "draw_text_at") {
-            if let (Some(text), Some(x_str), Some(y_str)) = (params.get(0), params.get(1), params.get(2)) {
-                if let (Ok(x), Ok(y)) = (x_str.parse::<i32>(), y_str.parse::<i32>()) {
-                    graphics.draw_text_at(text, x, y);
-                }
+            let value = tween_value_to_lua(&mut self.lua, animation.tween.value(tick.time_us));
+            self.call_frame_handler(&animation.on_update, alloc::vec![value]);
+            if cancelled(animation.id) {
+                continue;
             }
-        } else if let Some(params) = self.get_params(line, "draw_rect") {
-            if let (Some(x_str), Some(y_str), Some(w_str), Some(h_str), Some(f_str)) =
-                (params.get(0), params.get(1), params.get(2), params.get(3), params.get(4))
-            {
-                if let (Ok(x), Ok(y), Ok(w), Ok(h)) = (
-                    x_str.parse::<i32>(),
-                    y_str.parse::<i32>(),
-                    w_str.parse::<u32>(),
-                    h_str.parse::<u32>(),
-                ) {
-                    let filled = f_str == &"true";
-                    graphics.draw_rect(x, y, w, h, filled);
-                }
+            if !animation.tween.finished(tick.time_us) {
+                app_frames().animations.push(animation);
+            } else if let Some(on_done) = &animation.on_done {
+                self.call_frame_handler(on_done, Vec::new());
+            }
+        }
+        app_frames().cancelled.clear();
+
+        if let Some(graphics) = get_graphics_context() {
+            if app_ui().ui.is_dirty() {
+                app_ui().ui.render(graphics);
+            }
+            self.leave_app_surface(graphics);
+            graphics.present();
+        }
+        self.frame_clock.record(uptime_micros() - tick.time_us);
+        true
+    }
+
+    fn call_frame_handler(&mut self, handler: &Value, args: Vec<Value>) {
+        if let Err(e) = self.lua.call(handler, args) {
+            uart_send_str("❌ Error Lua en frame: ");
+            uart_send_str(&e.message);
+            uart_send_str("\n");
+        }
+    }
+
+    /// Estadísticas de los frames dibujados por `poll_frame`
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_clock.stats()
+    }
+
+    /// Volver a mostrar la app por encima de la consola del shell
+    pub fn bring_to_front(&mut self, graphics: &mut GraphicsManager) {
+        self.enter_app_surface(graphics);
+        self.leave_app_surface(graphics);
+        graphics.present();
+    }
+
+    /// Nombre de la app si está a la vista por encima de la consola
+    pub fn foreground_app(&self, graphics: &GraphicsManager) -> Option<&str> {
+        self.app_surface
+            .filter(|surface| graphics.surface_visible(*surface))
+            .map(|_| self.app_name.as_str())
+    }
+
+    /// Ocultar la app para que se vea la consola del shell; vuelve al frente
+    /// en su próxima ejecución, recarga o entrada del REPL
+    pub fn send_to_background(&self, graphics: &mut GraphicsManager) {
+        if let Some(surface) = self.app_surface {
+            graphics.set_surface_visible(surface, false);
+        }
+    }
+
+    /// Dibujar en la superficie de la app, creándola si hace falta, y traerla al frente
+    ///
+    /// Si no hay memoria para la superficie, la app dibuja en la del shell
+    /// limitada a su ventana bajo la cabecera.
+    fn enter_app_surface(&mut self, graphics: &mut GraphicsManager) {
+        if self.app_surface.is_none() {
+            self.app_surface = graphics.create_surface("app", graphics.app_area(), Z_APP);
+            if self.app_surface.is_none() {
+                uart_send_str("⚠️ Sin memoria para la superficie de la app, se usa la del shell\n");
+            }
+        }
+        match self.app_surface {
+            Some(surface) => {
+                graphics.set_surface(surface);
+                graphics.set_surface_visible(surface, true);
+                graphics.raise_surface(surface);
             }
+            None => graphics.set_viewport(graphics.app_area()),
         }
     }
 
-    /// Extrae los parámetros de una llamada a función Lua simple
-    /// Ejemplo: "draw_text("Hola", 10, 20)" -> ["Hola", "10", "20"]
-    fn get_params<'a>(&self, line: &'a str, func_name: &str) -> Option<alloc::vec::Vec<&'a str>> {
-        if line.starts_with(func_name) && line.contains('(') && line.ends_with(')') {
-            let start = line.find('(')? + 1;
-            let end = line.rfind(')')?;
-            if start >= end {
-                return Some(alloc::vec![]);
+    /// Volver a dibujar en la consola del shell; la app sigue visible
+    fn leave_app_surface(&self, graphics: &mut GraphicsManager) {
+        match self.app_surface {
+            Some(_) => {
+                graphics.set_surface(graphics.shell_surface());
             }
-            let params_str = &line[start..end];
-            let params = params_str
-                .split(',')
-                .map(|p| p.trim().trim_matches('"'))
-                .collect();
-            return Some(params);
+            None => graphics.reset_viewport(),
         }
-        None
+    }
+
+    /// Volver a la consola del shell y liberar la superficie de la app
+    fn close_app_surface(&mut self, graphics: &mut GraphicsManager) {
+        self.leave_app_surface(graphics);
+        if let Some(surface) = self.app_surface.take() {
+            graphics.destroy_surface(surface);
+        }
+    }
+
+    /// Restablecer el estado de dibujo y empezar con una superficie de la app nueva
+    ///
+    /// La superficie de la app anterior se libera: no queda ningún buffer huérfano
+    /// en el compositor entre ejecuciones ni recargas.
+    fn clear_app_surface(&mut self, graphics: &mut GraphicsManager) {
+        self.close_app_surface(graphics);
+        self.enter_app_surface(graphics);
+        graphics.reset();
+        self.leave_app_surface(graphics);
     }
 }
 
-// Se necesita la caja `alloc` para usar `Vec`
+// Se necesita la caja `alloc` para usar `Vec` y `String`
 extern crate alloc;
 
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
 // Variables globales para el contexto gráfico
 static mut GRAPHICS_CONTEXT: Option<*mut GraphicsManager> = None;
 
@@ -201,113 +497,1666 @@ fn get_graphics_context() -> Option<&'static mut GraphicsManager> {
     }
 }
 
-/// Helper para convertir string de color
-fn parse_color(color_str: &str) -> embedded_graphics::pixelcolor::Rgb888 {
-    match color_str {
-        "black" => colors::BLACK,
-        "white" => colors::WHITE,
-        "red" => colors::RED,
-        "green" => colors::GREEN,
-        "blue" => colors::BLUE,
-        "yellow" => colors::YELLOW,
-        "purple" => colors::PURPLE,
-        "orange" => colors::ORANGE,
-        "cyan" => embedded_graphics::pixelcolor::Rgb888::new(0, 255, 255),
-        _ => colors::WHITE,
+/// Widgets de la app y callbacks Lua de los que se activan
+struct AppUi {
+    ui: Ui,
+    handlers: BTreeMap<WidgetId, Value>,
+}
+
+/// Callbacks de frame y animaciones de la app
+struct AppFrames {
+    next_id: u32,
+    /// Callbacks de `request_frame` para el próximo frame, con su id
+    callbacks: Vec<(u32, Value)>,
+    animations: Vec<Animation>,
+    /// Animaciones canceladas durante el frame en curso
+    cancelled: Vec<u32>,
+}
+
+/// Animación de `animate`
+struct Animation {
+    id: u32,
+    tween: Tween,
+    on_update: Value,
+    on_done: Option<Value>,
+}
+
+// Frames pedidos por la app en ejecución
+static mut APP_FRAMES: AppFrames = AppFrames { next_id: 1, callbacks: Vec::new(), animations: Vec::new(), cancelled: Vec::new() };
+
+/// Frames pedidos por la app en ejecución
+///
+/// Como con `app_ui`, no se debe guardar la referencia mientras se llama a Lua.
+fn app_frames() -> &'static mut AppFrames {
+    unsafe { &mut *core::ptr::addr_of_mut!(APP_FRAMES) }
+}
+
+/// Niveles de anidamiento admitidos en un árbol declarativo de `ui_render`
+const MAX_UI_DEPTH: usize = 32;
+
+// Árbol de widgets de la app en ejecución, compartido por los bindings Lua y los imports WASM
+static mut APP_UI: AppUi = AppUi { ui: Ui::new(), handlers: BTreeMap::new() };
+
+/// Widgets de la app en ejecución
+///
+/// No se debe guardar la referencia mientras se llama a Lua: los callbacks vuelven a entrar aquí.
+fn app_ui() -> &'static mut AppUi {
+    unsafe { &mut *core::ptr::addr_of_mut!(APP_UI) }
+}
+
+/// Color en `index` (ver `theme::parse_color`) con su alfa; error si no es un color válido
+fn lua_color(args: &[Value], index: usize, func: &str) -> LuaResult<(Rgb888, u8)> {
+    let spec = lua::check_str(args, index, func)?;
+    theme::parse_color(&spec).ok_or_else(|| LuaError::new(&alloc::format!("{}: color inválido '{}'", func, spec)))
+}
+
+// ===== BINDINGS LUA =====
+
+/// Funciones del sistema registradas como globales en cada estado Lua de app,
+/// con el permiso del manifiesto que exige cada una
+const LUA_BINDINGS: &[(&str, Permission, NativeFn)] = &[
+    ("print", Permission::Log, lua::lua_print),
+    ("log", Permission::Log, lua_log),
+    ("clear_screen", Permission::Graphics, lua_clear_screen),
+    ("set_color", Permission::Graphics, lua_set_color),
+    ("set_opacity", Permission::Graphics, lua_set_opacity),
+    ("draw_text", Permission::Graphics, lua_draw_text),
+    ("draw_text_at", Permission::Graphics, lua_draw_text_at),
+    ("measure_text", Permission::Graphics, lua_measure_text),
+    ("draw_text_box", Permission::Graphics, lua_draw_text_box),
+    ("draw_rect", Permission::Graphics, lua_draw_rect),
+    ("draw_line", Permission::Graphics, lua_draw_line),
+    ("draw_circle", Permission::Graphics, lua_draw_circle),
+    ("draw_ellipse", Permission::Graphics, lua_draw_ellipse),
+    ("draw_arc", Permission::Graphics, lua_draw_arc),
+    ("draw_triangle", Permission::Graphics, lua_draw_triangle),
+    ("draw_round_rect", Permission::Graphics, lua_draw_round_rect),
+    ("draw_polygon", Permission::Graphics, lua_draw_polygon),
+    ("draw_image", Permission::Graphics, lua_draw_image),
+    ("new_line", Permission::Graphics, lua_new_line),
+    ("present", Permission::Graphics, lua_present),
+    ("set_font", Permission::Graphics, lua_set_font),
+    ("screen_size", Permission::Graphics, lua_screen_size),
+    ("theme", Permission::Graphics, lua_theme),
+    ("time_ms", Permission::Graphics, lua_time_ms),
+    ("request_frame", Permission::Graphics, lua_request_frame),
+    ("cancel_frame", Permission::Graphics, lua_cancel_frame),
+    ("ease", Permission::Graphics, lua_ease),
+    ("tween", Permission::Graphics, lua_tween),
+    ("animate", Permission::Graphics, lua_animate),
+    ("push_clip", Permission::Graphics, lua_push_clip),
+    ("pop_clip", Permission::Graphics, lua_pop_clip),
+    ("ui_label", Permission::Graphics, lua_ui_label),
+    ("ui_button", Permission::Graphics, lua_ui_button),
+    ("ui_text_field", Permission::Graphics, lua_ui_text_field),
+    ("ui_list", Permission::Graphics, lua_ui_list),
+    ("ui_row", Permission::Graphics, lua_ui_row),
+    ("ui_column", Permission::Graphics, lua_ui_column),
+    ("ui_stack", Permission::Graphics, lua_ui_stack),
+    ("ui_set_padding", Permission::Graphics, lua_ui_set_padding),
+    ("ui_set_spacing", Permission::Graphics, lua_ui_set_spacing),
+    ("ui_set_align", Permission::Graphics, lua_ui_set_align),
+    ("ui_set_size", Permission::Graphics, lua_ui_set_size),
+    ("ui_set_color", Permission::Graphics, lua_ui_set_color),
+    ("ui_set_background", Permission::Graphics, lua_ui_set_background),
+    ("ui_set_border", Permission::Graphics, lua_ui_set_border),
+    ("ui_set_text", Permission::Graphics, lua_ui_set_text),
+    ("ui_get_text", Permission::Graphics, lua_ui_get_text),
+    ("ui_selected", Permission::Graphics, lua_ui_selected),
+    ("ui_focus", Permission::Graphics, lua_ui_focus),
+    ("ui_show", Permission::Graphics, lua_ui_show),
+    ("ui_render", Permission::Graphics, lua_ui_render),
+];
+
+/// Contexto gráfico para un binding Lua, con error capturable si no existe
+fn lua_graphics() -> LuaResult<&'static mut GraphicsManager> {
+    get_graphics_context().ok_or_else(|| LuaError::new("contexto gráfico no disponible"))
+}
+
+fn lua_log(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let message = lua::check_str(&args, 0, "log")?;
+    uart_send_str("📱 ");
+    uart_send_str(&message);
+    uart_send_str("\n");
+    Ok(Vec::new())
+}
+
+fn lua_clear_screen(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_graphics()?.clear_screen();
+    Ok(Vec::new())
+}
+
+/// Fracción opcional de 0 a 1 en `index` convertida a alfa de 0 a 255 (opaco por defecto)
+fn lua_alpha(args: &[Value], index: usize, func: &str) -> LuaResult<u8> {
+    let fraction = lua::opt_number(args, index, func, 1.0)?;
+    Ok(libm::round(fraction.clamp(0.0, 1.0) * 255.0) as u8)
+}
+
+/// `set_color(color, alpha)`: alfa de 0 (transparente) a 1 (opaco), sobre el del propio color
+///
+/// `color` es un nombre, un papel del tema ("primary"), `#RGB[A]`, `#RRGGBB[AA]`, `rgb()`, `rgba()`, `hsl()` o `hsla()`.
+fn lua_set_color(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let (color, color_alpha) = lua_color(&args, 0, "set_color")?;
+    let alpha = lua_alpha(&args, 1, "set_color")?;
+    lua_graphics()?.set_color_alpha(color, mul_alpha(color_alpha, alpha));
+    Ok(Vec::new())
+}
+
+/// `set_opacity(opacity)`: opacidad global de 0 a 1 para todo lo que se dibuje
+fn lua_set_opacity(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let opacity = lua_alpha(&args, 0, "set_opacity")?;
+    lua_graphics()?.set_opacity(opacity);
+    Ok(Vec::new())
+}
+
+fn lua_draw_text(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "draw_text")?;
+    lua_graphics()?.draw_text(&text);
+    Ok(Vec::new())
+}
+
+fn lua_draw_text_at(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "draw_text_at")?;
+    let x = lua::check_i32(&args, 1, "draw_text_at")?;
+    let y = lua::check_i32(&args, 2, "draw_text_at")?;
+    lua_graphics()?.draw_text_at(&text, x, y);
+    Ok(Vec::new())
+}
+
+/// `measure_text(text, max_width)`: ancho, alto y número de líneas; sin `max_width` no se parte
+fn lua_measure_text(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "measure_text")?;
+    let max_width = lua::opt_number(&args, 1, "measure_text", 0.0)?.max(0.0) as u32;
+    let (width, height, lines) = lua_graphics()?.measure_text(&text, max_width, TextStyle::default().line_spacing);
+    Ok(alloc::vec![Value::Number(width as f64), Value::Number(height as f64), Value::Number(lines as f64)])
+}
+
+/// `draw_text_box(text, x, y, width, height, options)`: texto partido y alineado dentro de la caja
+///
+/// `options` admite `align` ("left", "center", "right"), `line_spacing` y
+/// `ellipsis` (por defecto `true`). Devuelve las líneas dibujadas y si se recortó el texto.
+fn lua_draw_text_box(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "draw_text_box")?;
+    let x = lua::check_i32(&args, 1, "draw_text_box")?;
+    let y = lua::check_i32(&args, 2, "draw_text_box")?;
+    let width = lua::check_u32(&args, 3, "draw_text_box")?;
+    let height = lua::check_u32(&args, 4, "draw_text_box")?;
+    let mut style = TextStyle::default();
+    match args.get(5) {
+        Some(Value::Table(options)) => {
+            let options = options.borrow();
+            match options.get_str("align") {
+                Value::Nil => {}
+                align => {
+                    let name = align.to_display();
+                    style.align = TextAlign::parse(&name)
+                        .ok_or_else(|| LuaError::new(&alloc::format!("draw_text_box: alineación desconocida '{}'", name)))?;
+                }
+            }
+            if let Some(spacing) = options.get_str("line_spacing").to_number() {
+                style.line_spacing = spacing.max(0.0) as u32;
+            }
+            if let value @ Value::Bool(_) = options.get_str("ellipsis") {
+                style.ellipsis = value.truthy();
+            }
+        }
+        Some(Value::Nil) | None => {}
+        Some(other) => return Err(LuaError::new(&alloc::format!("draw_text_box: se esperaba una tabla de opciones, se recibió {}", other.type_name()))),
+    }
+    let rect = Rectangle::new(Point::new(x, y), Size::new(width, height));
+    let layout = lua_graphics()?.draw_text_box(&text, rect, &style);
+    Ok(alloc::vec![Value::Number(layout.lines.len() as f64), Value::Bool(layout.truncated)])
+}
+
+fn lua_draw_rect(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let x = lua::check_i32(&args, 0, "draw_rect")?;
+    let y = lua::check_i32(&args, 1, "draw_rect")?;
+    let width = lua::check_u32(&args, 2, "draw_rect")?;
+    let height = lua::check_u32(&args, 3, "draw_rect")?;
+    let filled = lua::opt_bool(&args, 4, false);
+    lua_graphics()?.draw_rect(x, y, width, height, filled);
+    Ok(Vec::new())
+}
+
+/// Grosor de trazo opcional en `index` (1 pixel por defecto)
+fn lua_stroke(args: &[Value], index: usize, func: &str) -> LuaResult<u32> {
+    Ok(lua::opt_number(args, index, func, 1.0)?.max(1.0) as u32)
+}
+
+/// `draw_line(x1, y1, x2, y2, width)`
+fn lua_draw_line(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let x1 = lua::check_i32(&args, 0, "draw_line")?;
+    let y1 = lua::check_i32(&args, 1, "draw_line")?;
+    let x2 = lua::check_i32(&args, 2, "draw_line")?;
+    let y2 = lua::check_i32(&args, 3, "draw_line")?;
+    let stroke = lua_stroke(&args, 4, "draw_line")?;
+    lua_graphics()?.draw_line(x1, y1, x2, y2, stroke);
+    Ok(Vec::new())
+}
+
+/// `draw_circle(cx, cy, radius, filled, width)`
+fn lua_draw_circle(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let cx = lua::check_i32(&args, 0, "draw_circle")?;
+    let cy = lua::check_i32(&args, 1, "draw_circle")?;
+    let radius = lua::check_u32(&args, 2, "draw_circle")?;
+    let filled = lua::opt_bool(&args, 3, false);
+    let stroke = lua_stroke(&args, 4, "draw_circle")?;
+    lua_graphics()?.draw_circle(cx, cy, radius, filled, stroke);
+    Ok(Vec::new())
+}
+
+/// `draw_ellipse(x, y, width, height, filled, stroke)`
+fn lua_draw_ellipse(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let x = lua::check_i32(&args, 0, "draw_ellipse")?;
+    let y = lua::check_i32(&args, 1, "draw_ellipse")?;
+    let width = lua::check_u32(&args, 2, "draw_ellipse")?;
+    let height = lua::check_u32(&args, 3, "draw_ellipse")?;
+    let filled = lua::opt_bool(&args, 4, false);
+    let stroke = lua_stroke(&args, 5, "draw_ellipse")?;
+    lua_graphics()?.draw_ellipse(x, y, width, height, filled, stroke);
+    Ok(Vec::new())
+}
+
+/// `draw_arc(cx, cy, radius, start, sweep, width)`: ángulos en grados, horario desde las 3
+fn lua_draw_arc(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let cx = lua::check_i32(&args, 0, "draw_arc")?;
+    let cy = lua::check_i32(&args, 1, "draw_arc")?;
+    let radius = lua::check_u32(&args, 2, "draw_arc")?;
+    let start = lua::check_number(&args, 3, "draw_arc")? as f32;
+    let sweep = lua::check_number(&args, 4, "draw_arc")? as f32;
+    let stroke = lua_stroke(&args, 5, "draw_arc")?;
+    lua_graphics()?.draw_arc(cx, cy, radius, start, sweep, stroke);
+    Ok(Vec::new())
+}
+
+/// `draw_triangle(x1, y1, x2, y2, x3, y3, filled, width)`
+fn lua_draw_triangle(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let mut points = [(0, 0); 3];
+    for (i, point) in points.iter_mut().enumerate() {
+        point.0 = lua::check_i32(&args, i * 2, "draw_triangle")?;
+        point.1 = lua::check_i32(&args, i * 2 + 1, "draw_triangle")?;
     }
+    let filled = lua::opt_bool(&args, 6, false);
+    let stroke = lua_stroke(&args, 7, "draw_triangle")?;
+    lua_graphics()?.draw_triangle(points, filled, stroke);
+    Ok(Vec::new())
 }
 
-// ===== FUNCIONES EXPORTADAS PARA EL WASM =====
+/// `draw_round_rect(x, y, width, height, radius, filled, stroke)`
+fn lua_draw_round_rect(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let x = lua::check_i32(&args, 0, "draw_round_rect")?;
+    let y = lua::check_i32(&args, 1, "draw_round_rect")?;
+    let width = lua::check_u32(&args, 2, "draw_round_rect")?;
+    let height = lua::check_u32(&args, 3, "draw_round_rect")?;
+    let radius = lua::check_u32(&args, 4, "draw_round_rect")?;
+    let filled = lua::opt_bool(&args, 5, false);
+    let stroke = lua_stroke(&args, 6, "draw_round_rect")?;
+    lua_graphics()?.draw_round_rect(Rectangle::new(Point::new(x, y), Size::new(width, height)), radius, ShapeStyle::new(filled, stroke));
+    Ok(Vec::new())
+}
 
-/// Implementación de la función fos_log que espera el WASM
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_log(ptr: *const u8, len: usize) {
-    if ptr.is_null() || len == 0 {
-        return;
+/// `draw_polygon({x1, y1, x2, y2, ...}, filled, width)`
+fn lua_draw_polygon(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = lua::check_table(&args, 0, "draw_polygon")?;
+    let coords: Vec<i32> = {
+        let table = table.borrow();
+        let mut coords = Vec::with_capacity(table.len());
+        for i in 1..=table.len() {
+            match table.get(&Value::Number(i as f64)).to_number() {
+                Some(n) => coords.push(n as i32),
+                None => return Err(LuaError::new("draw_polygon: las coordenadas deben ser números")),
+            }
+        }
+        coords
+    };
+    let points: Vec<(i32, i32)> = coords.as_chunks::<2>().0.iter().map(|&[x, y]| (x, y)).collect();
+    let filled = lua::opt_bool(&args, 1, false);
+    let stroke = lua_stroke(&args, 2, "draw_polygon")?;
+    lua_graphics()?.draw_polygon(&points, filled, stroke);
+    Ok(Vec::new())
+}
+
+/// `draw_image(name, x, y, width, height)`: sin tamaño se dibuja al natural
+///
+/// Devuelve `true`, o `false` y el motivo si la imagen no existe o no se puede decodificar.
+fn lua_draw_image(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let name = lua::check_str(&args, 0, "draw_image")?;
+    let x = lua::check_i32(&args, 1, "draw_image")?;
+    let y = lua::check_i32(&args, 2, "draw_image")?;
+    let width = lua::opt_number(&args, 3, "draw_image", 0.0)?.max(0.0) as u32;
+    let height = lua::opt_number(&args, 4, "draw_image", 0.0)?.max(0.0) as u32;
+    match lua_graphics()?.draw_image(&name, x, y, width, height) {
+        Ok(()) => Ok(alloc::vec![Value::Bool(true)]),
+        Err(error) => Ok(alloc::vec![Value::Bool(false), Value::Str(error.message().into())]),
     }
+}
 
-    unsafe {
-        let slice = core::slice::from_raw_parts(ptr, len);
-        if let Ok(s) = core::str::from_utf8(slice) {
-            uart_send_str("📱 ");
-            uart_send_str(s);
-            uart_send_str("\n");
+fn lua_new_line(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_graphics()?.new_line();
+    Ok(Vec::new())
+}
+
+/// `set_font(name, size)`: devuelve `false` si la fuente no existe; sin tamaño usa el nativo
+fn lua_set_font(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let name = lua::check_str(&args, 0, "set_font")?;
+    let size = lua::opt_number(&args, 1, "set_font", 0.0)?.max(0.0) as u32;
+    let found = lua_graphics()?.set_font(&name, size);
+    Ok(alloc::vec![Value::Bool(found)])
+}
+
+fn lua_present(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_graphics()?.present();
+    Ok(Vec::new())
+}
+
+/// `screen_size()`: ancho y alto de la ventana de la app
+fn lua_screen_size(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let (width, height) = lua_graphics()?.viewport_size();
+    Ok(alloc::vec![Value::Number(width as f64), Value::Number(height as f64)])
+}
+
+/// `theme()`: tabla con la variante del tema ("light" o "dark") y el color `#RRGGBB` de cada papel
+fn lua_theme(lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let theme = theme::current();
+    let table = lua.new_table();
+    if let Value::Table(entries) = &table {
+        let mut entries = entries.borrow_mut();
+        entries.set_str("variant", Value::str(theme.variant.name()));
+        for role in theme::ROLES {
+            if let Some(color) = theme.role(role) {
+                entries.set_str(role, Value::str(&alloc::format!("#{:02X}{:02X}{:02X}", color.r(), color.g(), color.b())));
+            }
         }
     }
+    Ok(alloc::vec![table])
 }
 
-/// Limpiar pantalla
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_clear_screen() {
-    if let Some(graphics) = get_graphics_context() {
-        graphics.clear_screen();
+/// `push_clip(x, y, width, height)`: limitar el dibujo al rectángulo, dentro del recorte actual
+fn lua_push_clip(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let x = lua::check_i32(&args, 0, "push_clip")?;
+    let y = lua::check_i32(&args, 1, "push_clip")?;
+    let width = lua::check_u32(&args, 2, "push_clip")?;
+    let height = lua::check_u32(&args, 3, "push_clip")?;
+    lua_graphics()?.push_clip(Rectangle::new(Point::new(x, y), Size::new(width, height)));
+    Ok(Vec::new())
+}
+
+/// `pop_clip()`: restaurar el recorte anterior; error si no hay ninguno
+fn lua_pop_clip(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    if lua_graphics()?.pop_clip() {
+        Ok(Vec::new())
+    } else {
+        Err(LuaError::new("pop_clip: la pila de recortes está vacía"))
     }
 }
 
-/// Establecer color actual
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_set_color(ptr: *const u8, len: usize) {
-    if ptr.is_null() || len == 0 {
-        return;
+// ===== FRAMES Y ANIMACIONES =====
+
+/// `time_ms()`: milisegundos desde el arranque, con decimales
+fn lua_time_ms(_lua: &mut Lua, _args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    Ok(alloc::vec![Value::Number(uptime_micros() as f64 / 1000.0)])
+}
+
+/// Función en `index` para un callback; error si no lo es
+fn lua_callback(args: &[Value], index: usize, func: &str) -> LuaResult<Value> {
+    match args.get(index) {
+        Some(callback @ (Value::Function(_) | Value::Native(_))) => Ok(callback.clone()),
+        other => Err(LuaError::new(&alloc::format!(
+            "{}: se esperaba una función como callback, se recibió {}",
+            func,
+            other.map_or("nil", |value| value.type_name())
+        ))),
     }
-    
-    unsafe {
-        let slice = core::slice::from_raw_parts(ptr, len);
-        if let Ok(color_str) = core::str::from_utf8(slice) {
-            let color = parse_color(color_str);
-            if let Some(graphics) = get_graphics_context() {
-                graphics.set_color(color);
-            }
+}
+
+/// Curva opcional en `index` ("linear" por defecto); error si no existe
+fn lua_easing(args: &[Value], index: usize, func: &str) -> LuaResult<Easing> {
+    match args.get(index) {
+        None | Some(Value::Nil) => Ok(Easing::Linear),
+        Some(_) => {
+            let name = lua::check_str(args, index, func)?;
+            Easing::parse(&name).ok_or_else(|| {
+                let names: Vec<&str> = EASINGS.iter().map(|(name, _)| *name).collect();
+                LuaError::new(&alloc::format!("{}: curva desconocida '{}' (usa {})", func, name, names.join(", ")))
+            })
         }
     }
 }
 
-/// Dibujar texto en la posición actual del cursor
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_draw_text(ptr: *const u8, len: usize) {
-    if ptr.is_null() || len == 0 {
-        return;
+/// Valor interpolable en `index`: un número, una posición `{x, y}` o `{x = , y = }` o un color
+fn lua_tween_value(args: &[Value], index: usize, func: &str) -> LuaResult<TweenValue> {
+    match args.get(index) {
+        Some(Value::Number(value)) => Ok(TweenValue::Number(*value as f32)),
+        Some(Value::Str(_)) => {
+            let (color, alpha) = lua_color(args, index, func)?;
+            Ok(TweenValue::Color(color, alpha))
+        }
+        Some(Value::Table(table)) => {
+            let table = table.borrow();
+            let coordinate = |name: &str, position: f64| match (table.get_str(name), table.get(&Value::Number(position))) {
+                (Value::Number(value), _) | (_, Value::Number(value)) => Ok(value as f32),
+                _ => Err(LuaError::new(&alloc::format!("{}: a la posición le falta '{}'", func, name))),
+            };
+            Ok(TweenValue::Point(coordinate("x", 1.0)?, coordinate("y", 2.0)?))
+        }
+        other => Err(LuaError::new(&alloc::format!(
+            "{}: se esperaba un número, una posición o un color, se recibió {}",
+            func,
+            other.map_or("nil", |value| value.type_name())
+        ))),
     }
-    
-    unsafe {
-        let slice = core::slice::from_raw_parts(ptr, len);
-        if let Ok(text) = core::str::from_utf8(slice) {
-            if let Some(graphics) = get_graphics_context() {
-                graphics.draw_text(text);
+}
+
+/// Valor interpolado para Lua: número, tabla `{x, y}` o color `#RRGGBBAA`
+fn tween_value_to_lua(lua: &mut Lua, value: TweenValue) -> Value {
+    match value {
+        TweenValue::Number(value) => Value::Number(value as f64),
+        TweenValue::Point(x, y) => {
+            let table = lua.new_table();
+            if let Value::Table(entries) = &table {
+                let mut entries = entries.borrow_mut();
+                entries.set_str("x", Value::Number(x as f64));
+                entries.set_str("y", Value::Number(y as f64));
             }
+            table
+        }
+        TweenValue::Color(color, alpha) => {
+            Value::str(&alloc::format!("#{:02X}{:02X}{:02X}{:02X}", color.r(), color.g(), color.b(), alpha))
         }
     }
 }
 
-/// Dibujar texto en posición específica
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_draw_text_at(text_ptr: *const u8, text_len: usize, x: i32, y: i32) {
-    if text_ptr.is_null() || text_len == 0 {
-        return;
+fn next_frame_id() -> u32 {
+    let frames = app_frames();
+    let id = frames.next_id;
+    frames.next_id += 1;
+    id
+}
+
+/// `request_frame(callback)`: llamar a `callback(time_ms, dt_ms)` en el próximo frame; devuelve su id
+///
+/// Vale para un solo frame: una animación continua vuelve a pedirlo desde el callback.
+fn lua_request_frame(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let callback = lua_callback(&args, 0, "request_frame")?;
+    let id = next_frame_id();
+    app_frames().callbacks.push((id, callback));
+    Ok(alloc::vec![Value::Number(id as f64)])
+}
+
+/// `cancel_frame(id)`: cancelar un `request_frame` o un `animate`; devuelve si existía
+fn lua_cancel_frame(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua::check_u32(&args, 0, "cancel_frame")?;
+    let frames = app_frames();
+    let before = frames.callbacks.len() + frames.animations.len();
+    frames.callbacks.retain(|(callback, _)| *callback != id);
+    frames.animations.retain(|animation| animation.id != id);
+    // Puede ser una animación que se está procesando en este frame
+    frames.cancelled.push(id);
+    Ok(alloc::vec![Value::Bool(frames.callbacks.len() + frames.animations.len() < before)])
+}
+
+/// `ease(easing, t)`: progreso de la curva para `t` de 0 a 1
+fn lua_ease(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let easing = lua_easing(&args, 0, "ease")?;
+    let t = lua::check_number(&args, 1, "ease")?;
+    Ok(alloc::vec![Value::Number(easing.apply(t as f32) as f64)])
+}
+
+/// `tween(from, to, t, easing)`: valor a una fracción `t` (0 a 1) entre dos números, posiciones o colores
+fn lua_tween(lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let from = lua_tween_value(&args, 0, "tween")?;
+    let to = lua_tween_value(&args, 1, "tween")?;
+    let t = lua::check_number(&args, 2, "tween")?;
+    let easing = lua_easing(&args, 3, "tween")?;
+    let value = from
+        .lerp(to, easing.apply(t as f32))
+        .ok_or_else(|| LuaError::new("tween: los dos valores deben ser del mismo tipo"))?;
+    Ok(alloc::vec![tween_value_to_lua(lua, value)])
+}
+
+/// `animate(from, to, duration_ms, easing, on_update, on_done)`: llamar a
+/// `on_update(valor)` en cada frame durante `duration_ms` y a `on_done()` al
+/// terminar; devuelve el id para `cancel_frame`
+fn lua_animate(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let from = lua_tween_value(&args, 0, "animate")?;
+    let to = lua_tween_value(&args, 1, "animate")?;
+    let duration_ms = lua::check_number(&args, 2, "animate")?.max(0.0);
+    let easing = lua_easing(&args, 3, "animate")?;
+    let on_update = lua_callback(&args, 4, "animate")?;
+    let on_done = match args.get(5) {
+        None | Some(Value::Nil) => None,
+        Some(_) => Some(lua_callback(&args, 5, "animate")?),
+    };
+    let tween = Tween::new(from, to, (duration_ms * 1000.0) as u64, easing, uptime_micros())
+        .ok_or_else(|| LuaError::new("animate: los dos valores deben ser del mismo tipo"))?;
+    let id = next_frame_id();
+    app_frames().animations.push(Animation { id, tween, on_update, on_done });
+    Ok(alloc::vec![Value::Number(id as f64)])
+}
+
+// ===== WIDGETS =====
+
+/// Widget en `index`; error si no es un número
+fn lua_widget(args: &[Value], index: usize, func: &str) -> LuaResult<WidgetId> {
+    Ok(WidgetId::from_raw(lua::check_u32(args, index, func)?))
+}
+
+/// Resultado de un setter de widgets: error si el widget no existe o no admite la propiedad
+fn lua_widget_result(ok: bool, func: &str) -> LuaResult<Vec<Value>> {
+    if ok {
+        Ok(Vec::new())
+    } else {
+        Err(LuaError::new(&alloc::format!("{}: widget inválido", func)))
     }
-    
-    unsafe {
-        let slice = core::slice::from_raw_parts(text_ptr, text_len);
-        if let Ok(text) = core::str::from_utf8(slice) {
-            if let Some(graphics) = get_graphics_context() {
-                graphics.draw_text_at(text, x, y);
-            }
+}
+
+/// Guardar el callback opcional del widget y devolver su id
+fn lua_widget_created(id: WidgetId, handler: Option<&Value>) -> LuaResult<Vec<Value>> {
+    match handler {
+        Some(handler @ (Value::Function(_) | Value::Native(_))) => {
+            app_ui().handlers.insert(id, handler.clone());
         }
+        Some(Value::Nil) | None => {}
+        Some(other) => return Err(LuaError::new(&alloc::format!("se esperaba una función como callback, se recibió {}", other.type_name()))),
     }
+    Ok(alloc::vec![Value::Number(id.raw() as f64)])
 }
 
-/// Dibujar rectángulo
-#[unsafe(no_mangle)]
-pub extern "C" fn fos_draw_rect(x: i32, y: i32, width: u32, height: u32, filled: bool) {
-    if let Some(graphics) = get_graphics_context() {
-        graphics.draw_rect(x, y, width, height, filled);
+/// `ui_label(text)`
+fn lua_ui_label(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "ui_label")?;
+    lua_widget_created(app_ui().ui.label(&text), None)
+}
+
+/// `ui_button(text, on_click)`
+fn lua_ui_button(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let text = lua::check_str(&args, 0, "ui_button")?;
+    lua_widget_created(app_ui().ui.button(&text), args.get(1))
+}
+
+/// `ui_text_field(placeholder, on_submit)`: `on_submit(text)` se llama al pulsar Enter
+fn lua_ui_text_field(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let placeholder = lua::check_str(&args, 0, "ui_text_field")?;
+    lua_widget_created(app_ui().ui.text_field(&placeholder), args.get(1))
+}
+
+/// `ui_list(items, on_select)`: `on_select(index, item)` se llama al pulsar Enter
+fn lua_ui_list(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let table = lua::check_table(&args, 0, "ui_list")?;
+    let items = {
+        let table = table.borrow();
+        (1..=table.len()).map(|i| table.get(&Value::Number(i as f64)).to_display()).collect()
+    };
+    lua_widget_created(app_ui().ui.list(items), args.get(1))
+}
+
+/// Contenedor con los widgets pasados como argumentos
+fn lua_ui_container(args: &[Value], layout: Layout, func: &str) -> LuaResult<Vec<Value>> {
+    let children = (0..args.len()).map(|i| lua_widget(args, i, func)).collect::<LuaResult<Vec<_>>>()?;
+    match app_ui().ui.container(layout, &children) {
+        Some(id) => Ok(alloc::vec![Value::Number(id.raw() as f64)]),
+        None => Err(LuaError::new(&alloc::format!("{}: widget inválido o que ya está en otro contenedor", func))),
+    }
+}
+
+/// `ui_row(...)`: hijos de izquierda a derecha
+fn lua_ui_row(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_ui_container(&args, Layout::Row, "ui_row")
+}
+
+/// `ui_column(...)`: hijos de arriba abajo
+fn lua_ui_column(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_ui_container(&args, Layout::Column, "ui_column")
+}
+
+/// `ui_stack(...)`: hijos superpuestos
+fn lua_ui_stack(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua_ui_container(&args, Layout::Stack, "ui_stack")
+}
+
+fn lua_ui_set_padding(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_padding")?;
+    let padding = lua::check_u32(&args, 1, "ui_set_padding")?;
+    lua_widget_result(app_ui().ui.set_padding(id, padding), "ui_set_padding")
+}
+
+fn lua_ui_set_spacing(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_spacing")?;
+    let spacing = lua::check_u32(&args, 1, "ui_set_spacing")?;
+    lua_widget_result(app_ui().ui.set_spacing(id, spacing), "ui_set_spacing")
+}
+
+/// `ui_set_align(id, align)`: "start", "center", "end" o "stretch"
+fn lua_ui_set_align(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_align")?;
+    let name = lua::check_str(&args, 1, "ui_set_align")?;
+    let align = Align::parse(&name).ok_or_else(|| LuaError::new("ui_set_align: alineación desconocida"))?;
+    lua_widget_result(app_ui().ui.set_align(id, align), "ui_set_align")
+}
+
+/// `ui_set_size(id, width, height)`: 0 deja el tamaño del contenido
+fn lua_ui_set_size(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_size")?;
+    let width = lua::check_u32(&args, 1, "ui_set_size")?;
+    let height = lua::check_u32(&args, 2, "ui_set_size")?;
+    lua_widget_result(app_ui().ui.set_size(id, width, height), "ui_set_size")
+}
+
+fn lua_ui_set_color(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_color")?;
+    let (color, _) = lua_color(&args, 1, "ui_set_color")?;
+    lua_widget_result(app_ui().ui.set_color(id, color), "ui_set_color")
+}
+
+fn lua_ui_set_background(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_background")?;
+    let (color, _) = lua_color(&args, 1, "ui_set_background")?;
+    lua_widget_result(app_ui().ui.set_background(id, color), "ui_set_background")
+}
+
+fn lua_ui_set_border(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_border")?;
+    let (color, _) = lua_color(&args, 1, "ui_set_border")?;
+    lua_widget_result(app_ui().ui.set_border(id, color), "ui_set_border")
+}
+
+fn lua_ui_set_text(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_set_text")?;
+    let text = lua::check_str(&args, 1, "ui_set_text")?;
+    lua_widget_result(app_ui().ui.set_text(id, &text), "ui_set_text")
+}
+
+/// `ui_get_text(id)`: texto del widget o elemento seleccionado de una lista (nil si no tiene)
+fn lua_ui_get_text(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_get_text")?;
+    Ok(alloc::vec![app_ui().ui.text(id).map_or(Value::Nil, Value::str)])
+}
+
+/// `ui_selected(id)`: índice (desde 1) del elemento seleccionado de una lista
+fn lua_ui_selected(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_selected")?;
+    Ok(alloc::vec![app_ui().ui.selected(id).map_or(Value::Nil, |index| Value::Number(index as f64 + 1.0))])
+}
+
+fn lua_ui_focus(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let id = lua_widget(&args, 0, "ui_focus")?;
+    lua_widget_result(app_ui().ui.focus(id), "ui_focus")
+}
+
+/// `ui_show(root, x, y, width, height)`: mostrar el árbol; sin zona ocupa toda la ventana
+fn lua_ui_show(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    let root = lua_widget(&args, 0, "ui_show")?;
+    let x = lua::opt_number(&args, 1, "ui_show", 0.0)? as i32;
+    let y = lua::opt_number(&args, 2, "ui_show", 0.0)? as i32;
+    let width = lua::opt_number(&args, 3, "ui_show", 0.0)?.max(0.0) as u32;
+    let height = lua::opt_number(&args, 4, "ui_show", 0.0)?.max(0.0) as u32;
+    let graphics = lua_graphics()?;
+    let area = widget_area(graphics, x, y, width, height);
+    if !app_ui().ui.show(root, area) {
+        return lua_widget_result(false, "ui_show");
+    }
+    app_ui().ui.render(graphics);
+    Ok(Vec::new())
+}
+
+/// `ui_render(tree, x, y, width, height)`: sustituir la interfaz por la descrita en `tree`
+///
+/// `tree` es una tabla `{ type = "column", padding = 8, children = { ... } }`;
+/// solo se redibuja lo que cambia respecto a la interfaz anterior.
+fn lua_ui_render(_lua: &mut Lua, args: Vec<Value>) -> LuaResult<Vec<Value>> {
+    lua::check_table(&args, 0, "ui_render")?;
+    let x = lua::opt_number(&args, 1, "ui_render", 0.0)? as i32;
+    let y = lua::opt_number(&args, 2, "ui_render", 0.0)? as i32;
+    let width = lua::opt_number(&args, 3, "ui_render", 0.0)?.max(0.0) as u32;
+    let height = lua::opt_number(&args, 4, "ui_render", 0.0)?.max(0.0) as u32;
+    let graphics = lua_graphics()?;
+    let area = widget_area(graphics, x, y, width, height);
+    render_ui_tree(&args[0], area, graphics)?;
+    Ok(Vec::new())
+}
+
+/// Construir el árbol descrito en Lua y sustituir con él los widgets de la app
+fn render_ui_tree(tree: &Value, area: Rectangle, graphics: &mut GraphicsManager) -> LuaResult<()> {
+    let mut ui = Ui::new();
+    let mut handlers = BTreeMap::new();
+    let root = build_ui_node(&mut ui, &mut handlers, tree, 0)?;
+    ui.show(root, area);
+    let app = app_ui();
+    app.handlers = handlers;
+    app.ui.reconcile(ui, graphics);
+    Ok(())
+}
+
+/// Crear en `ui` el widget descrito por la tabla `node` y, si es un contenedor, sus hijos
+///
+/// Tipos: "label", "button", "text_field", "list", "row", "column" y "stack".
+/// Propiedades: text, placeholder, items, children, padding, spacing, align,
+/// width, height, color, background, border, on_click, on_submit y on_select.
+fn build_ui_node(ui: &mut Ui, handlers: &mut BTreeMap<WidgetId, Value>, node: &Value, depth: usize) -> LuaResult<WidgetId> {
+    let Value::Table(table) = node else {
+        return Err(LuaError::new(&alloc::format!("ui_render: se esperaba una tabla como nodo, se recibió {}", node.type_name())));
+    };
+    if depth > MAX_UI_DEPTH {
+        return Err(LuaError::new("ui_render: árbol demasiado profundo"));
+    }
+    let table = table.borrow();
+    let text = |name: &str| match table.get_str(name) {
+        Value::Nil => None,
+        value => Some(value.to_display()),
+    };
+
+    let kind = text("type").unwrap_or_default();
+    let id = match kind.as_str() {
+        "label" => ui.label(&text("text").unwrap_or_default()),
+        "button" => ui.button(&text("text").unwrap_or_default()),
+        "text_field" => {
+            let id = ui.text_field(&text("placeholder").unwrap_or_default());
+            if let Some(value) = text("text") {
+                ui.set_text(id, &value);
+            }
+            id
+        }
+        "list" => {
+            let items = match table.get_str("items") {
+                Value::Table(items) => {
+                    let items = items.borrow();
+                    (1..=items.len()).map(|i| items.get(&Value::Number(i as f64)).to_display()).collect()
+                }
+                _ => Vec::new(),
+            };
+            ui.list(items)
+        }
+        "row" | "column" | "stack" => {
+            let layout = match kind.as_str() {
+                "row" => Layout::Row,
+                "column" => Layout::Column,
+                _ => Layout::Stack,
+            };
+            let mut children = Vec::new();
+            if let Value::Table(nodes) = table.get_str("children") {
+                let nodes = nodes.borrow();
+                for i in 1..=nodes.len() {
+                    children.push(build_ui_node(ui, handlers, &nodes.get(&Value::Number(i as f64)), depth + 1)?);
+                }
+            }
+            // Los hijos recién creados no tienen padre: el contenedor siempre se puede crear
+            ui.container(layout, &children).ok_or_else(|| LuaError::new("ui_render: contenedor inválido"))?
+        }
+        _ => return Err(LuaError::new(&alloc::format!("ui_render: tipo de widget desconocido '{}'", kind))),
+    };
+
+    if let Some(padding) = table.get_str("padding").to_number() {
+        ui.set_padding(id, padding.max(0.0) as u32);
+    }
+    if let Some(spacing) = table.get_str("spacing").to_number() {
+        ui.set_spacing(id, spacing.max(0.0) as u32);
+    }
+    if let Some(name) = text("align") {
+        let align = Align::parse(&name).ok_or_else(|| LuaError::new(&alloc::format!("ui_render: alineación desconocida '{}'", name)))?;
+        ui.set_align(id, align);
+    }
+    let width = table.get_str("width").to_number().unwrap_or(0.0).max(0.0) as u32;
+    let height = table.get_str("height").to_number().unwrap_or(0.0).max(0.0) as u32;
+    ui.set_size(id, width, height);
+    let color = |name: &str| {
+        text(name)
+            .map(|spec| theme::parse_color(&spec).map(|(color, _)| color).ok_or_else(|| LuaError::new(&alloc::format!("ui_render: color inválido '{}'", spec))))
+            .transpose()
+    };
+    if let Some(color) = color("color")? {
+        ui.set_color(id, color);
+    }
+    if let Some(color) = color("background")? {
+        ui.set_background(id, color);
+    }
+    if let Some(color) = color("border")? {
+        ui.set_border(id, color);
+    }
+
+    // Cada widget activa un solo evento: un segundo manejador no se llamaría nunca
+    let mut declared: Option<&str> = None;
+    for name in ["on_click", "on_submit", "on_select"] {
+        match table.get_str(name) {
+            handler @ (Value::Function(_) | Value::Native(_)) => {
+                if let Some(previous) = declared {
+                    return Err(LuaError::new(&alloc::format!("ui_render: un widget admite un solo manejador ({} y {})", previous, name)));
+                }
+                declared = Some(name);
+                handlers.insert(id, handler);
+            }
+            Value::Nil => {}
+            other => return Err(LuaError::new(&alloc::format!("ui_render: {} debe ser una función, se recibió {}", name, other.type_name()))),
+        }
+    }
+    Ok(id)
+}
+
+/// Zona de un árbol de widgets; un tamaño 0 ocupa hasta el borde de la ventana
+fn widget_area(graphics: &GraphicsManager, x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+    let (window_width, window_height) = graphics.viewport_size();
+    let width = if width == 0 { (window_width as i32 - x).max(0) as u32 } else { width };
+    let height = if height == 0 { (window_height as i32 - y).max(0) as u32 } else { height };
+    Rectangle::new(Point::new(x, y), Size::new(width, height))
+}
+
+// ===== FUNCIONES EXPORTADAS PARA EL WASM =====
+
+/// Tabla de imports `fos_*` que el host ofrece al WASM, con el permiso que exige cada uno
+const WASM_IMPORTS: &[(&str, Permission)] = &[
+    ("fos_log", Permission::Log),
+    ("fos_clear_screen", Permission::Graphics),
+    ("fos_set_color", Permission::Graphics),
+    ("fos_set_color_alpha", Permission::Graphics),
+    ("fos_set_opacity", Permission::Graphics),
+    ("fos_draw_text", Permission::Graphics),
+    ("fos_draw_text_at", Permission::Graphics),
+    ("fos_measure_text", Permission::Graphics),
+    ("fos_draw_text_box", Permission::Graphics),
+    ("fos_draw_rect", Permission::Graphics),
+    ("fos_draw_line", Permission::Graphics),
+    ("fos_draw_circle", Permission::Graphics),
+    ("fos_draw_ellipse", Permission::Graphics),
+    ("fos_draw_arc", Permission::Graphics),
+    ("fos_draw_triangle", Permission::Graphics),
+    ("fos_draw_round_rect", Permission::Graphics),
+    ("fos_draw_polygon", Permission::Graphics),
+    ("fos_draw_image", Permission::Graphics),
+    ("fos_new_line", Permission::Graphics),
+    ("fos_present", Permission::Graphics),
+    ("fos_set_font", Permission::Graphics),
+    ("fos_screen_width", Permission::Graphics),
+    ("fos_screen_height", Permission::Graphics),
+    ("fos_theme_variant", Permission::Graphics),
+    ("fos_theme_color", Permission::Graphics),
+    ("fos_time_ms", Permission::Graphics),
+    ("fos_ease", Permission::Graphics),
+    ("fos_push_clip", Permission::Graphics),
+    ("fos_pop_clip", Permission::Graphics),
+    ("fos_ui_label", Permission::Graphics),
+    ("fos_ui_button", Permission::Graphics),
+    ("fos_ui_text_field", Permission::Graphics),
+    ("fos_ui_list", Permission::Graphics),
+    ("fos_ui_container", Permission::Graphics),
+    ("fos_ui_add_child", Permission::Graphics),
+    ("fos_ui_set_padding", Permission::Graphics),
+    ("fos_ui_set_spacing", Permission::Graphics),
+    ("fos_ui_set_align", Permission::Graphics),
+    ("fos_ui_set_size", Permission::Graphics),
+    ("fos_ui_set_color", Permission::Graphics),
+    ("fos_ui_set_background", Permission::Graphics),
+    ("fos_ui_set_border", Permission::Graphics),
+    ("fos_ui_set_text", Permission::Graphics),
+    ("fos_ui_get_text", Permission::Graphics),
+    ("fos_ui_selected", Permission::Graphics),
+    ("fos_ui_focus", Permission::Graphics),
+    ("fos_ui_show", Permission::Graphics),
+    ("fos_ui_poll_event", Permission::Input),
+];
+
+/// Códigos de error que devuelve `fos_last_error`
+pub const FOS_OK: i32 = 0;
+pub const FOS_ERR_PERMISSION_DENIED: i32 = 1;
+pub const FOS_ERR_NOT_FOUND: i32 = 2;
+pub const FOS_ERR_INVALID_DATA: i32 = 3;
+
+// Permisos de la app en ejecución y último error de una llamada al host
+static mut APP_PERMISSIONS: PermissionSet = PermissionSet::empty();
+static mut LAST_HOST_ERROR: i32 = FOS_OK;
+
+/// Fijar los permisos que se comprueban en los imports WASM
+fn set_app_permissions(permissions: PermissionSet) {
+    unsafe {
+        APP_PERMISSIONS = permissions;
+    }
+}
+
+/// Comprobar el permiso de un import; si se deniega se registra y queda en `fos_last_error`
+fn wasm_import_allowed(import: &str) -> bool {
+    let Some((_, permission)) = WASM_IMPORTS.iter().find(|(name, _)| *name == import) else {
+        return false;
+    };
+    if unsafe { APP_PERMISSIONS }.allows(*permission) {
+        return true;
+    }
+    manifest::log_denied(import, *permission);
+    unsafe {
+        LAST_HOST_ERROR = FOS_ERR_PERMISSION_DENIED;
+    }
+    false
+}
+
+/// Devolver y limpiar el código de error de la última llamada denegada
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_last_error() -> i32 {
+    unsafe {
+        let error = LAST_HOST_ERROR;
+        LAST_HOST_ERROR = FOS_OK;
+        error
+    }
+}
+
+/// Implementación de la función fos_log que espera el WASM
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_log(ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_log") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+
+    unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        if let Ok(s) = core::str::from_utf8(slice) {
+            uart_send_str("📱 ");
+            uart_send_str(s);
+            uart_send_str("\n");
+        }
+    }
+}
+
+/// Limpiar pantalla
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_clear_screen() {
+    if !wasm_import_allowed("fos_clear_screen") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.clear_screen();
+    }
+}
+
+/// Establecer color actual
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_set_color(ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_set_color") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+    
+    let Some((color, alpha)) = (unsafe { wasm_color(ptr, len) }) else {
+        return;
+    };
+    if let Some(graphics) = get_graphics_context() {
+        graphics.set_color_alpha(color, alpha);
+    }
+}
+
+/// Cambiar el color actual con alfa de 0 (transparente) a 255 (opaco)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_set_color_alpha(ptr: *const u8, len: usize, alpha: u32) {
+    if !wasm_import_allowed("fos_set_color_alpha") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+
+    let Some((color, color_alpha)) = (unsafe { wasm_color(ptr, len) }) else {
+        return;
+    };
+    if let Some(graphics) = get_graphics_context() {
+        graphics.set_color_alpha(color, mul_alpha(color_alpha, alpha.min(255) as u8));
+    }
+}
+
+/// Opacidad global de 0 a 255 para todo lo que se dibuje
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_set_opacity(opacity: u32) {
+    if !wasm_import_allowed("fos_set_opacity") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.set_opacity(opacity.min(255) as u8);
+    }
+}
+
+/// Dibujar texto en la posición actual del cursor
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_text(ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_draw_text") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+    
+    unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        if let Ok(text) = core::str::from_utf8(slice) {
+            if let Some(graphics) = get_graphics_context() {
+                graphics.draw_text(text);
+            }
+        }
+    }
+}
+
+/// Dibujar texto en posición específica
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_text_at(text_ptr: *const u8, text_len: usize, x: i32, y: i32) {
+    if !wasm_import_allowed("fos_draw_text_at") {
+        return;
+    }
+    if text_ptr.is_null() || text_len == 0 {
+        return;
+    }
+    
+    unsafe {
+        let slice = core::slice::from_raw_parts(text_ptr, text_len);
+        if let Ok(text) = core::str::from_utf8(slice) {
+            if let Some(graphics) = get_graphics_context() {
+                graphics.draw_text_at(text, x, y);
+            }
+        }
+    }
+}
+
+/// Dibujar rectángulo
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_rect(x: i32, y: i32, width: u32, height: u32, filled: bool) {
+    if !wasm_import_allowed("fos_draw_rect") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_rect(x, y, width, height, filled);
+    }
+}
+
+/// Dibujar línea de `stroke_width` pixels
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_line(x1: i32, y1: i32, x2: i32, y2: i32, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_line") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_line(x1, y1, x2, y2, stroke_width);
+    }
+}
+
+/// Dibujar círculo con centro en (cx, cy)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_circle(cx: i32, cy: i32, radius: u32, filled: bool, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_circle") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_circle(cx, cy, radius, filled, stroke_width);
+    }
+}
+
+/// Dibujar elipse inscrita en el rectángulo dado
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_ellipse(x: i32, y: i32, width: u32, height: u32, filled: bool, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_ellipse") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_ellipse(x, y, width, height, filled, stroke_width);
+    }
+}
+
+/// Dibujar arco; ángulos en grados, en sentido horario desde las 3
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_arc(cx: i32, cy: i32, radius: u32, start_deg: f32, sweep_deg: f32, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_arc") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_arc(cx, cy, radius, start_deg, sweep_deg, stroke_width);
+    }
+}
+
+/// Dibujar triángulo
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_triangle(x1: i32, y1: i32, x2: i32, y2: i32, x3: i32, y3: i32, filled: bool, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_triangle") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_triangle([(x1, y1), (x2, y2), (x3, y3)], filled, stroke_width);
+    }
+}
+
+/// Dibujar rectángulo con esquinas redondeadas de radio `radius`
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_round_rect(x: i32, y: i32, width: u32, height: u32, radius: u32, filled: bool, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_round_rect") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.draw_round_rect(Rectangle::new(Point::new(x, y), Size::new(width, height)), radius, ShapeStyle::new(filled, stroke_width));
+    }
+}
+
+/// Dibujar polígono cerrado a partir de `count` pares (x, y) de `i32` consecutivos
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_polygon(points: *const i32, count: usize, filled: bool, stroke_width: u32) {
+    if !wasm_import_allowed("fos_draw_polygon") {
+        return;
+    }
+    if points.is_null() || count == 0 {
+        return;
+    }
+
+    unsafe {
+        let coords = core::slice::from_raw_parts(points, count * 2);
+        let points: Vec<(i32, i32)> = coords.as_chunks::<2>().0.iter().map(|&[x, y]| (x, y)).collect();
+        if let Some(graphics) = get_graphics_context() {
+            graphics.draw_polygon(&points, filled, stroke_width);
+        }
+    }
+}
+
+/// Dibujar una imagen de los assets; `width`/`height` a 0 usan el tamaño natural
+///
+/// Si el asset no existe `fos_last_error` devuelve `FOS_ERR_NOT_FOUND`, y si no
+/// se puede decodificar, `FOS_ERR_INVALID_DATA`.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_image(ptr: *const u8, len: usize, x: i32, y: i32, width: u32, height: u32) {
+    if !wasm_import_allowed("fos_draw_image") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+
+    unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        if let Ok(name) = core::str::from_utf8(slice)
+            && let Some(graphics) = get_graphics_context()
+        {
+            match graphics.draw_image(name, x, y, width, height) {
+                Ok(()) => {}
+                Err(ImageError::NotFound) => LAST_HOST_ERROR = FOS_ERR_NOT_FOUND,
+                Err(ImageError::Decode(_)) => LAST_HOST_ERROR = FOS_ERR_INVALID_DATA,
+            }
+        }
     }
 }
 
 /// Nueva línea
 #[unsafe(no_mangle)]
 pub extern "C" fn fos_new_line() {
+    if !wasm_import_allowed("fos_new_line") {
+        return;
+    }
     if let Some(graphics) = get_graphics_context() {
         graphics.new_line();
     }
 }
+
+/// Mostrar el frame dibujado en el back buffer
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_present() {
+    if !wasm_import_allowed("fos_present") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.present();
+    }
+}
+
+/// Seleccionar fuente por nombre y altura en pixels (0 = tamaño nativo)
+///
+/// Si la fuente no existe se mantiene la actual y `fos_last_error` devuelve `FOS_ERR_NOT_FOUND`.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_set_font(ptr: *const u8, len: usize, size: u32) {
+    if !wasm_import_allowed("fos_set_font") {
+        return;
+    }
+    if ptr.is_null() || len == 0 {
+        return;
+    }
+
+    unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        if let Ok(name) = core::str::from_utf8(slice)
+            && let Some(graphics) = get_graphics_context()
+            && !graphics.set_font(name, size)
+        {
+            LAST_HOST_ERROR = FOS_ERR_NOT_FOUND;
+        }
+    }
+}
+
+/// Medir un texto partido a `max_width` (0 = sin partir) con la fuente actual
+///
+/// Escribe el ancho y el alto en `size[0]` y `size[1]` y devuelve el número de líneas.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_measure_text(ptr: *const u8, len: usize, max_width: u32, size: *mut u32) -> u32 {
+    if !wasm_import_allowed("fos_measure_text") {
+        return 0;
+    }
+    let (Some(text), Some(graphics)) = (unsafe { wasm_str(ptr, len) }, get_graphics_context()) else {
+        return 0;
+    };
+    let (width, height, lines) = graphics.measure_text(text, max_width, TextStyle::default().line_spacing);
+    if !size.is_null() {
+        unsafe {
+            size.write(width);
+            size.add(1).write(height);
+        }
+    }
+    lines as u32
+}
+
+/// Dibujar un texto partido y alineado dentro de la caja; devuelve las líneas dibujadas
+///
+/// `style`: bits 0-1 alineación (0 izquierda, 1 centro, 2 derecha), bit 2 terminar
+/// en "…" si no cabe y bits 8-15 pixels extra entre líneas.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_draw_text_box(ptr: *const u8, len: usize, x: i32, y: i32, width: u32, height: u32, style: u32) -> u32 {
+    if !wasm_import_allowed("fos_draw_text_box") {
+        return 0;
+    }
+    let (Some(text), Some(graphics)) = (unsafe { wasm_str(ptr, len) }, get_graphics_context()) else {
+        return 0;
+    };
+    let style = TextStyle {
+        align: match style & 0b11 {
+            1 => TextAlign::Center,
+            2 => TextAlign::Right,
+            _ => TextAlign::Left,
+        },
+        ellipsis: style & 0b100 != 0,
+        line_spacing: (style >> 8) & 0xFF,
+    };
+    let rect = Rectangle::new(Point::new(x, y), Size::new(width, height));
+    graphics.draw_text_box(text, rect, &style).lines.len() as u32
+}
+
+/// Ancho de la ventana de la app en pixels (0 sin permiso o sin contexto gráfico)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_screen_width() -> u32 {
+    if !wasm_import_allowed("fos_screen_width") {
+        return 0;
+    }
+    get_graphics_context().map_or(0, |graphics| graphics.viewport_size().0)
+}
+
+/// Alto de la ventana de la app en pixels (0 sin permiso o sin contexto gráfico)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_screen_height() -> u32 {
+    if !wasm_import_allowed("fos_screen_height") {
+        return 0;
+    }
+    get_graphics_context().map_or(0, |graphics| graphics.viewport_size().1)
+}
+
+/// Variante del tema del sistema: 0 clara, 1 oscura
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_theme_variant() -> u32 {
+    if !wasm_import_allowed("fos_theme_variant") {
+        return 0;
+    }
+    match theme::current().variant {
+        theme::Variant::Light => 0,
+        theme::Variant::Dark => 1,
+    }
+}
+
+/// Color de un papel del tema ("primary", "error"...) como 0xRRGGBB
+///
+/// Devuelve 0 y marca `FOS_ERR_NOT_FOUND` si el papel no existe.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_theme_color(ptr: *const u8, len: usize) -> u32 {
+    if !wasm_import_allowed("fos_theme_color") {
+        return 0;
+    }
+    match unsafe { wasm_str(ptr, len) }.and_then(|role| theme::current().role(role)) {
+        Some(color) => (color.r() as u32) << 16 | (color.g() as u32) << 8 | color.b() as u32,
+        None => {
+            unsafe {
+                LAST_HOST_ERROR = FOS_ERR_NOT_FOUND;
+            }
+            0
+        }
+    }
+}
+
+/// Milisegundos desde el arranque (da la vuelta a los ~49 días)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_time_ms() -> u32 {
+    if !wasm_import_allowed("fos_time_ms") {
+        return 0;
+    }
+    (uptime_micros() / 1000) as u32
+}
+
+/// Progreso de la curva de aceleración ("linear", "ease_in"...) para `t` de 0 a 1
+///
+/// Devuelve `t` y marca `FOS_ERR_NOT_FOUND` si la curva no existe.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ease(ptr: *const u8, len: usize, t: f32) -> f32 {
+    if !wasm_import_allowed("fos_ease") {
+        return t;
+    }
+    match unsafe { wasm_str(ptr, len) }.and_then(Easing::parse) {
+        Some(easing) => easing.apply(t),
+        None => {
+            unsafe {
+                LAST_HOST_ERROR = FOS_ERR_NOT_FOUND;
+            }
+            t
+        }
+    }
+}
+
+/// Limitar el dibujo al rectángulo (coordenadas locales), dentro del recorte actual
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_push_clip(x: i32, y: i32, width: u32, height: u32) {
+    if !wasm_import_allowed("fos_push_clip") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.push_clip(Rectangle::new(Point::new(x, y), Size::new(width, height)));
+    }
+}
+
+/// Restaurar el recorte anterior (sin efecto si la pila está vacía)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_pop_clip() {
+    if !wasm_import_allowed("fos_pop_clip") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        graphics.pop_clip();
+    }
+}
+
+// ===== WIDGETS PARA EL WASM =====
+//
+// Los widgets se identifican con el número que devuelven las funciones de
+// creación (0 si falla). Un id inválido deja `FOS_ERR_NOT_FOUND` en `fos_last_error`.
+// Las apps WASM leen con `fos_ui_poll_event` los widgets activados con Enter.
+
+/// Texto UTF-8 en la memoria del WASM
+///
+/// # Safety
+/// `ptr` debe apuntar a `len` bytes válidos.
+unsafe fn wasm_str<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(ptr, len) }).ok()
+}
+
+/// Color escrito en la memoria del WASM; marca `FOS_ERR_INVALID_DATA` si no es válido
+///
+/// # Safety
+/// `ptr` debe apuntar a `len` bytes válidos.
+unsafe fn wasm_color(ptr: *const u8, len: usize) -> Option<(Rgb888, u8)> {
+    let color = unsafe { wasm_str(ptr, len) }.and_then(theme::parse_color);
+    if color.is_none() {
+        unsafe {
+            LAST_HOST_ERROR = FOS_ERR_INVALID_DATA;
+        }
+    }
+    color
+}
+
+/// Marcar `FOS_ERR_NOT_FOUND` si una operación sobre un widget falló
+fn wasm_widget_result(ok: bool) {
+    if !ok {
+        unsafe {
+            LAST_HOST_ERROR = FOS_ERR_NOT_FOUND;
+        }
+    }
+}
+
+/// Crear una etiqueta
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_label(ptr: *const u8, len: usize) -> u32 {
+    if !wasm_import_allowed("fos_ui_label") {
+        return 0;
+    }
+    unsafe { wasm_str(ptr, len) }.map_or(0, |text| app_ui().ui.label(text).raw())
+}
+
+/// Crear un botón
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_button(ptr: *const u8, len: usize) -> u32 {
+    if !wasm_import_allowed("fos_ui_button") {
+        return 0;
+    }
+    unsafe { wasm_str(ptr, len) }.map_or(0, |text| app_ui().ui.button(text).raw())
+}
+
+/// Crear un campo de texto con su texto de ayuda
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_text_field(ptr: *const u8, len: usize) -> u32 {
+    if !wasm_import_allowed("fos_ui_text_field") {
+        return 0;
+    }
+    unsafe { wasm_str(ptr, len) }.map_or(0, |placeholder| app_ui().ui.text_field(placeholder).raw())
+}
+
+/// Crear una lista con los elementos separados por `\n`
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_list(ptr: *const u8, len: usize) -> u32 {
+    if !wasm_import_allowed("fos_ui_list") {
+        return 0;
+    }
+    unsafe { wasm_str(ptr, len) }.map_or(0, |items| app_ui().ui.list(items.split('\n').map(String::from).collect()).raw())
+}
+
+/// Crear un contenedor vacío: 0 fila, 1 columna, 2 pila
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_container(layout: u32) -> u32 {
+    if !wasm_import_allowed("fos_ui_container") {
+        return 0;
+    }
+    let layout = match layout {
+        0 => Layout::Row,
+        1 => Layout::Column,
+        2 => Layout::Stack,
+        _ => {
+            unsafe {
+                LAST_HOST_ERROR = FOS_ERR_INVALID_DATA;
+            }
+            return 0;
+        }
+    };
+    app_ui().ui.container(layout, &[]).map_or(0, WidgetId::raw)
+}
+
+/// Añadir un widget al final de un contenedor
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_add_child(parent: u32, child: u32) {
+    if !wasm_import_allowed("fos_ui_add_child") {
+        return;
+    }
+    wasm_widget_result(app_ui().ui.add_child(WidgetId::from_raw(parent), WidgetId::from_raw(child)));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_padding(id: u32, padding: u32) {
+    if !wasm_import_allowed("fos_ui_set_padding") {
+        return;
+    }
+    wasm_widget_result(app_ui().ui.set_padding(WidgetId::from_raw(id), padding));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_spacing(id: u32, spacing: u32) {
+    if !wasm_import_allowed("fos_ui_set_spacing") {
+        return;
+    }
+    wasm_widget_result(app_ui().ui.set_spacing(WidgetId::from_raw(id), spacing));
+}
+
+/// Alineación de los hijos: 0 inicio, 1 centro, 2 final, 3 ocupar todo
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_align(id: u32, align: u32) {
+    if !wasm_import_allowed("fos_ui_set_align") {
+        return;
+    }
+    let align = match align {
+        0 => Align::Start,
+        1 => Align::Center,
+        2 => Align::End,
+        _ => Align::Stretch,
+    };
+    wasm_widget_result(app_ui().ui.set_align(WidgetId::from_raw(id), align));
+}
+
+/// Tamaño fijo del widget (0 = el del contenido)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_size(id: u32, width: u32, height: u32) {
+    if !wasm_import_allowed("fos_ui_set_size") {
+        return;
+    }
+    wasm_widget_result(app_ui().ui.set_size(WidgetId::from_raw(id), width, height));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_color(id: u32, ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_ui_set_color") {
+        return;
+    }
+    if let Some((color, _)) = unsafe { wasm_color(ptr, len) } {
+        wasm_widget_result(app_ui().ui.set_color(WidgetId::from_raw(id), color));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_background(id: u32, ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_ui_set_background") {
+        return;
+    }
+    if let Some((color, _)) = unsafe { wasm_color(ptr, len) } {
+        wasm_widget_result(app_ui().ui.set_background(WidgetId::from_raw(id), color));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_border(id: u32, ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_ui_set_border") {
+        return;
+    }
+    if let Some((color, _)) = unsafe { wasm_color(ptr, len) } {
+        wasm_widget_result(app_ui().ui.set_border(WidgetId::from_raw(id), color));
+    }
+}
+
+/// Cambiar el texto de una etiqueta, botón o campo de texto
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_set_text(id: u32, ptr: *const u8, len: usize) {
+    if !wasm_import_allowed("fos_ui_set_text") {
+        return;
+    }
+    if let Some(text) = unsafe { wasm_str(ptr, len) } {
+        wasm_widget_result(app_ui().ui.set_text(WidgetId::from_raw(id), text));
+    }
+}
+
+/// Copiar a `buf` el texto del widget (o el elemento seleccionado de una lista)
+///
+/// Devuelve la longitud completa en bytes; si es mayor que `cap` el texto se trunca.
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_get_text(id: u32, buf: *mut u8, cap: usize) -> usize {
+    if !wasm_import_allowed("fos_ui_get_text") {
+        return 0;
+    }
+    let Some(text) = app_ui().ui.text(WidgetId::from_raw(id)) else {
+        wasm_widget_result(false);
+        return 0;
+    };
+    if !buf.is_null() {
+        unsafe { core::ptr::copy_nonoverlapping(text.as_ptr(), buf, text.len().min(cap)) };
+    }
+    text.len()
+}
+
+/// Índice (desde 0) del elemento seleccionado de una lista, o -1
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_selected(id: u32) -> i32 {
+    if !wasm_import_allowed("fos_ui_selected") {
+        return -1;
+    }
+    app_ui().ui.selected(WidgetId::from_raw(id)).map_or(-1, |index| index as i32)
+}
+
+/// Dar el foco a un botón, lista o campo de texto
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_focus(id: u32) {
+    if !wasm_import_allowed("fos_ui_focus") {
+        return;
+    }
+    wasm_widget_result(app_ui().ui.focus(WidgetId::from_raw(id)));
+}
+
+/// Mostrar el árbol de `root` en la zona dada (tamaño 0 = hasta el borde de la ventana)
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_show(root: u32, x: i32, y: i32, width: u32, height: u32) {
+    if !wasm_import_allowed("fos_ui_show") {
+        return;
+    }
+    if let Some(graphics) = get_graphics_context() {
+        let area = widget_area(graphics, x, y, width, height);
+        let shown = app_ui().ui.show(WidgetId::from_raw(root), area);
+        wasm_widget_result(shown);
+        if shown {
+            app_ui().ui.render(graphics);
+        }
+    }
+}
+
+/// Siguiente widget activado con Enter, o 0 si no hay ninguno
+#[unsafe(no_mangle)]
+pub extern "C" fn fos_ui_poll_event() -> u32 {
+    if !wasm_import_allowed("fos_ui_poll_event") {
+        return 0;
+    }
+    app_ui().ui.poll_activated().map_or(0, WidgetId::raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nodo de UI descrito por una expresión Lua
+    fn node(lua: &mut Lua, source: &str) -> Value {
+        let mut values = lua.exec(source).ok().unwrap_or_default();
+        values.pop().unwrap_or(Value::Nil)
+    }
+
+    #[test]
+    fn rejects_nodes_with_several_handlers() {
+        let mut lua = Lua::new();
+        let (mut ui, mut handlers) = (Ui::new(), BTreeMap::new());
+        let button = node(&mut lua, "return {type = 'button', text = 'ok', on_click = function() end}");
+        let id = build_ui_node(&mut ui, &mut handlers, &button, 0).ok();
+        assert!(id.is_some_and(|id| handlers.contains_key(&id)));
+
+        let both = node(&mut lua, "return {type = 'text_field', on_click = function() end, on_submit = function() end}");
+        let error = build_ui_node(&mut ui, &mut handlers, &both, 0).err().map(|e| e.message).unwrap_or_default();
+        assert!(error.contains("un solo manejador"), "{}", error);
+    }
+
+    #[test]
+    fn denies_input_without_the_permission() {
+        let mut permissions = PermissionSet::empty();
+        permissions.insert(Permission::Graphics);
+
+        let mut lua = Lua::new();
+        lua.set_permissions(permissions);
+        assert!(lua.check_permission("ui_handle_key", Permission::Graphics).is_ok());
+        let error = lua.check_permission("ui_handle_key", Permission::Input).err().map(|e| e.message).unwrap_or_default();
+        assert!(error.contains("requiere el permiso 'input'"), "{}", error);
+
+        lua.register_with_permission("poll_key", Permission::Input, |_, _| Ok(Vec::new()));
+        let caught = match node(&mut lua, "local ok, e = pcall(poll_key) return not ok and e") {
+            Value::Str(message) => String::from(&*message),
+            _ => String::new(),
+        };
+        assert!(caught.contains("permiso denegado: 'poll_key'"), "{}", caught);
+
+        set_app_permissions(permissions);
+        assert_eq!(fos_ui_poll_event(), 0);
+        assert_eq!(fos_last_error(), FOS_ERR_PERMISSION_DENIED);
+        assert_eq!(fos_last_error(), FOS_OK);
+    }
+}
@@ -0,0 +1,899 @@
+//! FerroOS Mobile - Toolkit de widgets
+//!
+//! Árbol retenido de widgets (etiquetas, botones, listas, campos de texto y
+//! contenedores fila/columna/pila) dibujado con el `GraphicsManager`. El árbol
+//! calcula su layout con el tamaño del texto en la fuente actual, mantiene el
+//! foco y se navega con el teclado: Tab/flechas mueven el foco o la selección
+//! y Enter activa el widget. Los widgets activados quedan en una cola de eventos.
+//!
+//! Un árbol nuevo puede sustituir al mostrado con `reconcile`, que compara
+//! ambos y solo redibuja las zonas de los widgets que han cambiado.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+use crate::graphics::{self, GraphicsManager, ShapeStyle};
+use crate::theme;
+
+/// Filas de una lista que se muestran a la vez; el resto se ve desplazando la selección
+const LIST_VISIBLE_ROWS: u32 = 6;
+
+/// Ancho mínimo de un campo de texto
+const TEXT_FIELD_MIN_WIDTH: u32 = 160;
+
+/// Eventos que se guardan sin leer; los más antiguos se descartan
+const MAX_PENDING_EVENTS: usize = 16;
+
+/// Zonas sueltas que se redibujan tras comparar dos árboles; con más se redibuja su unión
+const MAX_DAMAGE_REGIONS: usize = 8;
+
+/// Identificador de un widget; se pasa a Lua y al WASM como número (nunca 0)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct WidgetId(u32);
+
+impl WidgetId {
+    pub fn from_raw(raw: u32) -> Self {
+        WidgetId(raw)
+    }
+
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// Disposición de los hijos de un contenedor
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// De izquierda a derecha
+    Row,
+    /// De arriba abajo
+    Column,
+    /// Superpuestos, el último encima
+    Stack,
+}
+
+/// Alineación de los hijos en el eje secundario del contenedor (en ambos para `Stack`)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// Ocupar todo el espacio disponible
+    Stretch,
+}
+
+impl Align {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "start" => Some(Align::Start),
+            "center" => Some(Align::Center),
+            "end" => Some(Align::End),
+            "stretch" => Some(Align::Stretch),
+            _ => None,
+        }
+    }
+}
+
+/// Tecla de navegación ya decodificada
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Tab,
+    BackTab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Char(char),
+}
+
+#[derive(PartialEq)]
+enum WidgetKind {
+    Label { text: String },
+    Button { text: String },
+    TextField { text: String, placeholder: String },
+    List { items: Vec<String>, selected: usize },
+    Container { layout: Layout, children: Vec<WidgetId> },
+}
+
+struct Widget {
+    kind: WidgetKind,
+    parent: Option<WidgetId>,
+    padding: u32,
+    spacing: u32,
+    align: Align,
+    /// Tamaño fijo; 0 en un eje = el que pida el contenido
+    size: Size,
+    color: Option<Rgb888>,
+    background: Option<Rgb888>,
+    border: Option<Rgb888>,
+    /// Posición calculada en el último layout, en coordenadas locales del viewport
+    frame: Rectangle,
+    /// El texto se fijó con `set_text` (en un árbol declarativo, la clave `text`):
+    /// al reconciliar no se conserva lo escrito en el campo
+    text_set: bool,
+}
+
+impl Widget {
+    fn new(kind: WidgetKind, padding: u32) -> Self {
+        Self {
+            kind,
+            parent: None,
+            padding,
+            spacing: 8,
+            align: Align::Stretch,
+            size: Size::zero(),
+            color: None,
+            background: None,
+            border: None,
+            frame: Rectangle::zero(),
+            text_set: false,
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        matches!(self.kind, WidgetKind::Button { .. } | WidgetKind::TextField { .. } | WidgetKind::List { .. })
+    }
+
+    /// ¿Se dibuja igual que `other` puesto en el mismo sitio? (sin contar los hijos)
+    fn looks_like(&self, other: &Widget) -> bool {
+        let same_kind = match (&self.kind, &other.kind) {
+            (WidgetKind::Container { layout, .. }, WidgetKind::Container { layout: other_layout, .. }) => layout == other_layout,
+            (kind, other_kind) => kind == other_kind,
+        };
+        same_kind
+            && self.frame == other.frame
+            && self.padding == other.padding
+            && self.spacing == other.spacing
+            && self.align == other.align
+            && self.size == other.size
+            && self.color == other.color
+            && self.background == other.background
+            && self.border == other.border
+    }
+}
+
+/// Árbol de widgets con foco y cola de widgets activados
+pub struct Ui {
+    widgets: Vec<Widget>,
+    root: Option<WidgetId>,
+    /// Zona de la pantalla (coordenadas locales) que ocupa la raíz
+    area: Rectangle,
+    focus: Option<WidgetId>,
+    activated: VecDeque<WidgetId>,
+    dirty: bool,
+}
+
+impl Ui {
+    pub const fn new() -> Self {
+        Self {
+            widgets: Vec::new(),
+            root: None,
+            area: Rectangle::new(Point::zero(), Size::zero()),
+            focus: None,
+            activated: VecDeque::new(),
+            dirty: false,
+        }
+    }
+
+    /// Descartar todos los widgets
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // ===== Creación =====
+
+    pub fn label(&mut self, text: &str) -> WidgetId {
+        self.add(Widget::new(WidgetKind::Label { text: String::from(text) }, 0))
+    }
+
+    pub fn button(&mut self, text: &str) -> WidgetId {
+        self.add(Widget::new(WidgetKind::Button { text: String::from(text) }, 8))
+    }
+
+    pub fn text_field(&mut self, placeholder: &str) -> WidgetId {
+        let kind = WidgetKind::TextField { text: String::new(), placeholder: String::from(placeholder) };
+        self.add(Widget::new(kind, 6))
+    }
+
+    pub fn list(&mut self, items: Vec<String>) -> WidgetId {
+        self.add(Widget::new(WidgetKind::List { items, selected: 0 }, 4))
+    }
+
+    /// Crear un contenedor con sus hijos
+    ///
+    /// Devuelve `None` si algún hijo no existe o ya está en otro contenedor.
+    pub fn container(&mut self, layout: Layout, children: &[WidgetId]) -> Option<WidgetId> {
+        for (index, child) in children.iter().enumerate() {
+            if self.get(*child)?.parent.is_some() || children[..index].contains(child) {
+                return None;
+            }
+        }
+        let id = self.add(Widget::new(WidgetKind::Container { layout, children: Vec::new() }, 0));
+        for child in children {
+            self.add_child(id, *child);
+        }
+        Some(id)
+    }
+
+    /// Añadir `child` al final de un contenedor
+    ///
+    /// Falla si `parent` no es un contenedor o `child` ya tiene padre o contiene a `parent`.
+    pub fn add_child(&mut self, parent: WidgetId, child: WidgetId) -> bool {
+        if self.get(child).is_none_or(|widget| widget.parent.is_some()) || self.is_ancestor(child, parent) {
+            return false;
+        }
+        let Some(WidgetKind::Container { children, .. }) = self.get_mut(parent).map(|widget| &mut widget.kind) else {
+            return false;
+        };
+        children.push(child);
+        if let Some(widget) = self.get_mut(child) {
+            widget.parent = Some(parent);
+        }
+        self.dirty = true;
+        true
+    }
+
+    fn add(&mut self, widget: Widget) -> WidgetId {
+        self.widgets.push(widget);
+        WidgetId(self.widgets.len() as u32)
+    }
+
+    fn get(&self, id: WidgetId) -> Option<&Widget> {
+        self.widgets.get((id.0 as usize).checked_sub(1)?)
+    }
+
+    fn get_mut(&mut self, id: WidgetId) -> Option<&mut Widget> {
+        self.widgets.get_mut((id.0 as usize).checked_sub(1)?)
+    }
+
+    /// ¿Es `ancestor` el propio `id` o uno de sus contenedores?
+    fn is_ancestor(&self, ancestor: WidgetId, id: WidgetId) -> bool {
+        let mut current = Some(id);
+        while let Some(widget_id) = current {
+            if widget_id == ancestor {
+                return true;
+            }
+            current = self.get(widget_id).and_then(|widget| widget.parent);
+        }
+        false
+    }
+
+    /// Aplicar `change` al widget y marcar el árbol para redibujar; `false` si no existe
+    fn update(&mut self, id: WidgetId, change: impl FnOnce(&mut Widget)) -> bool {
+        match self.get_mut(id) {
+            Some(widget) => {
+                change(widget);
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // ===== Propiedades =====
+
+    /// Margen interior en pixels
+    pub fn set_padding(&mut self, id: WidgetId, padding: u32) -> bool {
+        self.update(id, |widget| widget.padding = padding)
+    }
+
+    /// Separación entre los hijos de un contenedor
+    pub fn set_spacing(&mut self, id: WidgetId, spacing: u32) -> bool {
+        self.update(id, |widget| widget.spacing = spacing)
+    }
+
+    /// Alineación de los hijos de un contenedor
+    pub fn set_align(&mut self, id: WidgetId, align: Align) -> bool {
+        self.update(id, |widget| widget.align = align)
+    }
+
+    /// Tamaño fijo; 0 en un eje deja el que pida el contenido
+    pub fn set_size(&mut self, id: WidgetId, width: u32, height: u32) -> bool {
+        self.update(id, |widget| widget.size = Size::new(width, height))
+    }
+
+    /// Color del texto
+    pub fn set_color(&mut self, id: WidgetId, color: Rgb888) -> bool {
+        self.update(id, |widget| widget.color = Some(color))
+    }
+
+    pub fn set_background(&mut self, id: WidgetId, color: Rgb888) -> bool {
+        self.update(id, |widget| widget.background = Some(color))
+    }
+
+    pub fn set_border(&mut self, id: WidgetId, color: Rgb888) -> bool {
+        self.update(id, |widget| widget.border = Some(color))
+    }
+
+    /// Cambiar el texto de una etiqueta, botón o campo de texto
+    pub fn set_text(&mut self, id: WidgetId, value: &str) -> bool {
+        let Some(widget) = self.get_mut(id) else {
+            return false;
+        };
+        match &mut widget.kind {
+            WidgetKind::Label { text } | WidgetKind::Button { text } | WidgetKind::TextField { text, .. } => {
+                *text = String::from(value);
+                widget.text_set = true;
+                self.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Texto de una etiqueta, botón o campo de texto, o el elemento seleccionado de una lista
+    pub fn text(&self, id: WidgetId) -> Option<&str> {
+        match &self.get(id)?.kind {
+            WidgetKind::Label { text } | WidgetKind::Button { text } | WidgetKind::TextField { text, .. } => Some(text),
+            WidgetKind::List { items, selected } => items.get(*selected).map(String::as_str),
+            WidgetKind::Container { .. } => None,
+        }
+    }
+
+    pub fn is_text_field(&self, id: WidgetId) -> bool {
+        self.get(id).is_some_and(|widget| matches!(widget.kind, WidgetKind::TextField { .. }))
+    }
+
+    /// Índice del elemento seleccionado de una lista
+    pub fn selected(&self, id: WidgetId) -> Option<usize> {
+        match &self.get(id)?.kind {
+            WidgetKind::List { items, selected } if !items.is_empty() => Some(*selected),
+            _ => None,
+        }
+    }
+
+    /// Mostrar el árbol de `root` en `area` (coordenadas locales) y enfocar su primer widget interactivo
+    pub fn show(&mut self, root: WidgetId, area: Rectangle) -> bool {
+        if self.get(root).is_none() {
+            return false;
+        }
+        self.root = Some(root);
+        self.area = area;
+        if self.focus.is_none_or(|focus| !self.is_ancestor(root, focus)) {
+            self.focus = self.focus_order().first().copied();
+        }
+        self.dirty = true;
+        true
+    }
+
+    pub fn root(&self) -> Option<WidgetId> {
+        self.root
+    }
+
+    /// ¿Hay cambios sin dibujar?
+    pub fn is_dirty(&self) -> bool {
+        self.dirty && self.root.is_some()
+    }
+
+    // ===== Foco y teclado =====
+
+    /// Enfocar un botón, lista o campo de texto
+    pub fn focus(&mut self, id: WidgetId) -> bool {
+        if !self.get(id).is_some_and(Widget::focusable) {
+            return false;
+        }
+        self.focus = Some(id);
+        self.dirty = true;
+        true
+    }
+
+    /// Widgets interactivos visibles, en el orden en que se recorren con Tab
+    fn focus_order(&self) -> Vec<WidgetId> {
+        let mut order = Vec::new();
+        let mut pending: Vec<WidgetId> = self.root.into_iter().collect();
+        while let Some(id) = pending.pop() {
+            let Some(widget) = self.get(id) else {
+                continue;
+            };
+            if widget.focusable() {
+                order.push(id);
+            }
+            if let WidgetKind::Container { children, .. } = &widget.kind {
+                pending.extend(children.iter().rev());
+            }
+        }
+        order
+    }
+
+    /// Mover el foco `step` posiciones (con vuelta al principio)
+    fn move_focus(&mut self, step: isize) -> bool {
+        let order = self.focus_order();
+        if order.is_empty() {
+            return false;
+        }
+        let next = match self.focus.and_then(|focus| order.iter().position(|id| *id == focus)) {
+            Some(current) => (current as isize + step).rem_euclid(order.len() as isize) as usize,
+            None => 0,
+        };
+        self.focus = Some(order[next]);
+        self.dirty = true;
+        true
+    }
+
+    /// Procesar una tecla; devuelve `true` si cambió algo que haya que redibujar
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        let Some(focus) = self.focus else {
+            return key != Key::Enter && self.move_focus(0);
+        };
+        if key == Key::Enter {
+            if self.activated.len() == MAX_PENDING_EVENTS {
+                self.activated.pop_front();
+            }
+            self.activated.push_back(focus);
+            return true;
+        }
+
+        let edited = match self.get_mut(focus).map(|widget| &mut widget.kind) {
+            Some(WidgetKind::List { items, selected }) => match key {
+                Key::Up if *selected > 0 => {
+                    *selected -= 1;
+                    true
+                }
+                Key::Down if *selected + 1 < items.len() => {
+                    *selected += 1;
+                    true
+                }
+                _ => false,
+            },
+            Some(WidgetKind::TextField { text, .. }) => match key {
+                Key::Char(c) => {
+                    text.push(c);
+                    true
+                }
+                Key::Backspace => text.pop().is_some(),
+                _ => false,
+            },
+            _ => false,
+        };
+        if edited {
+            self.dirty = true;
+            return true;
+        }
+        match key {
+            Key::Tab | Key::Down | Key::Right => self.move_focus(1),
+            Key::BackTab | Key::Up | Key::Left => self.move_focus(-1),
+            _ => false,
+        }
+    }
+
+    /// Siguiente widget activado con Enter, del más antiguo al más reciente
+    pub fn poll_activated(&mut self) -> Option<WidgetId> {
+        self.activated.pop_front()
+    }
+
+    // ===== Árboles declarativos =====
+
+    /// Sustituir el árbol por `next` (ya mostrado con `show`) redibujando solo lo que cambia
+    ///
+    /// Los widgets se emparejan por su posición en el árbol: los que siguen en el
+    /// mismo sitio conservan el foco, la selección de las listas y, si el nuevo
+    /// campo de texto no fija su texto, lo que se había escrito. Devuelve cuántas zonas
+    /// se han redibujado.
+    pub fn reconcile(&mut self, mut next: Ui, graphics: &mut GraphicsManager) -> usize {
+        let Some(new_root) = next.root else {
+            *self = next;
+            return 0;
+        };
+        let old_root = match self.root {
+            // Con cambios sin dibujar o en otra zona, el árbol anterior no vale para comparar
+            Some(root) if !self.dirty && self.area == next.area => root,
+            Some(root) => {
+                next.adopt_state(new_root, self, root);
+                *self = next;
+                self.render(graphics);
+                return 1;
+            }
+            None => {
+                *self = next;
+                self.render(graphics);
+                return 1;
+            }
+        };
+
+        next.adopt_state(new_root, self, old_root);
+        next.arrange(new_root, next.area, graphics);
+        next.dirty = false;
+        let mut damage = Vec::new();
+        self.diff(old_root, &next, new_root, &mut damage);
+        *self = next;
+
+        if damage.len() > MAX_DAMAGE_REGIONS {
+            let all = damage.iter().copied().reduce(graphics::union);
+            damage = all.into_iter().collect();
+        }
+        for rect in &damage {
+            self.paint(*rect, graphics);
+        }
+        damage.len()
+    }
+
+    /// Copiar el estado de interacción del subárbol `previous_id` de `previous` al subárbol `id`
+    fn adopt_state(&mut self, id: WidgetId, previous: &Ui, previous_id: WidgetId) {
+        let (Some(widget), Some(old)) = (self.get_mut(id), previous.get(previous_id)) else {
+            return;
+        };
+        let mut pairs = Vec::new();
+        let text_set = widget.text_set;
+        match (&mut widget.kind, &old.kind) {
+            (WidgetKind::TextField { text, .. }, WidgetKind::TextField { text: old_text, .. }) if !text_set => {
+                text.clone_from(old_text);
+            }
+            (WidgetKind::List { items, selected }, WidgetKind::List { selected: old_selected, .. }) => {
+                *selected = (*old_selected).min(items.len().saturating_sub(1));
+            }
+            (WidgetKind::Container { children, .. }, WidgetKind::Container { children: old_children, .. }) => {
+                pairs = children.iter().copied().zip(old_children.iter().copied()).collect();
+            }
+            _ => {}
+        }
+        if widget.focusable() && previous.focus == Some(previous_id) {
+            self.focus = Some(id);
+        }
+        for (child, old_child) in pairs {
+            self.adopt_state(child, previous, old_child);
+        }
+    }
+
+    /// Zonas a redibujar para pasar del subárbol `id` al subárbol `next_id` de `next`
+    fn diff(&self, id: WidgetId, next: &Ui, next_id: WidgetId, damage: &mut Vec<Rectangle>) {
+        let (Some(old), Some(new)) = (self.get(id), next.get(next_id)) else {
+            return;
+        };
+        let same_focus = (self.focus == Some(id)) == (next.focus == Some(next_id));
+        if !old.looks_like(new) || !same_focus {
+            damage.push(old.frame);
+            if new.frame != old.frame {
+                damage.push(new.frame);
+            }
+            return;
+        }
+        let (WidgetKind::Container { children: old_children, .. }, WidgetKind::Container { children, .. }) = (&old.kind, &new.kind) else {
+            return;
+        };
+        for index in 0..old_children.len().max(children.len()) {
+            match (old_children.get(index), children.get(index)) {
+                (Some(old_child), Some(child)) => self.diff(*old_child, next, *child, damage),
+                (Some(old_child), None) => damage.extend(self.get(*old_child).map(|widget| widget.frame)),
+                (None, Some(child)) => damage.extend(next.get(*child).map(|widget| widget.frame)),
+                (None, None) => {}
+            }
+        }
+    }
+
+    // ===== Layout =====
+
+    /// Tamaño que pide un widget con la fuente actual
+    fn measure(&self, id: WidgetId, graphics: &GraphicsManager) -> Size {
+        let Some(widget) = self.get(id) else {
+            return Size::zero();
+        };
+        let padding = widget.padding * 2;
+        let content = match &widget.kind {
+            WidgetKind::Label { text } | WidgetKind::Button { text } => {
+                let (width, height) = graphics.text_size(text);
+                Size::new(width, height)
+            }
+            WidgetKind::TextField { text, placeholder } => {
+                let (text_width, height) = graphics.text_size(text);
+                let (placeholder_width, _) = graphics.text_size(placeholder);
+                let (caret_width, _) = graphics.text_size("_");
+                Size::new((text_width + caret_width).max(placeholder_width).max(TEXT_FIELD_MIN_WIDTH), height)
+            }
+            WidgetKind::List { items, .. } => {
+                let width = items.iter().map(|item| graphics.text_size(item).0).max().unwrap_or(0);
+                let rows = (items.len() as u32).clamp(1, LIST_VISIBLE_ROWS);
+                Size::new(width + 8, rows * list_row_height(graphics))
+            }
+            WidgetKind::Container { layout, children } => {
+                let sizes = children.iter().map(|child| self.measure(*child, graphics));
+                let gaps = widget.spacing * (children.len() as u32).saturating_sub(1);
+                match layout {
+                    Layout::Row => sizes.fold(Size::new(gaps, 0), |acc, size| {
+                        Size::new(acc.width + size.width, acc.height.max(size.height))
+                    }),
+                    Layout::Column => sizes.fold(Size::new(0, gaps), |acc, size| {
+                        Size::new(acc.width.max(size.width), acc.height + size.height)
+                    }),
+                    Layout::Stack => sizes.fold(Size::zero(), |acc, size| acc.component_max(size)),
+                }
+            }
+        };
+        let width = if widget.size.width > 0 { widget.size.width } else { content.width + padding };
+        let height = if widget.size.height > 0 { widget.size.height } else { content.height + padding };
+        Size::new(width, height)
+    }
+
+    /// Colocar el widget en `frame` y repartir su interior entre los hijos
+    fn arrange(&mut self, id: WidgetId, frame: Rectangle, graphics: &GraphicsManager) {
+        let Some(widget) = self.get_mut(id) else {
+            return;
+        };
+        widget.frame = frame;
+        let WidgetKind::Container { layout, children } = &widget.kind else {
+            return;
+        };
+        let (layout, children, align, spacing) = (*layout, children.clone(), widget.align, widget.spacing as i32);
+        let padding = widget.padding;
+        let inner = Rectangle::new(
+            frame.top_left + Point::new(padding as i32, padding as i32),
+            Size::new(frame.size.width.saturating_sub(padding * 2), frame.size.height.saturating_sub(padding * 2)),
+        );
+
+        let mut offset = 0i32;
+        for child in children {
+            let size = self.measure(child, graphics);
+            let fixed = self.get(child).map_or(Size::zero(), |widget| widget.size);
+            let child_frame = match layout {
+                Layout::Row => {
+                    let width = size.width.min((inner.size.width as i32 - offset).max(0) as u32);
+                    let (y, height) = place(align, fixed.height > 0, size.height, inner.size.height);
+                    Rectangle::new(inner.top_left + Point::new(offset, y), Size::new(width, height))
+                }
+                Layout::Column => {
+                    let height = size.height.min((inner.size.height as i32 - offset).max(0) as u32);
+                    let (x, width) = place(align, fixed.width > 0, size.width, inner.size.width);
+                    Rectangle::new(inner.top_left + Point::new(x, offset), Size::new(width, height))
+                }
+                Layout::Stack => {
+                    let (x, width) = place(align, fixed.width > 0, size.width, inner.size.width);
+                    let (y, height) = place(align, fixed.height > 0, size.height, inner.size.height);
+                    Rectangle::new(inner.top_left + Point::new(x, y), Size::new(width, height))
+                }
+            };
+            match layout {
+                Layout::Row => offset += child_frame.size.width as i32 + spacing,
+                Layout::Column => offset += child_frame.size.height as i32 + spacing,
+                Layout::Stack => {}
+            }
+            self.arrange(child, child_frame, graphics);
+        }
+    }
+
+    // ===== Dibujo =====
+
+    /// Recalcular el layout y dibujar el árbol sobre su zona, borrándola antes
+    ///
+    /// Conserva el color de dibujo de la app.
+    pub fn render(&mut self, graphics: &mut GraphicsManager) {
+        let Some(root) = self.root else {
+            return;
+        };
+        self.arrange(root, self.area, graphics);
+        self.dirty = false;
+        self.paint(self.area, graphics);
+    }
+
+    /// Borrar `rect` (dentro de la zona del árbol) y redibujar los widgets que caen en él
+    fn paint(&self, rect: Rectangle, graphics: &mut GraphicsManager) {
+        let Some(root) = self.root else {
+            return;
+        };
+        let rect = rect.intersection(&self.area);
+        if rect.is_zero_sized() {
+            return;
+        }
+        let (color, alpha) = graphics.color();
+        graphics.push_clip(rect);
+        graphics.set_color(theme::current().background);
+        graphics.draw_rect(rect.top_left.x, rect.top_left.y, rect.size.width, rect.size.height, true);
+        self.draw(root, graphics);
+        graphics.pop_clip();
+        graphics.set_color_alpha(color, alpha);
+    }
+
+    fn draw(&self, id: WidgetId, graphics: &mut GraphicsManager) {
+        let Some(widget) = self.get(id) else {
+            return;
+        };
+        let frame = widget.frame;
+        if frame.is_zero_sized() {
+            return;
+        }
+        let (x, y, width, height) = (frame.top_left.x, frame.top_left.y, frame.size.width, frame.size.height);
+        let focused = self.focus == Some(id);
+        let theme = theme::current();
+        let foreground = widget.color.unwrap_or(theme.text);
+
+        if let Some(background) = widget.background {
+            graphics.set_color(background);
+            graphics.draw_rect(x, y, width, height, true);
+        }
+
+        match &widget.kind {
+            WidgetKind::Label { text } => {
+                let (_, text_height) = graphics.text_size(text);
+                graphics.set_color(foreground);
+                graphics.draw_text_at(text, x + widget.padding as i32, y + (height as i32 - text_height as i32) / 2);
+            }
+            WidgetKind::Button { text } => {
+                graphics.set_color(widget.background.unwrap_or(theme.primary));
+                graphics.draw_round_rect(frame, 6, ShapeStyle::new(true, 1));
+                let (text_width, text_height) = graphics.text_size(text);
+                graphics.set_color(widget.color.unwrap_or(theme.on_primary));
+                graphics.draw_text_at(text, x + (width as i32 - text_width as i32) / 2, y + (height as i32 - text_height as i32) / 2);
+                if focused {
+                    graphics.set_color(theme.focus);
+                    graphics.draw_round_rect(frame, 6, ShapeStyle::new(false, 2));
+                }
+            }
+            WidgetKind::TextField { text, placeholder } => {
+                graphics.set_color(widget.background.unwrap_or(theme.surface));
+                graphics.draw_rect(x, y, width, height, true);
+                let (shown, color) = if text.is_empty() && !focused {
+                    (placeholder.clone(), theme.text_muted)
+                } else if focused {
+                    (alloc::format!("{}_", text), foreground)
+                } else {
+                    (text.clone(), foreground)
+                };
+                let (_, text_height) = graphics.text_size(&shown);
+                graphics.push_clip(frame);
+                graphics.set_color(color);
+                graphics.draw_text_at(&shown, x + widget.padding as i32, y + (height as i32 - text_height as i32) / 2);
+                graphics.pop_clip();
+                graphics.set_color(if focused { theme.focus } else { widget.border.unwrap_or(theme.border) });
+                graphics.draw_rect(x, y, width, height, false);
+            }
+            WidgetKind::List { items, selected } => {
+                graphics.set_color(widget.background.unwrap_or(theme.surface));
+                graphics.draw_rect(x, y, width, height, true);
+                let row_height = list_row_height(graphics);
+                let inner_height = height.saturating_sub(widget.padding * 2);
+                let visible = (inner_height / row_height).max(1) as usize;
+                // Desplazar la lista para que la selección siempre quede a la vista
+                let first = (*selected + 1).saturating_sub(visible);
+                graphics.push_clip(frame);
+                for (row, item) in items.iter().enumerate().skip(first).take(visible) {
+                    let row_y = y + widget.padding as i32 + ((row - first) as u32 * row_height) as i32;
+                    if row == *selected {
+                        graphics.set_color(if focused { theme.primary } else { theme.selection });
+                        graphics.draw_rect(x + widget.padding as i32, row_y, width.saturating_sub(widget.padding * 2), row_height, true);
+                    }
+                    graphics.set_color(foreground);
+                    graphics.draw_text_at(item, x + widget.padding as i32 + 4, row_y + 2);
+                }
+                graphics.pop_clip();
+                graphics.set_color(if focused { theme.focus } else { widget.border.unwrap_or(theme.border) });
+                graphics.draw_rect(x, y, width, height, false);
+            }
+            WidgetKind::Container { children, .. } => {
+                for child in children {
+                    self.draw(*child, graphics);
+                }
+            }
+        }
+
+        if let (Some(border), false) = (widget.border, widget.focusable()) {
+            graphics.set_color(border);
+            graphics.draw_rect(x, y, width, height, false);
+        }
+    }
+}
+
+/// Posición y tamaño de un hijo en un eje según la alineación del contenedor
+fn place(align: Align, fixed: bool, wanted: u32, available: u32) -> (i32, u32) {
+    let size = if align == Align::Stretch && !fixed { available } else { wanted.min(available) };
+    let free = (available - size) as i32;
+    let offset = match align {
+        Align::Start | Align::Stretch => 0,
+        Align::Center => free / 2,
+        Align::End => free,
+    };
+    (offset, size)
+}
+
+/// Alto de una fila de lista con la fuente actual
+fn list_row_height(graphics: &GraphicsManager) -> u32 {
+    graphics.text_size("Ay").1 + 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    use crate::display::MemoryDisplay;
+    use crate::graphics::PixelFormat;
+
+    const AREA: Rectangle = Rectangle::new(Point::new(0, 0), Size::new(200, 160));
+    const MARK: Rgb888 = Rgb888::new(255, 0, 255);
+
+    struct Tree {
+        ui: Ui,
+        title: WidgetId,
+        list: WidgetId,
+        field: WidgetId,
+    }
+
+    fn graphics() -> GraphicsManager {
+        GraphicsManager::with_display(Box::new(MemoryDisplay::new(320, 240, PixelFormat::Bgra8888)))
+    }
+
+    fn tree(title: &str, field_text: Option<&str>) -> Tree {
+        let mut ui = Ui::new();
+        let label = ui.label(title);
+        let list = ui.list(vec!["uno".into(), "dos".into(), "tres".into()]);
+        let field = ui.text_field("nombre");
+        if let Some(text) = field_text {
+            ui.set_text(field, text);
+        }
+        let button = ui.button("Aceptar");
+        let root = ui.container(Layout::Column, &[label, list, field, button]).unwrap();
+        ui.show(root, AREA);
+        Tree { ui, title: label, list, field }
+    }
+
+    /// Pintar toda la zona de `MARK` para ver después qué se ha redibujado
+    fn mark(graphics: &mut GraphicsManager) {
+        graphics.set_color(MARK);
+        graphics.draw_rect(AREA.top_left.x, AREA.top_left.y, AREA.size.width, AREA.size.height, true);
+        graphics.present();
+    }
+
+    fn marked(graphics: &GraphicsManager, point: Point) -> bool {
+        graphics.display_pixel(point.x as u32, point.y as u32) == Some(MARK)
+    }
+
+    #[test]
+    fn an_unchanged_tree_paints_nothing() {
+        let mut graphics = graphics();
+        let mut shown = tree("Título", None);
+        shown.ui.render(&mut graphics);
+        mark(&mut graphics);
+
+        assert_eq!(shown.ui.reconcile(tree("Título", None).ui, &mut graphics), 0);
+        graphics.present();
+        assert!(AREA.points().all(|point| marked(&graphics, point)));
+    }
+
+    #[test]
+    fn a_label_change_repaints_only_its_frame() {
+        let mut graphics = graphics();
+        let mut shown = tree("Uno", None);
+        shown.ui.render(&mut graphics);
+        mark(&mut graphics);
+
+        assert_eq!(shown.ui.reconcile(tree("Dos", None).ui, &mut graphics), 1);
+        graphics.present();
+        let frame = shown.ui.get(shown.title).unwrap().frame;
+        assert!(!frame.is_zero_sized());
+        for point in AREA.points() {
+            assert_eq!(marked(&graphics, point), !frame.contains(point), "({}, {})", point.x, point.y);
+        }
+    }
+
+    #[test]
+    fn focus_and_selection_survive_a_rerender() {
+        let mut graphics = graphics();
+        let mut shown = tree("Título", None);
+        shown.ui.render(&mut graphics);
+        assert!(shown.ui.focus(shown.list));
+        assert!(shown.ui.handle_key(Key::Down));
+        shown.ui.render(&mut graphics);
+
+        let next = tree("Otro título", None);
+        shown.ui.reconcile(next.ui, &mut graphics);
+        assert_eq!(shown.ui.focus, Some(next.list));
+        assert_eq!(shown.ui.selected(next.list), Some(1));
+    }
+
+    #[test]
+    fn typed_text_survives_unless_the_node_sets_it() {
+        let mut graphics = graphics();
+        let mut shown = tree("Título", None);
+        shown.ui.render(&mut graphics);
+        assert!(shown.ui.focus(shown.field));
+        shown.ui.handle_key(Key::Char('a'));
+        shown.ui.render(&mut graphics);
+
+        shown.ui.reconcile(tree("Título", None).ui, &mut graphics);
+        assert_eq!(shown.ui.text(shown.field), Some("a"));
+        shown.ui.reconcile(tree("Título", Some("")).ui, &mut graphics);
+        assert_eq!(shown.ui.text(shown.field), Some(""));
+    }
+}
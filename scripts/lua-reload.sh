@@ -0,0 +1,32 @@
+#!/usr/bin/env bash
+# FerroOS Mobile - Hot-reload de un script Lua por UART
+#
+# Uso: scripts/lua-reload.sh <script.lua> <dispositivo-serie>
+#   Ejemplo con QEMU: arrancar con `-serial pty` y pasar el /dev/pts/N que indica QEMU.
+#
+# Envía el comando `u` al shell del kernel y después la trama:
+#   u32 LE longitud | código Lua | u32 LE CRC-32
+set -euo pipefail
+
+SCRIPT=${1:?Uso: $0 <script.lua> <dispositivo-serie>}
+DEVICE=${2:?Uso: $0 <script.lua> <dispositivo-serie>}
+
+if [ ! -f "$SCRIPT" ]; then
+  echo "❌ Script no encontrado: $SCRIPT" >&2
+  exit 1
+fi
+
+python3 - "$SCRIPT" "$DEVICE" <<'EOF'
+import struct, sys, time, zlib
+
+script, device = sys.argv[1], sys.argv[2]
+source = open(script, "rb").read()
+frame = struct.pack("<I", len(source)) + source + struct.pack("<I", zlib.crc32(source) & 0xFFFFFFFF)
+
+with open(device, "wb", buffering=0) as tty:
+    tty.write(b"u\r")
+    time.sleep(0.2)  # Dar tiempo al shell para entrar en modo recepción
+    tty.write(frame)
+
+print(f"[OK] Enviado {script}: {len(source)} bytes, CRC-32 {zlib.crc32(source) & 0xFFFFFFFF:08x}")
+EOF
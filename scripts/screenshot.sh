@@ -0,0 +1,126 @@
+#!/usr/bin/env bash
+# FerroOS Mobile - Captura de pantalla por UART a PNG
+#
+# Uso: scripts/screenshot.sh <dispositivo-serie|log> [salida.png]
+#   Con un dispositivo serie (p. ej. el /dev/pts/N de QEMU con `-serial pty`)
+#   envía el comando `p` al shell del kernel y espera la captura.
+#   Con un fichero (p. ej. un log de `-serial file:uart.log`) convierte la
+#   última captura que contenga.
+#
+# El kernel envía la pantalla en QOI, en base64, entre las líneas:
+#   FOS-SCREENSHOT BEGIN <ancho>x<alto> <formato> qoi
+#   FOS-SCREENSHOT END <bytes> <crc32>
+set -euo pipefail
+
+SOURCE=${1:?Uso: $0 <dispositivo-serie|log> [salida.png]}
+OUTPUT=${2:-screenshot-$(date +%Y%m%d-%H%M%S).png}
+
+if [ ! -e "$SOURCE" ]; then
+  echo "❌ No existe: $SOURCE" >&2
+  exit 1
+fi
+
+python3 - "$SOURCE" "$OUTPUT" <<'EOF'
+import base64, os, stat, struct, sys, time, zlib
+
+source, output = sys.argv[1], sys.argv[2]
+TIMEOUT = 120  # Segundos de espera a la captura completa por el puerto serie
+
+
+def read_from_device(device):
+    with open(device, "r+b", buffering=0) as tty:
+        tty.write(b"p\r")
+        data = b""
+        deadline = time.time() + TIMEOUT
+        while b"FOS-SCREENSHOT END" not in data or not data.endswith(b"\n"):
+            if time.time() > deadline:
+                sys.exit("❌ Tiempo de espera agotado esperando la captura")
+            data += tty.read(4096)
+    return data.decode("utf-8", "replace")
+
+
+def extract(text):
+    lines = text.replace("\r", "").split("\n")
+    begins = [i for i, line in enumerate(lines) if line.startswith("FOS-SCREENSHOT BEGIN ")]
+    if not begins:
+        sys.exit("❌ No hay ninguna captura en la entrada")
+    start = begins[-1]
+    header = lines[start].split()
+    for end in range(start + 1, len(lines)):
+        if lines[end].startswith("FOS-SCREENSHOT END "):
+            break
+    else:
+        sys.exit("❌ Captura incompleta (falta la línea END)")
+    _, _, size, crc = lines[end].split()
+    payload = base64.b64decode("".join(lines[start + 1:end]))
+    if len(payload) != int(size):
+        sys.exit(f"❌ Tamaño incorrecto: {len(payload)} bytes, se esperaban {size}")
+    if zlib.crc32(payload) & 0xFFFFFFFF != int(crc, 16):
+        sys.exit("❌ Checksum CRC-32 incorrecto, captura descartada")
+    return header[2], header[3], payload
+
+
+def decode_qoi(data):
+    if data[:4] != b"qoif":
+        sys.exit("❌ La captura no es QOI")
+    width, height, channels, _ = struct.unpack(">IIBB", data[4:14])
+    pixels = bytearray()
+    index = [(0, 0, 0, 0)] * 64
+    r, g, b, a = 0, 0, 0, 255
+    pos, total = 14, width * height
+    run = 0
+    while len(pixels) < total * 3:
+        if run > 0:
+            run -= 1
+        else:
+            op = data[pos]
+            pos += 1
+            if op == 0xFE:
+                r, g, b = data[pos:pos + 3]
+                pos += 3
+            elif op == 0xFF:
+                r, g, b, a = data[pos:pos + 4]
+                pos += 4
+            elif op >> 6 == 0:
+                r, g, b, a = index[op]
+            elif op >> 6 == 1:
+                r = (r + ((op >> 4) & 3) - 2) & 0xFF
+                g = (g + ((op >> 2) & 3) - 2) & 0xFF
+                b = (b + (op & 3) - 2) & 0xFF
+            elif op >> 6 == 2:
+                dg = (op & 0x3F) - 32
+                extra = data[pos]
+                pos += 1
+                r = (r + dg + (extra >> 4) - 8) & 0xFF
+                g = (g + dg) & 0xFF
+                b = (b + dg + (extra & 0x0F) - 8) & 0xFF
+            else:
+                run = op & 0x3F
+            index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = (r, g, b, a)
+        pixels += bytes((r, g, b))
+    return width, height, bytes(pixels)
+
+
+def write_png(path, width, height, rgb):
+    def chunk(kind, body):
+        return struct.pack(">I", len(body)) + kind + body + struct.pack(">I", zlib.crc32(kind + body) & 0xFFFFFFFF)
+
+    stride = width * 3
+    raw = b"".join(b"\x00" + rgb[y * stride:(y + 1) * stride] for y in range(height))
+    with open(path, "wb") as png:
+        png.write(b"\x89PNG\r\n\x1a\n")
+        png.write(chunk(b"IHDR", struct.pack(">IIBBBBB", width, height, 8, 2, 0, 0, 0)))
+        png.write(chunk(b"IDAT", zlib.compress(raw, 9)))
+        png.write(chunk(b"IEND", b""))
+
+
+if stat.S_ISCHR(os.stat(source).st_mode):
+    text = read_from_device(source)
+else:
+    text = open(source, "r", encoding="utf-8", errors="replace").read()
+
+size, pixel_format, payload = extract(text)
+width, height, rgb = decode_qoi(payload)
+write_png(output, width, height, rgb)
+print(f"[OK] {output}: {width}x{height} (pantalla {size} {pixel_format}), {len(payload)} bytes QOI")
+EOF
@@ -24,13 +24,14 @@ name = "${APP_NAME}"
 id = "com.fos.${APP_NAME}"
 version = "0.1.0"
 entry = "${APP_NAME}.wasm"
-permissions = ["log"]
+permissions = ["log", "graphics"]
 min_platform = "fOS:0.1"
 EOF
 
 cp "$WASM_SRC" "$OUT_DIR/${APP_NAME}.wasm"
-if [ -f ./sdk/src/assets/app.lua ]; then
-  cp ./sdk/src/assets/app.lua "$OUT_DIR/assets/app.lua"
+# Assets de la app (script Lua, fuentes .psf/.bdf, imágenes...); el kernel los embebe al compilar
+if [ -d ./sdk/src/assets ]; then
+  cp -r ./sdk/src/assets/. "$OUT_DIR/assets/"
 fi
 echo "{}" > "$OUT_DIR/hashes.json"
 echo "UNSIGNED" > "$OUT_DIR/signature.sig"
@@ -2,55 +2,54 @@
 -- Pipeline: Lua → Zig → WASM → Rust
 
 -- Limpiar pantalla y configurar
+local w, h = screen_size()
 clear_screen()
+
+-- Cabecera translúcida
+set_color("blue", 0.3)
+draw_round_rect(40, 40, w - 80, 90, 12, true)
+
 set_color("blue")
 draw_text_at("FerroOS Mobile", 300, 50)
+draw_image("logo.bmp", w - 110, 50)
 
 -- Título de la aplicación
 set_color("white")
 draw_text_at("📱 Messenger Pro v2.1", 280, 100)
 
--- Barra de estado
-set_color("green")
-draw_rect(50, 140, 700, 3, true)
+-- Estado de la aplicación
+local status = ui_label("✅ APLICACIÓN LISTA")
+ui_set_color(status, "green")
+
+local function on_contact(index, name)
+    ui_set_text(status, "💬 Abriendo conversación: " .. name)
+end
 
--- Información de conexión
-set_color("yellow")
-draw_text("🚀 Iniciando aplicación...")
-draw_text("✓ Conectando a servidor...")
-set_color("green")
-draw_text("✓ Verificando permisos...")
-draw_text("✓ Cargando contactos...")
+local function on_send(text)
+    ui_set_text(status, "📤 Enviado: " .. text)
+end
 
--- Separador
-set_color("purple")
-draw_rect(100, 280, 600, 2, true)
+local function on_sync()
+    ui_set_text(status, "🔄 Sincronizando mensajes...")
+end
 
 -- Mensajes recientes
-set_color("cyan")
-draw_text_at("💬 Mensajes recientes:", 100, 300)
+local title = ui_label("💬 Mensajes recientes:")
+ui_set_color(title, "cyan")
+local messages = ui_list({"María: ¿Vienes a la reunión?", "Luis: ¡El proyecto quedó genial!", "Ana: Gracias por la ayuda"}, on_contact)
 
-set_color("white")
-draw_text("  • María: ¿Vienes a la reunión?")
-draw_text("  • Luis: ¡El proyecto quedó genial!")
-draw_text("  • Ana: Gracias por la ayuda")
+-- Respuesta rápida
+local reply = ui_text_field("Escribe un mensaje...", on_send)
+local sync = ui_button("Sincronizar", on_sync)
+local actions = ui_row(reply, sync)
 
 -- Notificaciones
-set_color("orange")
-draw_text_at("🔔 3 notificaciones pendientes", 100, 450)
-
--- Estado del sistema
-set_color("green")
-draw_text("⚡ Ahorro de batería: ACTIVO")
+local notice = ui_label("🔔 3 notificaciones pendientes")
+ui_set_color(notice, "orange")
 
 -- Marco de la aplicación
-set_color("blue")
-draw_rect(40, 40, 720, 520, false)
-
--- Botón de estado
-set_color("green")
-draw_rect(300, 500, 200, 40, true)
-set_color("black")
-draw_text_at("✅ APLICACIÓN LISTA", 320, 510)
-
-
+local content = ui_column(title, messages, actions, notice, status)
+ui_set_padding(content, 12)
+ui_set_spacing(content, 10)
+ui_set_border(content, "blue")
+ui_show(content, 40, 140, w - 80, h - 180)
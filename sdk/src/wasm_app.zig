@@ -4,14 +4,58 @@ const std = @import("std");
 extern fn fos_log(ptr: [*]const u8, len: usize) void;
 extern fn fos_clear_screen() void;
 extern fn fos_set_color(ptr: [*]const u8, len: usize) void;
+extern fn fos_set_color_alpha(ptr: [*]const u8, len: usize, alpha: u32) void;
+extern fn fos_set_opacity(opacity: u32) void;
 extern fn fos_draw_text(ptr: [*]const u8, len: usize) void;
 extern fn fos_draw_text_at(text_ptr: [*]const u8, text_len: usize, x: i32, y: i32) void;
+extern fn fos_measure_text(ptr: [*]const u8, len: usize, max_width: u32, size: *[2]u32) u32;
+extern fn fos_draw_text_box(ptr: [*]const u8, len: usize, x: i32, y: i32, width: u32, height: u32, style: u32) u32;
 extern fn fos_draw_rect(x: i32, y: i32, width: u32, height: u32, filled: bool) void;
+extern fn fos_draw_line(x1: i32, y1: i32, x2: i32, y2: i32, stroke_width: u32) void;
+extern fn fos_draw_circle(cx: i32, cy: i32, radius: u32, filled: bool, stroke_width: u32) void;
+extern fn fos_draw_ellipse(x: i32, y: i32, width: u32, height: u32, filled: bool, stroke_width: u32) void;
+extern fn fos_draw_arc(cx: i32, cy: i32, radius: u32, start_deg: f32, sweep_deg: f32, stroke_width: u32) void;
+extern fn fos_draw_triangle(x1: i32, y1: i32, x2: i32, y2: i32, x3: i32, y3: i32, filled: bool, stroke_width: u32) void;
+extern fn fos_draw_round_rect(x: i32, y: i32, width: u32, height: u32, radius: u32, filled: bool, stroke_width: u32) void;
+extern fn fos_draw_polygon(points: [*]const i32, count: usize, filled: bool, stroke_width: u32) void;
+extern fn fos_draw_image(ptr: [*]const u8, len: usize, x: i32, y: i32, width: u32, height: u32) void;
 extern fn fos_new_line() void;
+extern fn fos_present() void;
+extern fn fos_set_font(ptr: [*]const u8, len: usize, size: u32) void;
+extern fn fos_screen_width() u32;
+extern fn fos_screen_height() u32;
+extern fn fos_push_clip(x: i32, y: i32, width: u32, height: u32) void;
+extern fn fos_pop_clip() void;
+extern fn fos_ui_label(ptr: [*]const u8, len: usize) u32;
+extern fn fos_ui_button(ptr: [*]const u8, len: usize) u32;
+extern fn fos_ui_text_field(ptr: [*]const u8, len: usize) u32;
+extern fn fos_ui_list(ptr: [*]const u8, len: usize) u32;
+extern fn fos_ui_container(layout: u32) u32;
+extern fn fos_ui_add_child(parent: u32, child: u32) void;
+extern fn fos_ui_set_padding(id: u32, padding: u32) void;
+extern fn fos_ui_set_spacing(id: u32, spacing: u32) void;
+extern fn fos_ui_set_align(id: u32, alignment: u32) void;
+extern fn fos_ui_set_size(id: u32, width: u32, height: u32) void;
+extern fn fos_ui_set_color(id: u32, ptr: [*]const u8, len: usize) void;
+extern fn fos_ui_set_background(id: u32, ptr: [*]const u8, len: usize) void;
+extern fn fos_ui_set_border(id: u32, ptr: [*]const u8, len: usize) void;
+extern fn fos_ui_set_text(id: u32, ptr: [*]const u8, len: usize) void;
+extern fn fos_ui_get_text(id: u32, buf: [*]u8, cap: usize) usize;
+extern fn fos_ui_selected(id: u32) i32;
+extern fn fos_ui_focus(id: u32) void;
+extern fn fos_ui_show(root: u32, x: i32, y: i32, width: u32, height: u32) void;
+extern fn fos_ui_poll_event() u32;
+extern fn fos_last_error() i32;
+
+// Códigos de fos_last_error()
+const FOS_ERR_PERMISSION_DENIED: i32 = 1;
+const FOS_ERR_NOT_FOUND: i32 = 2;
+const FOS_ERR_INVALID_DATA: i32 = 3;
 
 export fn _start() noreturn {
     const script: []const u8 = @embedFile("assets/app.lua");
     runScript(script);
+    fos_present();
     std.process.exit(0);
 }
 
@@ -22,15 +66,46 @@ fn log(s: []const u8) void {
 fn runScript(src: []const u8) void {
     // Intérprete Lua extendido con funciones gráficas
     var it = std.mem.tokenizeAny(u8, src, "\r\n");
+    // Profundidad de bloques dentro de una función que se está saltando
+    var function_depth: usize = 0;
     while (it.next()) |line| {
         const trimmed = std.mem.trim(u8, line, " \t");
         if (trimmed.len == 0) continue;
         
         // Ignorar comentarios
         if (std.mem.startsWith(u8, trimmed, "--")) continue;
+
+        // Las funciones (callbacks de widgets) no se ejecutan aquí: una app WASM
+        // lee los widgets activados con fos_ui_poll_event
+        if (function_depth > 0 or isFunctionStart(trimmed)) {
+            if (std.mem.eql(u8, trimmed, "end")) {
+                function_depth -= 1;
+            } else if (isBlockStart(trimmed)) {
+                function_depth += 1;
+            }
+            continue;
+        }
         
         // Procesar comandos gráficos
-        if (processGraphicsCommand(trimmed)) continue;
+        if (processGraphicsCommand(trimmed)) {
+            // El host deniega las llamadas sin permiso en el manifiesto
+            switch (fos_last_error()) {
+                FOS_ERR_PERMISSION_DENIED => {
+                    log("[Permiso denegado]");
+                    log(trimmed);
+                },
+                FOS_ERR_NOT_FOUND => {
+                    log("[Recurso no encontrado]");
+                    log(trimmed);
+                },
+                FOS_ERR_INVALID_DATA => {
+                    log("[Recurso inválido]");
+                    log(trimmed);
+                },
+                else => {},
+            }
+            continue;
+        }
         
         // Procesar print() tradicional
         if (std.mem.startsWith(u8, trimmed, "print(") and std.mem.endsWith(u8, trimmed, ")")) {
@@ -59,14 +134,54 @@ fn processGraphicsCommand(line: []const u8) bool {
         fos_new_line();
         return true;
     }
+
+    // local w, h = screen_size(): w y h se resuelven en parseCoord
+    if (std.mem.eql(u8, line, "local w, h = screen_size()")) {
+        return true;
+    }
+
+    // pop_clip()
+    if (std.mem.eql(u8, line, "pop_clip()")) {
+        fos_pop_clip();
+        return true;
+    }
+
+    // push_clip(x, y, width, height)
+    if (std.mem.startsWith(u8, line, "push_clip(") and std.mem.endsWith(u8, line, ")")) {
+        var args = ShapeArgs{};
+        if (parseShapeArgs(line[10 .. line.len - 1], &args) and args.count == 4 and args.values[2] >= 0 and args.values[3] >= 0) {
+            fos_push_clip(args.values[0], args.values[1], @intCast(args.values[2]), @intCast(args.values[3]));
+            return true;
+        }
+    }
+
+    // present()
+    if (std.mem.eql(u8, line, "present()")) {
+        fos_present();
+        return true;
+    }
     
-    // set_color("color")
+    // set_color("color") / set_color("color", alpha)
     if (std.mem.startsWith(u8, line, "set_color(") and std.mem.endsWith(u8, line, ")")) {
         const inner = line[10 .. line.len - 1];
         if (parseStringLiteral(inner)) |color| {
             fos_set_color(color.ptr, color.len);
             return true;
         }
+        if (std.mem.lastIndexOfScalar(u8, inner, ',')) |comma| {
+            const color = parseStringLiteral(inner[0..comma]) orelse return false;
+            const alpha = parseFraction(inner[comma + 1 ..]) orelse return false;
+            fos_set_color_alpha(color.ptr, color.len, alpha);
+            return true;
+        }
+    }
+
+    // set_opacity(opacity)
+    if (std.mem.startsWith(u8, line, "set_opacity(") and std.mem.endsWith(u8, line, ")")) {
+        if (parseFraction(line[12 .. line.len - 1])) |opacity| {
+            fos_set_opacity(opacity);
+            return true;
+        }
     }
     
     // draw_text("text")
@@ -85,6 +200,27 @@ fn processGraphicsCommand(line: []const u8) bool {
         }
     }
     
+    // draw_text_box("text", x, y, width, height)
+    if (std.mem.startsWith(u8, line, "draw_text_box(")) {
+        if (parseDrawTextBox(line)) {
+            return true;
+        }
+    }
+    
+    // set_font("name", size)
+    if (std.mem.startsWith(u8, line, "set_font(")) {
+        if (parseSetFont(line)) {
+            return true;
+        }
+    }
+    
+    // draw_image("name", x, y) / draw_image("name", x, y, width, height)
+    if (std.mem.startsWith(u8, line, "draw_image(")) {
+        if (parseDrawImage(line)) {
+            return true;
+        }
+    }
+    
     // draw_rect(x, y, width, height, filled)
     if (std.mem.startsWith(u8, line, "draw_rect(")) {
         if (parseDrawRect(line)) {
@@ -92,10 +228,299 @@ fn processGraphicsCommand(line: []const u8) bool {
         }
     }
     
+    // draw_line / draw_circle / draw_ellipse / draw_arc / draw_triangle / draw_round_rect / draw_polygon
+    if (parseShape(line)) {
+        return true;
+    }
+
+    // local nombre = ui_label(...) / ui_button(...) / ui_row(a, b) ...
+    if (std.mem.startsWith(u8, line, "local ")) {
+        if (parseUiBinding(line)) {
+            return true;
+        }
+    }
+
+    // ui_set_padding(nombre, n) / ui_show(nombre) ...
+    if (std.mem.startsWith(u8, line, "ui_")) {
+        if (parseUiStatement(line)) {
+            return true;
+        }
+    }
+    
     return false;
 }
 
+fn isFunctionStart(line: []const u8) bool {
+    return std.mem.startsWith(u8, line, "function ") or std.mem.startsWith(u8, line, "local function ");
+}
+
+// Líneas que abren un bloque cerrado por `end`
+fn isBlockStart(line: []const u8) bool {
+    return isFunctionStart(line) or std.mem.endsWith(u8, line, " then") or std.mem.endsWith(u8, line, " do");
+}
+
+// ===== Widgets =====
+
+// Widgets creados con `local nombre = ui_...(...)`, para usarlos por nombre
+const Widget = struct {
+    name: []const u8,
+    id: u32,
+};
+var widgets: [64]Widget = undefined;
+var widget_count: usize = 0;
+
+fn widgetId(name: []const u8) ?u32 {
+    const wanted = std.mem.trim(u8, name, " \t");
+    for (widgets[0..widget_count]) |widget| {
+        if (std.mem.eql(u8, widget.name, wanted)) return widget.id;
+    }
+    return null;
+}
+
+// Primer argumento de texto de una llamada; el resto (p. ej. el callback) se ignora
+fn firstStringArg(params: []const u8) ?[]const u8 {
+    const t = std.mem.trim(u8, params, " \t");
+    if (t.len < 2 or t[0] != '"') return null;
+    const close = std.mem.indexOfScalarPos(u8, t, 1, '"') orelse return null;
+    return t[1..close];
+}
+
+// Parsear `local nombre = ui_xxx(...)` y recordar el widget creado
+fn parseUiBinding(line: []const u8) bool {
+    const eq = std.mem.indexOfScalar(u8, line, '=') orelse return false;
+    const name = std.mem.trim(u8, line[6..eq], " \t");
+    const call = std.mem.trim(u8, line[eq + 1 ..], " \t");
+    if (name.len == 0 or widget_count == widgets.len) return false;
+
+    const open = std.mem.indexOfScalar(u8, call, '(') orelse return false;
+    if (call[call.len - 1] != ')') return false;
+    const func = call[0..open];
+    const params = call[open + 1 .. call.len - 1];
+
+    const id: u32 = blk: {
+        if (std.mem.eql(u8, func, "ui_label")) {
+            const text = parseStringLiteral(params) orelse return false;
+            break :blk fos_ui_label(text.ptr, text.len);
+        }
+        if (std.mem.eql(u8, func, "ui_button")) {
+            const text = firstStringArg(params) orelse return false;
+            break :blk fos_ui_button(text.ptr, text.len);
+        }
+        if (std.mem.eql(u8, func, "ui_text_field")) {
+            const placeholder = firstStringArg(params) orelse return false;
+            break :blk fos_ui_text_field(placeholder.ptr, placeholder.len);
+        }
+        if (std.mem.eql(u8, func, "ui_list")) {
+            // {"a", "b", ...}: los elementos se pasan separados por saltos de línea
+            const open_brace = std.mem.indexOfScalar(u8, params, '{') orelse return false;
+            const close_brace = std.mem.lastIndexOfScalar(u8, params, '}') orelse return false;
+            var items: [1024]u8 = undefined;
+            var len: usize = 0;
+            var rest = params[open_brace + 1 .. close_brace];
+            while (std.mem.indexOfScalar(u8, rest, '"')) |quote| {
+                const end = std.mem.indexOfScalarPos(u8, rest, quote + 1, '"') orelse return false;
+                const item = rest[quote + 1 .. end];
+                if (len + item.len + 1 > items.len) return false;
+                if (len > 0) {
+                    items[len] = '\n';
+                    len += 1;
+                }
+                @memcpy(items[len .. len + item.len], item);
+                len += item.len;
+                rest = rest[end + 1 ..];
+            }
+            break :blk fos_ui_list(&items, len);
+        }
+
+        const layout: u32 = if (std.mem.eql(u8, func, "ui_row"))
+            0
+        else if (std.mem.eql(u8, func, "ui_column"))
+            1
+        else if (std.mem.eql(u8, func, "ui_stack"))
+            2
+        else
+            return false;
+        const container = fos_ui_container(layout);
+        var children = std.mem.splitScalar(u8, params, ',');
+        while (children.next()) |child| {
+            if (std.mem.trim(u8, child, " \t").len == 0) continue;
+            fos_ui_add_child(container, widgetId(child) orelse return false);
+        }
+        break :blk container;
+    };
+    if (id == 0) return false;
+
+    widgets[widget_count] = .{ .name = name, .id = id };
+    widget_count += 1;
+    return true;
+}
+
+// Parsear `ui_set_xxx(nombre, ...)`, `ui_focus(nombre)` y `ui_show(nombre[, x, y, width, height])`
+fn parseUiStatement(line: []const u8) bool {
+    const open = std.mem.indexOfScalar(u8, line, '(') orelse return false;
+    if (line[line.len - 1] != ')') return false;
+    const func = line[0..open];
+    const params = line[open + 1 .. line.len - 1];
+
+    const comma = std.mem.indexOfScalar(u8, params, ',') orelse params.len;
+    const id = widgetId(params[0..comma]) orelse return false;
+    const rest = if (comma < params.len) std.mem.trim(u8, params[comma + 1 ..], " \t") else "";
+
+    if (std.mem.eql(u8, func, "ui_focus")) {
+        fos_ui_focus(id);
+    } else if (std.mem.eql(u8, func, "ui_show")) {
+        if (rest.len == 0) {
+            fos_ui_show(id, 0, 0, 0, 0);
+            return true;
+        }
+        var args = ShapeArgs{};
+        if (!parseShapeArgs(rest, &args) or args.count != 4 or args.values[2] < 0 or args.values[3] < 0) return false;
+        fos_ui_show(id, args.values[0], args.values[1], @intCast(args.values[2]), @intCast(args.values[3]));
+    } else if (std.mem.eql(u8, func, "ui_set_padding") or std.mem.eql(u8, func, "ui_set_spacing")) {
+        const value = parseCoord(rest) orelse return false;
+        if (value < 0) return false;
+        if (func[7] == 'p') fos_ui_set_padding(id, @intCast(value)) else fos_ui_set_spacing(id, @intCast(value));
+    } else if (std.mem.eql(u8, func, "ui_set_size")) {
+        var args = ShapeArgs{};
+        if (!parseShapeArgs(rest, &args) or args.count != 2 or args.values[0] < 0 or args.values[1] < 0) return false;
+        fos_ui_set_size(id, @intCast(args.values[0]), @intCast(args.values[1]));
+    } else {
+        const text = parseStringLiteral(rest) orelse return false;
+        if (std.mem.eql(u8, func, "ui_set_align")) {
+            const alignments = [_][]const u8{ "start", "center", "end", "stretch" };
+            for (alignments, 0..) |name, index| {
+                if (std.mem.eql(u8, text, name)) {
+                    fos_ui_set_align(id, @intCast(index));
+                    return true;
+                }
+            }
+            return false;
+        } else if (std.mem.eql(u8, func, "ui_set_color")) {
+            fos_ui_set_color(id, text.ptr, text.len);
+        } else if (std.mem.eql(u8, func, "ui_set_background")) {
+            fos_ui_set_background(id, text.ptr, text.len);
+        } else if (std.mem.eql(u8, func, "ui_set_border")) {
+            fos_ui_set_border(id, text.ptr, text.len);
+        } else if (std.mem.eql(u8, func, "ui_set_text")) {
+            fos_ui_set_text(id, text.ptr, text.len);
+        } else {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Argumentos de una primitiva: coordenadas, `filled` opcional y grosor de trazo opcional
+const ShapeArgs = struct {
+    values: [16]i32 = undefined,
+    count: usize = 0,
+    filled: bool = false,
+    stroke: u32 = 1,
+};
+
+// Parsear `nombre(a, b, ..., filled, stroke)`; `points` se separan entre llaves `{...}`
+fn parseShapeArgs(params: []const u8, args: *ShapeArgs) bool {
+    var rest = params;
+    // Tabla de puntos de draw_polygon
+    if (std.mem.startsWith(u8, std.mem.trim(u8, rest, " \t"), "{")) {
+        const open = std.mem.indexOf(u8, rest, "{") orelse return false;
+        const close = std.mem.indexOf(u8, rest, "}") orelse return false;
+        var coords = std.mem.splitScalar(u8, rest[open + 1 .. close], ',');
+        while (coords.next()) |part| {
+            if (args.count == args.values.len) return false;
+            args.values[args.count] = parseCoord(std.mem.trim(u8, part, " \t")) orelse return false;
+            args.count += 1;
+        }
+        rest = rest[close + 1 ..];
+        rest = std.mem.trimLeft(u8, rest, " \t");
+        if (rest.len == 0) return true;
+        if (rest[0] != ',') return false;
+        rest = rest[1..];
+    }
+
+    var parts = std.mem.splitScalar(u8, rest, ',');
+    var seen_filled = false;
+    while (parts.next()) |raw| {
+        const part = std.mem.trim(u8, raw, " \t");
+        if (std.mem.eql(u8, part, "true") or std.mem.eql(u8, part, "false")) {
+            args.filled = std.mem.eql(u8, part, "true");
+            seen_filled = true;
+            continue;
+        }
+        const value = parseCoord(part) orelse return false;
+        if (seen_filled) {
+            if (value < 1) return false;
+            args.stroke = @intCast(value);
+        } else {
+            if (args.count == args.values.len) return false;
+            args.values[args.count] = value;
+            args.count += 1;
+        }
+    }
+    return true;
+}
+
+fn parseShape(line: []const u8) bool {
+    const start = std.mem.indexOf(u8, line, "(") orelse return false;
+    const end = std.mem.lastIndexOf(u8, line, ")") orelse return false;
+    if (end <= start or end != line.len - 1) return false;
+
+    const name = line[0..start];
+    var args = ShapeArgs{};
+    if (!parseShapeArgs(line[start + 1 .. end], &args)) return false;
+    const v = args.values;
+
+    if (std.mem.eql(u8, name, "draw_line")) {
+        // draw_line no tiene relleno: el quinto número es el grosor
+        if (args.count == 5 and v[4] > 0) {
+            args.stroke = @intCast(v[4]);
+        } else if (args.count != 4) return false;
+        fos_draw_line(v[0], v[1], v[2], v[3], args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_circle")) {
+        if (args.count != 3 or v[2] < 0) return false;
+        fos_draw_circle(v[0], v[1], @intCast(v[2]), args.filled, args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_ellipse")) {
+        if (args.count != 4 or v[2] < 0 or v[3] < 0) return false;
+        fos_draw_ellipse(v[0], v[1], @intCast(v[2]), @intCast(v[3]), args.filled, args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_arc")) {
+        if (args.count == 6 and v[5] > 0) {
+            args.stroke = @intCast(v[5]);
+        } else if (args.count != 5) return false;
+        if (v[2] < 0) return false;
+        fos_draw_arc(v[0], v[1], @intCast(v[2]), @floatFromInt(v[3]), @floatFromInt(v[4]), args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_triangle")) {
+        if (args.count != 6) return false;
+        fos_draw_triangle(v[0], v[1], v[2], v[3], v[4], v[5], args.filled, args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_round_rect")) {
+        if (args.count != 5 or v[2] < 0 or v[3] < 0 or v[4] < 0) return false;
+        fos_draw_round_rect(v[0], v[1], @intCast(v[2]), @intCast(v[3]), @intCast(v[4]), args.filled, args.stroke);
+    } else if (std.mem.eql(u8, name, "draw_polygon")) {
+        if (args.count < 4 or args.count % 2 != 0) return false;
+        fos_draw_polygon(&args.values, args.count / 2, args.filled, args.stroke);
+    } else {
+        return false;
+    }
+    return true;
+}
+
 // Parsear draw_text_at("text", x, y)
+// Estilo por defecto de draw_text_box: alineado a la izquierda, con "…" y 2 pixels entre líneas
+const TEXT_BOX_STYLE: u32 = (1 << 2) | (2 << 8);
+
+fn parseDrawTextBox(line: []const u8) bool {
+    if (line[line.len - 1] != ')') return false;
+    const params = line["draw_text_box(".len .. line.len - 1];
+    const text = firstStringArg(params) orelse return false;
+    // Lo que sigue a las comillas de cierre: ", x, y, width, height"
+    const after = std.mem.trim(u8, params[std.mem.indexOfScalar(u8, params, '"').? + text.len + 2 ..], " \t");
+    if (after.len == 0 or after[0] != ',') return false;
+
+    var args = ShapeArgs{};
+    if (!parseShapeArgs(after[1..], &args) or args.count != 4 or args.values[2] < 0 or args.values[3] < 0) return false;
+    _ = fos_draw_text_box(text.ptr, text.len, args.values[0], args.values[1], @intCast(args.values[2]), @intCast(args.values[3]), TEXT_BOX_STYLE);
+    return true;
+}
+
 fn parseDrawTextAt(line: []const u8) bool {
     // Extraer contenido entre paréntesis
     const start = std.mem.indexOf(u8, line, "(") orelse return false;
@@ -130,13 +555,69 @@ fn parseDrawTextAt(line: []const u8) bool {
     const text = parseStringLiteral(text_part) orelse return false;
     
     // Parsear coordenadas
-    const x = std.fmt.parseInt(i32, x_part, 10) catch return false;
-    const y = std.fmt.parseInt(i32, y_part, 10) catch return false;
+    const x = parseCoord(x_part) orelse return false;
+    const y = parseCoord(y_part) orelse return false;
     
     fos_draw_text_at(text.ptr, text.len, x, y);
     return true;
 }
 
+// Parsear una fracción de 0 a 1 (alfa u opacidad de Lua) a un valor de 0 a 255
+fn parseFraction(s: []const u8) ?u32 {
+    const value = std.fmt.parseFloat(f32, std.mem.trim(u8, s, " \t")) catch return null;
+    return @intFromFloat(@round(std.math.clamp(value, 0.0, 1.0) * 255.0));
+}
+
+// Parsear una coordenada: entero, `w`/`h` (tamaño de pantalla) o `w - 100`, `h / 2`...
+fn parseCoord(s: []const u8) ?i32 {
+    if (std.fmt.parseInt(i32, s, 10)) |n| return n else |_| {}
+    if (s.len == 0) return null;
+
+    const base: i32 = switch (s[0]) {
+        'w' => @intCast(fos_screen_width()),
+        'h' => @intCast(fos_screen_height()),
+        else => return null,
+    };
+    const rest = std.mem.trim(u8, s[1..], " \t");
+    if (rest.len == 0) return base;
+
+    const operand = std.fmt.parseInt(i32, std.mem.trim(u8, rest[1..], " \t"), 10) catch return null;
+    return switch (rest[0]) {
+        '+' => base + operand,
+        '-' => base - operand,
+        '*' => base * operand,
+        '/' => if (operand == 0) null else @divTrunc(base, operand),
+        else => null,
+    };
+}
+
+// Parsear draw_image("name", x, y[, width, height])
+fn parseDrawImage(line: []const u8) bool {
+    const start = std.mem.indexOf(u8, line, "(") orelse return false;
+    const end = std.mem.lastIndexOf(u8, line, ")") orelse return false;
+    if (end <= start) return false;
+
+    const params = line[start + 1 .. end];
+    // El nombre va entre comillas: el resto son números
+    const name_end = std.mem.indexOfPos(u8, params, 1, "\"") orelse return false;
+    const name = parseStringLiteral(std.mem.trim(u8, params[0 .. name_end + 1], " \t")) orelse return false;
+
+    var values = [4]i32{ 0, 0, 0, 0 };
+    var count: usize = 0;
+    var parts = std.mem.splitScalar(u8, params[name_end + 1 ..], ',');
+    _ = parts.next(); // texto entre el nombre y la primera coma
+    while (parts.next()) |part| {
+        if (count == values.len) return false;
+        values[count] = parseCoord(std.mem.trim(u8, part, " \t")) orelse return false;
+        count += 1;
+    }
+    if (count != 2 and count != 4) return false;
+    if (values[2] < 0 or values[3] < 0) return false;
+
+    fos_draw_image(name.ptr, name.len, values[0], values[1], @intCast(values[2]), @intCast(values[3]));
+    return true;
+}
+
 // Parsear draw_rect(x, y, width, height, filled)
 fn parseDrawRect(line: []const u8) bool {
     const start = std.mem.indexOf(u8, line, "(") orelse return false;
@@ -154,13 +635,33 @@ fn parseDrawRect(line: []const u8) bool {
     const height_str = std.mem.trim(u8, parts.next() orelse return false, " \t");
     const filled_str = std.mem.trim(u8, parts.next() orelse return false, " \t");
     
-    const x = std.fmt.parseInt(i32, x_str, 10) catch return false;
-    const y = std.fmt.parseInt(i32, y_str, 10) catch return false;
-    const width = std.fmt.parseInt(u32, width_str, 10) catch return false;
-    const height = std.fmt.parseInt(u32, height_str, 10) catch return false;
+    const x = parseCoord(x_str) orelse return false;
+    const y = parseCoord(y_str) orelse return false;
+    const width = parseCoord(width_str) orelse return false;
+    const height = parseCoord(height_str) orelse return false;
+    if (width < 0 or height < 0) return false;
     const filled = std.mem.eql(u8, filled_str, "true");
     
-    fos_draw_rect(x, y, width, height, filled);
+    fos_draw_rect(x, y, @intCast(width), @intCast(height), filled);
+    return true;
+}
+
+// Parsear set_font("name", size) o set_font("name")
+fn parseSetFont(line: []const u8) bool {
+    const start = std.mem.indexOf(u8, line, "(") orelse return false;
+    const end = std.mem.lastIndexOf(u8, line, ")") orelse return false;
+    if (end <= start) return false;
+    
+    const params = line[start + 1 .. end];
+    var parts = std.mem.splitScalar(u8, params, ',');
+    
+    const name = parseStringLiteral(parts.next() orelse return false) orelse return false;
+    var size: u32 = 0;
+    if (parts.next()) |size_part| {
+        size = std.fmt.parseInt(u32, std.mem.trim(u8, size_part, " \t"), 10) catch return false;
+    }
+    
+    fos_set_font(name.ptr, name.len, size);
     return true;
 }
 